@@ -0,0 +1,33 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use sen5x_rs::{crc, parse};
+
+const MEASUREMENT: [u8; 24] = [
+    0x00, 0x12, 0xA0, 0x00, 0x16, 0x64, 0x00, 0x18, 0x7B, 0x00, 0x1A, 0x19, 0x15, 0x8A, 0x39, 0x11,
+    0x81, 0x50, 0x01, 0x68, 0x77, 0x00, 0x0A, 0x5A,
+];
+
+fn crc8(c: &mut Criterion) {
+    let word = [0xbe, 0xef];
+    let bytes = [0xa5u8; 32];
+    c.bench_function("crc word (table)", |b| {
+        b.iter(|| crc::crc(std::hint::black_box(&word)))
+    });
+    c.bench_function("crc word (bitwise)", |b| {
+        b.iter(|| crc::crc_bitwise(std::hint::black_box(&word)))
+    });
+    c.bench_function("crc 32 bytes (table)", |b| {
+        b.iter(|| crc::crc(std::hint::black_box(&bytes)))
+    });
+    c.bench_function("crc 32 bytes (bitwise)", |b| {
+        b.iter(|| crc::crc_bitwise(std::hint::black_box(&bytes)))
+    });
+}
+
+fn measurement(c: &mut Criterion) {
+    c.bench_function("decode measurement", |b| {
+        b.iter(|| parse::measurement::<()>(std::hint::black_box(&MEASUREMENT)))
+    });
+}
+
+criterion_group!(benches, crc8, measurement);
+criterion_main!(benches);
@@ -0,0 +1,26 @@
+//! Write valid frames encoded by `sen5x_rs::model` to the corpus of every fuzz target.
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use sen5x_rs::model;
+use sen5x_rs::test_vectors::MEASUREMENTS;
+
+fn write(target: &str, name: &str, frame: &[u8]) -> io::Result<()> {
+    let dir = Path::new("corpus").join(target);
+    fs::create_dir_all(&dir)?;
+    fs::write(dir.join(name), frame)
+}
+
+fn main() -> io::Result<()> {
+    for (i, vector) in MEASUREMENTS.iter().enumerate() {
+        write("measurement", &format!("vector-{i}"), &model::measurement(&vector.raw))?;
+    }
+    write("data_ready", "ready", &model::data_ready(true))?;
+    write("data_ready", "not-ready", &model::data_ready(false))?;
+    let mut name = [0u8; 32];
+    name[..5].copy_from_slice(b"SEN55");
+    write("product_name", "sen55", &model::ascii(&name))?;
+    write("product_name", "empty", &model::ascii(&[0; 32]))?;
+    Ok(())
+}
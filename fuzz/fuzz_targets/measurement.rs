@@ -0,0 +1,12 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use sen5x_rs::{model, parse};
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(frame) = <&[u8; 24]>::try_from(data) {
+        if let Ok(raw) = parse::measurement::<()>(frame) {
+            assert_eq!(&model::measurement(&raw), frame);
+        }
+    }
+});
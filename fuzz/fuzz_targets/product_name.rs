@@ -0,0 +1,10 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use sen5x_rs::parse;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(frame) = <&[u8; 48]>::try_from(data) {
+        let _ = parse::product_name::<()>(frame);
+    }
+});
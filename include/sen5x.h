@@ -0,0 +1,53 @@
+/* C interface of the sen5x-rs decoding functions, built with the `ffi` feature. */
+#ifndef SEN5X_H
+#define SEN5X_H
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define SEN5X_OK 0
+#define SEN5X_ERROR_NULL -1
+#define SEN5X_ERROR_WRONG_SIZE -2
+#define SEN5X_ERROR_CRC -3
+
+/* Raw values of a `Read Measured Values` response. */
+typedef struct {
+    uint16_t pm1_0;      /* [ug/m3] x10 */
+    uint16_t pm2_5;      /* [ug/m3] x10 */
+    uint16_t pm4_0;      /* [ug/m3] x10 */
+    uint16_t pm10_0;     /* [ug/m3] x10 */
+    int16_t temperature; /* [degC] x200 */
+    int16_t humidity;    /* [%RH] x100 */
+    int16_t voc_index;   /* x10 */
+    int16_t nox_index;   /* x10 */
+} sen5x_data_raw_t;
+
+/* Values of a `Read Measured Values` response in physical units, NAN if not measured. */
+typedef struct {
+    float pm1_0;       /* [ug/m3] */
+    float pm2_5;       /* [ug/m3] */
+    float pm4_0;       /* [ug/m3] */
+    float pm10_0;      /* [ug/m3] */
+    float humidity;    /* [%RH] */
+    float temperature; /* [degC] */
+    float voc_index;
+    float nox_index;
+} sen5x_data_t;
+
+uint8_t sen5x_crc(const uint8_t *data, size_t len);
+int32_t sen5x_decode_measurement_raw(const uint8_t *frame, size_t len, sen5x_data_raw_t *out);
+int32_t sen5x_decode_measurement(const uint8_t *frame, size_t len, sen5x_data_t *out);
+int32_t sen5x_convert(const sen5x_data_raw_t *raw, sen5x_data_t *out);
+int32_t sen5x_decode_data_ready(const uint8_t *frame, size_t len, bool *out);
+int32_t sen5x_decode_device_status(const uint8_t *frame, size_t len, uint32_t *out);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* SEN5X_H */
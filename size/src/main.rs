@@ -0,0 +1,110 @@
+//! Code size probe for the feature tiers.
+//!
+//! Reads and converts a measurement and, depending on the tier, rates and formats it, so the
+//! linked code of a tier can be measured with the commands in the README.
+#![cfg_attr(target_arch = "wasm32", no_std, no_main)]
+
+#[cfg(target_arch = "wasm32")]
+mod probe {
+    use core::convert::Infallible;
+
+    use embedded_hal::delay::DelayNs;
+    use embedded_hal::i2c::{ErrorType, I2c, Operation};
+    use sen5x_rs::Sen5x;
+
+    extern "C" {
+        fn bus_read(buf: *mut u8, len: usize);
+        #[cfg(feature = "fmt")]
+        fn output(buf: *const u8, len: usize);
+    }
+
+    struct Bus;
+
+    impl ErrorType for Bus {
+        type Error = Infallible;
+    }
+
+    impl I2c for Bus {
+        fn transaction(
+            &mut self,
+            _: u8,
+            operations: &mut [Operation<'_>],
+        ) -> Result<(), Infallible> {
+            for operation in operations {
+                if let Operation::Read(buf) = operation {
+                    // SAFETY: the host fills at most `len` bytes of `buf`.
+                    unsafe { bus_read(buf.as_mut_ptr(), buf.len()) };
+                }
+            }
+            Ok(())
+        }
+    }
+
+    struct Delay;
+
+    impl DelayNs for Delay {
+        fn delay_ns(&mut self, _: u32) {}
+    }
+
+    #[cfg(feature = "fmt")]
+    struct Buffer {
+        bytes: [u8; 128],
+        len: usize,
+    }
+
+    #[cfg(feature = "fmt")]
+    impl core::fmt::Write for Buffer {
+        fn write_str(&mut self, s: &str) -> core::fmt::Result {
+            let end = self.len + s.len();
+            self.bytes
+                .get_mut(self.len..end)
+                .ok_or(core::fmt::Error)?
+                .copy_from_slice(s.as_bytes());
+            self.len = end;
+            Ok(())
+        }
+    }
+
+    /// Read one measurement and pass its PM2.5 concentration, score and CSV row to the host.
+    #[no_mangle]
+    pub extern "C" fn measure() -> f32 {
+        let mut sensor = Sen5x::new(Bus, Delay);
+        let Ok(data) = sensor
+            .start_measurement()
+            .and_then(|()| sensor.measure_blocking())
+        else {
+            return f32::NAN;
+        };
+
+        #[cfg(feature = "fmt")]
+        {
+            use core::fmt::Write;
+
+            let mut buffer = Buffer {
+                bytes: [0; 128],
+                len: 0,
+            };
+            if write!(buffer, "{}", sen5x_rs::csv::Csv::new().row(&data)).is_ok() {
+                // SAFETY: `len` bytes of the buffer were written.
+                unsafe { output(buffer.bytes.as_ptr(), buffer.len) };
+            }
+        }
+
+        #[cfg(feature = "metrics")]
+        if let Some(score) = sen5x_rs::iaq::IaqScore::new().score(&data) {
+            return score;
+        }
+
+        data.pm.pm2_5
+    }
+
+    #[panic_handler]
+    fn panic(_: &core::panic::PanicInfo<'_>) -> ! {
+        loop {}
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn main() {
+    println!("build this example for wasm32-unknown-unknown to measure the code size");
+}
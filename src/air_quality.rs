@@ -0,0 +1,304 @@
+//! A sensor independent interface for air quality readings.
+//!
+//! Firmware combining several sensors, e.g. a SEN5x with a CO₂ sensor, can implement
+//! [`AirQualityRead`] for each of them and [`AirQualityReading::merge`] the readings into a
+//! single report.
+//!
+//! Products combining a SEN5x with sensors without a driver in this crate, e.g. a CO₂, pressure
+//! or ambient light sensor, can collect the values of all of them with their timestamps in a
+//! [`FusedAirQuality`] record.
+//!
+//! ```
+//! use sen5x_rs::air_quality::FusedAirQuality;
+//! # let data = sen5x_rs::types::Sen5xData::sample();
+//!
+//! let report = FusedAirQuality::new()
+//!     .with_sen5x(data, 10_000)
+//!     .with_co2(612.0, 9_500)
+//!     .with_pressure(1013.2, 8_000);
+//! assert_eq!(report.reading().co2, Some(612.0));
+//! assert_eq!(report.oldest(), Some(8_000));
+//! assert!(!report.is_fresh(10_000, 1_000));
+//! ```
+use crate::fields::FieldId;
+use crate::types::{Sen5xData, Sen5xDataRaw};
+
+/// Values of an air quality reading. Values not measured by a sensor are `None`.
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub struct AirQualityReading {
+    /// Mass Concentration PM1.0 [μg/m³]
+    pub pm1_0: Option<f32>,
+    /// Mass Concentration PM2.5 [μg/m³]
+    pub pm2_5: Option<f32>,
+    /// Mass Concentration PM4.0 [μg/m³]
+    pub pm4_0: Option<f32>,
+    /// Mass Concentration PM10.0 [μg/m³]
+    pub pm10_0: Option<f32>,
+    /// Ambient Temperature [°C]
+    pub temperature: Option<f32>,
+    /// Ambient Humidity [%RH]
+    pub humidity: Option<f32>,
+    /// VOC Index
+    pub voc_index: Option<f32>,
+    /// NOx Index
+    pub nox_index: Option<f32>,
+    /// CO₂ concentration [ppm]
+    pub co2: Option<f32>,
+}
+
+impl AirQualityReading {
+    /// Combine two readings. Values present in `self` take precedence over those in `other`.
+    pub fn merge(self, other: Self) -> Self {
+        Self {
+            pm1_0: self.pm1_0.or(other.pm1_0),
+            pm2_5: self.pm2_5.or(other.pm2_5),
+            pm4_0: self.pm4_0.or(other.pm4_0),
+            pm10_0: self.pm10_0.or(other.pm10_0),
+            temperature: self.temperature.or(other.temperature),
+            humidity: self.humidity.or(other.humidity),
+            voc_index: self.voc_index.or(other.voc_index),
+            nox_index: self.nox_index.or(other.nox_index),
+            co2: self.co2.or(other.co2),
+        }
+    }
+}
+
+/// Scale an unsigned value, `None` if the sensor reports it as unknown.
+fn unsigned(value: u16, scale: f32) -> Option<f32> {
+    (value != u16::MAX).then(|| f32::from(value) / scale)
+}
+
+/// Scale a signed value, `None` if the sensor reports it as unknown.
+fn signed(value: i16, scale: f32) -> Option<f32> {
+    (value != i16::MAX).then(|| f32::from(value) / scale)
+}
+
+impl From<Sen5xDataRaw> for AirQualityReading {
+    /// Values the sensor reports as unknown, e.g. the gas indices of a SEN50 or while the VOC
+    /// algorithm is starting up, are `None`.
+    fn from(data: Sen5xDataRaw) -> Self {
+        Self {
+            pm1_0: unsigned(data.pm1_0, 10.0),
+            pm2_5: unsigned(data.pm2_5, 10.0),
+            pm4_0: unsigned(data.pm4_0, 10.0),
+            pm10_0: unsigned(data.pm10_0, 10.0),
+            temperature: signed(data.temperature, 200.0),
+            humidity: signed(data.humidity, 100.0),
+            voc_index: signed(data.voc_index, 10.0),
+            nox_index: signed(data.nox_index, 10.0),
+            co2: None,
+        }
+    }
+}
+
+/// A value and the time it was measured at, in units chosen by the application, e.g. ms since
+/// boot.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Stamped<T> {
+    /// The measured value.
+    pub value: T,
+    /// When the value was measured.
+    pub timestamp: u64,
+}
+
+/// A combined record of a SEN5x measurement and values of external sensors.
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub struct FusedAirQuality {
+    /// Measurement of the SEN5x.
+    pub sen5x: Option<Stamped<Sen5xData>>,
+    /// CO₂ concentration [ppm]
+    pub co2: Option<Stamped<f32>>,
+    /// Barometric pressure [hPa]
+    pub pressure: Option<Stamped<f32>>,
+    /// Ambient light [lx]
+    pub ambient_light: Option<Stamped<f32>>,
+}
+
+impl FusedAirQuality {
+    /// An empty record.
+    pub const fn new() -> Self {
+        Self {
+            sen5x: None,
+            co2: None,
+            pressure: None,
+            ambient_light: None,
+        }
+    }
+
+    /// Set the measurement of the SEN5x taken at `timestamp`.
+    pub const fn with_sen5x(self, data: Sen5xData, timestamp: u64) -> Self {
+        Self {
+            sen5x: Some(Stamped {
+                value: data,
+                timestamp,
+            }),
+            ..self
+        }
+    }
+
+    /// Set the CO₂ concentration [ppm] measured at `timestamp`.
+    pub const fn with_co2(self, ppm: f32, timestamp: u64) -> Self {
+        Self {
+            co2: Some(Stamped {
+                value: ppm,
+                timestamp,
+            }),
+            ..self
+        }
+    }
+
+    /// Set the barometric pressure [hPa] measured at `timestamp`.
+    pub const fn with_pressure(self, hpa: f32, timestamp: u64) -> Self {
+        Self {
+            pressure: Some(Stamped {
+                value: hpa,
+                timestamp,
+            }),
+            ..self
+        }
+    }
+
+    /// Set the ambient light [lx] measured at `timestamp`.
+    pub const fn with_ambient_light(self, lux: f32, timestamp: u64) -> Self {
+        Self {
+            ambient_light: Some(Stamped {
+                value: lux,
+                timestamp,
+            }),
+            ..self
+        }
+    }
+
+    /// Timestamps of the values present.
+    fn timestamps(&self) -> impl Iterator<Item = u64> {
+        let sen5x = self.sen5x.map(|sample| sample.timestamp);
+        let others = [self.co2, self.pressure, self.ambient_light];
+        sen5x
+            .into_iter()
+            .chain(others.into_iter().flatten().map(|sample| sample.timestamp))
+    }
+
+    /// The timestamp of the oldest value, `None` if the record is empty.
+    pub fn oldest(&self) -> Option<u64> {
+        self.timestamps().min()
+    }
+
+    /// Whether all values were measured at most `max_age` before `now`.
+    pub fn is_fresh(&self, now: u64, max_age: u64) -> bool {
+        self.timestamps()
+            .all(|timestamp| now.saturating_sub(timestamp) <= max_age)
+    }
+
+    /// The values of the SEN5x and the CO₂ concentration as a reading.
+    pub fn reading(&self) -> AirQualityReading {
+        let data = self.sen5x.map(|sample| sample.value);
+        let value = |id| data.and_then(|data| data.value(id));
+        AirQualityReading {
+            pm1_0: value(FieldId::Pm1_0),
+            pm2_5: value(FieldId::Pm2_5),
+            pm4_0: value(FieldId::Pm4_0),
+            pm10_0: value(FieldId::Pm10_0),
+            temperature: value(FieldId::Temperature),
+            humidity: value(FieldId::Humidity),
+            voc_index: value(FieldId::VocIndex),
+            nox_index: value(FieldId::NoxIndex),
+            co2: self.co2.map(|sample| sample.value),
+        }
+    }
+}
+
+/// A sensor providing air quality readings.
+pub trait AirQualityRead {
+    /// Error returned when the reading failed.
+    type Error;
+
+    /// Read the current values.
+    fn read_air_quality(&mut self) -> Result<AirQualityReading, Self::Error>;
+}
+
+#[cfg(feature = "driver")]
+impl<I2C, D, E> AirQualityRead for crate::Sen5x<I2C, D>
+where
+    I2C: embedded_hal::i2c::I2c<Error = E>,
+    D: embedded_hal::delay::DelayNs,
+{
+    type Error = crate::Error<E>;
+
+    fn read_air_quality(&mut self) -> Result<AirQualityReading, Self::Error> {
+        self.measurement_raw().map(AirQualityReading::from)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_vectors::MEASUREMENTS;
+
+    /// Test reading through the trait and merging with another sensor
+    #[cfg(feature = "driver")]
+    #[test]
+    fn test_read_and_merge() {
+        use crate::Sen5x;
+
+        let sim = crate::simulator::Simulator::new();
+        let mut sensor = Sen5x::new(sim.clone(), sim.delay());
+        sensor.start_measurement().unwrap();
+        sim.advance_ms(1000);
+        let reading = sensor.read_air_quality().unwrap();
+        assert_eq!(reading.pm2_5, Some(MEASUREMENTS[0].data.pm.pm2_5));
+        assert_eq!(reading.co2, None);
+
+        let co2_sensor = AirQualityReading {
+            co2: Some(420.0),
+            temperature: Some(30.0),
+            ..AirQualityReading::default()
+        };
+        let report = reading.merge(co2_sensor);
+        assert_eq!(report.co2, Some(420.0));
+        assert_eq!(report.temperature, reading.temperature);
+    }
+
+    /// Test that the record keeps the timestamp of each source
+    #[test]
+    fn test_fused() {
+        let empty = FusedAirQuality::new();
+        assert_eq!(empty.oldest(), None);
+        assert!(empty.is_fresh(0, 0));
+        assert_eq!(empty.reading(), AirQualityReading::default());
+
+        let data = MEASUREMENTS[0].data;
+        let report = empty
+            .with_ambient_light(120.0, 500)
+            .with_sen5x(data, 2_000)
+            .with_co2(420.0, 1_500);
+        assert_eq!(
+            report.ambient_light.map(|sample| sample.timestamp),
+            Some(500)
+        );
+        assert_eq!(report.oldest(), Some(500));
+        assert!(report.is_fresh(2_000, 1_500));
+        assert!(!report.is_fresh(2_001, 1_500));
+
+        let reading = report.reading();
+        assert_eq!(reading.pm2_5, Some(data.pm.pm2_5));
+        assert_eq!(reading.nox_index, data.gas.and_then(|gas| gas.nox_index));
+        assert_eq!(reading.co2, Some(420.0));
+    }
+
+    /// Test that values reported as unknown are missing
+    #[test]
+    fn test_unknown_values() {
+        let reading = AirQualityReading::from(Sen5xDataRaw {
+            humidity: i16::MAX,
+            temperature: i16::MAX,
+            voc_index: i16::MAX,
+            nox_index: i16::MAX,
+            ..MEASUREMENTS[0].raw
+        });
+        assert_eq!(reading.pm1_0, Some(1.8));
+        assert_eq!(reading.humidity, None);
+        assert_eq!(reading.temperature, None);
+        assert_eq!(reading.voc_index, None);
+        assert_eq!(reading.nox_index, None);
+    }
+}
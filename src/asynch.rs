@@ -0,0 +1,353 @@
+//! Asynchronous driver built on the `embedded-hal-async` traits.
+//!
+//! [`Sen5xAsync`] mirrors the measurement related part of [`crate::Sen5x`], waiting for the
+//! execution time of a command with an asynchronous delay instead of blocking.
+use core::time::Duration;
+
+use embedded_hal_async::{delay::DelayNs, i2c::I2c};
+
+use crate::commands::{Command, WaitStrategy};
+use crate::errors::bus_error;
+use crate::frame::MAX_WORDS;
+use crate::observer::{self, MeasurementObserver};
+use crate::parse;
+use crate::types::{DeviceStatus, ParticulateMatter, Sen5xData, Sen5xDataRaw};
+use crate::Error;
+
+/// The default I²C address of the sensor.
+const SEN5X_I2C_ADDRESS: u8 = 0x69;
+
+/// Initial interval at which [`Sen5xAsync::wait_data_ready`] polls the data-ready flag [ms].
+const DATA_READY_BACKOFF_MIN_MS: u32 = 10;
+
+/// Longest interval at which [`Sen5xAsync::wait_data_ready`] polls the data-ready flag [ms].
+const DATA_READY_BACKOFF_MAX_MS: u32 = 250;
+
+/// Asynchronous SEN5x sensor instance.
+#[derive(Debug, Default)]
+pub struct Sen5xAsync<I2C, D> {
+    /// The concrete I²C device implementation.
+    i2c: I2C,
+    /// The concrete Delay implementation.
+    delay: D,
+    /// Whether the periodic measurement is running.
+    is_running: bool,
+    /// The I²C address of the sensor.
+    address: u8,
+    /// How long to wait after issuing a command.
+    wait: WaitStrategy,
+    /// Additional delay between the write and the read phase of a command [µs].
+    guard_us: u32,
+    /// Receives the new samples.
+    measurement_observer: Option<&'static dyn MeasurementObserver>,
+}
+
+impl<I2C, D, E> Sen5xAsync<I2C, D>
+where
+    I2C: I2c<Error = E>,
+    D: DelayNs,
+{
+    /// Create a new instance using the default I2C address.
+    pub fn new(i2c: I2C, delay: D) -> Self {
+        Self::with_i2c_address(i2c, delay, SEN5X_I2C_ADDRESS)
+    }
+
+    /// Create a new instance using a custom I2C address.
+    pub fn with_i2c_address(i2c: I2C, delay: D, address: u8) -> Self {
+        Self {
+            i2c,
+            delay,
+            is_running: false,
+            address,
+            wait: WaitStrategy::Datasheet,
+            guard_us: 0,
+            measurement_observer: None,
+        }
+    }
+
+    /// The I2C address of the sensor.
+    pub fn address(&self) -> u8 {
+        self.address
+    }
+
+    /// Mutable access to the I²C bus, e.g. to talk to other devices between measurements.
+    pub fn i2c_mut(&mut self) -> &mut I2C {
+        &mut self.i2c
+    }
+
+    /// Mutable access to the delay provider.
+    pub fn delay_mut(&mut self) -> &mut D {
+        &mut self.delay
+    }
+
+    /// Set how long to wait after issuing a command.
+    pub fn set_wait_strategy(&mut self, wait: WaitStrategy) {
+        self.wait = wait;
+    }
+
+    /// Wait for `delay` in addition to the execution time between writing a command and reading
+    /// its response, rounded up to whole microseconds. See [`crate::Sen5x::set_guard_delay`].
+    pub fn set_guard_delay(&mut self, delay: impl Into<Duration>) {
+        self.guard_us = crate::sen5x::guard_us(delay.into());
+    }
+
+    /// The additional delay between writing a command and reading its response.
+    pub fn guard_delay(&self) -> Duration {
+        Duration::from_micros(self.guard_us.into())
+    }
+
+    /// Register `observer` to receive every sample read and the errors of the reads, or remove it
+    /// with `None`. See [`crate::observer`].
+    pub fn set_measurement_observer(&mut self, observer: Option<&'static dyn MeasurementObserver>) {
+        self.measurement_observer = observer;
+    }
+
+    /// Whether the periodic measurement was started by this driver.
+    pub fn is_measuring(&self) -> bool {
+        self.is_running
+    }
+
+    /// Start periodic measurement, signal update interval is 1 second.
+    pub async fn start_measurement(&mut self) -> Result<(), Error<E>> {
+        self.write_command(Command::StartMeasurement).await?;
+        self.is_running = true;
+        Ok(())
+    }
+
+    /// Stop periodic measurement and return to idle mode.
+    pub async fn stop_measurement(&mut self) -> Result<(), Error<E>> {
+        self.write_command(Command::StopMeasurement).await?;
+        self.is_running = false;
+        Ok(())
+    }
+
+    /// Check whether new measurement data is available for read-out.
+    pub async fn data_ready_status(&mut self) -> Result<bool, Error<E>> {
+        self.read_frame(Command::GetReadDataReadyStatus)
+            .await
+            .map(parse::data_ready_words)
+    }
+
+    /// Wait until new measurement data is available for read-out.
+    ///
+    /// The data-ready flag is polled with an exponentially growing interval, starting at 10 ms
+    /// and capped at 250 ms. Returns `Error::Timeout` if no data got ready within `timeout`.
+    pub async fn wait_data_ready(&mut self, timeout: impl Into<Duration>) -> Result<(), Error<E>> {
+        let timeout = timeout.into();
+        let poll =
+            Duration::from_millis(self.wait.delay_ms(Command::GetReadDataReadyStatus).into());
+        let mut waited = Duration::ZERO;
+        let mut backoff = Duration::from_millis(DATA_READY_BACKOFF_MIN_MS.into());
+        loop {
+            if self.data_ready_status().await? {
+                return Ok(());
+            }
+            waited = waited.saturating_add(poll);
+            if waited >= timeout {
+                return Err(Error::Timeout);
+            }
+            // The step is at most the maximum backoff and always fits.
+            let step = backoff.min(timeout.saturating_sub(waited));
+            let step_us = u32::try_from(step.as_micros()).unwrap_or(u32::MAX);
+            self.delay.delay_us(step_us).await;
+            waited = waited.saturating_add(step);
+            backoff = backoff
+                .saturating_mul(2)
+                .min(Duration::from_millis(DATA_READY_BACKOFF_MAX_MS.into()));
+        }
+    }
+
+    /// Read raw sensor data.
+    pub async fn measurement_raw(&mut self) -> Result<Sen5xDataRaw, Error<E>> {
+        self.read_frame(Command::ReadMeasurement)
+            .await
+            .map(parse::measurement_words)
+    }
+
+    /// Read converted sensor data.
+    pub async fn measurement(&mut self) -> Result<Sen5xData, Error<E>> {
+        let result = self.measurement_raw().await.map(Sen5xData::from);
+        self.observe(&result, |observer, data| observer.on_measurement(data));
+        result
+    }
+
+    /// Read only the mass concentrations, see [`crate::Sen5x::measurement_pm`].
+    pub async fn measurement_pm(&mut self) -> Result<ParticulateMatter, Error<E>> {
+        let result = self
+            .read_frame(Command::ReadMeasurement)
+            .await
+            .map(ParticulateMatter::from_words);
+        self.observe(&result, |observer, &pm| {
+            observer.on_measurement(&Sen5xData {
+                pm,
+                env: None,
+                gas: None,
+            });
+        });
+        result
+    }
+
+    /// Forward the new sample of `result`, with `sample`, or its error to the measurement observer.
+    pub(crate) fn observe<T>(
+        &self,
+        result: &Result<T, Error<E>>,
+        sample: impl FnOnce(&dyn MeasurementObserver, &T),
+    ) {
+        observer::notify(self.measurement_observer, result, sample);
+    }
+
+    /// Read the device status register.
+    pub async fn device_status(&mut self) -> Result<DeviceStatus, Error<E>> {
+        self.read_frame(Command::ReadDeviceStatus)
+            .await
+            .map(parse::device_status_words)
+    }
+
+    /// Writes commands without additional arguments.
+    async fn write_command(&mut self, cmd: Command) -> Result<(), Error<E>> {
+        let spec = cmd.spec();
+        if !spec.availability.allows(self.is_running) {
+            return Err(Error::NotAllowed);
+        }
+        self.i2c
+            .write(self.address, &spec.code.to_be_bytes())
+            .await
+            .map_err(bus_error::<I2C>)?;
+        let delay = self.wait.delay_ms(cmd);
+        if delay > 0 {
+            self.delay.delay_ms(delay).await;
+        }
+        Ok(())
+    }
+
+    /// Issue `cmd` and read back `WORDS` words, validating the CRC of each of them.
+    async fn read_frame<const WORDS: usize>(
+        &mut self,
+        cmd: Command,
+    ) -> Result<[u16; WORDS], Error<E>> {
+        const { assert!(WORDS <= MAX_WORDS, "responses hold at most 16 words") };
+        if WORDS == 0 || WORDS > cmd.response_words() {
+            return Err(Error::WrongBufferSize);
+        }
+        let mut buf = [0u8; MAX_WORDS * 3];
+        let frame = buf.get_mut(..WORDS * 3).ok_or(Error::WrongBufferSize)?;
+        self.write_command(cmd).await?;
+        if self.guard_us > 0 {
+            self.delay.delay_us(self.guard_us).await;
+        }
+        self.i2c
+            .read(self.address, frame)
+            .await
+            .map_err(bus_error::<I2C>)?;
+        parse::words(frame)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use core::future::Future;
+    use core::pin::pin;
+    use core::task::{Context, Poll, Waker};
+
+    use super::*;
+    use crate::simulator::Simulator;
+    use crate::test_vectors::MEASUREMENTS;
+
+    /// Run `future` to completion on the current thread.
+    pub(crate) fn block_on<F: Future>(future: F) -> F::Output {
+        struct ThreadWaker(std::thread::Thread);
+
+        impl std::task::Wake for ThreadWaker {
+            fn wake(self: std::sync::Arc<Self>) {
+                self.0.unpark();
+            }
+        }
+
+        let waker = Waker::from(std::sync::Arc::new(ThreadWaker(std::thread::current())));
+        let mut context = Context::from_waker(&waker);
+        let mut future = pin!(future);
+        loop {
+            match future.as_mut().poll(&mut context) {
+                Poll::Ready(output) => return output,
+                Poll::Pending => std::thread::park(),
+            }
+        }
+    }
+
+    /// Test a measurement against the simulator
+    #[test]
+    fn test_measurement() {
+        let sim = Simulator::new();
+        let mut sensor = Sen5xAsync::new(sim.clone(), sim.delay());
+        block_on(async {
+            sensor.start_measurement().await.unwrap();
+            sim.advance_ms(1000);
+            assert!(sensor.data_ready_status().await.unwrap());
+            assert_eq!(sensor.measurement().await.unwrap(), MEASUREMENTS[0].data);
+            assert!(!sensor.data_ready_status().await.unwrap());
+            sensor.stop_measurement().await.unwrap();
+        });
+        assert!(!sensor.is_measuring());
+    }
+
+    /// Test waiting for new data with a deadline
+    #[test]
+    fn test_wait_data_ready() {
+        let sim = Simulator::new();
+        let mut sensor = Sen5xAsync::new(sim.clone(), sim.delay());
+        block_on(async {
+            let start = sim.now_ms();
+            assert_eq!(
+                sensor.wait_data_ready(Duration::from_millis(500)).await,
+                Err(Error::Timeout)
+            );
+            assert!((500..600).contains(&(sim.now_ms() - start)));
+
+            sensor.start_measurement().await.unwrap();
+            sensor
+                .wait_data_ready(Duration::from_secs(2))
+                .await
+                .unwrap();
+            assert!(sensor.data_ready_status().await.unwrap());
+
+            sensor.measurement().await.unwrap();
+            sensor.wait_data_ready(Duration::MAX).await.unwrap();
+        });
+    }
+
+    /// Test that the samples and the errors of the reads are reported to the observer
+    #[test]
+    fn test_measurement_observer() {
+        use core::sync::atomic::{AtomicU32, Ordering};
+
+        static SAMPLES: AtomicU32 = AtomicU32::new(0);
+        static ERRORS: AtomicU32 = AtomicU32::new(0);
+
+        struct Counter;
+
+        impl MeasurementObserver for Counter {
+            fn on_measurement(&self, data: &Sen5xData) {
+                assert_eq!(data.pm, MEASUREMENTS[0].data.pm);
+                SAMPLES.fetch_add(1, Ordering::Relaxed);
+            }
+
+            fn on_error(&self, _: &Error<()>) {
+                ERRORS.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+
+        let sim = Simulator::new();
+        let mut sensor = Sen5xAsync::new(sim.clone(), sim.delay());
+        sensor.set_measurement_observer(Some(&Counter));
+        block_on(async {
+            sensor.start_measurement().await.unwrap();
+            sim.advance_ms(1000);
+            sensor.measurement().await.unwrap();
+            sensor.measurement_pm().await.unwrap();
+            sim.nack_transaction(0);
+            assert!(sensor.measurement().await.is_err());
+        });
+        assert_eq!(SAMPLES.load(Ordering::Relaxed), 2);
+        assert_eq!(ERRORS.load(Ordering::Relaxed), 1);
+    }
+}
@@ -0,0 +1,222 @@
+//! Diagnostic tool for SEN5x sensors attached to a Linux I²C bus.
+//!
+//! ```text
+//! sen5x [--bus /dev/i2c-1] [--address 0x69] <command>
+//! ```
+//!
+//! Build with `cargo run --features cli -- <command>`.
+use std::process::ExitCode;
+use std::thread;
+use std::time::Duration;
+
+use linux_embedded_hal::{Delay, I2cdev};
+use sen5x_rs::csv::Csv;
+use sen5x_rs::Sen5x;
+
+const USAGE: &str = "\
+usage: sen5x [--bus PATH] [--address ADDR] <command>
+
+options:
+    --bus PATH        I2C bus device (default /dev/i2c-1)
+    --address ADDR    I2C address of the sensor (default 0x69)
+
+commands:
+    probe             check whether the sensor responds
+    info              print product name, serial number and firmware version
+    measure [COUNT]   print COUNT measurements (default: until interrupted)
+    clean             run the fan cleaning and wait for it to finish
+    status            print the device status
+    clear-status      print and clear the device status";
+
+const DEFAULT_BUS: &str = "/dev/i2c-1";
+const DEFAULT_ADDRESS: u8 = 0x69;
+
+/// Duration of the fan cleaning according to the datasheet.
+const FAN_CLEANING: Duration = Duration::from_secs(10);
+
+type Sensor = Sen5x<I2cdev, Delay>;
+type Error = sen5x_rs::Error<linux_embedded_hal::I2CError>;
+
+#[derive(Debug, PartialEq)]
+struct Args {
+    bus: String,
+    address: u8,
+    command: String,
+    count: Option<u32>,
+}
+
+fn parse_address(value: &str) -> Option<u8> {
+    match value
+        .strip_prefix("0x")
+        .or_else(|| value.strip_prefix("0X"))
+    {
+        Some(hex) => u8::from_str_radix(hex, 16).ok(),
+        None => value.parse().ok(),
+    }
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Args, String> {
+    let mut bus = DEFAULT_BUS.to_owned();
+    let mut address = DEFAULT_ADDRESS;
+    let mut positional = Vec::new();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--bus" => bus = args.next().ok_or("--bus requires a value")?,
+            "--address" => {
+                let value = args.next().ok_or("--address requires a value")?;
+                address = parse_address(&value).ok_or(format!("invalid address {value}"))?;
+            }
+            "-h" | "--help" => return Err(String::new()),
+            _ if arg.starts_with('-') => return Err(format!("unknown option {arg}")),
+            _ => positional.push(arg),
+        }
+    }
+    let mut positional = positional.into_iter();
+    let command = positional.next().ok_or("missing command")?;
+    let count = match positional.next() {
+        Some(count) if command == "measure" => Some(
+            count
+                .parse()
+                .map_err(|_| format!("invalid count {count}"))?,
+        ),
+        Some(arg) => return Err(format!("unexpected argument {arg}")),
+        None => None,
+    };
+    Ok(Args {
+        bus,
+        address,
+        command,
+        count,
+    })
+}
+
+fn print_status(sensor: &mut Sensor, clear: bool) -> Result<(), Error> {
+    let status = if clear {
+        sensor.device_status_and_clear()?
+    } else {
+        sensor.device_status()?
+    };
+    println!("status:                  {:#010x}", status.bits());
+    println!("fan speed warning:       {}", status.fan_speed_warning());
+    println!("fan cleaning:            {}", status.fan_cleaning());
+    println!("gas sensor error:        {}", status.gas_sensor_error());
+    println!(
+        "RHT communication error: {}",
+        status.rht_communication_error()
+    );
+    println!("laser failure:           {}", status.laser_failure());
+    println!("fan failure:             {}", status.fan_failure());
+    Ok(())
+}
+
+fn measure(sensor: &mut Sensor, count: Option<u32>) -> Result<(), Error> {
+    sensor.start_measurement()?;
+    let csv = Csv::new();
+    println!("{}", csv.header());
+    let mut printed = 0;
+    while count.is_none_or(|count| printed < count) {
+        if !sensor.data_ready_status()? {
+            thread::sleep(Duration::from_millis(100));
+            continue;
+        }
+        let data = sensor.measurement()?;
+        println!("{}", csv.row(&data));
+        printed += 1;
+    }
+    sensor.stop_measurement()
+}
+
+fn run(sensor: &mut Sensor, args: &Args) -> Result<(), Error> {
+    match args.command.as_str() {
+        "probe" => {
+            let firmware = sensor.read_firmware_version()?;
+            println!(
+                "sensor responding at {:#04x}, firmware {firmware}",
+                sensor.address()
+            );
+        }
+        "info" => {
+            let info = sensor.identify()?;
+            println!("product:  {}", info.product_name_str().unwrap_or("?"));
+            println!("serial:   {}", info.serial_number);
+            println!("firmware: {}", info.firmware_version);
+        }
+        "measure" => measure(sensor, args.count)?,
+        "clean" => {
+            sensor.start_measurement()?;
+            sensor.start_fan_cleaning_blocking(FAN_CLEANING)?;
+            sensor.stop_measurement()?;
+            println!("fan cleaning finished");
+        }
+        "status" => print_status(sensor, false)?,
+        "clear-status" => print_status(sensor, true)?,
+        _ => unreachable!(),
+    }
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    let args = match parse_args(std::env::args().skip(1)) {
+        Ok(args)
+            if matches!(
+                args.command.as_str(),
+                "probe" | "info" | "measure" | "clean" | "status" | "clear-status"
+            ) =>
+        {
+            args
+        }
+        Ok(args) => {
+            eprintln!("unknown command {}\n\n{USAGE}", args.command);
+            return ExitCode::from(2);
+        }
+        Err(message) => {
+            if !message.is_empty() {
+                eprintln!("{message}\n");
+            }
+            eprintln!("{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+    let i2c = match I2cdev::new(&args.bus) {
+        Ok(i2c) => i2c,
+        Err(e) => {
+            eprintln!("failed to open {}: {e}", args.bus);
+            return ExitCode::FAILURE;
+        }
+    };
+    let mut sensor = Sen5x::with_i2c_address(i2c, Delay, args.address);
+    match run(&mut sensor, &args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {e:?}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Args, String> {
+        parse_args(args.iter().map(|arg| arg.to_string()))
+    }
+
+    /// Test parsing of options and commands
+    #[test]
+    fn test_parse_args() {
+        assert_eq!(
+            parse(&["--bus", "/dev/i2c-3", "--address", "0x6a", "measure", "5"]),
+            Ok(Args {
+                bus: "/dev/i2c-3".to_owned(),
+                address: 0x6A,
+                command: "measure".to_owned(),
+                count: Some(5),
+            })
+        );
+        assert_eq!(parse(&["info"]).unwrap().address, DEFAULT_ADDRESS);
+        assert!(parse(&[]).is_err());
+        assert!(parse(&["info", "5"]).is_err());
+        assert!(parse(&["--address", "0x100", "info"]).is_err());
+    }
+}
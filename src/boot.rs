@@ -0,0 +1,249 @@
+//! A boot sequence resilient against brown-outs.
+//!
+//! After a brown-out the sensor may be in any state: still measuring, busy or briefly not
+//! responding. [`Sen5x::init_robust`] resets it, waits, identifies it, restores the persisted
+//! configuration and VOC algorithm state and starts the measurement, retrying every step. The
+//! [`InitReport`] tells what was restored and how many retries were needed.
+//!
+//! ```
+//! use embedded_hal::{delay::DelayNs, i2c::I2c};
+//! use sen5x_rs::boot::{InitError, InitOptions, InitReport};
+//! use sen5x_rs::types::{Config, VocAlgorithmState};
+//! use sen5x_rs::Sen5x;
+//!
+//! fn boot<I2C: I2c, D: DelayNs>(
+//!     sensor: &mut Sen5x<I2C, D>,
+//!     config_blob: &[u8],
+//!     voc_blob: &[u8],
+//! ) -> Result<InitReport, InitError<I2C::Error>> {
+//!     let mut options = InitOptions::new();
+//!     if let Ok(config) = Config::from_blob(config_blob) {
+//!         options = options.with_config(config);
+//!     }
+//!     if let Ok(state) = VocAlgorithmState::from_blob(voc_blob) {
+//!         options = options.with_voc_state(state);
+//!     }
+//!     sensor.init_robust(&options)
+//! }
+//! ```
+use core::time::Duration;
+
+use embedded_hal::{delay::DelayNs, i2c::I2c};
+
+use crate::types::{Config, DeviceInfo, DeviceStatus, VocAlgorithmState};
+use crate::{Error, Sen5x};
+
+/// A step of [`Sen5x::init_robust`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum InitStep {
+    /// Resetting the sensor.
+    Reset,
+    /// Reading the product name, serial number and firmware version.
+    Probe,
+    /// Restoring the configuration.
+    Config,
+    /// Restoring the VOC algorithm state.
+    VocState,
+    /// Starting the measurement.
+    Start,
+}
+
+/// What [`Sen5x::init_robust`] does.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct InitOptions {
+    attempts: u8,
+    retry_delay: Duration,
+    config: Option<Config>,
+    voc_state: Option<VocAlgorithmState>,
+    start: bool,
+}
+
+impl Default for InitOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl InitOptions {
+    /// Three attempts per step, 100 ms apart, nothing to restore and starting the measurement.
+    pub const fn new() -> Self {
+        Self {
+            attempts: 3,
+            retry_delay: Duration::from_millis(100),
+            config: None,
+            voc_state: None,
+            start: true,
+        }
+    }
+
+    /// Try every step up to `attempts` times, at least once.
+    pub const fn with_attempts(self, attempts: u8) -> Self {
+        Self { attempts, ..self }
+    }
+
+    /// Wait `retry_delay` before retrying a step.
+    pub const fn with_retry_delay(self, retry_delay: Duration) -> Self {
+        Self {
+            retry_delay,
+            ..self
+        }
+    }
+
+    /// Restore `config`, e.g. decoded with [`Config::from_blob`].
+    pub const fn with_config(self, config: Config) -> Self {
+        Self {
+            config: Some(config),
+            ..self
+        }
+    }
+
+    /// Restore `voc_state`, e.g. decoded with [`VocAlgorithmState::from_blob`].
+    pub const fn with_voc_state(self, voc_state: VocAlgorithmState) -> Self {
+        Self {
+            voc_state: Some(voc_state),
+            ..self
+        }
+    }
+
+    /// Leave the sensor in idle mode instead of starting the measurement.
+    pub const fn without_start(self) -> Self {
+        Self {
+            start: false,
+            ..self
+        }
+    }
+}
+
+/// Outcome of [`Sen5x::init_robust`].
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct InitReport {
+    /// Identification of the sensor.
+    pub info: DeviceInfo,
+    /// Device status flags raised before the reset, e.g. a fan error which caused the restart.
+    pub status: DeviceStatus,
+    /// Whether the configuration was restored.
+    pub config_restored: bool,
+    /// Whether the VOC algorithm state was restored.
+    pub voc_state_restored: bool,
+    /// Whether the measurement was started.
+    pub measuring: bool,
+    /// Retries needed in total, `0` if every step succeeded at the first attempt.
+    pub retries: u32,
+}
+
+/// A step of [`Sen5x::init_robust`] failed in every attempt.
+#[derive(Debug, PartialEq)]
+pub struct InitError<E> {
+    /// The step which failed.
+    pub step: InitStep,
+    /// The error of the last attempt.
+    pub error: Error<E>,
+}
+
+impl<I2C, D, E> Sen5x<I2C, D>
+where
+    I2C: I2c<Error = E>,
+    D: DelayNs,
+{
+    /// Bring the sensor from any state into a known one, retrying each step.
+    ///
+    /// Reads and clears the device status, resets the sensor, identifies it, restores the
+    /// configuration and VOC algorithm state of `options` and starts the measurement. The status
+    /// is read before the reset, which clears it, and is not retried.
+    pub fn init_robust(&mut self, options: &InitOptions) -> Result<InitReport, InitError<E>> {
+        let mut retries = 0;
+        let status = self.device_status_and_clear().unwrap_or_default();
+        self.retry(options, &mut retries, InitStep::Reset, Self::reinit)?;
+        let info = self.retry(options, &mut retries, InitStep::Probe, Self::identify)?;
+        if let Some(config) = &options.config {
+            self.retry(options, &mut retries, InitStep::Config, |sensor| {
+                sensor.set_config(config)
+            })?;
+        }
+        if let Some(state) = options.voc_state {
+            self.retry(options, &mut retries, InitStep::VocState, |sensor| {
+                sensor.set_voc_algorithm_state(state)
+            })?;
+        }
+        if options.start {
+            self.retry(
+                options,
+                &mut retries,
+                InitStep::Start,
+                Self::start_measurement,
+            )?;
+        }
+        Ok(InitReport {
+            info,
+            status,
+            config_restored: options.config.is_some(),
+            voc_state_restored: options.voc_state.is_some(),
+            measuring: options.start,
+            retries,
+        })
+    }
+
+    /// Run `f` up to the attempts of `options`, counting the retries in `retries`.
+    fn retry<T>(
+        &mut self,
+        options: &InitOptions,
+        retries: &mut u32,
+        step: InitStep,
+        mut f: impl FnMut(&mut Self) -> Result<T, Error<E>>,
+    ) -> Result<T, InitError<E>> {
+        let mut attempt = 1;
+        loop {
+            match f(self) {
+                Ok(value) => return Ok(value),
+                Err(error) if attempt >= options.attempts => {
+                    return Err(InitError { step, error });
+                }
+                Err(_) => {
+                    attempt += 1;
+                    *retries += 1;
+                    let delay_us =
+                        u32::try_from(options.retry_delay.as_micros()).unwrap_or(u32::MAX);
+                    self.delay_mut().delay_us(delay_us);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulator::Simulator;
+    use crate::types::AutoCleanInterval;
+
+    /// Test that failing steps are retried and reported
+    #[test]
+    fn test_init_robust() {
+        let sim = Simulator::new();
+        let mut sensor = Sen5x::new(sim.clone(), sim.delay());
+        sensor.start_measurement().unwrap();
+        sim.raise_status(DeviceStatus::FAN_SPEED_WARNING);
+
+        let config = Config {
+            auto_clean_interval: AutoCleanInterval::Disabled,
+            ..Config::default()
+        };
+        let options = InitOptions::new().with_config(config);
+        // The status is read in two transactions, the reinit command fails once.
+        sim.nack_transaction(2);
+        let report = sensor.init_robust(&options).unwrap();
+        assert!(report.status.fan_speed_warning());
+        assert_eq!(report.info.product_name_str(), Some("SEN55"));
+        assert!(report.config_restored && !report.voc_state_restored && report.measuring);
+        assert_eq!(report.retries, 1);
+        assert!(sensor.is_measuring());
+        sensor.stop_measurement().unwrap();
+        assert_eq!(sensor.config().unwrap(), config);
+
+        let options = InitOptions::new().with_attempts(2).without_start();
+        sim.set_address(0x10);
+        let error = sensor.init_robust(&options).unwrap_err();
+        assert_eq!(error.step, InitStep::Reset);
+        assert!(matches!(error.error, Error::Nack(_)));
+    }
+}
@@ -0,0 +1,172 @@
+//! A monotonic clock for the time-based parts of the crate.
+//!
+//! Parts driven by elapsed time, e.g. [`StateSaver`](crate::saver::StateSaver) and
+//! [`ComplianceTracker`](crate::compliance::ComplianceTracker), take the time as an argument
+//! instead of reading a timer, so they are portable across HALs and executors. A [`Clock`] is the
+//! single place the time comes from, and a [`Stopwatch`] turns it into the time elapsed between
+//! calls of a loop.
+//!
+//! Adapters are provided for closures ([`FnClock`]), for `embassy-time` with the `embassy`
+//! feature, for `fugit` instants of RTIC monotonics with the `fugit` feature and for
+//! `std::time::Instant` with the `std` feature.
+//!
+//! ```
+//! use core::cell::Cell;
+//! use core::time::Duration;
+//! use sen5x_rs::clock::{FnClock, Stopwatch};
+//!
+//! let ticks_ms = Cell::new(0u64);
+//! let clock = FnClock(|| Duration::from_millis(ticks_ms.get()));
+//! let mut stopwatch = Stopwatch::new();
+//! assert_eq!(stopwatch.lap(&clock), Duration::ZERO);
+//! ticks_ms.set(1500);
+//! assert_eq!(stopwatch.lap(&clock), Duration::from_millis(1500));
+//! ```
+use core::time::Duration;
+
+/// A monotonic source of time.
+pub trait Clock {
+    /// The time since a fixed epoch, e.g. the start of the device. Must never decrease.
+    fn now(&self) -> Duration;
+
+    /// The time since `earlier`, a value of [`Clock::now`], zero if it is in the future.
+    fn elapsed_since(&self, earlier: Duration) -> Duration {
+        self.now().saturating_sub(earlier)
+    }
+}
+
+impl<C: Clock + ?Sized> Clock for &C {
+    fn now(&self) -> Duration {
+        (**self).now()
+    }
+}
+
+/// A clock reading the time from a closure, e.g. wrapping the tick counter of a HAL.
+#[derive(Debug, Copy, Clone)]
+pub struct FnClock<F>(pub F);
+
+impl<F: Fn() -> Duration> Clock for FnClock<F> {
+    fn now(&self) -> Duration {
+        (self.0)()
+    }
+}
+
+/// The clock of `embassy-time`.
+#[cfg(feature = "embassy")]
+#[derive(Debug, Copy, Clone, Default)]
+pub struct EmbassyClock;
+
+#[cfg(feature = "embassy")]
+impl Clock for EmbassyClock {
+    fn now(&self) -> Duration {
+        Duration::from_micros(embassy_time::Instant::now().as_micros())
+    }
+}
+
+/// A clock reading `fugit` instants from a closure, e.g. `|| Mono::now()` of an RTIC monotonic.
+#[cfg(feature = "fugit")]
+#[derive(Debug, Copy, Clone)]
+pub struct FugitClock<F>(pub F);
+
+#[cfg(feature = "fugit")]
+impl<F, const NOM: u64, const DENOM: u64> Clock for FugitClock<F>
+where
+    F: Fn() -> fugit::MonotonicInstant<u64, NOM, DENOM>,
+{
+    fn now(&self) -> Duration {
+        (self.0)().duration_since_epoch().into()
+    }
+}
+
+/// A clock of `std::time::Instant`, counting from its creation.
+#[cfg(feature = "std")]
+#[derive(Debug, Copy, Clone)]
+pub struct StdClock {
+    start: std::time::Instant,
+}
+
+#[cfg(feature = "std")]
+impl Default for StdClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "std")]
+impl StdClock {
+    /// A clock starting now.
+    pub fn new() -> Self {
+        Self {
+            start: std::time::Instant::now(),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl Clock for StdClock {
+    fn now(&self) -> Duration {
+        self.start.elapsed()
+    }
+}
+
+/// Measures the time between successive calls, e.g. of a measurement loop.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub struct Stopwatch {
+    last: Option<Duration>,
+}
+
+impl Stopwatch {
+    /// A stopwatch which has not been started.
+    pub const fn new() -> Self {
+        Self { last: None }
+    }
+
+    /// The time since the previous lap, zero on the first one.
+    pub fn lap(&mut self, clock: &impl Clock) -> Duration {
+        let now = clock.now();
+        let elapsed = self
+            .last
+            .map_or(Duration::ZERO, |last| now.saturating_sub(last));
+        self.last = Some(now);
+        elapsed
+    }
+
+    /// Stop the stopwatch, so the next lap is zero again.
+    pub fn reset(&mut self) {
+        self.last = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::cell::Cell;
+
+    /// Test that laps measure the time between calls and never go backwards
+    #[test]
+    fn test_stopwatch() {
+        let now = Cell::new(Duration::from_secs(10));
+        let clock = FnClock(|| now.get());
+        let mut stopwatch = Stopwatch::new();
+        assert_eq!(stopwatch.lap(&clock), Duration::ZERO);
+        now.set(Duration::from_secs(12));
+        assert_eq!(stopwatch.lap(&&clock), Duration::from_secs(2));
+        now.set(Duration::from_secs(11));
+        assert_eq!(stopwatch.lap(&clock), Duration::ZERO);
+        assert_eq!(
+            clock.elapsed_since(Duration::from_secs(5)),
+            Duration::from_secs(6)
+        );
+
+        stopwatch.reset();
+        assert_eq!(stopwatch.lap(&clock), Duration::ZERO);
+    }
+
+    /// Test that fugit instants are converted from their time base
+    #[cfg(feature = "fugit")]
+    #[test]
+    fn test_fugit_clock() {
+        let clock = FugitClock(|| fugit::MonotonicInstant::<u64, 1, 32_768>::from_ticks(49_152));
+        assert_eq!(clock.now(), Duration::from_millis(1500));
+    }
+}
@@ -1,3 +1,5 @@
+use core::time::Duration;
+
 #[derive(Debug, Copy, Clone)]
 /// List of SEN5x sensor commands.
 /// [Datasheet](https://sensirion.com/media/documents/6791EFA0/62A1F68F/Sensirion_Datasheet_Environmental_Node_SEN5x.pdf) (page 18, ch6.1).
@@ -21,21 +23,381 @@ pub enum Command {
     StartFanCleaning,
     /// Gets firmware version.
     ReadFirmwareVersion,
+    /// Reads the device status register.
+    ReadDeviceStatus,
+    /// Reads the device status register and clears all flags afterwards.
+    ReadAndClearDeviceStatus,
+    /// Reads or writes the interval of the periodic fan cleaning [s].
+    AutoCleaningInterval,
+    /// Reads or writes the tuning parameters of the VOC algorithm.
+    VocTuningParameters,
+    /// Reads or writes the tuning parameters of the NOx algorithm.
+    NoxTuningParameters,
+    /// Reads or writes the temperature compensation parameters.
+    TemperatureCompensation,
+    /// Reads or writes the state of the VOC algorithm.
+    VocAlgorithmState,
+    /// Reads the uncompensated humidity and temperature and the raw signals of the gas sensors.
+    /// Requires firmware version 2.
+    ReadRawValues,
+}
+
+/// Execution time of [`Command::StartMeasurement`] [ms].
+pub const START_MEASUREMENT_MS: u32 = 50;
+/// Execution time of [`Command::StopMeasurement`] [ms].
+pub const STOP_MEASUREMENT_MS: u32 = 200;
+/// Execution time of [`Command::GetReadDataReadyStatus`] [ms].
+pub const GET_READ_DATA_READY_STATUS_MS: u32 = 20;
+/// Execution time of [`Command::GetSerialNumber`] [ms].
+pub const GET_SERIAL_NUMBER_MS: u32 = 20;
+/// Execution time of [`Command::ReadProductName`] [ms].
+pub const READ_PRODUCT_NAME_MS: u32 = 20;
+/// Execution time of [`Command::ReadMeasurement`] [ms].
+pub const READ_MEASUREMENT_MS: u32 = 20;
+/// Execution time of [`Command::Reinit`] [ms].
+pub const REINIT_MS: u32 = 100;
+/// Execution time of [`Command::StartFanCleaning`] [ms].
+pub const START_FAN_CLEANING_MS: u32 = 20;
+/// Execution time of [`Command::ReadFirmwareVersion`] [ms].
+pub const READ_FIRMWARE_VERSION_MS: u32 = 20;
+/// Execution time of [`Command::ReadDeviceStatus`] [ms].
+pub const READ_DEVICE_STATUS_MS: u32 = 20;
+/// Execution time of [`Command::ReadAndClearDeviceStatus`] [ms].
+pub const READ_AND_CLEAR_DEVICE_STATUS_MS: u32 = 20;
+/// Execution time of [`Command::AutoCleaningInterval`] [ms].
+pub const AUTO_CLEANING_INTERVAL_MS: u32 = 20;
+/// Execution time of [`Command::VocTuningParameters`] [ms].
+pub const VOC_TUNING_PARAMETERS_MS: u32 = 20;
+/// Execution time of [`Command::NoxTuningParameters`] [ms].
+pub const NOX_TUNING_PARAMETERS_MS: u32 = 20;
+/// Execution time of [`Command::TemperatureCompensation`] [ms].
+pub const TEMPERATURE_COMPENSATION_MS: u32 = 20;
+/// Execution time of [`Command::VocAlgorithmState`] [ms].
+pub const VOC_ALGORITHM_STATE_MS: u32 = 20;
+/// Execution time of [`Command::ReadRawValues`] [ms].
+pub const READ_RAW_VALUES_MS: u32 = 20;
+
+/// Clock of the I²C bus in standard mode [Hz].
+pub const STANDARD_MODE_HZ: u32 = 100_000;
+/// Clock of the I²C bus in fast mode [Hz].
+pub const FAST_MODE_HZ: u32 = 400_000;
+
+/// Operating modes in which a command can be executed.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Availability {
+    /// In idle and measurement mode.
+    Always,
+    /// Only in idle mode. The driver rejects the command with `Error::NotAllowed` while it runs a
+    /// measurement.
+    Idle,
+    /// Only in measurement mode. The driver rejects the command with `Error::NotAllowed` while
+    /// the sensor is idle.
+    Measuring,
+}
+
+impl Availability {
+    /// Whether the command can be executed while the measurement is running.
+    pub const fn while_measuring(self) -> bool {
+        !matches!(self, Self::Idle)
+    }
+
+    /// Whether the command can be executed in idle mode.
+    pub const fn while_idle(self) -> bool {
+        !matches!(self, Self::Measuring)
+    }
+
+    /// Whether the command can be executed in measurement mode if `measuring`, else in idle mode.
+    pub const fn allows(self, measuring: bool) -> bool {
+        if measuring {
+            self.while_measuring()
+        } else {
+            self.while_idle()
+        }
+    }
+}
+
+/// A value contained in a response word.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Field {
+    /// Name of the value.
+    pub name: &'static str,
+    /// Whether the word is a two's complement signed integer.
+    pub signed: bool,
+    /// Factor the value is scaled with.
+    pub scale: u16,
+}
+
+impl Field {
+    pub(crate) const fn unsigned(name: &'static str, scale: u16) -> Self {
+        Self {
+            name,
+            signed: false,
+            scale,
+        }
+    }
+
+    pub(crate) const fn signed(name: &'static str, scale: u16) -> Self {
+        Self {
+            name,
+            signed: true,
+            scale,
+        }
+    }
+
+    /// The integer value of `word`, taking the signedness into account.
+    pub const fn raw(&self, word: u16) -> i32 {
+        if self.signed {
+            word.cast_signed() as i32
+        } else {
+            word as i32
+        }
+    }
+
+    /// The value of `word` in physical units.
+    pub fn value(&self, word: u16) -> f32 {
+        self.raw(word) as f32 / f32::from(self.scale)
+    }
+
+    /// The word closest to `value`, saturating below the value the sensor reports as unknown.
+    pub fn word(&self, value: f32) -> u16 {
+        let scaled = value * f32::from(self.scale);
+        // Float to integer casts saturate and map NaN to zero.
+        #[allow(clippy::cast_possible_truncation)]
+        let rounded = (scaled + if scaled < 0.0 { -0.5 } else { 0.5 }) as i32;
+        if self.signed {
+            let value = rounded.clamp(i16::MIN.into(), (i16::MAX - 1).into());
+            i16::try_from(value).unwrap_or_default().cast_unsigned()
+        } else {
+            u16::try_from(rounded.clamp(0, (u16::MAX - 1).into())).unwrap_or_default()
+        }
+    }
+}
+
+/// Fields of the [`Command::ReadMeasurement`] response.
+pub const MEASUREMENT_FIELDS: [Field; 8] = [
+    Field::unsigned("pm1_0", 10),
+    Field::unsigned("pm2_5", 10),
+    Field::unsigned("pm4_0", 10),
+    Field::unsigned("pm10_0", 10),
+    Field::signed("humidity", 100),
+    Field::signed("temperature", 200),
+    Field::signed("voc_index", 10),
+    Field::signed("nox_index", 10),
+];
+
+/// Fields of the [`Command::ReadRawValues`] response.
+pub const RAW_VALUES_FIELDS: [Field; 4] = [
+    Field::signed("raw_humidity", 100),
+    Field::signed("raw_temperature", 200),
+    Field::unsigned("raw_voc", 1),
+    Field::unsigned("raw_nox", 1),
+];
+
+/// Description of a command as given in the datasheet.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct CommandSpec {
+    /// The 16-bit command code.
+    pub code: u16,
+    /// Execution time [ms].
+    pub execution_time_ms: u32,
+    /// Number of words the sensor responds with, `0` for commands without response.
+    pub response_words: usize,
+    /// Modes in which the command can be issued without arguments.
+    pub availability: Availability,
+    /// Number of words written along with the command, `0` if it takes no arguments.
+    pub argument_words: usize,
+    /// Modes in which the command can be issued with arguments.
+    pub write_availability: Availability,
+    /// Interpretation of the response words, empty for opaque or text responses.
+    pub fields: &'static [Field],
+    /// Lowest major firmware version supporting the command.
+    pub min_firmware: u8,
+}
+
+impl CommandSpec {
+    /// A command without response.
+    pub(crate) const fn send(
+        code: u16,
+        execution_time_ms: u32,
+        availability: Availability,
+    ) -> Self {
+        Self::read(code, execution_time_ms, 0, availability)
+    }
+
+    /// A command responding with `response_words` words.
+    pub(crate) const fn read(
+        code: u16,
+        execution_time_ms: u32,
+        response_words: usize,
+        availability: Availability,
+    ) -> Self {
+        Self {
+            code,
+            execution_time_ms,
+            response_words,
+            availability,
+            argument_words: 0,
+            write_availability: availability,
+            fields: &[],
+            min_firmware: 0,
+        }
+    }
+
+    /// The command also writes `argument_words` words.
+    pub(crate) const fn write(
+        self,
+        argument_words: usize,
+        write_availability: Availability,
+    ) -> Self {
+        Self {
+            argument_words,
+            write_availability,
+            ..self
+        }
+    }
+
+    /// The response words contain `fields`.
+    pub(crate) const fn fields(self, fields: &'static [Field]) -> Self {
+        Self { fields, ..self }
+    }
+
+    /// The command requires the major firmware version `min_firmware`.
+    pub(crate) const fn firmware(self, min_firmware: u8) -> Self {
+        Self {
+            min_firmware,
+            ..self
+        }
+    }
+
+    /// Longest time the command takes at a bus clock of `bus_hz`: writing its code and argument
+    /// words, the execution time and reading the response.
+    pub const fn worst_case_duration(&self, bus_hz: u32) -> Duration {
+        // Address, code and arguments, each word followed by its CRC.
+        let write = 3 + 3 * self.argument_words as u64;
+        let read = if self.response_words > 0 {
+            1 + 3 * self.response_words as u64
+        } else {
+            0
+        };
+        let phases = if self.response_words > 0 { 2 } else { 1 };
+        // 9 clock cycles per byte including the acknowledge, and one each for the start and stop
+        // condition of every phase.
+        let cycles = (write + read) * 9 + phases * 2;
+        let hz = if bus_hz == 0 { 1 } else { bus_hz as u64 };
+        let bus = Duration::from_micros((cycles * 1_000_000).div_ceil(hz));
+        bus.saturating_add(Duration::from_millis(self.execution_time_ms as u64))
+    }
 }
 
 impl Command {
+    /// The datasheet description of the command.
+    pub const fn spec(self) -> CommandSpec {
+        use Availability::*;
+
+        match self {
+            Self::StartMeasurement => CommandSpec::send(0x0021, START_MEASUREMENT_MS, Idle),
+            Self::StopMeasurement => CommandSpec::send(0x0104, STOP_MEASUREMENT_MS, Measuring),
+            Self::GetReadDataReadyStatus => {
+                CommandSpec::read(0x0202, GET_READ_DATA_READY_STATUS_MS, 1, Always)
+            }
+            Self::GetSerialNumber => CommandSpec::read(0xD033, GET_SERIAL_NUMBER_MS, 16, Always),
+            Self::ReadProductName => CommandSpec::read(0xD014, READ_PRODUCT_NAME_MS, 16, Always),
+            Self::ReadMeasurement => CommandSpec::read(0x03C4, READ_MEASUREMENT_MS, 8, Measuring)
+                .fields(&MEASUREMENT_FIELDS),
+            Self::Reinit => CommandSpec::send(0xD304, REINIT_MS, Always),
+            Self::StartFanCleaning => CommandSpec::send(0x5607, START_FAN_CLEANING_MS, Measuring),
+            Self::ReadFirmwareVersion => {
+                CommandSpec::read(0xD100, READ_FIRMWARE_VERSION_MS, 1, Always)
+            }
+            Self::ReadDeviceStatus => CommandSpec::read(0xD206, READ_DEVICE_STATUS_MS, 2, Always),
+            Self::ReadAndClearDeviceStatus => {
+                CommandSpec::read(0xD210, READ_AND_CLEAR_DEVICE_STATUS_MS, 2, Always)
+            }
+            Self::AutoCleaningInterval => {
+                CommandSpec::read(0x8004, AUTO_CLEANING_INTERVAL_MS, 2, Always).write(2, Always)
+            }
+            Self::VocTuningParameters => {
+                CommandSpec::read(0x60D0, VOC_TUNING_PARAMETERS_MS, 6, Idle).write(6, Idle)
+            }
+            Self::NoxTuningParameters => {
+                CommandSpec::read(0x60E1, NOX_TUNING_PARAMETERS_MS, 6, Idle).write(6, Idle)
+            }
+            Self::TemperatureCompensation => {
+                CommandSpec::read(0x60B2, TEMPERATURE_COMPENSATION_MS, 3, Idle).write(3, Idle)
+            }
+            Self::VocAlgorithmState => {
+                CommandSpec::read(0x6181, VOC_ALGORITHM_STATE_MS, 4, Always).write(4, Idle)
+            }
+            Self::ReadRawValues => CommandSpec::read(0x03D2, READ_RAW_VALUES_MS, 4, Measuring)
+                .fields(&RAW_VALUES_FIELDS)
+                .firmware(2),
+        }
+    }
+
     // Command, execution time ms, possibility to execute during measurements.
     pub fn as_tuple(self) -> (u16, u32, bool) {
+        let spec = self.spec();
+        (
+            spec.code,
+            spec.execution_time_ms,
+            spec.availability.while_measuring(),
+        )
+    }
+
+    /// Number of words the sensor responds with, `0` for commands without response.
+    pub fn response_words(self) -> usize {
+        self.spec().response_words
+    }
+
+    /// Execution time of the command as given in the datasheet [ms].
+    pub fn execution_time_ms(self) -> u32 {
+        self.spec().execution_time_ms
+    }
+
+    /// Longest time the command takes at a bus clock of `bus_hz`, including the transfer of the
+    /// arguments and the response, e.g. for task budgets or watchdog windows.
+    ///
+    /// ```
+    /// use core::time::Duration;
+    /// use sen5x_rs::commands::{Command, STANDARD_MODE_HZ};
+    ///
+    /// const BUDGET: Duration = Command::ReadMeasurement.worst_case_duration(STANDARD_MODE_HZ);
+    /// assert!(BUDGET > Duration::from_millis(22));
+    /// ```
+    pub const fn worst_case_duration(self, bus_hz: u32) -> Duration {
+        self.spec().worst_case_duration(bus_hz)
+    }
+}
+
+/// How the driver waits for a command to finish executing before continuing.
+#[derive(Debug, Copy, Clone, Default)]
+pub enum WaitStrategy {
+    /// Wait for the execution time given in the datasheet.
+    #[default]
+    Datasheet,
+    /// Never wait. The application has to space the commands itself, e.g. when polling externally.
+    Skip,
+    /// Wait for a fixed time after every command [ms].
+    Fixed(u32),
+    /// Wait for the time returned for the command [ms].
+    Custom(fn(Command) -> u32),
+}
+
+impl WaitStrategy {
+    /// Wait for a fixed time after every command, rounded up to whole milliseconds.
+    pub fn fixed(delay: impl Into<Duration>) -> Self {
+        let delay = delay.into();
+        let ms = delay.as_millis() + u128::from(delay.subsec_nanos() % 1_000_000 != 0);
+        Self::Fixed(u32::try_from(ms).unwrap_or(u32::MAX))
+    }
+
+    /// Time to wait after issuing `cmd` [ms].
+    pub fn delay_ms(self, cmd: Command) -> u32 {
         match self {
-            Self::StartMeasurement => (0x0021, 50, false),
-            Self::StopMeasurement => (0x0104, 200, true),
-            Self::GetReadDataReadyStatus => (0x0202, 20, true),
-            Self::GetSerialNumber => (0xD033, 20, false),
-            Self::ReadProductName => (0xD014, 20, true),
-            Self::ReadMeasurement => (0x03C4, 20, true),
-            Self::Reinit => (0xD304, 100, false),
-            Self::StartFanCleaning => (0x5607, 20, true),
-            Self::ReadFirmwareVersion => (0xD100, 20, true),
+            Self::Datasheet => cmd.execution_time_ms(),
+            Self::Skip => 0,
+            Self::Fixed(ms) => ms,
+            Self::Custom(f) => f(cmd),
         }
     }
 }
@@ -0,0 +1,251 @@
+//! Time spent above WHO 2021 guideline levels and outside EN 16798-1 indoor categories.
+//!
+//! A [`ComplianceTracker`] attributes the time between measurements to every [`Criterion`] the
+//! later measurement violates and sums it up over the last 24 hours in hourly slots, e.g. for a
+//! building management system reporting the hours of poor indoor climate per day.
+//!
+//! The WHO guideline levels are defined for the 24-hour mean, the tracker counts the time the
+//! measured concentration is above them, which is a stricter criterion.
+//!
+//! ```
+//! use core::time::Duration;
+//! use sen5x_rs::compliance::{ComplianceTracker, Criterion};
+//! # let data = sen5x_rs::types::Sen5xData::sample();
+//!
+//! let mut tracker = ComplianceTracker::new();
+//! for minute in 0..=60 {
+//!     tracker.add(&data, minute * 60);
+//! }
+//! // 55 %RH is outside the 30 to 50 %RH of category I.
+//! assert_eq!(tracker.exceeded(Criterion::HumidityCategory1), Duration::from_secs(3600));
+//! assert_eq!(tracker.exceeded(Criterion::WhoPm2_5), Duration::ZERO);
+//! ```
+use core::time::Duration;
+
+use crate::types::Sen5xData;
+
+/// A level or range measurements are compared with.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Criterion {
+    /// PM2.5 above the WHO 2021 24-hour guideline level of 15 µg/m³.
+    WhoPm2_5,
+    /// PM10 above the WHO 2021 24-hour guideline level of 45 µg/m³.
+    WhoPm10,
+    /// Humidity outside the 30 to 50 %RH of EN 16798-1 category I.
+    HumidityCategory1,
+    /// Humidity outside the 25 to 60 %RH of EN 16798-1 category II.
+    HumidityCategory2,
+    /// Humidity outside the 20 to 70 %RH of EN 16798-1 category III.
+    HumidityCategory3,
+    /// Temperature outside the range of EN 16798-1 category I.
+    TemperatureCategory1,
+    /// Temperature outside the range of EN 16798-1 category II.
+    TemperatureCategory2,
+    /// Temperature outside the range of EN 16798-1 category III.
+    TemperatureCategory3,
+}
+
+impl Criterion {
+    /// All criteria.
+    pub const ALL: [Self; 8] = [
+        Self::WhoPm2_5,
+        Self::WhoPm10,
+        Self::HumidityCategory1,
+        Self::HumidityCategory2,
+        Self::HumidityCategory3,
+        Self::TemperatureCategory1,
+        Self::TemperatureCategory2,
+        Self::TemperatureCategory3,
+    ];
+}
+
+/// Operative temperature ranges [°C] of the EN 16798-1 categories I, II and III.
+pub type TemperatureRanges = [(f32, f32); 3];
+
+/// Ranges of offices and similar rooms during the heating season.
+pub const HEATING_SEASON: TemperatureRanges = [(21.0, 23.0), (20.0, 24.0), (19.0, 25.0)];
+
+/// Ranges of offices and similar rooms during the cooling season.
+pub const COOLING_SEASON: TemperatureRanges = [(23.5, 25.5), (23.0, 26.0), (22.0, 27.0)];
+
+const HUMIDITY_RANGES: [(f32, f32); 3] = [(30.0, 50.0), (25.0, 60.0), (20.0, 70.0)];
+
+/// Longest time between measurements attributed to the later one [s].
+const MAX_GAP_S: u64 = 3600;
+
+/// The slot of `hour` in the window.
+fn slot(hour: u64) -> usize {
+    #[allow(clippy::cast_possible_truncation)] // below 24
+    let slot = (hour % 24) as usize;
+    slot
+}
+
+/// Sums up the time measurements violate each criterion over the last 24 hours.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ComplianceTracker {
+    temperature: TemperatureRanges,
+    /// Seconds of violation of every criterion in the hours of the window.
+    slots: [[u16; 8]; 24],
+    /// Hour of the latest measurement.
+    hour: u64,
+    /// Timestamp of the latest measurement [s].
+    last: Option<u64>,
+}
+
+impl Default for ComplianceTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ComplianceTracker {
+    /// A tracker using the temperature ranges of the heating season.
+    pub const fn new() -> Self {
+        Self {
+            temperature: HEATING_SEASON,
+            slots: [[0; 8]; 24],
+            hour: 0,
+            last: None,
+        }
+    }
+
+    /// Use the temperature ranges `temperature`, e.g. [`COOLING_SEASON`].
+    pub const fn with_temperature_ranges(self, temperature: TemperatureRanges) -> Self {
+        Self {
+            temperature,
+            ..self
+        }
+    }
+
+    /// Whether `data` violates `criterion`, `None` if the value was not measured.
+    pub fn violates(&self, criterion: Criterion, data: &Sen5xData) -> Option<bool> {
+        let outside = |value: f32, (low, high): (f32, f32)| value < low || value > high;
+        let humidity = data.env.map(|env| env.humidity);
+        let temperature = data.env.map(|env| env.temperature);
+        match criterion {
+            Criterion::WhoPm2_5 => Some(data.pm.pm2_5 > 15.0),
+            Criterion::WhoPm10 => Some(data.pm.pm10_0 > 45.0),
+            Criterion::HumidityCategory1 => humidity.map(|h| outside(h, HUMIDITY_RANGES[0])),
+            Criterion::HumidityCategory2 => humidity.map(|h| outside(h, HUMIDITY_RANGES[1])),
+            Criterion::HumidityCategory3 => humidity.map(|h| outside(h, HUMIDITY_RANGES[2])),
+            Criterion::TemperatureCategory1 => temperature.map(|t| outside(t, self.temperature[0])),
+            Criterion::TemperatureCategory2 => temperature.map(|t| outside(t, self.temperature[1])),
+            Criterion::TemperatureCategory3 => temperature.map(|t| outside(t, self.temperature[2])),
+        }
+    }
+
+    /// Record `data` measured at `timestamp` [s].
+    ///
+    /// The time since the previous measurement, up to an hour, counts towards the criteria
+    /// `data` violates, in the hour of `timestamp`. Timestamps older than the previous one are
+    /// ignored.
+    pub fn add(&mut self, data: &Sen5xData, timestamp: u64) {
+        let elapsed = match self.last {
+            Some(last) if timestamp < last => return,
+            Some(last) => (timestamp - last).min(MAX_GAP_S),
+            None => 0,
+        };
+        self.advance(timestamp / 3600);
+        self.last = Some(timestamp);
+
+        let violated = Criterion::ALL.map(|criterion| self.violates(criterion, data) == Some(true));
+        let elapsed = u16::try_from(elapsed).unwrap_or(u16::MAX);
+        for (seconds, violated) in self.slots[slot(self.hour)].iter_mut().zip(violated) {
+            if violated {
+                *seconds = seconds.saturating_add(elapsed).min(3600);
+            }
+        }
+    }
+
+    /// Move the window to end with `hour`, clearing the hours in between.
+    fn advance(&mut self, hour: u64) {
+        if self.last.is_none() {
+            self.hour = hour;
+            return;
+        }
+        let skipped = hour.saturating_sub(self.hour).min(24);
+        for offset in 1..=skipped {
+            self.slots[slot(self.hour + offset)] = [0; 8];
+        }
+        self.hour = self.hour.max(hour);
+    }
+
+    /// Time `criterion` was violated within the 24 hours up to the latest measurement.
+    pub fn exceeded(&self, criterion: Criterion) -> Duration {
+        let seconds = self
+            .slots
+            .iter()
+            .map(|slot| u64::from(slot[criterion as usize]))
+            .sum();
+        Duration::from_secs(seconds)
+    }
+
+    /// Remove all recorded measurements.
+    pub fn reset(&mut self) {
+        *self = Self::new().with_temperature_ranges(self.temperature);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Environment;
+
+    /// Test that violations are summed up over a rolling 24 hour window
+    #[test]
+    fn test_window() {
+        let clean = crate::test_vectors::MEASUREMENTS[0].data;
+        let mut polluted = clean;
+        polluted.pm.pm2_5 = 20.0;
+        polluted.env = Some(Environment {
+            humidity: 45.0,
+            temperature: 26.0,
+        });
+
+        let mut tracker = ComplianceTracker::new();
+        assert_eq!(tracker.violates(Criterion::WhoPm10, &polluted), Some(false));
+        assert_eq!(
+            tracker.violates(Criterion::TemperatureCategory3, &polluted),
+            Some(true)
+        );
+        let cooling = tracker.with_temperature_ranges(COOLING_SEASON);
+        assert_eq!(
+            cooling.violates(Criterion::TemperatureCategory1, &polluted),
+            Some(true)
+        );
+        assert_eq!(
+            cooling.violates(Criterion::TemperatureCategory2, &polluted),
+            Some(false)
+        );
+
+        // Two hours polluted, then clean for a day.
+        for minute in 0..=120 {
+            tracker.add(&polluted, minute * 60);
+        }
+        tracker.add(&polluted, 30);
+        let two_hours = Duration::from_secs(7200);
+        assert_eq!(tracker.exceeded(Criterion::WhoPm2_5), two_hours);
+        assert_eq!(
+            tracker.exceeded(Criterion::HumidityCategory1),
+            Duration::ZERO
+        );
+        assert_eq!(tracker.exceeded(Criterion::TemperatureCategory1), two_hours);
+
+        tracker.add(&clean, 23 * 3600);
+        assert_eq!(tracker.exceeded(Criterion::WhoPm2_5), two_hours);
+        // The first hour left the window.
+        tracker.add(&clean, 24 * 3600 + 60);
+        assert_eq!(
+            tracker.exceeded(Criterion::WhoPm2_5),
+            Duration::from_secs(3660)
+        );
+        tracker.add(&polluted, 100 * 3600);
+        assert_eq!(
+            tracker.exceeded(Criterion::WhoPm2_5),
+            Duration::from_secs(3600)
+        );
+
+        tracker.reset();
+        assert_eq!(tracker.exceeded(Criterion::WhoPm2_5), Duration::ZERO);
+    }
+}
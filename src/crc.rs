@@ -1,4 +1,39 @@
-pub(crate) fn crc(data: &[u8]) -> u8 {
+//! CRC-8 checksum protecting every word exchanged with the sensor.
+//!
+//! Polynomial `0x31` (x⁸ + x⁵ + x⁴ + 1), initialization `0xFF`, no reflection, no final XOR.
+
+/// Lookup table with the checksum of every single byte.
+const TABLE: [u8; 256] = table();
+
+const fn table() -> [u8; 256] {
+    let mut table = [0u8; 256];
+    let mut i = 0;
+    while i < 256 {
+        #[allow(clippy::cast_possible_truncation)] // `i` is below 256
+        let mut crc = i as u8;
+        let mut bit = 0;
+        while bit < 8 {
+            if crc & 0x80 == 0 {
+                crc <<= 1;
+            } else {
+                crc = (crc << 1) ^ 0x31u8;
+            }
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+}
+
+/// Calculate the checksum of `data` using a lookup table.
+pub fn crc(data: &[u8]) -> u8 {
+    data.iter()
+        .fold(0xFF, |crc, byte| TABLE[usize::from(crc ^ byte)])
+}
+
+/// Calculate the checksum of `data` bit by bit, without the lookup table.
+pub fn crc_bitwise(data: &[u8]) -> u8 {
     let mut crc: u8 = 0xFF;
     for byte in data.iter().copied() {
         crc ^= byte;
@@ -10,15 +45,24 @@ pub(crate) fn crc(data: &[u8]) -> u8 {
             }
         }
     }
-    return crc;
+    crc
 }
 
 #[cfg(test)]
 mod tests {
-    use super::crc;
+    use super::{crc, crc_bitwise};
 
     #[test]
     fn example() {
         assert_eq!(crc(&[0xbe, 0xef]), 0x92);
+        assert_eq!(crc_bitwise(&[0xbe, 0xef]), 0x92);
+    }
+
+    #[test]
+    fn table_matches_bitwise() {
+        for word in 0..=u16::MAX {
+            let bytes = word.to_be_bytes();
+            assert_eq!(crc(&bytes), crc_bitwise(&bytes));
+        }
     }
 }
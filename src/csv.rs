@@ -0,0 +1,231 @@
+//! CSV formatting of measurements without allocation, e.g. for logging to an SD card.
+//!
+//! The header and rows implement [`core::fmt::Display`] and can be written with `write!` to any
+//! [`core::fmt::Write`] or `std::io::Write`. Values which were not measured are left empty. Line
+//! endings are left to the caller.
+//!
+//! ```
+//! use core::fmt::Write;
+//! use sen5x_rs::csv::Csv;
+//! # let data = sen5x_rs::types::Sen5xData::sample();
+//!
+//! let csv = Csv::new().with_separator(';').with_timestamp("time_s");
+//! let mut log = String::new();
+//! writeln!(log, "{}", csv.header())?;
+//! writeln!(log, "{}", csv.row_at(60, &data))?;
+//! assert_eq!(
+//!     log,
+//!     "time_s;pm1_0;pm2_5;pm4_0;pm10_0;humidity;temperature;voc_index;nox_index\n\
+//!      60;1.8;2.2;2.4;2.6;55.14;22.405;36.0;1.0\n"
+//! );
+//! # Ok::<(), core::fmt::Error>(())
+//! ```
+use core::fmt;
+
+use crate::fields::{FieldId, FIELDS};
+use crate::types::Sen5xData;
+
+/// Layout of the CSV output.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Csv {
+    separator: char,
+    timestamp: Option<&'static str>,
+}
+
+impl Default for Csv {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Csv {
+    /// Comma separated values without a timestamp column.
+    pub const fn new() -> Self {
+        Self {
+            separator: ',',
+            timestamp: None,
+        }
+    }
+
+    /// Separate the values with `separator`.
+    pub const fn with_separator(self, separator: char) -> Self {
+        Self { separator, ..self }
+    }
+
+    /// Start every row with a timestamp column named `name`.
+    pub const fn with_timestamp(self, name: &'static str) -> Self {
+        Self {
+            timestamp: Some(name),
+            ..self
+        }
+    }
+
+    /// The header row naming the columns.
+    pub fn header(&self) -> Header<'_> {
+        Header(self)
+    }
+
+    /// A row with the values of `data`. The timestamp column is left empty.
+    pub fn row<'a, F>(&'a self, data: &'a Sen5xData<F>) -> Row<'a, F> {
+        Row {
+            csv: self,
+            timestamp: None,
+            data,
+        }
+    }
+
+    /// A row with the values of `data` measured at `timestamp`.
+    ///
+    /// The timestamp is only written if the layout has a timestamp column.
+    pub fn row_at<'a, F>(&'a self, timestamp: u64, data: &'a Sen5xData<F>) -> Row<'a, F> {
+        Row {
+            csv: self,
+            timestamp: Some(timestamp),
+            data,
+        }
+    }
+
+    /// Write the timestamp column, if any, and the value columns produced by `value`.
+    fn write(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+        timestamp: impl FnOnce(&mut fmt::Formatter<'_>) -> fmt::Result,
+        mut value: impl FnMut(&mut fmt::Formatter<'_>, FieldId) -> fmt::Result,
+    ) -> fmt::Result {
+        let mut first = true;
+        if self.timestamp.is_some() {
+            timestamp(f)?;
+            first = false;
+        }
+        for id in FieldId::ALL {
+            if !first {
+                write!(f, "{}", self.separator)?;
+            }
+            value(f, id)?;
+            first = false;
+        }
+        Ok(())
+    }
+}
+
+/// Number of decimals needed to represent a value with the resolution of the sensor.
+pub(crate) fn decimals(id: FieldId) -> usize {
+    match FIELDS[id as usize].field.scale {
+        0..=1 => 0,
+        2..=10 => 1,
+        11..=100 => 2,
+        101..=1000 => 3,
+        _ => 4,
+    }
+}
+
+/// Header row of a [`Csv`] layout.
+#[derive(Debug, Copy, Clone)]
+pub struct Header<'a>(&'a Csv);
+
+impl fmt::Display for Header<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.write(
+            f,
+            |f| f.write_str(self.0.timestamp.unwrap_or_default()),
+            |f, id| f.write_str(id.name()),
+        )
+    }
+}
+
+/// Row of a [`Csv`] layout.
+#[derive(Debug, Copy, Clone)]
+pub struct Row<'a, F = f32> {
+    csv: &'a Csv,
+    timestamp: Option<u64>,
+    data: &'a Sen5xData<F>,
+}
+
+impl<F: fmt::Display + Copy> fmt::Display for Row<'_, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.csv.write(
+            f,
+            |f| match self.timestamp {
+                Some(timestamp) => write!(f, "{timestamp}"),
+                None => Ok(()),
+            },
+            |f, id| match self.data.value(id) {
+                Some(value) => write!(f, "{:.*}", decimals(id), value),
+                None => Ok(()),
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_vectors::MEASUREMENTS;
+
+    /// Test the default layout and an empty timestamp column
+    #[test]
+    fn test_layouts() {
+        let data = MEASUREMENTS[2].data;
+        let csv = Csv::new();
+        assert_eq!(
+            csv.header().to_string(),
+            "pm1_0,pm2_5,pm4_0,pm10_0,humidity,temperature,voc_index,nox_index"
+        );
+        assert_eq!(
+            csv.row(&data).to_string(),
+            "100.0,150.0,175.0,180.0,30.00,25.000,100.0,250.0"
+        );
+        assert_eq!(csv.row_at(5, &data).to_string(), csv.row(&data).to_string());
+
+        let csv = Csv::new().with_separator('\t').with_timestamp("t");
+        assert_eq!(
+            csv.row(&data).to_string(),
+            "\t100.0\t150.0\t175.0\t180.0\t30.00\t25.000\t100.0\t250.0"
+        );
+        assert!(csv.header().to_string().starts_with("t\tpm1_0\t"));
+
+        let data = Sen5xData {
+            env: None,
+            gas: None,
+            ..data
+        };
+        assert_eq!(
+            Csv::new().row(&data).to_string(),
+            "100.0,150.0,175.0,180.0,,,,"
+        );
+    }
+
+    /// Test formatting into a fixed size buffer
+    #[test]
+    fn test_fixed_buffer() {
+        struct Buffer {
+            bytes: [u8; 64],
+            len: usize,
+        }
+
+        impl fmt::Write for Buffer {
+            fn write_str(&mut self, s: &str) -> fmt::Result {
+                let end = self.len + s.len();
+                self.bytes
+                    .get_mut(self.len..end)
+                    .ok_or(fmt::Error)?
+                    .copy_from_slice(s.as_bytes());
+                self.len = end;
+                Ok(())
+            }
+        }
+
+        use core::fmt::Write;
+        let mut buffer = Buffer {
+            bytes: [0; 64],
+            len: 0,
+        };
+        let csv = Csv::new().with_timestamp("t");
+        write!(buffer, "{}", csv.row_at(42, &MEASUREMENTS[0].data)).unwrap();
+        assert_eq!(
+            &buffer.bytes[..buffer.len],
+            b"42,1.8,2.2,2.4,2.6,55.14,22.405,36.0,1.0"
+        );
+        assert!(write!(buffer, "{}", csv.header().to_string().repeat(2)).is_err());
+    }
+}
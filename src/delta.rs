@@ -0,0 +1,116 @@
+//! Changes between measurements, for telemetry reporting on change instead of periodically.
+//!
+//! [`Sen5xData::delta`] computes the change of every value since the last reported measurement
+//! and [`Delta::changed_beyond`] tells whether any of them exceeds its [`Thresholds`], so a
+//! node only transmits measurements which differ noticeably.
+//!
+//! ```
+//! use sen5x_rs::delta::Thresholds;
+//! use sen5x_rs::fields::FieldId;
+//! # let reported = sen5x_rs::types::Sen5xData::sample();
+//! # let mut data = reported;
+//! # data.pm.pm2_5 += 1.5;
+//!
+//! let thresholds = Thresholds::uniform(5.0).with(FieldId::Pm2_5, 1.0);
+//! let delta = data.delta(&reported);
+//! assert!(delta.changed_beyond(&thresholds));
+//! ```
+use crate::fields::FieldId;
+use crate::types::Sen5xData;
+
+/// The change of every value between two measurements.
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub struct Delta([Option<f32>; 8]);
+
+impl Delta {
+    /// The change of the value `id`, `None` if neither measurement has it.
+    ///
+    /// A value which became available or unavailable changed by infinity.
+    pub fn get(&self, id: FieldId) -> Option<f32> {
+        self.0[id as usize]
+    }
+
+    /// Iterate over the change of every value.
+    pub fn iter(&self) -> impl Iterator<Item = (FieldId, Option<f32>)> + '_ {
+        FieldId::ALL.into_iter().zip(self.0)
+    }
+
+    /// Whether the magnitude of the change of any value exceeds its threshold.
+    pub fn changed_beyond(&self, thresholds: &Thresholds) -> bool {
+        self.iter()
+            .any(|(id, delta)| delta.is_some_and(|delta| delta.abs() > thresholds.get(id)))
+    }
+}
+
+/// The smallest changes of the values worth reporting.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Thresholds([f32; 8]);
+
+impl Default for Thresholds {
+    /// Thresholds of zero, reporting every change.
+    fn default() -> Self {
+        Self::uniform(0.0)
+    }
+}
+
+impl Thresholds {
+    /// The same threshold for every value.
+    pub const fn uniform(threshold: f32) -> Self {
+        Self([threshold; 8])
+    }
+
+    /// Set the threshold of the value `id`, `f32::INFINITY` to ignore the value.
+    pub const fn with(mut self, id: FieldId, threshold: f32) -> Self {
+        self.0[id as usize] = threshold;
+        self
+    }
+
+    /// The threshold of the value `id`.
+    pub const fn get(&self, id: FieldId) -> f32 {
+        self.0[id as usize]
+    }
+}
+
+impl Sen5xData {
+    /// The change of every value since `prev`.
+    pub fn delta(&self, prev: &Self) -> Delta {
+        let mut delta = Delta::default();
+        for (slot, id) in delta.0.iter_mut().zip(FieldId::ALL) {
+            *slot = match (self.value(id), prev.value(id)) {
+                (Some(value), Some(prev)) => Some(value - prev),
+                (None, None) => None,
+                _ => Some(f32::INFINITY),
+            };
+        }
+        delta
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_vectors::MEASUREMENTS;
+
+    /// Test that the changes are compared with the threshold of each value
+    #[test]
+    fn test_delta() {
+        let prev = MEASUREMENTS[0].data;
+        let mut data = prev;
+        data.pm.pm10_0 -= 2.0;
+        data.gas = None;
+
+        let delta = data.delta(&prev);
+        assert_eq!(delta.get(FieldId::Pm10_0), Some(-2.0));
+        assert_eq!(delta.get(FieldId::Pm1_0), Some(0.0));
+        assert_eq!(delta.get(FieldId::VocIndex), Some(f32::INFINITY));
+        assert_eq!(data.delta(&data).get(FieldId::NoxIndex), None);
+
+        assert!(delta.changed_beyond(&Thresholds::default()));
+        assert!(delta.changed_beyond(&Thresholds::uniform(1.0).with(FieldId::VocIndex, 100.0)));
+        let relaxed = Thresholds::uniform(3.0)
+            .with(FieldId::VocIndex, f32::INFINITY)
+            .with(FieldId::NoxIndex, f32::INFINITY);
+        assert!(!delta.changed_beyond(&relaxed));
+        assert!(!prev.delta(&prev).changed_beyond(&Thresholds::default()));
+    }
+}
@@ -0,0 +1,317 @@
+//! Measurement task for the Embassy framework.
+//!
+//! The functions own the sensor for as long as they run, start the periodic measurement, check
+//! for new data on every tick of an [`embassy_time::Ticker`] and publish every new sample, so
+//! applications only subscribe to the channel or watch. The `_with_jitter` variants add a
+//! random [`Jitter`] to every check, so several sensors on a shared bus are not checked at the
+//! same time. The `_sequenced` variants publish every sample with its sequence number from a
+//! [`Sequencer`], so subscribers detect missed samples.
+//!
+//! [`Sequencer`]: crate::sequence::Sequencer
+use core::convert::Infallible;
+
+use embassy_sync::blocking_mutex::raw::RawMutex;
+use embassy_sync::{channel, watch};
+use embassy_time::{Duration, Ticker, Timer};
+use embedded_hal_async::{delay::DelayNs, i2c::I2c};
+
+use crate::asynch::Sen5xAsync;
+use crate::clock::Clock;
+use crate::jitter::{Jitter, Rng};
+use crate::sequence::{Sequenced, Sequencer};
+use crate::types::Sen5xData;
+use crate::Error;
+
+/// Interval at which the data-ready flag is checked by default, a fifth of the signal update
+/// interval.
+pub const DEFAULT_POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Waits between the checks of the data-ready flag.
+trait Pace {
+    /// Wait until the next check.
+    async fn wait(&mut self);
+}
+
+impl Pace for Ticker {
+    async fn wait(&mut self) {
+        self.next().await;
+    }
+}
+
+/// Waits the poll interval with a random delay added.
+struct Jittered<R> {
+    interval: Duration,
+    jitter: Jitter<R>,
+}
+
+impl<R: Rng> Pace for Jittered<R> {
+    async fn wait(&mut self) {
+        let offset = self.jitter.offset();
+        let offset = Duration::from_micros(u64::try_from(offset.as_micros()).unwrap_or(u64::MAX));
+        Timer::after(self.interval + offset).await;
+    }
+}
+
+/// Start the measurement if necessary and wait for the next sample.
+///
+/// The sample and the errors are reported to the measurement observer of `sensor`.
+async fn next_sample<I2C, D, E>(
+    sensor: &mut Sen5xAsync<I2C, D>,
+    pace: &mut impl Pace,
+) -> Result<Sen5xData, Error<E>>
+where
+    I2C: I2c<Error = E>,
+    D: DelayNs,
+{
+    let result = wait_for_data(sensor, pace).await;
+    sensor.observe(&result, |_, ()| {});
+    result?;
+    sensor.measurement().await
+}
+
+/// Start the measurement if necessary and wait until the data-ready flag is set.
+async fn wait_for_data<I2C, D, E>(
+    sensor: &mut Sen5xAsync<I2C, D>,
+    pace: &mut impl Pace,
+) -> Result<(), Error<E>>
+where
+    I2C: I2c<Error = E>,
+    D: DelayNs,
+{
+    if !sensor.is_measuring() {
+        sensor.start_measurement().await?;
+    }
+    loop {
+        pace.wait().await;
+        if sensor.data_ready_status().await? {
+            return Ok(());
+        }
+    }
+}
+
+/// Measure continuously and send every sample to `sender`, waiting while the channel is full.
+///
+/// The data-ready flag is checked every `poll_interval`. Runs until communicating with the sensor
+/// fails and returns the error.
+pub async fn run_channel<I2C, D, E, M, const N: usize>(
+    sensor: &mut Sen5xAsync<I2C, D>,
+    poll_interval: Duration,
+    sender: channel::Sender<'_, M, Sen5xData, N>,
+) -> Result<Infallible, Error<E>>
+where
+    I2C: I2c<Error = E>,
+    D: DelayNs,
+    M: RawMutex,
+{
+    let mut ticker = Ticker::every(poll_interval);
+    loop {
+        let data = next_sample(sensor, &mut ticker).await?;
+        sender.send(data).await;
+    }
+}
+
+/// Measure continuously and publish the latest sample to `sender`.
+///
+/// The data-ready flag is checked every `poll_interval`. Runs until communicating with the sensor
+/// fails and returns the error.
+pub async fn run_watch<I2C, D, E, M, const N: usize>(
+    sensor: &mut Sen5xAsync<I2C, D>,
+    poll_interval: Duration,
+    sender: watch::Sender<'_, M, Sen5xData, N>,
+) -> Result<Infallible, Error<E>>
+where
+    I2C: I2c<Error = E>,
+    D: DelayNs,
+    M: RawMutex,
+{
+    let mut ticker = Ticker::every(poll_interval);
+    loop {
+        let data = next_sample(sensor, &mut ticker).await?;
+        sender.send(data);
+    }
+}
+
+/// Like [`run_channel`], checking the data-ready flag `poll_interval` plus a random `jitter`
+/// after the previous check.
+pub async fn run_channel_with_jitter<I2C, D, E, M, R, const N: usize>(
+    sensor: &mut Sen5xAsync<I2C, D>,
+    poll_interval: Duration,
+    jitter: Jitter<R>,
+    sender: channel::Sender<'_, M, Sen5xData, N>,
+) -> Result<Infallible, Error<E>>
+where
+    I2C: I2c<Error = E>,
+    D: DelayNs,
+    M: RawMutex,
+    R: Rng,
+{
+    let mut pace = Jittered {
+        interval: poll_interval,
+        jitter,
+    };
+    loop {
+        let data = next_sample(sensor, &mut pace).await?;
+        sender.send(data).await;
+    }
+}
+
+/// Like [`run_watch`], checking the data-ready flag `poll_interval` plus a random `jitter`
+/// after the previous check.
+pub async fn run_watch_with_jitter<I2C, D, E, M, R, const N: usize>(
+    sensor: &mut Sen5xAsync<I2C, D>,
+    poll_interval: Duration,
+    jitter: Jitter<R>,
+    sender: watch::Sender<'_, M, Sen5xData, N>,
+) -> Result<Infallible, Error<E>>
+where
+    I2C: I2c<Error = E>,
+    D: DelayNs,
+    M: RawMutex,
+    R: Rng,
+{
+    let mut pace = Jittered {
+        interval: poll_interval,
+        jitter,
+    };
+    loop {
+        let data = next_sample(sensor, &mut pace).await?;
+        sender.send(data);
+    }
+}
+
+/// Like [`run_channel`], numbering the samples by the signal update interval read from `clock`,
+/// e.g. [`EmbassyClock`](crate::clock::EmbassyClock).
+pub async fn run_channel_sequenced<I2C, D, E, M, const N: usize>(
+    sensor: &mut Sen5xAsync<I2C, D>,
+    poll_interval: Duration,
+    clock: impl Clock,
+    sender: channel::Sender<'_, M, Sequenced<Sen5xData>, N>,
+) -> Result<Infallible, Error<E>>
+where
+    I2C: I2c<Error = E>,
+    D: DelayNs,
+    M: RawMutex,
+{
+    let mut ticker = Ticker::every(poll_interval);
+    let mut sequencer = Sequencer::new();
+    loop {
+        let data = next_sample(sensor, &mut ticker).await?;
+        sender.send(sequencer.stamp_at(data, clock.now())).await;
+    }
+}
+
+/// Like [`run_watch`], numbering the samples by the signal update interval read from `clock`,
+/// e.g. [`EmbassyClock`](crate::clock::EmbassyClock).
+pub async fn run_watch_sequenced<I2C, D, E, M, const N: usize>(
+    sensor: &mut Sen5xAsync<I2C, D>,
+    poll_interval: Duration,
+    clock: impl Clock,
+    sender: watch::Sender<'_, M, Sequenced<Sen5xData>, N>,
+) -> Result<Infallible, Error<E>>
+where
+    I2C: I2c<Error = E>,
+    D: DelayNs,
+    M: RawMutex,
+{
+    let mut ticker = Ticker::every(poll_interval);
+    let mut sequencer = Sequencer::new();
+    loop {
+        let data = next_sample(sensor, &mut ticker).await?;
+        sender.send(sequencer.stamp_at(data, clock.now()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::future::Future;
+    use core::pin::pin;
+    use core::task::Poll;
+
+    use embassy_sync::blocking_mutex::raw::NoopRawMutex;
+    use embassy_sync::channel::Channel;
+    use embassy_sync::watch::Watch;
+
+    use super::*;
+    use crate::asynch::tests::block_on;
+    use crate::simulator::Simulator;
+    use crate::test_vectors::MEASUREMENTS;
+
+    /// Poll `a` and `b` until `b` completes.
+    async fn until<A: Future, B: Future>(a: A, b: B) -> B::Output {
+        let mut a = pin!(a);
+        let mut b = pin!(b);
+        core::future::poll_fn(|cx| {
+            if let Poll::Ready(output) = b.as_mut().poll(cx) {
+                return Poll::Ready(output);
+            }
+            let _ = a.as_mut().poll(cx);
+            Poll::Pending
+        })
+        .await
+    }
+
+    /// Test that samples are sent to a channel
+    #[test]
+    fn test_run_channel() {
+        let sim = Simulator::new();
+        let mut sensor = Sen5xAsync::new(sim.clone(), sim.delay());
+        let channel = Channel::<NoopRawMutex, Sen5xData, 1>::new();
+        let task = run_channel(&mut sensor, Duration::from_millis(1), channel.sender());
+        let samples = block_on(until(task, async {
+            [channel.receive().await, channel.receive().await]
+        }));
+        assert_eq!(samples, [MEASUREMENTS[0].data; 2]);
+        assert!(sim.now_ms() >= 2000);
+    }
+
+    /// Test that the latest sample is published to a watch
+    #[test]
+    fn test_run_watch() {
+        let sim = Simulator::new();
+        let mut sensor = Sen5xAsync::new(sim.clone(), sim.delay());
+        let watch = Watch::<NoopRawMutex, Sen5xData, 1>::new();
+        let mut receiver = watch.receiver().unwrap();
+        let task = run_watch(&mut sensor, Duration::from_millis(1), watch.sender());
+        let sample = block_on(until(task, receiver.changed()));
+        assert_eq!(sample, MEASUREMENTS[0].data);
+    }
+
+    /// Test that samples are sent with jittered checks
+    #[test]
+    fn test_run_channel_with_jitter() {
+        use crate::jitter::XorShift32;
+
+        let sim = Simulator::new();
+        let mut sensor = Sen5xAsync::new(sim.clone(), sim.delay());
+        let channel = Channel::<NoopRawMutex, Sen5xData, 1>::new();
+        let jitter = Jitter::new(XorShift32::new(1), core::time::Duration::from_millis(1));
+        let task = run_channel_with_jitter(
+            &mut sensor,
+            Duration::from_millis(1),
+            jitter,
+            channel.sender(),
+        );
+        let sample = block_on(until(task, channel.receive()));
+        assert_eq!(sample, MEASUREMENTS[0].data);
+    }
+
+    /// Test that the samples sent are numbered consecutively
+    #[test]
+    fn test_run_channel_sequenced() {
+        let sim = Simulator::new();
+        let mut sensor = Sen5xAsync::new(sim.clone(), sim.delay());
+        let channel = Channel::<NoopRawMutex, Sequenced<Sen5xData>, 1>::new();
+        let task = run_channel_sequenced(
+            &mut sensor,
+            Duration::from_millis(1),
+            &sim,
+            channel.sender(),
+        );
+        let samples = block_on(until(task, async {
+            [channel.receive().await, channel.receive().await]
+        }));
+        assert_eq!(samples.map(|sample| sample.sequence), [0, 1]);
+        assert_eq!(samples.map(|sample| sample.missed), [0, 0]);
+        assert_eq!(samples[1].sample, MEASUREMENTS[0].data);
+    }
+}
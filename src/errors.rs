@@ -1,17 +1,28 @@
-use embedded_hal as hal;
-use hal::i2c::I2c;
-use sensirion_i2c::i2c;
+use crate::types::{DeviceVariant, InvalidValue};
 
 /// SEN5x errors
 #[derive(Debug, PartialEq)]
 #[cfg_attr(feature = "thiserror", derive(thiserror::Error))]
 pub enum Error<E> {
     #[cfg_attr(feature = "thiserror", error("I2C: {0}"))]
-    /// I²C bus error
+    /// I²C bus error not covered by the other variants
     I2c(E),
-    #[cfg_attr(feature = "thiserror", error("CRC"))]
+    #[cfg_attr(feature = "thiserror", error("NACK: {0}"))]
+    /// The sensor did not acknowledge, e.g. because it is absent, busy or the command is not
+    /// available in the current mode
+    Nack(E),
+    #[cfg_attr(feature = "thiserror", error("Arbitration loss: {0}"))]
+    /// Another master took over the bus
+    ArbitrationLoss(E),
+    #[cfg_attr(feature = "thiserror", error("Bus error: {0}"))]
+    /// Misplaced start or stop condition, e.g. caused by noise on the bus
+    Bus(E),
+    #[cfg_attr(feature = "thiserror", error("CRC of word {word}"))]
     /// CRC checksum validation failed
-    Crc,
+    Crc {
+        /// Index of the first word of the response whose checksum did not match.
+        word: usize,
+    },
     #[cfg_attr(feature = "thiserror", error("Self Test"))]
     /// Self-test measure failure
     SelfTest,
@@ -21,17 +32,130 @@ pub enum Error<E> {
     #[cfg_attr(feature = "thiserror", error("Internal"))]
     /// Internal fail
     Internal,
+    #[cfg_attr(feature = "thiserror", error("Wrong Buffer Size"))]
+    /// The buffer size does not match the response of the command
+    WrongBufferSize,
+    #[cfg_attr(feature = "thiserror", error("Timeout"))]
+    /// The operation did not complete in time
+    Timeout,
+    #[cfg_attr(feature = "thiserror", error("Unavailable"))]
+    /// The sensor does not measure the value or currently reports it as unknown
+    Unavailable,
+    #[cfg_attr(feature = "thiserror", error("Invalid input: {field} = {value}"))]
+    /// A parameter is outside of the range accepted by the sensor
+    InvalidInput {
+        /// Name of the offending parameter.
+        field: &'static str,
+        /// The rejected value, in the unit sent to the sensor.
+        value: i64,
+    },
+    #[cfg_attr(feature = "thiserror", error("Unsupported"))]
+    /// The sensor or the driver does not support the command
+    Unsupported,
+    #[cfg_attr(feature = "thiserror", error("Wrong device: {found:?}"))]
+    /// The product name does not match the expected variant
+    WrongDevice {
+        /// The variant found, `None` if the product name is not a SEN5x or SEN6x.
+        found: Option<DeviceVariant>,
+    },
+    #[cfg_attr(
+        feature = "thiserror",
+        error("Unsupported firmware: {found}, requires {required}")
+    )]
+    /// The firmware of the sensor is too old for the command
+    UnsupportedFirmware {
+        /// Lowest major firmware version supporting the command.
+        required: u8,
+        /// Major firmware version of the sensor.
+        found: u8,
+    },
 }
 
-impl<E, I2C> From<i2c::Error<I2C>> for Error<E>
+/// Result of the driver, with `E` the error of the I²C bus.
+pub type Result<T, E> = core::result::Result<T, Error<E>>;
+
+#[cfg(feature = "driver")]
+impl<E, I2C> From<sensirion_i2c::i2c::Error<I2C>> for Error<E>
 where
-    I2C: I2c<Error = E>,
+    I2C: embedded_hal::i2c::I2c<Error = E>,
 {
-    fn from(err: i2c::Error<I2C>) -> Self {
+    fn from(err: sensirion_i2c::i2c::Error<I2C>) -> Self {
+        use sensirion_i2c::i2c;
+
         match err {
-            i2c::Error::Crc => Error::Crc,
-            i2c::Error::I2cWrite(e) => Error::I2c(e),
-            i2c::Error::I2cRead(e) => Error::I2c(e),
+            // The position of the word is not reported.
+            i2c::Error::Crc => Error::Crc { word: 0 },
+            i2c::Error::I2cWrite(e) => bus_error::<I2C>(e),
+            i2c::Error::I2cRead(e) => bus_error::<I2C>(e),
+        }
+    }
+}
+
+#[cfg(feature = "driver")]
+impl<E> Error<E>
+where
+    E: embedded_hal::i2c::Error,
+{
+    /// Classify an error of the I²C bus by its kind.
+    pub fn from_i2c(err: E) -> Self {
+        use embedded_hal::i2c::ErrorKind;
+
+        match err.kind() {
+            ErrorKind::NoAcknowledge(_) => Error::Nack(err),
+            ErrorKind::ArbitrationLoss => Error::ArbitrationLoss(err),
+            ErrorKind::Bus => Error::Bus(err),
+            _ => Error::I2c(err),
         }
     }
 }
+
+/// Classify an error of the bus `I`.
+///
+/// Unlike [`Error::from_i2c`], this does not require an `embedded_hal::i2c::Error` bound on the
+/// error type of generic code, only on the bus.
+#[cfg(feature = "driver")]
+pub(crate) fn bus_error<I>(err: I::Error) -> Error<I::Error>
+where
+    I: embedded_hal::i2c::ErrorType,
+{
+    Error::from_i2c(err)
+}
+
+impl<E> Error<E> {
+    /// The error of the I²C bus, `None` if the error was not caused by the bus.
+    pub fn i2c_error(&self) -> Option<&E> {
+        match self {
+            Error::I2c(e) | Error::Nack(e) | Error::ArbitrationLoss(e) | Error::Bus(e) => Some(e),
+            _ => None,
+        }
+    }
+
+    /// The error with the error of the I²C bus left out.
+    pub fn without_bus(&self) -> Error<()> {
+        match *self {
+            Error::I2c(_) => Error::I2c(()),
+            Error::Nack(_) => Error::Nack(()),
+            Error::ArbitrationLoss(_) => Error::ArbitrationLoss(()),
+            Error::Bus(_) => Error::Bus(()),
+            Error::Crc { word } => Error::Crc { word },
+            Error::SelfTest => Error::SelfTest,
+            Error::NotAllowed => Error::NotAllowed,
+            Error::Internal => Error::Internal,
+            Error::WrongBufferSize => Error::WrongBufferSize,
+            Error::Timeout => Error::Timeout,
+            Error::Unavailable => Error::Unavailable,
+            Error::InvalidInput { field, value } => Error::InvalidInput { field, value },
+            Error::Unsupported => Error::Unsupported,
+            Error::WrongDevice { found } => Error::WrongDevice { found },
+            Error::UnsupportedFirmware { required, found } => {
+                Error::UnsupportedFirmware { required, found }
+            }
+        }
+    }
+}
+
+impl<E> From<InvalidValue> for Error<E> {
+    fn from(InvalidValue { field, value }: InvalidValue) -> Self {
+        Error::InvalidInput { field, value }
+    }
+}
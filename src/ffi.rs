@@ -0,0 +1,297 @@
+//! C bindings for the decoding and conversion functions.
+//!
+//! Firmware written in C which owns the I²C bus can pass the bytes read from the sensor to these
+//! functions. Build a static or dynamic library with
+//!
+//! ```text
+//! cargo rustc --release --no-default-features --features ffi,std --crate-type staticlib
+//! ```
+//!
+//! and include `include/sen5x.h`. On bare-metal targets, drop the `std` feature and link the
+//! library through a Rust crate providing a panic handler.
+//!
+//! All functions return [`SEN5X_OK`] on success or a negative error code, and only write to `out`
+//! on success.
+use core::slice;
+
+use crate::fields::FieldId;
+use crate::types::{Sen5xData, Sen5xDataRaw};
+use crate::{crc, parse, Error};
+
+/// The function succeeded.
+pub const SEN5X_OK: i32 = 0;
+/// A pointer argument was null.
+pub const SEN5X_ERROR_NULL: i32 = -1;
+/// The frame length does not match the response.
+pub const SEN5X_ERROR_WRONG_SIZE: i32 = -2;
+/// The CRC of a word did not match.
+pub const SEN5X_ERROR_CRC: i32 = -3;
+
+/// Values of a measurement in physical units, `NaN` for values which were not measured.
+///
+/// The C layout of [`Sen5xData`], declared as `sen5x_data_t` in the header.
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[repr(C)]
+pub struct Sen5xValues {
+    /// Mass Concentration PM1.0 [μg/m³]
+    pub pm1_0: f32,
+    /// Mass Concentration PM2.5 [μg/m³]
+    pub pm2_5: f32,
+    /// Mass Concentration PM4.0 [μg/m³]
+    pub pm4_0: f32,
+    /// Mass Concentration PM10 [μg/m³]
+    pub pm10_0: f32,
+    /// Compensated Ambient Humidity [%RH]
+    pub humidity: f32,
+    /// Compensated Ambient Temperature [°C]
+    pub temperature: f32,
+    /// VOC Index
+    pub voc_index: f32,
+    /// NOx Index
+    pub nox_index: f32,
+}
+
+impl From<Sen5xData> for Sen5xValues {
+    fn from(data: Sen5xData) -> Self {
+        let value = |id| data.value(id).unwrap_or(f32::NAN);
+        Self {
+            pm1_0: value(FieldId::Pm1_0),
+            pm2_5: value(FieldId::Pm2_5),
+            pm4_0: value(FieldId::Pm4_0),
+            pm10_0: value(FieldId::Pm10_0),
+            humidity: value(FieldId::Humidity),
+            temperature: value(FieldId::Temperature),
+            voc_index: value(FieldId::VocIndex),
+            nox_index: value(FieldId::NoxIndex),
+        }
+    }
+}
+
+/// Map a decoding result to a status code, writing the value to `out` on success.
+///
+/// # Safety
+///
+/// `out` has to be null or valid for writes.
+unsafe fn write<T>(result: Result<T, Error<()>>, out: *mut T) -> i32 {
+    match result {
+        Ok(value) => {
+            // SAFETY: the caller guarantees `out` is null or valid for writes.
+            match unsafe { out.as_mut() } {
+                Some(out) => {
+                    *out = value;
+                    SEN5X_OK
+                }
+                None => SEN5X_ERROR_NULL,
+            }
+        }
+        Err(Error::WrongBufferSize) => SEN5X_ERROR_WRONG_SIZE,
+        Err(_) => SEN5X_ERROR_CRC,
+    }
+}
+
+/// View `len` bytes at `frame` as a slice, `None` if `frame` is null.
+///
+/// # Safety
+///
+/// `frame` has to be null or valid for reads of `len` bytes.
+unsafe fn frame<'a>(frame: *const u8, len: usize) -> Option<&'a [u8]> {
+    if frame.is_null() {
+        None
+    } else {
+        // SAFETY: the caller guarantees `frame` is valid for reads of `len` bytes.
+        Some(unsafe { slice::from_raw_parts(frame, len) })
+    }
+}
+
+/// Calculate the CRC of `len` bytes at `data`. Returns `0xFF` for null pointers.
+///
+/// # Safety
+///
+/// `data` has to be null or valid for reads of `len` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn sen5x_crc(data: *const u8, len: usize) -> u8 {
+    // SAFETY: forwarded from the caller.
+    match unsafe { frame(data, len) } {
+        Some(data) => crc::crc(data),
+        None => 0xFF,
+    }
+}
+
+/// Decode the 24 bytes of a `Read Measured Values` response into raw values.
+///
+/// # Safety
+///
+/// `frame` has to be null or valid for reads of `len` bytes and `out` null or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn sen5x_decode_measurement_raw(
+    frame: *const u8,
+    len: usize,
+    out: *mut Sen5xDataRaw,
+) -> i32 {
+    // SAFETY: forwarded from the caller.
+    let Some(frame) = (unsafe { self::frame(frame, len) }) else {
+        return SEN5X_ERROR_NULL;
+    };
+    let result = parse::words(frame).map(parse::measurement_words);
+    // SAFETY: forwarded from the caller.
+    unsafe { write(result, out) }
+}
+
+/// Decode the 24 bytes of a `Read Measured Values` response into physical units.
+///
+/// # Safety
+///
+/// `frame` has to be null or valid for reads of `len` bytes and `out` null or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn sen5x_decode_measurement(
+    frame: *const u8,
+    len: usize,
+    out: *mut Sen5xValues,
+) -> i32 {
+    // SAFETY: forwarded from the caller.
+    let Some(frame) = (unsafe { self::frame(frame, len) }) else {
+        return SEN5X_ERROR_NULL;
+    };
+    let result = parse::words(frame)
+        .map(parse::measurement_words)
+        .map(Sen5xData::from)
+        .map(Sen5xValues::from);
+    // SAFETY: forwarded from the caller.
+    unsafe { write(result, out) }
+}
+
+/// Convert raw values to physical units.
+///
+/// # Safety
+///
+/// `raw` has to be null or valid for reads and `out` null or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn sen5x_convert(raw: *const Sen5xDataRaw, out: *mut Sen5xValues) -> i32 {
+    // SAFETY: the caller guarantees `raw` is null or valid for reads.
+    match unsafe { raw.as_ref() } {
+        // SAFETY: forwarded from the caller.
+        Some(raw) => unsafe { write(Ok(Sen5xValues::from(Sen5xData::from(*raw))), out) },
+        None => SEN5X_ERROR_NULL,
+    }
+}
+
+/// Decode the 3 bytes of a `Read Data-Ready Flag` response.
+///
+/// # Safety
+///
+/// `frame` has to be null or valid for reads of `len` bytes and `out` null or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn sen5x_decode_data_ready(
+    frame: *const u8,
+    len: usize,
+    out: *mut bool,
+) -> i32 {
+    // SAFETY: forwarded from the caller.
+    let Some(frame) = (unsafe { self::frame(frame, len) }) else {
+        return SEN5X_ERROR_NULL;
+    };
+    // SAFETY: forwarded from the caller.
+    unsafe { write(parse::words(frame).map(parse::data_ready_words), out) }
+}
+
+/// Decode the 6 bytes of a `Read Device Status` response into the status register.
+///
+/// # Safety
+///
+/// `frame` has to be null or valid for reads of `len` bytes and `out` null or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn sen5x_decode_device_status(
+    frame: *const u8,
+    len: usize,
+    out: *mut u32,
+) -> i32 {
+    // SAFETY: forwarded from the caller.
+    let Some(frame) = (unsafe { self::frame(frame, len) }) else {
+        return SEN5X_ERROR_NULL;
+    };
+    let result = parse::words(frame)
+        .map(parse::device_status_words)
+        .map(|status| status.bits());
+    // SAFETY: forwarded from the caller.
+    unsafe { write(result, out) }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::ptr;
+
+    use super::*;
+    use crate::test_vectors::{DATA_READY, MEASUREMENTS};
+
+    /// Test decoding through the C interface
+    #[test]
+    fn test_decode() {
+        let vector = &MEASUREMENTS[0];
+        let mut raw = MEASUREMENTS[1].raw;
+        let mut data = Sen5xValues::from(MEASUREMENTS[1].data);
+        let mut ready = false;
+        unsafe {
+            assert_eq!(
+                sen5x_decode_measurement_raw(vector.frame.as_ptr(), vector.frame.len(), &mut raw),
+                SEN5X_OK
+            );
+            assert_eq!(
+                sen5x_decode_measurement(vector.frame.as_ptr(), vector.frame.len(), &mut data),
+                SEN5X_OK
+            );
+            assert_eq!(
+                sen5x_decode_data_ready(DATA_READY.as_ptr(), DATA_READY.len(), &mut ready),
+                SEN5X_OK
+            );
+        }
+        assert_eq!(raw, vector.raw);
+        assert_eq!(data, Sen5xValues::from(vector.data));
+        assert!(ready);
+
+        let mut converted = Sen5xValues::from(MEASUREMENTS[1].data);
+        assert_eq!(unsafe { sen5x_convert(&raw, &mut converted) }, SEN5X_OK);
+        assert_eq!(converted, data);
+        assert_eq!(converted.nox_index, 1.0);
+    }
+
+    /// Test that unknown values are converted to NaN
+    #[test]
+    fn test_unknown() {
+        let raw = Sen5xDataRaw {
+            nox_index: i16::MAX,
+            ..MEASUREMENTS[0].raw
+        };
+        let mut data = Sen5xValues::from(MEASUREMENTS[1].data);
+        assert_eq!(unsafe { sen5x_convert(&raw, &mut data) }, SEN5X_OK);
+        assert!(data.nox_index.is_nan());
+        assert_eq!(data.voc_index, 36.0);
+    }
+
+    /// Test that errors are reported and leave the output untouched
+    #[test]
+    fn test_errors() {
+        let mut frame = MEASUREMENTS[0].frame;
+        let mut raw = MEASUREMENTS[1].raw;
+        unsafe {
+            assert_eq!(
+                sen5x_decode_measurement_raw(frame.as_ptr(), 21, &mut raw),
+                SEN5X_ERROR_WRONG_SIZE
+            );
+            assert_eq!(
+                sen5x_decode_measurement_raw(ptr::null(), 24, &mut raw),
+                SEN5X_ERROR_NULL
+            );
+            assert_eq!(
+                sen5x_decode_measurement_raw(frame.as_ptr(), 24, ptr::null_mut()),
+                SEN5X_ERROR_NULL
+            );
+            frame[2] ^= 1;
+            assert_eq!(
+                sen5x_decode_measurement_raw(frame.as_ptr(), 24, &mut raw),
+                SEN5X_ERROR_CRC
+            );
+            assert_eq!(sen5x_crc([0xBE, 0xEF].as_ptr(), 2), 0x92);
+        }
+        assert_eq!(raw, MEASUREMENTS[1].raw);
+    }
+}
@@ -0,0 +1,333 @@
+//! Metadata of the values of a measurement.
+//!
+//! Exporters for CSV, InfluxDB line protocol or CBOR can iterate over [`FIELDS`] or
+//! [`Sen5xDataRaw::fields`] instead of naming every value of a measurement. Display code can loop
+//! over a [`Sen5xData`], which yields the label, value and unit of every channel.
+use crate::commands::{Field, MEASUREMENT_FIELDS};
+use crate::types::{DeviceVariant, Sen5xData, Sen5xDataRaw};
+
+/// Identifies a value of a measurement.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum FieldId {
+    /// Mass Concentration PM1.0
+    Pm1_0,
+    /// Mass Concentration PM2.5
+    Pm2_5,
+    /// Mass Concentration PM4.0
+    Pm4_0,
+    /// Mass Concentration PM10.0
+    Pm10_0,
+    /// Ambient Humidity
+    Humidity,
+    /// Ambient Temperature
+    Temperature,
+    /// VOC Index
+    VocIndex,
+    /// NOx Index
+    NoxIndex,
+}
+
+/// Description of a value of a measurement.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct FieldInfo {
+    /// The value described.
+    pub id: FieldId,
+    /// Name, signedness and scale of the word in the response.
+    pub field: Field,
+    /// Unit of the value, empty for the dimensionless indices.
+    pub unit: &'static str,
+    /// Variants measuring the value.
+    pub variants: &'static [DeviceVariant],
+}
+
+const ALL: &[DeviceVariant] = &[
+    DeviceVariant::Sen50,
+    DeviceVariant::Sen54,
+    DeviceVariant::Sen55,
+    DeviceVariant::Sen60,
+    DeviceVariant::Sen63c,
+    DeviceVariant::Sen65,
+    DeviceVariant::Sen66,
+    DeviceVariant::Sen68,
+];
+const RHT: &[DeviceVariant] = &[
+    DeviceVariant::Sen54,
+    DeviceVariant::Sen55,
+    DeviceVariant::Sen63c,
+    DeviceVariant::Sen65,
+    DeviceVariant::Sen66,
+    DeviceVariant::Sen68,
+];
+const VOC: &[DeviceVariant] = &[
+    DeviceVariant::Sen54,
+    DeviceVariant::Sen55,
+    DeviceVariant::Sen65,
+    DeviceVariant::Sen66,
+    DeviceVariant::Sen68,
+];
+const NOX: &[DeviceVariant] = &[
+    DeviceVariant::Sen55,
+    DeviceVariant::Sen65,
+    DeviceVariant::Sen66,
+    DeviceVariant::Sen68,
+];
+
+const fn info(id: FieldId, unit: &'static str, variants: &'static [DeviceVariant]) -> FieldInfo {
+    FieldInfo {
+        id,
+        field: MEASUREMENT_FIELDS[id as usize],
+        unit,
+        variants,
+    }
+}
+
+/// Values of a measurement in the order of the `Read Measured Values` response.
+pub const FIELDS: [FieldInfo; 8] = [
+    info(FieldId::Pm1_0, "µg/m³", ALL),
+    info(FieldId::Pm2_5, "µg/m³", ALL),
+    info(FieldId::Pm4_0, "µg/m³", ALL),
+    info(FieldId::Pm10_0, "µg/m³", ALL),
+    info(FieldId::Humidity, "%RH", RHT),
+    info(FieldId::Temperature, "°C", RHT),
+    info(FieldId::VocIndex, "", VOC),
+    info(FieldId::NoxIndex, "", NOX),
+];
+
+impl FieldId {
+    /// All values in the order of the `Read Measured Values` response.
+    pub const ALL: [Self; 8] = [
+        Self::Pm1_0,
+        Self::Pm2_5,
+        Self::Pm4_0,
+        Self::Pm10_0,
+        Self::Humidity,
+        Self::Temperature,
+        Self::VocIndex,
+        Self::NoxIndex,
+    ];
+
+    /// Description of the value.
+    pub const fn info(self) -> &'static FieldInfo {
+        &FIELDS[self as usize]
+    }
+
+    /// Name of the value, matching the field of [`Sen5xDataRaw`].
+    pub const fn name(self) -> &'static str {
+        self.info().field.name
+    }
+
+    /// Unit of the value, empty for the dimensionless indices.
+    pub const fn unit(self) -> &'static str {
+        self.info().unit
+    }
+
+    /// Human readable name of the value, e.g. for a display.
+    pub const fn label(self) -> &'static str {
+        match self {
+            Self::Pm1_0 => "PM1.0",
+            Self::Pm2_5 => "PM2.5",
+            Self::Pm4_0 => "PM4.0",
+            Self::Pm10_0 => "PM10",
+            Self::Humidity => "Humidity",
+            Self::Temperature => "Temperature",
+            Self::VocIndex => "VOC Index",
+            Self::NoxIndex => "NOx Index",
+        }
+    }
+
+    /// Whether `variant` measures the value.
+    pub fn available_on(self, variant: DeviceVariant) -> bool {
+        self.info().variants.contains(&variant)
+    }
+}
+
+impl Sen5xDataRaw {
+    /// The word of the response holding the value `id`.
+    pub fn word(&self, id: FieldId) -> u16 {
+        match id {
+            FieldId::Pm1_0 => self.pm1_0,
+            FieldId::Pm2_5 => self.pm2_5,
+            FieldId::Pm4_0 => self.pm4_0,
+            FieldId::Pm10_0 => self.pm10_0,
+            FieldId::Humidity => self.humidity.cast_unsigned(),
+            FieldId::Temperature => self.temperature.cast_unsigned(),
+            FieldId::VocIndex => self.voc_index.cast_unsigned(),
+            FieldId::NoxIndex => self.nox_index.cast_unsigned(),
+        }
+    }
+
+    /// The value `id` in physical units, `None` if the sensor reports it as unknown.
+    pub fn value(&self, id: FieldId) -> Option<f32> {
+        let field = id.info().field;
+        let word = self.word(id);
+        let unknown = if field.signed { 0x7FFF } else { 0xFFFF };
+        (word != unknown).then(|| field.value(word))
+    }
+
+    /// Iterate over all values in physical units, `None` for values reported as unknown.
+    pub fn fields(&self) -> impl Iterator<Item = (FieldId, Option<f32>)> + '_ {
+        FieldId::ALL.into_iter().map(|id| (id, self.value(id)))
+    }
+}
+
+impl<F: Copy> Sen5xData<F> {
+    /// The value `id` in physical units, `None` if it was not measured.
+    pub fn value(&self, id: FieldId) -> Option<F> {
+        match id {
+            FieldId::Pm1_0 => Some(self.pm.pm1_0),
+            FieldId::Pm2_5 => Some(self.pm.pm2_5),
+            FieldId::Pm4_0 => Some(self.pm.pm4_0),
+            FieldId::Pm10_0 => Some(self.pm.pm10_0),
+            FieldId::Humidity => self.env.map(|env| env.humidity),
+            FieldId::Temperature => self.env.map(|env| env.temperature),
+            FieldId::VocIndex => self.gas.map(|gas| gas.voc_index),
+            FieldId::NoxIndex => self.gas.and_then(|gas| gas.nox_index),
+        }
+    }
+
+    /// Mutable access to the value `id`, `None` if it was not measured.
+    pub fn value_mut(&mut self, id: FieldId) -> Option<&mut F> {
+        match id {
+            FieldId::Pm1_0 => Some(&mut self.pm.pm1_0),
+            FieldId::Pm2_5 => Some(&mut self.pm.pm2_5),
+            FieldId::Pm4_0 => Some(&mut self.pm.pm4_0),
+            FieldId::Pm10_0 => Some(&mut self.pm.pm10_0),
+            FieldId::Humidity => self.env.as_mut().map(|env| &mut env.humidity),
+            FieldId::Temperature => self.env.as_mut().map(|env| &mut env.temperature),
+            FieldId::VocIndex => self.gas.as_mut().map(|gas| &mut gas.voc_index),
+            FieldId::NoxIndex => self.gas.as_mut().and_then(|gas| gas.nox_index.as_mut()),
+        }
+    }
+
+    /// Iterate over the label, value and unit of every channel, `None` for values which were not
+    /// measured.
+    pub fn iter(&self) -> Channels<F> {
+        Channels {
+            data: *self,
+            ids: FieldId::ALL.into_iter(),
+        }
+    }
+}
+
+/// A channel of a measurement: its label, value and unit.
+pub type Channel<F> = (&'static str, Option<F>, &'static str);
+
+/// Iterator over the channels of a [`Sen5xData`], returned by [`Sen5xData::iter`].
+#[derive(Debug, Clone)]
+pub struct Channels<F> {
+    data: Sen5xData<F>,
+    ids: core::array::IntoIter<FieldId, 8>,
+}
+
+impl<F: Copy> Iterator for Channels<F> {
+    type Item = Channel<F>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let id = self.ids.next()?;
+        Some((id.label(), self.data.value(id), id.unit()))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.ids.size_hint()
+    }
+}
+
+impl<F: Copy> ExactSizeIterator for Channels<F> {}
+
+impl<F: Copy> IntoIterator for Sen5xData<F> {
+    type Item = Channel<F>;
+    type IntoIter = Channels<F>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<F: Copy> IntoIterator for &Sen5xData<F> {
+    type Item = Channel<F>;
+    type IntoIter = Channels<F>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_vectors::MEASUREMENTS;
+    use crate::types::Sen5xData;
+
+    /// Test that the table is in the order of the response and matches the converted values
+    #[test]
+    fn test_fields() {
+        for (i, info) in FIELDS.iter().enumerate() {
+            assert_eq!(info.id, FieldId::ALL[i]);
+            assert_eq!(info.field, MEASUREMENT_FIELDS[i]);
+        }
+        let raw = MEASUREMENTS[0].raw;
+        let data = Sen5xData::from(raw);
+        let precise = Sen5xData::<f64>::from_raw(raw);
+        let env = precise.env.unwrap();
+        assert_eq!(env.temperature, 22.405);
+        assert_eq!(env.humidity, 55.14);
+        for id in FieldId::ALL {
+            assert_eq!(precise.value(id).map(|value| value as f32), data.value(id));
+            assert_eq!(raw.value(id), data.value(id));
+        }
+        assert_eq!(FieldId::Temperature.name(), "temperature");
+        assert_eq!(FieldId::Temperature.unit(), "°C");
+    }
+
+    /// Test iterating over the channels of a measurement
+    #[test]
+    fn test_channels() {
+        use core::fmt::Write;
+
+        let data = MEASUREMENTS[0].data;
+        assert_eq!(data.iter().len(), 8);
+        let mut channels = data.into_iter();
+        assert_eq!(channels.next(), Some(("PM1.0", Some(1.8), "µg/m³")));
+        assert_eq!(channels.nth(4), Some(("Temperature", Some(22.405), "°C")));
+
+        let data = Sen5xData { gas: None, ..data };
+        let mut rendered = String::new();
+        for (label, value, unit) in &data {
+            match value {
+                Some(value) => writeln!(rendered, "{label}: {value} {unit}").unwrap(),
+                None => writeln!(rendered, "{label}: -").unwrap(),
+            }
+        }
+        assert!(rendered.starts_with("PM1.0: 1.8 µg/m³\n"));
+        assert!(rendered.ends_with(
+            "Humidity: 55.14 %RH\nTemperature: 22.405 °C\nVOC Index: -\nNOx Index: -\n"
+        ));
+    }
+
+    /// Test that unknown values and values of other variants are identified
+    #[test]
+    fn test_unknown_values() {
+        let raw = Sen5xDataRaw {
+            voc_index: 0x7FFF,
+            nox_index: 0x7FFF,
+            ..MEASUREMENTS[0].raw
+        };
+        let known: usize = raw.fields().filter(|(_, value)| value.is_some()).count();
+        assert_eq!(known, 6);
+        let data = Sen5xData::from(raw);
+        assert_eq!(data.gas, None);
+        for id in FieldId::ALL {
+            assert_eq!(raw.value(id), data.value(id));
+        }
+        assert!(FieldId::Pm2_5.available_on(DeviceVariant::Sen50));
+        assert!(!FieldId::Humidity.available_on(DeviceVariant::Sen50));
+        assert!(FieldId::VocIndex.available_on(DeviceVariant::Sen54));
+        assert!(!FieldId::NoxIndex.available_on(DeviceVariant::Sen54));
+        assert_eq!(
+            DeviceVariant::from_product_name(&crate::parse::ascii_words([
+                0x5345, 0x4E35, 0x3400, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0
+            ])),
+            Some(DeviceVariant::Sen54)
+        );
+    }
+}
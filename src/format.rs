@@ -0,0 +1,235 @@
+//! Formatting of values for displays, driven by the metadata of [`crate::fields`].
+//!
+//! A [`ValueFormat`] renders every value with the resolution of the sensor or a configured
+//! number of decimals, in the configured units and with the decimal separator of the locale, so
+//! display firmware does not need a format string per value.
+//!
+//! ```
+//! use sen5x_rs::fields::FieldId;
+//! use sen5x_rs::format::{TemperatureUnit, ValueFormat};
+//! # let data = sen5x_rs::types::Sen5xData::sample();
+//!
+//! let format = ValueFormat::new()
+//!     .with_temperature_unit(TemperatureUnit::Fahrenheit)
+//!     .with_decimals(FieldId::Temperature, 1)
+//!     .with_decimal_separator(',');
+//! let lines: Vec<String> = format
+//!     .channels(&data)
+//!     .map(|(label, value)| format!("{label}: {}", value.unwrap()))
+//!     .collect();
+//! assert_eq!(lines[1], "PM2.5: 2,2 µg/m³");
+//! assert_eq!(lines[5], "Temperature: 72,3 °F");
+//! ```
+use core::fmt::{self, Write};
+
+use crate::csv::decimals;
+use crate::fields::FieldId;
+use crate::types::Sen5xData;
+
+/// Unit of the temperature.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum TemperatureUnit {
+    /// Degrees Celsius.
+    #[default]
+    Celsius,
+    /// Degrees Fahrenheit.
+    Fahrenheit,
+}
+
+/// How to format the values.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ValueFormat {
+    /// Decimals of every value, `None` for the resolution of the sensor.
+    decimals: [Option<usize>; 8],
+    temperature: TemperatureUnit,
+    separator: char,
+    ascii: bool,
+    units: bool,
+}
+
+impl Default for ValueFormat {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ValueFormat {
+    /// Values with the resolution of the sensor, in °C, with a decimal point and their unit.
+    pub const fn new() -> Self {
+        Self {
+            decimals: [None; 8],
+            temperature: TemperatureUnit::Celsius,
+            separator: '.',
+            ascii: false,
+            units: true,
+        }
+    }
+
+    /// Render the value `id` with `decimals` decimals.
+    pub const fn with_decimals(mut self, id: FieldId, decimals: usize) -> Self {
+        self.decimals[id as usize] = Some(decimals);
+        self
+    }
+
+    /// Render the temperature in `unit`.
+    pub const fn with_temperature_unit(self, unit: TemperatureUnit) -> Self {
+        Self {
+            temperature: unit,
+            ..self
+        }
+    }
+
+    /// Separate the decimals with `separator`, e.g. `,` for many European locales.
+    pub const fn with_decimal_separator(self, separator: char) -> Self {
+        Self { separator, ..self }
+    }
+
+    /// Write the units in ASCII, e.g. `ug/m3`, for fonts without `µ`, `³` and `°`.
+    pub const fn with_ascii_units(self) -> Self {
+        Self {
+            ascii: true,
+            ..self
+        }
+    }
+
+    /// Render the values without their unit.
+    pub const fn without_units(self) -> Self {
+        Self {
+            units: false,
+            ..self
+        }
+    }
+
+    /// The unit of the value `id`, empty for the dimensionless indices.
+    pub const fn unit(&self, id: FieldId) -> &'static str {
+        match (id, self.temperature, self.ascii) {
+            (FieldId::Temperature, TemperatureUnit::Celsius, false) => "°C",
+            (FieldId::Temperature, TemperatureUnit::Celsius, true) => "C",
+            (FieldId::Temperature, TemperatureUnit::Fahrenheit, false) => "°F",
+            (FieldId::Temperature, TemperatureUnit::Fahrenheit, true) => "F",
+            (FieldId::Pm1_0 | FieldId::Pm2_5 | FieldId::Pm4_0 | FieldId::Pm10_0, _, true) => {
+                "ug/m3"
+            }
+            _ => id.unit(),
+        }
+    }
+
+    /// The value `id` in physical units, formatted.
+    pub fn value(&self, id: FieldId, value: f32) -> Formatted<'_> {
+        let value = match (id, self.temperature) {
+            (FieldId::Temperature, TemperatureUnit::Fahrenheit) => value * 1.8 + 32.0,
+            _ => value,
+        };
+        Formatted {
+            format: self,
+            id,
+            value,
+        }
+    }
+
+    /// Iterate over the label and the formatted value of every channel of `data`, `None` for
+    /// values which were not measured.
+    pub fn channels<'a>(
+        &'a self,
+        data: &'a Sen5xData,
+    ) -> impl Iterator<Item = (&'static str, Option<Formatted<'a>>)> + 'a {
+        FieldId::ALL.into_iter().map(|id| {
+            (
+                id.label(),
+                data.value(id).map(|value| self.value(id, value)),
+            )
+        })
+    }
+}
+
+/// A formatted value, returned by [`ValueFormat::value`].
+#[derive(Debug, Copy, Clone)]
+pub struct Formatted<'a> {
+    format: &'a ValueFormat,
+    id: FieldId,
+    value: f32,
+}
+
+impl fmt::Display for Formatted<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let format = self.format;
+        let decimals = format.decimals[self.id as usize].unwrap_or_else(|| decimals(self.id));
+        let mut out = Localized {
+            f,
+            separator: format.separator,
+        };
+        write!(out, "{:.*}", decimals, self.value)?;
+        let unit = format.unit(self.id);
+        if format.units && !unit.is_empty() {
+            write!(f, " {unit}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Writes to `f`, replacing the decimal point with `separator`.
+struct Localized<'a, 'b> {
+    f: &'a mut fmt::Formatter<'b>,
+    separator: char,
+}
+
+impl Write for Localized<'_, '_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for part in s.split_inclusive('.') {
+            match part.strip_suffix('.') {
+                Some(digits) => {
+                    self.f.write_str(digits)?;
+                    self.f.write_char(self.separator)?;
+                }
+                None => self.f.write_str(part)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_vectors::MEASUREMENTS;
+
+    /// Test the decimals, units and separators of the formatted values
+    #[test]
+    fn test_format() {
+        let format = ValueFormat::new();
+        assert_eq!(
+            format.value(FieldId::Temperature, 22.405).to_string(),
+            "22.405 °C"
+        );
+        assert_eq!(format.value(FieldId::VocIndex, 36.0).to_string(), "36.0");
+        assert_eq!(
+            format
+                .with_decimals(FieldId::Pm10_0, 0)
+                .value(FieldId::Pm10_0, 2.6)
+                .to_string(),
+            "3 µg/m³"
+        );
+
+        let ascii = format
+            .with_ascii_units()
+            .with_temperature_unit(TemperatureUnit::Fahrenheit);
+        assert_eq!(
+            ascii.value(FieldId::Temperature, -40.0).to_string(),
+            "-40.000 F"
+        );
+        assert_eq!(ascii.value(FieldId::Pm1_0, 1.8).to_string(), "1.8 ug/m3");
+        assert_eq!(ascii.unit(FieldId::Humidity), "%RH");
+
+        let bare = format.without_units().with_decimal_separator(',');
+        assert_eq!(bare.value(FieldId::Humidity, 55.14).to_string(), "55,14");
+
+        let data = Sen5xData {
+            gas: None,
+            ..MEASUREMENTS[0].data
+        };
+        let channels: Vec<_> = format.channels(&data).collect();
+        assert_eq!(channels.len(), 8);
+        assert_eq!(channels[0].0, "PM1.0");
+        assert!(channels[6].1.is_none());
+    }
+}
@@ -0,0 +1,131 @@
+//! Encoding of commands and decoding of responses for transfers run outside of the driver.
+//!
+//! Firmware transferring data with a DMA-driven I²C engine can build the exact bytes to write
+//! with [`command`] or [`command_with_args`], read [`response_len`] bytes itself and hand the
+//! buffer back to [`decode`] or to the typed decoders of [`crate::parse`].
+//!
+//! ```
+//! use sen5x_rs::commands::Command;
+//! use sen5x_rs::frame;
+//!
+//! let request = frame::command(Command::ReadMeasurement);
+//! assert_eq!(request.as_bytes(), [0x03, 0xC4]);
+//! assert_eq!(frame::response_len(Command::ReadMeasurement), 24);
+//!
+//! // Filled by the DMA transfer.
+//! let response = [0xBE, 0xEF, 0x92, 0x00, 0x2A, 0xDC];
+//! let mut words = [0u16; 2];
+//! frame::decode::<()>(&response, &mut words)?;
+//! assert_eq!(words, [0xBEEF, 42]);
+//! # Ok::<(), sen5x_rs::Error<()>>(())
+//! ```
+#![cfg_attr(not(test), deny(clippy::indexing_slicing))]
+use crate::commands::Command;
+use crate::crc;
+use crate::parse;
+use crate::Error;
+
+/// Largest number of words written or read at once.
+pub const MAX_WORDS: usize = 16;
+
+/// The bytes of a command code followed by its argument words, each protected by a CRC.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct CommandFrame {
+    bytes: [u8; 2 + MAX_WORDS * 3],
+    len: usize,
+}
+
+impl CommandFrame {
+    /// The bytes to write to the sensor.
+    pub fn as_bytes(&self) -> &[u8] {
+        self.bytes.get(..self.len).unwrap_or(&self.bytes)
+    }
+}
+
+/// Encode the command `code` followed by `args`.
+pub fn encode<E>(code: u16, args: &[u16]) -> Result<CommandFrame, Error<E>> {
+    if args.len() > MAX_WORDS {
+        return Err(Error::WrongBufferSize);
+    }
+    let mut bytes = [0u8; 2 + MAX_WORDS * 3];
+    bytes[..2].copy_from_slice(&code.to_be_bytes());
+    for (chunk, word) in bytes[2..].chunks_exact_mut(3).zip(args) {
+        let [hi, lo] = word.to_be_bytes();
+        chunk.copy_from_slice(&[hi, lo, crc::crc(&[hi, lo])]);
+    }
+    Ok(CommandFrame {
+        bytes,
+        len: 2 + args.len() * 3,
+    })
+}
+
+/// The bytes issuing `cmd` without arguments, e.g. to read a value or start the measurement.
+pub fn command(cmd: Command) -> CommandFrame {
+    let [hi, lo] = cmd.spec().code.to_be_bytes();
+    let mut bytes = [0u8; 2 + MAX_WORDS * 3];
+    bytes[..2].copy_from_slice(&[hi, lo]);
+    CommandFrame { bytes, len: 2 }
+}
+
+/// The bytes writing `args` with `cmd`.
+///
+/// Fails with [`Error::WrongBufferSize`] if the command does not take that many arguments.
+pub fn command_with_args<E>(cmd: Command, args: &[u16]) -> Result<CommandFrame, Error<E>> {
+    let spec = cmd.spec();
+    if args.len() != spec.argument_words {
+        return Err(Error::WrongBufferSize);
+    }
+    encode(spec.code, args)
+}
+
+/// Number of bytes of the response to `cmd`, `0` for commands without response.
+pub const fn response_len(cmd: Command) -> usize {
+    cmd.spec().response_words * 3
+}
+
+/// Validate the CRC of every word of `frame` and store the words in `words`.
+///
+/// `frame` has to hold exactly 3 bytes per word of `words`.
+pub fn decode<E>(frame: &[u8], words: &mut [u16]) -> Result<(), Error<E>> {
+    if frame.len() != words.len() * 3 {
+        return Err(Error::WrongBufferSize);
+    }
+    for (index, (word, chunk)) in words.iter_mut().zip(frame.chunks_exact(3)).enumerate() {
+        *word = parse::checked_word(chunk, index)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type Result<T> = core::result::Result<T, Error<()>>;
+
+    /// Test that arguments are framed with a CRC per word and checked against the command
+    #[test]
+    fn test_command_with_args() {
+        let frame: Result<_> = command_with_args(Command::AutoCleaningInterval, &[0, 0xBEEF]);
+        assert_eq!(
+            frame.unwrap().as_bytes(),
+            [0x80, 0x04, 0x00, 0x00, 0x81, 0xBE, 0xEF, 0x92]
+        );
+        let frame: Result<_> = command_with_args(Command::AutoCleaningInterval, &[0]);
+        assert_eq!(frame, Err(Error::WrongBufferSize));
+        let frame: Result<_> = encode(0x8004, &[0; 17]);
+        assert_eq!(frame, Err(Error::WrongBufferSize));
+    }
+
+    /// Test decoding a response read by the application
+    #[test]
+    fn test_decode() {
+        let vector = &crate::test_vectors::MEASUREMENTS[0];
+        let mut words = [0u16; 8];
+        let result: Result<_> = decode(&vector.frame, &mut words);
+        result.unwrap();
+        assert_eq!(words[1], vector.raw.pm2_5);
+
+        let result: Result<_> = decode(&vector.frame[..21], &mut words);
+        assert_eq!(result, Err(Error::WrongBufferSize));
+    }
+}
@@ -0,0 +1,214 @@
+//! Typed health events of a sensor.
+//!
+//! A [`HealthMonitor`] compares the device status register and the outcome of transactions with
+//! the previous ones and emits a [`Sen5xEvent`] for every warning and fault raised and when all
+//! of them cleared. Each event has a [`Severity`], so telemetry can map events to alerts without
+//! parsing strings.
+//!
+//! ```
+//! use sen5x_rs::health::{Fault, HealthMonitor, Sen5xEvent, Severity};
+//! use sen5x_rs::types::DeviceStatus;
+//!
+//! let mut monitor = HealthMonitor::new();
+//! let mut events = monitor.check(DeviceStatus::from_bits(DeviceStatus::FAN_FAILURE));
+//! let event = events.next().unwrap();
+//! assert_eq!(event, Sen5xEvent::Fault(Fault::Fan));
+//! assert_eq!(event.severity(), Severity::Critical);
+//! # assert_eq!(events.next(), None);
+//! ```
+use crate::types::DeviceStatus;
+
+/// How urgently an event needs attention.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    /// Informational, e.g. a recovery.
+    Info,
+    /// Measurements may be degraded.
+    Warning,
+    /// Measurements are unavailable or invalid.
+    Critical,
+}
+
+/// A condition degrading the measurements.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Warning {
+    /// The fan speed is out of range.
+    FanSpeed,
+}
+
+/// A condition making the measurements unavailable or invalid.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Fault {
+    /// The gas sensor failed.
+    GasSensor,
+    /// The internal communication with the RH/T sensor failed.
+    RhtCommunication,
+    /// The laser current is out of range.
+    Laser,
+    /// The fan is blocked or broken.
+    Fan,
+    /// The sensor does not respond on the bus or responds with corrupted data.
+    Communication,
+}
+
+impl Fault {
+    /// Severity of the fault.
+    pub const fn severity(self) -> Severity {
+        Severity::Critical
+    }
+}
+
+/// A change of the health of a sensor.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Sen5xEvent {
+    /// A warning was raised.
+    Warning(Warning),
+    /// A fault was raised.
+    Fault(Fault),
+    /// All warnings and faults cleared.
+    Recovered,
+}
+
+impl Sen5xEvent {
+    /// Severity of the event.
+    pub const fn severity(&self) -> Severity {
+        match self {
+            Self::Warning(_) => Severity::Warning,
+            Self::Fault(fault) => fault.severity(),
+            Self::Recovered => Severity::Info,
+        }
+    }
+}
+
+/// Flags of the device status register and the event each of them raises.
+const FLAGS: [(u32, Sen5xEvent); 5] = [
+    (
+        DeviceStatus::FAN_SPEED_WARNING,
+        Sen5xEvent::Warning(Warning::FanSpeed),
+    ),
+    (
+        DeviceStatus::GAS_SENSOR_ERROR,
+        Sen5xEvent::Fault(Fault::GasSensor),
+    ),
+    (
+        DeviceStatus::RHT_COMMUNICATION_ERROR,
+        Sen5xEvent::Fault(Fault::RhtCommunication),
+    ),
+    (DeviceStatus::LASER_FAILURE, Sen5xEvent::Fault(Fault::Laser)),
+    (DeviceStatus::FAN_FAILURE, Sen5xEvent::Fault(Fault::Fan)),
+];
+
+/// Mask of the flags raising events.
+const MASK: u32 = {
+    let mut mask = 0;
+    let mut i = 0;
+    while i < FLAGS.len() {
+        mask |= FLAGS[i].0;
+        i += 1;
+    }
+    mask
+};
+
+/// Events emitted by a single check.
+pub type Events = core::iter::Flatten<core::array::IntoIter<Option<Sen5xEvent>, 6>>;
+
+/// Tracks the health of a sensor and emits events on changes.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub struct HealthMonitor {
+    /// Flags raising events of the last status checked.
+    flags: u32,
+    /// Whether the last transaction failed.
+    communication: bool,
+}
+
+impl HealthMonitor {
+    /// Create a monitor of a healthy sensor.
+    pub const fn new() -> Self {
+        Self {
+            flags: 0,
+            communication: false,
+        }
+    }
+
+    /// Whether no warning or fault is active.
+    pub fn is_healthy(&self) -> bool {
+        self.flags == 0 && !self.communication
+    }
+
+    /// Compare `status` with the previous one, emitting the warnings and faults it raises.
+    ///
+    /// A status read from the sensor also proves that the communication works.
+    pub fn check(&mut self, status: DeviceStatus) -> Events {
+        let was_healthy = self.is_healthy();
+        let flags = status.bits() & MASK;
+        let raised = flags & !self.flags;
+        self.flags = flags;
+        self.communication = false;
+
+        let mut events = [None; 6];
+        for (event, &(flag, raises)) in events.iter_mut().zip(&FLAGS) {
+            if raised & flag != 0 {
+                *event = Some(raises);
+            }
+        }
+        if !was_healthy && self.is_healthy() {
+            events[5] = Some(Sen5xEvent::Recovered);
+        }
+        events.into_iter().flatten()
+    }
+
+    /// Record whether a transaction succeeded, emitting a fault when the communication is lost
+    /// and the recovery when it is restored.
+    pub fn transaction(&mut self, ok: bool) -> Option<Sen5xEvent> {
+        let was_healthy = self.is_healthy();
+        let lost = !ok && !self.communication;
+        self.communication = !ok;
+        if lost {
+            Some(Sen5xEvent::Fault(Fault::Communication))
+        } else if !was_healthy && self.is_healthy() {
+            Some(Sen5xEvent::Recovered)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test that events are emitted once per raised flag and on recovery
+    #[test]
+    fn test_events() {
+        let mut monitor = HealthMonitor::new();
+        assert_eq!(monitor.check(DeviceStatus::default()).next(), None);
+
+        let status = DeviceStatus::from_bits(
+            DeviceStatus::FAN_SPEED_WARNING
+                | DeviceStatus::LASER_FAILURE
+                | DeviceStatus::FAN_CLEANING,
+        );
+        let events: Vec<_> = monitor.check(status).collect();
+        assert_eq!(
+            events,
+            [
+                Sen5xEvent::Warning(Warning::FanSpeed),
+                Sen5xEvent::Fault(Fault::Laser)
+            ]
+        );
+        assert_eq!(monitor.check(status).next(), None);
+
+        assert_eq!(
+            monitor.transaction(false),
+            Some(Sen5xEvent::Fault(Fault::Communication))
+        );
+        assert_eq!(monitor.transaction(false), None);
+        assert_eq!(monitor.transaction(true), None);
+        assert!(!monitor.is_healthy());
+
+        let events: Vec<_> = monitor.check(DeviceStatus::default()).collect();
+        assert_eq!(events, [Sen5xEvent::Recovered]);
+        assert_eq!(Sen5xEvent::Recovered.severity(), Severity::Info);
+        assert!(Severity::Critical > Severity::Warning);
+    }
+}
@@ -0,0 +1,177 @@
+//! Estimation of the self-heating of an enclosure from the power state of its components.
+//!
+//! A display, charger or radio sharing the enclosure with the sensor warms it up, so the
+//! temperature offset needed depends on which of them are powered. [`SelfHeating`] combines a
+//! base compensation with the offset each heat source adds when it is on. The enclosure does not
+//! warm up instantly, so the estimate approaches the offset of the current power state with the
+//! thermal time constant of the enclosure.
+//!
+//! ```
+//! use core::time::Duration;
+//! use sen5x_rs::heating::SelfHeating;
+//! use sen5x_rs::types::TemperatureCompensation;
+//!
+//! const DISPLAY: usize = 0;
+//! const CHARGER: usize = 1;
+//! let mut heating = SelfHeating::<2>::new(TemperatureCompensation {
+//!     offset: 0.5,
+//!     ..TemperatureCompensation::DATASHEET_DEFAULT
+//! })
+//! .with_source(DISPLAY, 1.5)
+//! .with_source(CHARGER, 3.0)
+//! .with_time_constant(Duration::ZERO);
+//!
+//! let compensation = heating.update([true, false], Duration::from_secs(60)).unwrap();
+//! assert_eq!(compensation.offset, 2.0);
+//! ```
+use core::time::Duration;
+
+use crate::types::TemperatureCompensation;
+
+/// Estimates the temperature compensation from the power state of `N` heat sources.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct SelfHeating<const N: usize> {
+    base: TemperatureCompensation,
+    /// Offset added by each source when it is on [°C].
+    sources: [f32; N],
+    time_constant: Duration,
+    min_change: f32,
+    /// Estimated offset [°C].
+    offset: f32,
+    /// Offset of the last compensation returned [°C].
+    reported: Option<f32>,
+}
+
+impl<const N: usize> SelfHeating<N> {
+    /// Default thermal time constant, typical for a small plastic enclosure.
+    pub const DEFAULT_TIME_CONSTANT: Duration = Duration::from_secs(600);
+
+    /// Default smallest change of the offset reported, ten steps of its resolution [°C].
+    pub const DEFAULT_MIN_CHANGE: f32 = 0.05;
+
+    /// An estimator using `base` while all sources are off, none of which adds an offset yet.
+    pub const fn new(base: TemperatureCompensation) -> Self {
+        Self {
+            base,
+            sources: [0.0; N],
+            time_constant: Self::DEFAULT_TIME_CONSTANT,
+            min_change: Self::DEFAULT_MIN_CHANGE,
+            offset: base.offset,
+            reported: None,
+        }
+    }
+
+    /// Add `offset` [°C] to the base offset while the source `index` is on.
+    ///
+    /// Indices outside of the `N` sources are ignored.
+    pub const fn with_source(mut self, index: usize, offset: f32) -> Self {
+        if index < N {
+            self.sources[index] = offset;
+        }
+        self
+    }
+
+    /// Use the thermal time constant `time_constant` of the enclosure, zero to follow power state
+    /// changes immediately.
+    pub const fn with_time_constant(self, time_constant: Duration) -> Self {
+        Self {
+            time_constant,
+            ..self
+        }
+    }
+
+    /// Only report changes of the offset of at least `min_change` [°C], limiting the writes to
+    /// the sensor.
+    pub const fn with_min_change(self, min_change: f32) -> Self {
+        Self { min_change, ..self }
+    }
+
+    /// The offset [°C] reached after the power state `on` lasted long enough.
+    pub fn target(&self, on: [bool; N]) -> f32 {
+        self.sources
+            .iter()
+            .zip(on)
+            .filter(|(_, on)| *on)
+            .fold(self.base.offset, |offset, (source, _)| offset + source)
+    }
+
+    /// The estimated compensation.
+    pub fn compensation(&self) -> TemperatureCompensation {
+        TemperatureCompensation {
+            offset: self.offset,
+            ..self.base
+        }
+    }
+
+    /// Update the estimate with the power state `on` of the sources during the `elapsed` time.
+    ///
+    /// Returns the compensation to write to the sensor, e.g. with
+    /// `Sen5x::set_temperature_compensation` in idle mode, when it changed by at least the
+    /// minimum change from the last one returned. The first update always returns it.
+    pub fn update(&mut self, on: [bool; N], elapsed: Duration) -> Option<TemperatureCompensation> {
+        let target = self.target(on);
+        let (tau, dt) = (self.time_constant.as_secs_f32(), elapsed.as_secs_f32());
+        // First order lag, discretized to stay stable for any elapsed time.
+        self.offset = if tau > 0.0 {
+            self.offset + dt / (tau + dt) * (target - self.offset)
+        } else {
+            target
+        };
+        // Snap to the target once the remaining difference is not reported anyway.
+        if (target - self.offset).abs() < self.min_change / 2.0 {
+            self.offset = target;
+        }
+        match self.reported {
+            Some(reported) if (self.offset - reported).abs() < self.min_change => None,
+            _ => {
+                self.reported = Some(self.offset);
+                Some(self.compensation())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MINUTE: Duration = Duration::from_secs(60);
+
+    /// Test that the estimate approaches the offset of the power state with the time constant
+    #[test]
+    fn test_self_heating() {
+        let base = TemperatureCompensation {
+            offset: 1.0,
+            slope: 0.01,
+            time_constant: 0,
+        };
+        let mut heating = SelfHeating::<2>::new(base)
+            .with_source(0, 2.0)
+            .with_source(1, 4.0)
+            .with_source(2, 100.0);
+        assert_eq!(heating.target([true, true]), 7.0);
+        assert_eq!(heating.update([false, false], MINUTE), Some(base));
+        assert_eq!(heating.update([false, false], MINUTE), None);
+
+        // A single update over one time constant covers half of the step.
+        let compensation = heating.update([false, true], 10 * MINUTE).unwrap();
+        assert_eq!(compensation.offset, 3.0);
+        assert_eq!(compensation.slope, base.slope);
+        assert_eq!(
+            heating.update([false, true], Duration::from_millis(100)),
+            None
+        );
+        for _ in 0..100 {
+            heating.update([false, true], MINUTE);
+        }
+        assert_eq!(heating.compensation().offset, 5.0);
+
+        let mut coarse = heating
+            .with_min_change(1.0)
+            .with_time_constant(Duration::ZERO);
+        assert_eq!(
+            coarse.update([true, true], MINUTE).map(|c| c.offset),
+            Some(7.0)
+        );
+    }
+}
@@ -0,0 +1,220 @@
+//! Distribution summaries of measurements without storing the samples.
+//!
+//! A [`Histogram`] counts samples in buckets with fixed edges and estimates percentiles from the
+//! counts, e.g. the p50 and p95 of PM2.5 over a reporting interval. [`Histograms`] keeps one per
+//! value of a measurement.
+//!
+//! ```
+//! use sen5x_rs::fields::FieldId;
+//! use sen5x_rs::histogram::Histograms;
+//! # let data = sen5x_rs::types::Sen5xData::sample();
+//!
+//! let mut histograms = Histograms::new([5.0, 10.0, 25.0, 50.0])
+//!     .with_edges(FieldId::Humidity, [20.0, 40.0, 60.0, 80.0]);
+//! histograms.add(&data);
+//! let pm2_5 = histograms.get(FieldId::Pm2_5);
+//! assert_eq!(pm2_5.count(), 1);
+//! assert_eq!(pm2_5.p95(), Some(2.2));
+//! ```
+use crate::fields::FieldId;
+use crate::types::Sen5xData;
+
+/// Counts of samples in `N` buckets and above the last of them.
+///
+/// Bucket `i` holds the samples up to `edges[i]` and above the previous edge. The edges have to
+/// be ascending.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Histogram<const N: usize> {
+    edges: [f32; N],
+    counts: [u32; N],
+    /// Number of samples above the last edge.
+    overflow: u32,
+    min: f32,
+    max: f32,
+}
+
+impl<const N: usize> Histogram<N> {
+    /// An empty histogram with the upper `edges` of its buckets.
+    pub const fn new(edges: [f32; N]) -> Self {
+        Self {
+            edges,
+            counts: [0; N],
+            overflow: 0,
+            min: f32::INFINITY,
+            max: f32::NEG_INFINITY,
+        }
+    }
+
+    /// Count `value`, ignoring NaN.
+    pub fn add(&mut self, value: f32) {
+        if value.is_nan() {
+            return;
+        }
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+        let count = match self.edges.iter().position(|&edge| value <= edge) {
+            Some(i) => &mut self.counts[i],
+            None => &mut self.overflow,
+        };
+        *count = count.saturating_add(1);
+    }
+
+    /// Remove all samples, keeping the edges.
+    pub fn reset(&mut self) {
+        *self = Self::new(self.edges);
+    }
+
+    /// Upper edges of the buckets.
+    pub fn edges(&self) -> &[f32; N] {
+        &self.edges
+    }
+
+    /// Number of samples in each bucket.
+    pub fn counts(&self) -> &[u32; N] {
+        &self.counts
+    }
+
+    /// Number of samples above the last edge.
+    pub fn overflow(&self) -> u32 {
+        self.overflow
+    }
+
+    /// Number of samples.
+    pub fn count(&self) -> u32 {
+        self.counts
+            .iter()
+            .fold(self.overflow, |sum, &count| sum.saturating_add(count))
+    }
+
+    /// Smallest and largest sample, `None` if empty.
+    pub fn range(&self) -> Option<(f32, f32)> {
+        (self.count() > 0).then_some((self.min, self.max))
+    }
+
+    /// Estimate of the `p`-th quantile, for `p` from 0 to 1, `None` if empty.
+    ///
+    /// The samples are assumed to be evenly spread within their bucket, bounded by the smallest
+    /// and largest sample.
+    pub fn percentile(&self, p: f32) -> Option<f32> {
+        let total = self.count();
+        if total == 0 {
+            return None;
+        }
+        let rank = p.clamp(0.0, 1.0) * total as f32;
+        let mut below = 0u32;
+        let buckets = self.counts.iter().chain([&self.overflow]).enumerate();
+        for (i, &count) in buckets {
+            if count == 0 {
+                continue;
+            }
+            let lower = i
+                .checked_sub(1)
+                .and_then(|i| self.edges.get(i))
+                .map_or(self.min, |&edge| edge.max(self.min));
+            let upper = self
+                .edges
+                .get(i)
+                .map_or(self.max, |&edge| edge.min(self.max));
+            let end = below.saturating_add(count);
+            if end as f32 >= rank {
+                let fraction = (rank - below as f32) / count as f32;
+                return Some(lower + (upper - lower) * fraction);
+            }
+            below = end;
+        }
+        Some(self.max)
+    }
+
+    /// Estimate of the median, `None` if empty.
+    pub fn p50(&self) -> Option<f32> {
+        self.percentile(0.5)
+    }
+
+    /// Estimate of the 95th percentile, `None` if empty.
+    pub fn p95(&self) -> Option<f32> {
+        self.percentile(0.95)
+    }
+}
+
+/// A [`Histogram`] of every value of a measurement.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Histograms<const N: usize>([Histogram<N>; 8]);
+
+impl<const N: usize> Histograms<N> {
+    /// Histograms of every value with the same `edges`.
+    pub const fn new(edges: [f32; N]) -> Self {
+        Self([Histogram::new(edges); 8])
+    }
+
+    /// Use `edges` for the value `id`, e.g. for the humidity with a range different from the
+    /// mass concentrations. Removes its samples.
+    pub const fn with_edges(mut self, id: FieldId, edges: [f32; N]) -> Self {
+        self.0[id as usize] = Histogram::new(edges);
+        self
+    }
+
+    /// Count the values of `data` which were measured.
+    pub fn add(&mut self, data: &Sen5xData) {
+        for (histogram, id) in self.0.iter_mut().zip(FieldId::ALL) {
+            if let Some(value) = data.value(id) {
+                histogram.add(value);
+            }
+        }
+    }
+
+    /// Remove all samples, e.g. at the start of a reporting interval.
+    pub fn reset(&mut self) {
+        self.0.iter_mut().for_each(Histogram::reset);
+    }
+
+    /// The histogram of the value `id`.
+    pub fn get(&self, id: FieldId) -> &Histogram<N> {
+        &self.0[id as usize]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test that percentiles are interpolated within the buckets
+    #[test]
+    fn test_percentile() {
+        let mut histogram = Histogram::new([10.0, 20.0, 30.0]);
+        assert_eq!(histogram.p50(), None);
+        for value in 1..=40 {
+            histogram.add(value as f32);
+        }
+        histogram.add(f32::NAN);
+        assert_eq!(histogram.counts(), &[10, 10, 10]);
+        assert_eq!(histogram.overflow(), 10);
+        assert_eq!(histogram.count(), 40);
+        assert_eq!(histogram.range(), Some((1.0, 40.0)));
+        assert_eq!(histogram.p50(), Some(20.0));
+        assert_eq!(histogram.percentile(0.25), Some(10.0));
+        assert_eq!(histogram.p95(), Some(38.0));
+        assert_eq!(histogram.percentile(0.0), Some(1.0));
+        assert_eq!(histogram.percentile(1.0), Some(40.0));
+
+        histogram.reset();
+        histogram.add(12.0);
+        assert_eq!(histogram.p95(), Some(12.0));
+        assert_eq!(histogram.edges(), &[10.0, 20.0, 30.0]);
+    }
+
+    /// Test that only measured values are counted
+    #[test]
+    fn test_histograms() {
+        let data = Sen5xData {
+            env: None,
+            ..crate::test_vectors::MEASUREMENTS[0].data
+        };
+        let mut histograms = Histograms::new([1.0, 2.0, 3.0]);
+        histograms.add(&data);
+        assert_eq!(histograms.get(FieldId::Pm2_5).counts(), &[0, 0, 1]);
+        assert_eq!(histograms.get(FieldId::Humidity).count(), 0);
+        assert_eq!(histograms.get(FieldId::VocIndex).overflow(), 1);
+        histograms.reset();
+        assert_eq!(histograms.get(FieldId::Pm2_5).count(), 0);
+    }
+}
@@ -0,0 +1,275 @@
+//! Home Assistant MQTT discovery of the channels of a sensor.
+//!
+//! [`Discovery`] builds the retained config message announcing each channel as a sensor entity
+//! of one device, from the metadata of [`crate::fields`]. The entities read their values from a
+//! JSON state message, formatted by [`state`]. The messages implement [`core::fmt::Display`] and
+//! are written without allocation into any buffer implementing [`core::fmt::Write`].
+//!
+//! ```
+//! use core::fmt::Write;
+//! use sen5x_rs::fields::FieldId;
+//! use sen5x_rs::homeassistant::Discovery;
+//! use sen5x_rs::types::DeviceVariant;
+//!
+//! let discovery = Discovery::new("sen55_0001", "home/livingroom/air")
+//!     .with_name("Living room")
+//!     .with_model(DeviceVariant::Sen55);
+//! let mut topic = String::new();
+//! let mut config = String::new();
+//! for id in discovery.channels() {
+//!     topic.clear();
+//!     config.clear();
+//!     write!(topic, "{}", discovery.topic(id))?;
+//!     write!(config, "{}", discovery.config(id))?;
+//!     // Publish `config` to `topic` with the retain flag.
+//! }
+//! assert_eq!(topic, "homeassistant/sensor/sen55_0001/nox_index/config");
+//! # Ok::<(), core::fmt::Error>(())
+//! ```
+use core::fmt;
+
+use crate::csv::decimals;
+use crate::fields::FieldId;
+use crate::types::{DeviceVariant, Sen5xData};
+
+/// Describes the device announced to Home Assistant.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Discovery<'a> {
+    prefix: &'a str,
+    node_id: &'a str,
+    name: &'a str,
+    state_topic: &'a str,
+    model: Option<DeviceVariant>,
+}
+
+impl<'a> Discovery<'a> {
+    /// A device identified by `node_id` publishing its state to `state_topic`.
+    ///
+    /// `node_id` is part of the topics and of the unique ids of the entities, e.g. the serial
+    /// number of the sensor, and may only contain ASCII letters, digits, `_` and `-`.
+    pub const fn new(node_id: &'a str, state_topic: &'a str) -> Self {
+        Self {
+            prefix: "homeassistant",
+            node_id,
+            name: "SEN5x",
+            state_topic,
+            model: None,
+        }
+    }
+
+    /// Publish the config messages below `prefix` instead of `homeassistant`.
+    pub const fn with_prefix(self, prefix: &'a str) -> Self {
+        Self { prefix, ..self }
+    }
+
+    /// Show the device as `name` instead of `SEN5x`.
+    pub const fn with_name(self, name: &'a str) -> Self {
+        Self { name, ..self }
+    }
+
+    /// Report the device as `model` and announce only the channels it measures.
+    pub const fn with_model(self, model: DeviceVariant) -> Self {
+        Self {
+            model: Some(model),
+            ..self
+        }
+    }
+
+    /// The channels to announce, all of them if the model is not set.
+    pub fn channels(&self) -> impl Iterator<Item = FieldId> {
+        let model = self.model;
+        FieldId::ALL
+            .into_iter()
+            .filter(move |id| model.is_none_or(|model| id.available_on(model)))
+    }
+
+    /// The topic of the config message of `id`.
+    pub fn topic(&self, id: FieldId) -> Topic<'_> {
+        Topic {
+            discovery: self,
+            id,
+        }
+    }
+
+    /// The config message of `id`.
+    pub fn config(&self, id: FieldId) -> Config<'_> {
+        Config {
+            discovery: self,
+            id,
+        }
+    }
+}
+
+/// The device class of `id`, `None` if Home Assistant has no class for it.
+pub const fn device_class(id: FieldId) -> Option<&'static str> {
+    match id {
+        FieldId::Pm1_0 => Some("pm1"),
+        FieldId::Pm2_5 => Some("pm25"),
+        FieldId::Pm10_0 => Some("pm10"),
+        FieldId::Humidity => Some("humidity"),
+        FieldId::Temperature => Some("temperature"),
+        FieldId::Pm4_0 | FieldId::VocIndex | FieldId::NoxIndex => None,
+    }
+}
+
+/// The unit of `id` as expected by Home Assistant, `None` for the dimensionless indices.
+pub const fn unit(id: FieldId) -> Option<&'static str> {
+    match id {
+        FieldId::Humidity => Some("%"),
+        FieldId::VocIndex | FieldId::NoxIndex => None,
+        _ => Some(id.unit()),
+    }
+}
+
+/// Topic of a config message, returned by [`Discovery::topic`].
+#[derive(Debug, Copy, Clone)]
+pub struct Topic<'a> {
+    discovery: &'a Discovery<'a>,
+    id: FieldId,
+}
+
+impl fmt::Display for Topic<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Discovery {
+            prefix, node_id, ..
+        } = self.discovery;
+        write!(f, "{prefix}/sensor/{node_id}/{}/config", self.id.name())
+    }
+}
+
+/// Config message of a channel, returned by [`Discovery::config`].
+#[derive(Debug, Copy, Clone)]
+pub struct Config<'a> {
+    discovery: &'a Discovery<'a>,
+    id: FieldId,
+}
+
+impl fmt::Display for Config<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Discovery {
+            node_id,
+            name,
+            state_topic,
+            model,
+            ..
+        } = self.discovery;
+        let id = self.id;
+        write!(
+            f,
+            "{{\"name\":\"{}\",\"unique_id\":\"{}_{}\",\"state_topic\":\"{}\",",
+            id.label(),
+            Escaped(node_id),
+            id.name(),
+            Escaped(state_topic)
+        )?;
+        write!(
+            f,
+            "\"value_template\":\"{{{{ value_json.{} }}}}\",",
+            id.name()
+        )?;
+        if let Some(unit) = unit(id) {
+            write!(f, "\"unit_of_measurement\":\"{unit}\",")?;
+        }
+        if let Some(class) = device_class(id) {
+            write!(f, "\"device_class\":\"{class}\",")?;
+        }
+        write!(
+            f,
+            "\"state_class\":\"measurement\",\"device\":{{\"identifiers\":[\"{}\"],\
+             \"name\":\"{}\",\"manufacturer\":\"Sensirion\"",
+            Escaped(node_id),
+            Escaped(name)
+        )?;
+        if let Some(model) = model {
+            write!(f, ",\"model\":\"{}\"", model.name())?;
+        }
+        f.write_str("}}")
+    }
+}
+
+/// The state message with the values of `data`, `null` for values which were not measured.
+pub fn state<F>(data: &Sen5xData<F>) -> State<'_, F> {
+    State(data)
+}
+
+/// State message of a measurement, returned by [`state`].
+#[derive(Debug, Copy, Clone)]
+pub struct State<'a, F = f32>(&'a Sen5xData<F>);
+
+impl<F: Copy + fmt::Display> fmt::Display for State<'_, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut separator = '{';
+        for id in FieldId::ALL {
+            write!(f, "{separator}\"{}\":", id.name())?;
+            match self.0.value(id) {
+                Some(value) => write!(f, "{value:.*}", decimals(id))?,
+                None => f.write_str("null")?,
+            }
+            separator = ',';
+        }
+        f.write_str("}")
+    }
+}
+
+/// A string escaped for a JSON string literal.
+struct Escaped<'a>(&'a str);
+
+impl fmt::Display for Escaped<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for c in self.0.chars() {
+            match c {
+                '"' => f.write_str("\\\"")?,
+                '\\' => f.write_str("\\\\")?,
+                c if c.is_control() => write!(f, "\\u{:04x}", u32::from(c))?,
+                c => write!(f, "{c}")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_vectors::MEASUREMENTS;
+
+    /// Test the config message of a channel with a unit and a device class
+    #[test]
+    fn test_config() {
+        let discovery = Discovery::new("sen55", "air/\"state\"").with_model(DeviceVariant::Sen55);
+        assert_eq!(
+            discovery.config(FieldId::Pm2_5).to_string(),
+            "{\"name\":\"PM2.5\",\"unique_id\":\"sen55_pm2_5\",\"state_topic\":\"air/\\\"state\\\"\",\
+             \"value_template\":\"{{ value_json.pm2_5 }}\",\"unit_of_measurement\":\"µg/m³\",\
+             \"device_class\":\"pm25\",\"state_class\":\"measurement\",\"device\":{\"identifiers\":\
+             [\"sen55\"],\"name\":\"SEN5x\",\"manufacturer\":\"Sensirion\",\"model\":\"SEN55\"}}"
+        );
+        let config = discovery.config(FieldId::VocIndex).to_string();
+        assert!(!config.contains("unit_of_measurement") && !config.contains("device_class"));
+        assert_eq!(
+            discovery
+                .with_prefix("ha")
+                .topic(FieldId::Humidity)
+                .to_string(),
+            "ha/sensor/sen55/humidity/config"
+        );
+        let sen50 = discovery.with_model(DeviceVariant::Sen50);
+        assert_eq!(sen50.channels().count(), 4);
+    }
+
+    /// Test that the state message holds every value with the resolution of the sensor
+    #[test]
+    fn test_state() {
+        assert_eq!(
+            state(&MEASUREMENTS[0].data).to_string(),
+            "{\"pm1_0\":1.8,\"pm2_5\":2.2,\"pm4_0\":2.4,\"pm10_0\":2.6,\"humidity\":55.14,\
+             \"temperature\":22.405,\"voc_index\":36.0,\"nox_index\":1.0}"
+        );
+        let data = Sen5xData {
+            env: None,
+            gas: None,
+            ..MEASUREMENTS[0].data
+        };
+        assert!(state(&data).to_string().ends_with("\"nox_index\":null}"));
+    }
+}
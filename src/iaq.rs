@@ -0,0 +1,215 @@
+//! A composite indoor air quality score from 0 (poor) to 100 (excellent) for user interfaces.
+//!
+//! [`IaqScore`] rates the PM2.5 concentration, the VOC and NOx index and the humidity from 0 to
+//! 100 each and combines the ratings in a weighted mean. Values which were not measured, e.g.
+//! the humidity of a SEN50, are left out and the weights of the others scaled.
+//!
+//! By default, the weights and ratings are:
+//!
+//! | Value     | Weight | Rated 100       | Rated 0                |
+//! |-----------|--------|-----------------|------------------------|
+//! | PM2.5     | 40 %   | up to 5 µg/m³   | from 75 µg/m³          |
+//! | VOC Index | 30 %   | up to 100       | from 400               |
+//! | NOx Index | 15 %   | up to 20        | from 250               |
+//! | Humidity  | 15 %   | 40 to 60 %RH    | below 20, above 80 %RH |
+//!
+//! The PM2.5 ramp starts at the annual WHO 2021 guideline. The indices rate the average
+//! conditions the sensor has learned with 100 for VOC and 1 for NOx, so only events above them
+//! lower the score.
+//!
+//! ```
+//! use sen5x_rs::iaq::IaqScore;
+//! # let data = sen5x_rs::types::Sen5xData::sample();
+//!
+//! let score = IaqScore::new().score(&data);
+//! assert_eq!(score, Some(100.0));
+//! ```
+use crate::fields::FieldId;
+use crate::types::Sen5xData;
+
+/// Rates a value with 100 up to `good`, decreasing linearly to 0 at `bad`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Ramp {
+    /// Highest value rated 100.
+    pub good: f32,
+    /// Lowest value rated 0.
+    pub bad: f32,
+}
+
+impl Ramp {
+    /// The rating of `value`.
+    pub fn rate(&self, value: f32) -> f32 {
+        if value <= self.good {
+            100.0
+        } else if value >= self.bad {
+            0.0
+        } else {
+            100.0 * (self.bad - value) / (self.bad - self.good)
+        }
+    }
+}
+
+/// Rates the humidity with 100 within the comfort band, decreasing linearly to 0 at the limits.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Comfort {
+    /// Humidity rated 0 when drier [%RH].
+    pub dry: f32,
+    /// Lower end of the comfort band [%RH].
+    pub low: f32,
+    /// Upper end of the comfort band [%RH].
+    pub high: f32,
+    /// Humidity rated 0 when more humid [%RH].
+    pub humid: f32,
+}
+
+impl Comfort {
+    /// The rating of `humidity`.
+    pub fn rate(&self, humidity: f32) -> f32 {
+        if humidity < self.low {
+            Ramp {
+                good: -self.low,
+                bad: -self.dry,
+            }
+            .rate(-humidity)
+        } else {
+            Ramp {
+                good: self.high,
+                bad: self.humid,
+            }
+            .rate(humidity)
+        }
+    }
+}
+
+/// Weights of the ratings in the score, relative to their sum.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Weights {
+    /// Weight of the PM2.5 concentration.
+    pub pm2_5: f32,
+    /// Weight of the VOC index.
+    pub voc_index: f32,
+    /// Weight of the NOx index.
+    pub nox_index: f32,
+    /// Weight of the humidity.
+    pub humidity: f32,
+}
+
+/// Configuration of the composite score.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct IaqScore {
+    /// Weights of the ratings.
+    pub weights: Weights,
+    /// Rating of the PM2.5 concentration [µg/m³].
+    pub pm2_5: Ramp,
+    /// Rating of the VOC index.
+    pub voc_index: Ramp,
+    /// Rating of the NOx index.
+    pub nox_index: Ramp,
+    /// Rating of the humidity.
+    pub humidity: Comfort,
+}
+
+impl Default for IaqScore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl IaqScore {
+    /// The default weighting documented in the [module](self).
+    pub const fn new() -> Self {
+        Self {
+            weights: Weights {
+                pm2_5: 0.4,
+                voc_index: 0.3,
+                nox_index: 0.15,
+                humidity: 0.15,
+            },
+            pm2_5: Ramp {
+                good: 5.0,
+                bad: 75.0,
+            },
+            voc_index: Ramp {
+                good: 100.0,
+                bad: 400.0,
+            },
+            nox_index: Ramp {
+                good: 20.0,
+                bad: 250.0,
+            },
+            humidity: Comfort {
+                dry: 20.0,
+                low: 40.0,
+                high: 60.0,
+                humid: 80.0,
+            },
+        }
+    }
+
+    /// Use `weights` instead of the default ones, e.g. a weight of 0 to ignore a value.
+    pub const fn with_weights(self, weights: Weights) -> Self {
+        Self { weights, ..self }
+    }
+
+    /// The rating of the value `id` and its weight, `None` for values not part of the score.
+    pub fn rate(&self, id: FieldId, value: f32) -> Option<(f32, f32)> {
+        let weights = &self.weights;
+        match id {
+            FieldId::Pm2_5 => Some((self.pm2_5.rate(value), weights.pm2_5)),
+            FieldId::VocIndex => Some((self.voc_index.rate(value), weights.voc_index)),
+            FieldId::NoxIndex => Some((self.nox_index.rate(value), weights.nox_index)),
+            FieldId::Humidity => Some((self.humidity.rate(value), weights.humidity)),
+            _ => None,
+        }
+    }
+
+    /// The score of `data`, `None` if none of the rated values was measured.
+    pub fn score(&self, data: &Sen5xData) -> Option<f32> {
+        let (sum, weights) = FieldId::ALL
+            .into_iter()
+            .filter_map(|id| self.rate(id, data.value(id)?))
+            .fold((0.0, 0.0), |(sum, weights), (rating, weight)| {
+                (sum + rating * weight, weights + weight)
+            });
+        (weights > 0.0).then(|| sum / weights)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{Environment, GasIndices};
+
+    /// Test the ratings and their weighting, leaving out values not measured
+    #[test]
+    fn test_score() {
+        let iaq = IaqScore::new();
+        assert_eq!(iaq.pm2_5.rate(40.0), 50.0);
+        assert_eq!(iaq.humidity.rate(30.0), 50.0);
+        assert_eq!(iaq.humidity.rate(50.0), 100.0);
+        assert_eq!(iaq.humidity.rate(90.0), 0.0);
+        assert_eq!(iaq.rate(FieldId::Temperature, 35.0), None);
+
+        let mut data = crate::test_vectors::MEASUREMENTS[0].data;
+        data.pm.pm2_5 = 40.0;
+        data.env = Some(Environment {
+            humidity: 30.0,
+            temperature: 21.0,
+        });
+        data.gas = Some(GasIndices {
+            voc_index: 250.0,
+            nox_index: None,
+        });
+        // (0.4 * 50 + 0.3 * 50 + 0.15 * 50) / 0.85
+        assert_eq!(iaq.score(&data), Some(50.0));
+
+        data.env = None;
+        data.gas = None;
+        assert_eq!(iaq.score(&data), Some(50.0));
+        let pm_ignored = iaq.with_weights(Weights {
+            pm2_5: 0.0,
+            ..iaq.weights
+        });
+        assert_eq!(pm_ignored.score(&data), None);
+    }
+}
@@ -0,0 +1,108 @@
+//! Random jitter of poll times.
+//!
+//! Sensors sharing a bus or multiplexer which are all polled at the same fixed interval hit the
+//! bus in bursts, and once aligned they stay aligned. A [`Jitter`] adds a random delay of up to a
+//! maximum to every interval, so the transactions of different sensors drift apart. The random
+//! numbers come from an [`Rng`], a closure wrapping the hardware RNG of the controller or the
+//! small [`XorShift32`] generator, seeded differently on every device, e.g. from the serial
+//! number.
+//!
+//! ```
+//! use core::time::Duration;
+//! use sen5x_rs::jitter::{Jitter, XorShift32};
+//!
+//! let mut jitter = Jitter::new(XorShift32::new(0x5EED), Duration::from_millis(50));
+//! let interval = jitter.apply(Duration::from_millis(200));
+//! assert!(interval >= Duration::from_millis(200) && interval < Duration::from_millis(250));
+//! ```
+use core::time::Duration;
+
+/// A source of uniformly distributed random numbers.
+pub trait Rng {
+    /// The next random number.
+    fn next_u32(&mut self) -> u32;
+}
+
+impl<F: FnMut() -> u32> Rng for F {
+    fn next_u32(&mut self) -> u32 {
+        self()
+    }
+}
+
+/// The xorshift32 generator, small and fast but not cryptographically secure.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct XorShift32(u32);
+
+impl XorShift32 {
+    /// A generator seeded with `seed`. The seed `0`, which would only generate zeros, is replaced.
+    pub const fn new(seed: u32) -> Self {
+        Self(if seed == 0 { 0x9E37_79B9 } else { seed })
+    }
+}
+
+impl Rng for XorShift32 {
+    fn next_u32(&mut self) -> u32 {
+        let mut x = self.0;
+        x ^= x << 13;
+        x ^= x >> 17;
+        x ^= x << 5;
+        self.0 = x;
+        x
+    }
+}
+
+/// Adds a random delay to intervals.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Jitter<R> {
+    rng: R,
+    max: Duration,
+}
+
+impl<R: Rng> Jitter<R> {
+    /// Jitter of up to `max`, drawn from `rng`.
+    pub const fn new(rng: R, max: Duration) -> Self {
+        Self { rng, max }
+    }
+
+    /// The largest delay added.
+    pub fn max(&self) -> Duration {
+        self.max
+    }
+
+    /// A random delay below the maximum.
+    pub fn offset(&mut self) -> Duration {
+        let nanos = (self.max.as_nanos() * u128::from(self.rng.next_u32())) >> 32;
+        Duration::from_nanos(u64::try_from(nanos).unwrap_or(u64::MAX))
+    }
+
+    /// `interval` with a random delay below the maximum added.
+    pub fn apply(&mut self, interval: Duration) -> Duration {
+        interval.saturating_add(self.offset())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test that the delays stay below the maximum and spread over it
+    #[test]
+    fn test_jitter() {
+        let max = Duration::from_millis(100);
+        let mut jitter = Jitter::new(XorShift32::new(0), max);
+        let (mut low, mut high) = (false, false);
+        for _ in 0..1000 {
+            let offset = jitter.offset();
+            assert!(offset < max);
+            low |= offset < max / 10;
+            high |= offset > max * 9 / 10;
+        }
+        assert!(low && high);
+
+        let mut constant = Jitter::new(|| u32::MAX, max);
+        assert!(constant.apply(Duration::from_secs(1)) < Duration::from_secs(1) + max);
+        let mut none = Jitter::new(|| 0, max);
+        assert_eq!(none.apply(Duration::from_secs(1)), Duration::from_secs(1));
+        assert_eq!(none.max(), max);
+    }
+}
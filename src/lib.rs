@@ -2,15 +2,149 @@
 //! This driver was built using [embedded-hal](https://docs.rs/embedded-hal/) traits.
 //! The implementation is based on [scd4x-rs](https://github.com/hauju/scd4x-rs) and [sgpc3-rs](https://github.com/mjaakkol/sgpc3-rs).
 //! This driver is compatible with `embedded-hal` v1.0.
-#![cfg_attr(not(test), no_std)]
+//!
+//! The driver is enabled by the default `driver` feature. Without it, the crate only contains the
+//! types and the decoding and conversion code, which do not depend on `embedded-hal` and can be
+//! reused on the host side, e.g. on a gateway or in a WASM dashboard.
+//!
+//! These modules build for `wasm32-unknown-unknown`. With the `serde` feature, the measurement
+//! and status types implement `Serialize` and `Deserialize`, so a dashboard can decode frames
+//! forwarded over a WebSocket with [`parse`] and hand the result to JavaScript, e.g. with
+//! `serde-wasm-bindgen`.
+//!
+//! The features are grouped into tiers for parts with little flash, e.g. next to a BLE stack:
+//!
+//! - `minimal`: only the driver, reading raw and converted measurements.
+//! - default: additionally the derived metrics of the `metrics` feature, e.g. `air_quality`,
+//!   and the string formatting of the `fmt` feature, e.g. `csv` and `prometheus`.
+//! - `full`: additionally the async driver, the SPS30 driver, the `fugit` and
+//!   `embedded-storage` integrations and the postcard and CBOR payloads of `payload`.
+//!
+//! The conversion to physical units is part of every tier. Code using only
+//! `Sen5x::measurement_raw` does not link the floating point conversion.
+//!
+//! Features only cost flash when their code is used. The `size` crate in the repository reads,
+//! rates and formats a measurement with the code of a tier, and its WebAssembly size, built with
+//! `cargo build --release --target wasm32-unknown-unknown` and optimized for size, was:
+//!
+//! | Tier                 | Code used                           | Size    |
+//! |----------------------|-------------------------------------|---------|
+//! | `minimal`            | driver and conversion               | 3.1 kB  |
+//! | `minimal`, `metrics` | and the `iaq` score                 | 4.2 kB  |
+//! | default              | and a `csv` row                     | 24.5 kB |
+//! | `full`               | the same, the other features unused | 24.5 kB |
+//!
+//! The formatting of floating point numbers by `core::fmt` makes up most of the default tier.
+//! Sizes on other targets, e.g. Thumb, differ.
+//!
+//! The library does not panic: malformed responses and out of range parameters are reported as
+//! errors, and arithmetic on durations and counters saturates instead of overflowing.
+#![cfg_attr(not(any(test, feature = "std")), no_std)]
+#![cfg_attr(
+    not(test),
+    deny(
+        clippy::unwrap_used,
+        clippy::expect_used,
+        clippy::panic,
+        clippy::cast_possible_truncation
+    )
+)]
 
+#[cfg(feature = "driver")]
 mod sen5x;
-pub use crate::sen5x::Sen5x;
+#[cfg(feature = "driver")]
+pub use crate::sen5x::{NoDelay, Sen5x};
 
 pub mod commands;
+pub mod prelude;
 
 mod errors;
-pub use errors::Error;
+pub use errors::{Error, Result};
 
+pub mod parse;
 pub mod types;
-mod crc;
+pub mod fields;
+pub mod measurement;
+pub mod health;
+#[cfg(feature = "fmt")]
+pub mod csv;
+#[cfg(feature = "fmt")]
+pub mod prometheus;
+#[cfg(feature = "fmt")]
+pub mod format;
+#[cfg(feature = "homeassistant")]
+pub mod homeassistant;
+pub mod clock;
+pub mod sequence;
+pub mod jitter;
+pub mod crc;
+pub mod frame;
+pub mod model;
+#[cfg(any(feature = "postcard", feature = "cbor"))]
+pub mod payload;
+pub mod persist;
+#[cfg(feature = "driver")]
+pub mod saver;
+#[cfg(feature = "driver")]
+pub mod ramp;
+#[cfg(feature = "driver")]
+pub mod recovery;
+#[cfg(feature = "driver")]
+pub mod boot;
+#[cfg(feature = "driver")]
+pub mod lifecycle;
+#[cfg(feature = "driver")]
+pub mod observer;
+#[cfg(feature = "driver")]
+pub mod scan;
+#[cfg(feature = "driver")]
+pub mod multi;
+#[cfg(feature = "driver")]
+pub mod statics;
+#[cfg(feature = "driver")]
+pub mod queue;
+#[cfg(feature = "driver")]
+pub mod protocol;
+#[cfg(feature = "driver")]
+pub mod sen6x;
+#[cfg(feature = "sps30")]
+pub mod sps30;
+#[cfg(feature = "units")]
+pub mod units;
+#[cfg(feature = "metrics")]
+pub mod air_quality;
+#[cfg(feature = "metrics")]
+pub mod delta;
+#[cfg(feature = "metrics")]
+pub mod histogram;
+#[cfg(feature = "metrics")]
+pub mod smoothing;
+#[cfg(feature = "metrics")]
+pub mod iaq;
+#[cfg(feature = "metrics")]
+pub mod compliance;
+#[cfg(feature = "metrics")]
+pub mod heating;
+#[cfg(feature = "metrics")]
+pub mod pipeline;
+
+#[cfg(feature = "ffi")]
+pub mod ffi;
+
+#[cfg(feature = "async")]
+pub mod asynch;
+
+#[cfg(feature = "embedded-sensors")]
+mod sensors;
+
+#[cfg(feature = "embassy")]
+pub mod embassy;
+
+#[cfg(feature = "embedded-storage")]
+pub mod storage;
+
+#[cfg(all(feature = "driver", any(test, feature = "simulator")))]
+pub mod simulator;
+
+#[cfg(any(test, feature = "test-vectors"))]
+pub mod test_vectors;
@@ -0,0 +1,117 @@
+//! Lifecycle events of the driver.
+//!
+//! A [`LifecycleObserver`] registered with [`Sen5x::set_observer`] is told about every change of
+//! the lifecycle the driver performs, so logging and metrics of the driver activity can live in
+//! one place instead of around every call site. Functions and closures without captures are
+//! observers, observers with state use interior mutability, e.g. atomics.
+//!
+//! ```
+//! use core::sync::atomic::{AtomicU32, Ordering};
+//! # use embedded_hal_mock::eh1::{delay::NoopDelay, i2c::{Mock, Transaction}};
+//! use sen5x_rs::lifecycle::{LifecycleEvent, LifecycleObserver};
+//! use sen5x_rs::Sen5x;
+//!
+//! struct Starts(AtomicU32);
+//!
+//! impl LifecycleObserver for Starts {
+//!     fn on_event(&self, event: LifecycleEvent) {
+//!         if event == LifecycleEvent::Started {
+//!             self.0.fetch_add(1, Ordering::Relaxed);
+//!         }
+//!     }
+//! }
+//!
+//! static STARTS: Starts = Starts(AtomicU32::new(0));
+//!
+//! # let mut i2c = Mock::new(&[Transaction::write(0x69, vec![0x00, 0x21])]);
+//! let mut sensor = Sen5x::new(i2c.clone(), NoopDelay);
+//! sensor.set_observer(Some(&STARTS));
+//! sensor.start_measurement().unwrap();
+//! assert_eq!(STARTS.0.load(Ordering::Relaxed), 1);
+//! # i2c.done();
+//! ```
+use core::fmt;
+
+#[cfg(doc)]
+use crate::Sen5x;
+
+/// A change of the lifecycle performed by the driver.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum LifecycleEvent {
+    /// The periodic measurement was started.
+    Started,
+    /// The periodic measurement was stopped.
+    Stopped,
+    /// The fan cleaning was started manually.
+    CleaningStarted,
+    /// The sensor was reinitialized, by the reinit command or the general call reset.
+    Reinitialized,
+    /// A transaction succeeded after the previous one failed on the bus or with a corrupted
+    /// response.
+    RecoveredAfterFault,
+}
+
+/// Receives the [`LifecycleEvent`]s of a driver.
+///
+/// Called synchronously after the change, so it should return quickly and must not block.
+pub trait LifecycleObserver: Sync {
+    /// Handle `event`.
+    fn on_event(&self, event: LifecycleEvent);
+}
+
+impl<F: Fn(LifecycleEvent) + Sync> LifecycleObserver for F {
+    fn on_event(&self, event: LifecycleEvent) {
+        self(event);
+    }
+}
+
+impl fmt::Debug for dyn LifecycleObserver {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("LifecycleObserver")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulator::Simulator;
+    use crate::Sen5x;
+    use std::sync::Mutex;
+    use std::vec::Vec;
+
+    static EVENTS: Mutex<Vec<LifecycleEvent>> = Mutex::new(Vec::new());
+
+    fn record(event: LifecycleEvent) {
+        EVENTS.lock().unwrap().push(event);
+    }
+
+    /// Test that lifecycle changes of the driver are reported to the observer
+    #[test]
+    fn test_lifecycle_events() {
+        let sim = Simulator::new();
+        let mut sensor = Sen5x::new(sim.clone(), sim.delay());
+        sensor.set_observer(Some(&record));
+        sensor.start_measurement().unwrap();
+        sensor.start_fan_cleaning().unwrap();
+        sim.nack_transaction(0);
+        assert!(sensor.stop_measurement().is_err());
+        sensor.stop_measurement().unwrap();
+        sensor.reinit().unwrap();
+        sensor.general_call_reset().unwrap();
+        sensor.set_observer(None);
+        sensor.start_measurement().unwrap();
+
+        use LifecycleEvent::*;
+        assert_eq!(
+            *EVENTS.lock().unwrap(),
+            [
+                Started,
+                CleaningStarted,
+                RecoveredAfterFault,
+                Stopped,
+                Reinitialized,
+                Reinitialized
+            ]
+        );
+    }
+}
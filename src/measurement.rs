@@ -0,0 +1,544 @@
+//! Decoding of the measurements of every SEN5x and SEN6x variant into one type.
+//!
+//! The variants read their measurements with different commands and lay out the words of the
+//! response differently. [`DeviceVariant::layout`] describes the response of each variant as a
+//! table of [`Channel`]s, from which [`Measurement::decode`] fills the channels the variant
+//! measures, e.g. for code handling a mixed fleet of sensors.
+//!
+//! A [`ChannelMask`] restricts decoding to the channels an application cares about and selects
+//! the words of the compact encoding of [`Measurement::encode_compact`], e.g. for LPWAN links.
+//!
+//! ```
+//! use sen5x_rs::measurement::{Channel, ChannelMask, Measurement};
+//!
+//! let mask = ChannelMask::of(&[Channel::Pm2_5, Channel::Humidity]);
+//! let measurement = Measurement {
+//!     pm2_5: Some(2.2),
+//!     humidity: Some(55.14),
+//!     ..Measurement::default()
+//! };
+//! let mut buf = [0u8; 6];
+//! let len = measurement.encode_compact::<()>(mask, &mut buf)?;
+//! assert_eq!(buf[..len], [0x00, 0x12, 0x00, 0x16, 0x15, 0x8A]);
+//! assert_eq!(Measurement::decode_compact::<()>(&buf[..len])?, measurement);
+//! # Ok::<(), sen5x_rs::Error<()>>(())
+//! ```
+use crate::commands::{Field, MEASUREMENT_FIELDS};
+use crate::parse;
+use crate::types::{DeviceVariant, Environment, GasIndices, ParticulateMatter, Sen5xData};
+use crate::Error;
+
+/// A value measured by at least one variant.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Channel {
+    /// Mass Concentration PM1.0 [µg/m³]
+    Pm1_0,
+    /// Mass Concentration PM2.5 [µg/m³]
+    Pm2_5,
+    /// Mass Concentration PM4.0 [µg/m³]
+    Pm4_0,
+    /// Mass Concentration PM10.0 [µg/m³]
+    Pm10_0,
+    /// Ambient Humidity [%RH]
+    Humidity,
+    /// Ambient Temperature [°C]
+    Temperature,
+    /// VOC Index
+    VocIndex,
+    /// NOx Index
+    NoxIndex,
+    /// CO₂ concentration [ppm]
+    Co2,
+    /// Formaldehyde concentration [ppb]
+    Hcho,
+}
+
+impl Channel {
+    /// Name, signedness and scale of the word of the channel.
+    pub const fn field(self) -> Field {
+        match self {
+            Self::Co2 => Field::unsigned("co2", 1),
+            Self::Hcho => Field::unsigned("hcho", 10),
+            // The other channels are laid out like in the SEN5x response.
+            _ => MEASUREMENT_FIELDS[self as usize],
+        }
+    }
+}
+
+impl Channel {
+    /// Every channel, in the order of their bits in a [`ChannelMask`].
+    pub const ALL: [Self; 10] = [
+        Self::Pm1_0,
+        Self::Pm2_5,
+        Self::Pm4_0,
+        Self::Pm10_0,
+        Self::Humidity,
+        Self::Temperature,
+        Self::VocIndex,
+        Self::NoxIndex,
+        Self::Co2,
+        Self::Hcho,
+    ];
+
+    /// The word the sensors report until the channel is available.
+    const fn unknown(self) -> u16 {
+        if self.field().signed {
+            i16::MAX.cast_unsigned()
+        } else {
+            u16::MAX
+        }
+    }
+}
+
+/// A set of channels.
+///
+/// Defaults to all channels.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct ChannelMask(u16);
+
+impl Default for ChannelMask {
+    fn default() -> Self {
+        Self::ALL
+    }
+}
+
+impl ChannelMask {
+    /// No channel.
+    pub const NONE: Self = Self(0);
+    /// Every channel.
+    pub const ALL: Self = Self((1 << Channel::ALL.len()) - 1);
+
+    /// The set of `channels`.
+    pub const fn of(channels: &[Channel]) -> Self {
+        let mut mask = Self::NONE;
+        let mut i = 0;
+        while i < channels.len() {
+            mask = mask.with(channels[i]);
+            i += 1;
+        }
+        mask
+    }
+
+    /// The set with `channel` added.
+    pub const fn with(self, channel: Channel) -> Self {
+        Self(self.0 | 1 << channel as u16)
+    }
+
+    /// The set with `channel` removed.
+    pub const fn without(self, channel: Channel) -> Self {
+        Self(self.0 & !(1 << channel as u16))
+    }
+
+    /// Whether `channel` is in the set.
+    pub const fn contains(self, channel: Channel) -> bool {
+        self.0 & 1 << channel as u16 != 0
+    }
+
+    /// The bits of the set, bit `n` for the channel `n` of [`Channel::ALL`].
+    pub const fn bits(self) -> u16 {
+        self.0
+    }
+
+    /// The set of `bits`, ignoring bits of no channel.
+    pub const fn from_bits(bits: u16) -> Self {
+        Self(bits & Self::ALL.0)
+    }
+
+    /// Number of channels in the set.
+    pub const fn len(self) -> usize {
+        self.0.count_ones() as usize
+    }
+
+    /// Whether the set is empty.
+    pub const fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    /// The channels in the set, in the order of [`Channel::ALL`].
+    pub fn iter(self) -> impl Iterator<Item = Channel> {
+        Channel::ALL
+            .into_iter()
+            .filter(move |&channel| self.contains(channel))
+    }
+}
+
+/// The response of the command reading the measured values of a variant.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Layout {
+    /// Command code reading the measured values.
+    pub code: u16,
+    /// The channel of every word of the response.
+    pub channels: &'static [Channel],
+}
+
+const PM: [Channel; 4] = [
+    Channel::Pm1_0,
+    Channel::Pm2_5,
+    Channel::Pm4_0,
+    Channel::Pm10_0,
+];
+
+const SEN5X: [Channel; 8] = [
+    Channel::Pm1_0,
+    Channel::Pm2_5,
+    Channel::Pm4_0,
+    Channel::Pm10_0,
+    Channel::Humidity,
+    Channel::Temperature,
+    Channel::VocIndex,
+    Channel::NoxIndex,
+];
+
+const SEN63C: [Channel; 7] = [
+    Channel::Pm1_0,
+    Channel::Pm2_5,
+    Channel::Pm4_0,
+    Channel::Pm10_0,
+    Channel::Humidity,
+    Channel::Temperature,
+    Channel::Co2,
+];
+
+const SEN66: [Channel; 9] = [
+    Channel::Pm1_0,
+    Channel::Pm2_5,
+    Channel::Pm4_0,
+    Channel::Pm10_0,
+    Channel::Humidity,
+    Channel::Temperature,
+    Channel::VocIndex,
+    Channel::NoxIndex,
+    Channel::Co2,
+];
+
+const SEN68: [Channel; 9] = [
+    Channel::Pm1_0,
+    Channel::Pm2_5,
+    Channel::Pm4_0,
+    Channel::Pm10_0,
+    Channel::Humidity,
+    Channel::Temperature,
+    Channel::VocIndex,
+    Channel::NoxIndex,
+    Channel::Hcho,
+];
+
+impl DeviceVariant {
+    /// The layout of the measurement response of the variant.
+    pub const fn layout(self) -> Layout {
+        let (code, channels): (u16, &'static [Channel]) = match self {
+            // The SEN50 and SEN54 report the values they do not measure as unknown.
+            Self::Sen50 | Self::Sen54 | Self::Sen55 => (0x03C4, &SEN5X),
+            // Reads the mass concentrations, the number concentrations following them are skipped.
+            Self::Sen60 => (0xEC05, &PM),
+            Self::Sen63c => (0x0471, &SEN63C),
+            Self::Sen65 => (0x0446, &SEN5X),
+            Self::Sen66 => (0x0300, &SEN66),
+            Self::Sen68 => (0x0467, &SEN68),
+        };
+        Layout { code, channels }
+    }
+}
+
+impl DeviceVariant {
+    /// The channels the variant measures.
+    pub const fn channels(self) -> ChannelMask {
+        match self {
+            // The SEN54 reports the NOx index as unknown.
+            Self::Sen54 => ChannelMask::of(&SEN5X).without(Channel::NoxIndex),
+            // The layouts of the others contain only measured channels.
+            Self::Sen50 | Self::Sen60 => ChannelMask::of(&PM),
+            _ => ChannelMask::of(self.layout().channels),
+        }
+    }
+}
+
+impl Layout {
+    /// Number of words to read to get every channel of `mask` the layout contains.
+    pub fn words_for(&self, mask: ChannelMask) -> usize {
+        self.channels
+            .iter()
+            .rposition(|&channel| mask.contains(channel))
+            .map_or(0, |last| last + 1)
+    }
+}
+
+/// Size of the largest compact encoding of a measurement, with every channel.
+pub const COMPACT_MAX_SIZE: usize = 2 + 2 * Channel::ALL.len();
+
+/// Values of a measurement of any variant, channels which are not measured or not available yet
+/// are `None`.
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Measurement {
+    /// Mass Concentration PM1.0 [µg/m³]
+    pub pm1_0: Option<f32>,
+    /// Mass Concentration PM2.5 [µg/m³]
+    pub pm2_5: Option<f32>,
+    /// Mass Concentration PM4.0 [µg/m³]
+    pub pm4_0: Option<f32>,
+    /// Mass Concentration PM10.0 [µg/m³]
+    pub pm10_0: Option<f32>,
+    /// Ambient Humidity [%RH]
+    pub humidity: Option<f32>,
+    /// Ambient Temperature [°C]
+    pub temperature: Option<f32>,
+    /// VOC Index
+    pub voc_index: Option<f32>,
+    /// NOx Index
+    pub nox_index: Option<f32>,
+    /// CO₂ concentration [ppm]
+    pub co2: Option<f32>,
+    /// Formaldehyde concentration [ppb]
+    pub hcho: Option<f32>,
+}
+
+impl Measurement {
+    /// Decode the words of the measurement response of `variant`.
+    ///
+    /// Words missing from `words` leave their channels `None`, surplus words are ignored.
+    pub fn decode(variant: DeviceVariant, words: &[u16]) -> Self {
+        let mut measurement = Self::default();
+        for (&channel, &word) in variant.layout().channels.iter().zip(words) {
+            measurement.set_word(channel, word);
+        }
+        measurement
+    }
+
+    /// Decode the channels of `mask` from the measurement response `frame` of `variant`.
+    ///
+    /// Only the CRCs of the words of `mask` are validated and only they are converted, the other
+    /// channels are `None`. `frame` may end after the last word of `mask`, see
+    /// [`Layout::words_for`].
+    pub fn decode_masked<E>(
+        variant: DeviceVariant,
+        frame: &[u8],
+        mask: ChannelMask,
+    ) -> Result<Self, Error<E>> {
+        let layout = variant.layout();
+        let words = layout.words_for(mask);
+        if frame.len() < words * 3 || frame.len() > layout.channels.len() * 3 {
+            return Err(Error::WrongBufferSize);
+        }
+        let mut measurement = Self::default();
+        for (index, (&channel, chunk)) in layout.channels.iter().zip(frame.chunks(3)).enumerate() {
+            if mask.contains(channel) {
+                measurement.set_word(channel, parse::checked_word(chunk, index)?);
+            }
+        }
+        Ok(measurement)
+    }
+
+    /// Encode the channels of `mask` into `buf`, returning the number of bytes written.
+    ///
+    /// The encoding is the big-endian mask followed by the big-endian word of each channel of
+    /// `mask` as reported by the sensors, in the order of [`Channel::ALL`]. Channels without a
+    /// value are encoded as unknown. Fails with [`Error::WrongBufferSize`] if `buf` is too small.
+    pub fn encode_compact<E>(&self, mask: ChannelMask, buf: &mut [u8]) -> Result<usize, Error<E>> {
+        let len = 2 + 2 * mask.len();
+        let buf = buf.get_mut(..len).ok_or(Error::WrongBufferSize)?;
+        let (head, words) = buf.split_at_mut(2);
+        head.copy_from_slice(&mask.bits().to_be_bytes());
+        for (chunk, channel) in words.chunks_exact_mut(2).zip(mask.iter()) {
+            let word = self
+                .get(channel)
+                .map_or(channel.unknown(), |value| channel.field().word(value));
+            chunk.copy_from_slice(&word.to_be_bytes());
+        }
+        Ok(len)
+    }
+
+    /// Decode a measurement encoded with [`Measurement::encode_compact`].
+    ///
+    /// Fails with [`Error::WrongBufferSize`] if the length of `bytes` does not match its mask.
+    pub fn decode_compact<E>(bytes: &[u8]) -> Result<Self, Error<E>> {
+        let [hi, lo, ref words @ ..] = *bytes else {
+            return Err(Error::WrongBufferSize);
+        };
+        let mask = ChannelMask::from_bits(u16::from_be_bytes([hi, lo]));
+        if words.len() != 2 * mask.len() {
+            return Err(Error::WrongBufferSize);
+        }
+        let mut measurement = Self::default();
+        for (&[hi, lo], channel) in words.as_chunks::<2>().0.iter().zip(mask.iter()) {
+            measurement.set_word(channel, u16::from_be_bytes([hi, lo]));
+        }
+        Ok(measurement)
+    }
+
+    /// The value of `channel`.
+    pub fn get(&self, channel: Channel) -> Option<f32> {
+        match channel {
+            Channel::Pm1_0 => self.pm1_0,
+            Channel::Pm2_5 => self.pm2_5,
+            Channel::Pm4_0 => self.pm4_0,
+            Channel::Pm10_0 => self.pm10_0,
+            Channel::Humidity => self.humidity,
+            Channel::Temperature => self.temperature,
+            Channel::VocIndex => self.voc_index,
+            Channel::NoxIndex => self.nox_index,
+            Channel::Co2 => self.co2,
+            Channel::Hcho => self.hcho,
+        }
+    }
+
+    /// The SEN5x values of the measurement, `None` unless all mass concentrations are known.
+    pub fn to_sen5x_data(&self) -> Option<Sen5xData> {
+        Some(Sen5xData {
+            pm: ParticulateMatter {
+                pm1_0: self.pm1_0?,
+                pm2_5: self.pm2_5?,
+                pm4_0: self.pm4_0?,
+                pm10_0: self.pm10_0?,
+            },
+            env: self
+                .humidity
+                .zip(self.temperature)
+                .map(|(humidity, temperature)| Environment {
+                    humidity,
+                    temperature,
+                }),
+            gas: self.voc_index.map(|voc_index| GasIndices {
+                voc_index,
+                nox_index: self.nox_index,
+            }),
+        })
+    }
+
+    fn set_word(&mut self, channel: Channel, word: u16) {
+        *self.slot(channel) = (word != channel.unknown()).then(|| channel.field().value(word));
+    }
+
+    fn slot(&mut self, channel: Channel) -> &mut Option<f32> {
+        match channel {
+            Channel::Pm1_0 => &mut self.pm1_0,
+            Channel::Pm2_5 => &mut self.pm2_5,
+            Channel::Pm4_0 => &mut self.pm4_0,
+            Channel::Pm10_0 => &mut self.pm10_0,
+            Channel::Humidity => &mut self.humidity,
+            Channel::Temperature => &mut self.temperature,
+            Channel::VocIndex => &mut self.voc_index,
+            Channel::NoxIndex => &mut self.nox_index,
+            Channel::Co2 => &mut self.co2,
+            Channel::Hcho => &mut self.hcho,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test that the layouts of the variants select the channels of the words
+    #[test]
+    fn test_decode() {
+        let words = [18, 22, 24, 26, 5514, 4481, 360, 10, 650];
+        let sen66 = Measurement::decode(DeviceVariant::Sen66, &words);
+        assert_eq!(sen66.pm2_5, Some(2.2));
+        assert_eq!(sen66.temperature, Some(22.405));
+        assert_eq!(sen66.nox_index, Some(1.0));
+        assert_eq!(sen66.co2, Some(650.0));
+        assert_eq!(sen66.hcho, None);
+
+        let sen68 = Measurement::decode(DeviceVariant::Sen68, &words);
+        assert_eq!(sen68.co2, None);
+        assert_eq!(sen68.get(Channel::Hcho), Some(65.0));
+
+        let sen63c = Measurement::decode(DeviceVariant::Sen63c, &words[..7]);
+        assert_eq!(sen63c.voc_index, None);
+        assert_eq!(sen63c.co2, Some(360.0));
+
+        let sen50 = Measurement::decode(DeviceVariant::Sen50, &[18, 22, 24, 26, 0x7FFF, 0x7FFF]);
+        assert_eq!(sen50.pm10_0, Some(2.6));
+        assert_eq!(sen50.humidity, None);
+        assert_eq!(sen50.voc_index, None);
+
+        let sen60 = Measurement::decode(DeviceVariant::Sen60, &words);
+        assert_eq!(sen60.pm1_0, Some(1.8));
+        assert_eq!(sen60.humidity, None);
+
+        let sen55 = Measurement::decode(DeviceVariant::Sen55, &words);
+        assert_eq!(
+            sen55.to_sen5x_data(),
+            Some(crate::test_vectors::MEASUREMENTS[0].data)
+        );
+        assert_eq!(sen50.to_sen5x_data().map(|data| data.env), Some(None));
+        let humidity = Measurement {
+            humidity: Some(50.0),
+            ..Measurement::default()
+        };
+        assert_eq!(humidity.to_sen5x_data(), None);
+    }
+
+    /// Test that only the words of the mask are read and validated
+    #[test]
+    fn test_decode_masked() {
+        let vector = &crate::test_vectors::MEASUREMENTS[0];
+        let mask = ChannelMask::of(&[Channel::Pm2_5, Channel::Humidity]);
+        let layout = DeviceVariant::Sen55.layout();
+        assert_eq!(layout.words_for(mask), 5);
+        assert_eq!(layout.words_for(ChannelMask::of(&[Channel::Co2])), 0);
+
+        let mut frame = vector.frame;
+        // Corrupt the temperature, which is not in the mask.
+        frame[17] ^= 0xFF;
+        let result: Result<_, Error<()>> =
+            Measurement::decode_masked(DeviceVariant::Sen55, &frame[..15], mask);
+        let measurement = result.unwrap();
+        assert_eq!(measurement.get(Channel::Pm2_5), Some(vector.data.pm.pm2_5));
+        assert_eq!(
+            measurement.humidity,
+            vector.data.env.map(|env| env.humidity)
+        );
+        assert_eq!(measurement.pm1_0, None);
+
+        let result: Result<_, Error<()>> = Measurement::decode_masked(
+            DeviceVariant::Sen55,
+            &frame,
+            mask.with(Channel::Temperature),
+        );
+        assert_eq!(result, Err(Error::Crc { word: 5 }));
+        let result: Result<_, Error<()>> =
+            Measurement::decode_masked(DeviceVariant::Sen55, &frame[..12], mask);
+        assert_eq!(result, Err(Error::WrongBufferSize));
+    }
+
+    /// Test that the compact encoding round-trips and omits the channels not in the mask
+    #[test]
+    fn test_compact() {
+        let measurement = Measurement::decode(
+            DeviceVariant::Sen66,
+            &[18, 22, 24, 26, 5514, 0xFFEC, 0x7FFF, 10, 650],
+        );
+        let mut buf = [0u8; COMPACT_MAX_SIZE];
+        let len = measurement
+            .encode_compact::<()>(ChannelMask::ALL, &mut buf)
+            .unwrap();
+        assert_eq!(len, COMPACT_MAX_SIZE);
+        let decoded = Measurement::decode_compact::<()>(&buf[..len]).unwrap();
+        assert_eq!(decoded, measurement);
+        assert_eq!(decoded.temperature, Some(-0.1));
+        assert_eq!(decoded.voc_index, None);
+        assert_eq!(buf[len - 2..], [0xFF, 0xFF]);
+
+        let mask = ChannelMask::ALL
+            .without(Channel::Hcho)
+            .without(Channel::Pm4_0);
+        assert_eq!(mask.len(), 8);
+        let len = measurement.encode_compact::<()>(mask, &mut buf).unwrap();
+        assert_eq!(len, 18);
+        let decoded = Measurement::decode_compact::<()>(&buf[..len]).unwrap();
+        assert_eq!(decoded.pm4_0, None);
+        assert_eq!(decoded.co2, Some(650.0));
+
+        assert_eq!(
+            measurement.encode_compact::<()>(mask, &mut buf[..17]),
+            Err(Error::WrongBufferSize)
+        );
+        assert_eq!(
+            Measurement::decode_compact::<()>(&buf[..len - 1]),
+            Err(Error::WrongBufferSize)
+        );
+    }
+}
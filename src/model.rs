@@ -0,0 +1,183 @@
+//! Encoding of sensor responses, the inverse of [`crate::parse`].
+//!
+//! The model turns values into the words the sensor responds with and frames the words with a CRC
+//! byte each, exactly as the sensor does. It is pure and does not model timing or state, so
+//! decoding an encoded response yields the original value. The simulator builds its responses
+//! with it, and valid frames from it seed the fuzz corpus, since random bytes rarely pass the CRC.
+//!
+//! ```
+//! use sen5x_rs::{model, parse};
+//!
+//! let frame = model::firmware_version(2);
+//! assert_eq!(frame, [0x02, 0x00, 0x58]);
+//! assert_eq!(parse::firmware_version::<()>(&frame), Ok(2));
+//! ```
+#![cfg_attr(not(test), deny(clippy::indexing_slicing))]
+use crate::crc;
+use crate::parse::{
+    ASCII_LEN, DATA_READY_LEN, DEVICE_STATUS_LEN, FIRMWARE_VERSION_LEN, MEASUREMENT_LEN,
+};
+use crate::types::{DeviceStatus, Sen5xDataRaw, SerialNumber};
+
+/// Write `words` to `frame`, each followed by its CRC, and return the number of bytes written.
+///
+/// Words which do not fit into `frame` are left out.
+pub fn encode_words(words: &[u16], frame: &mut [u8]) -> usize {
+    let mut len = 0;
+    for (chunk, word) in frame.chunks_exact_mut(3).zip(words) {
+        let [hi, lo] = word.to_be_bytes();
+        chunk.copy_from_slice(&[hi, lo, crc::crc(&[hi, lo])]);
+        len += 3;
+    }
+    len
+}
+
+/// The frame of a response consisting of `words`.
+fn response<const W: usize, const N: usize>(words: [u16; W]) -> [u8; N] {
+    const { assert!(N == W * 3) };
+    let mut frame = [0u8; N];
+    encode_words(&words, &mut frame);
+    frame
+}
+
+/// The words of a `Read Measured Values` response holding `data`.
+pub fn measurement_words(data: &Sen5xDataRaw) -> [u16; 8] {
+    [
+        data.pm1_0,
+        data.pm2_5,
+        data.pm4_0,
+        data.pm10_0,
+        data.humidity.cast_unsigned(),
+        data.temperature.cast_unsigned(),
+        data.voc_index.cast_unsigned(),
+        data.nox_index.cast_unsigned(),
+    ]
+}
+
+/// The words of a 16 word ASCII string response holding `text`.
+pub fn ascii_words(text: &[u8; 32]) -> [u16; 16] {
+    let mut words = [0u16; 16];
+    for (word, &pair) in words.iter_mut().zip(text.as_chunks::<2>().0) {
+        *word = u16::from_be_bytes(pair);
+    }
+    words
+}
+
+/// The words of a `Read Device Status` response holding `bits`.
+pub fn device_status_words(bits: u32) -> [u16; 2] {
+    let [b0, b1, b2, b3] = bits.to_be_bytes();
+    [u16::from_be_bytes([b0, b1]), u16::from_be_bytes([b2, b3])]
+}
+
+/// The word of a `Read Firmware Version` response holding `version`.
+pub fn firmware_version_word(version: u8) -> u16 {
+    u16::from(version) << 8
+}
+
+/// Encode a `Read Measured Values` response.
+pub fn measurement(data: &Sen5xDataRaw) -> [u8; MEASUREMENT_LEN] {
+    response(measurement_words(data))
+}
+
+/// Encode a `Read Data-Ready Flag` response.
+pub fn data_ready(ready: bool) -> [u8; DATA_READY_LEN] {
+    response([u16::from(ready)])
+}
+
+/// Encode a `Read Product Name` or `Read Serial Number` response holding `text`.
+pub fn ascii(text: &[u8; 32]) -> [u8; ASCII_LEN] {
+    response(ascii_words(text))
+}
+
+/// Encode a `Read Serial Number` response.
+pub fn serial_number(serial: &SerialNumber) -> [u8; ASCII_LEN] {
+    ascii(serial.as_bytes())
+}
+
+/// Encode a `Read Device Status` response.
+pub fn device_status(status: DeviceStatus) -> [u8; DEVICE_STATUS_LEN] {
+    response(device_status_words(status.bits()))
+}
+
+/// Encode a `Read Firmware Version` response.
+pub fn firmware_version(version: u8) -> [u8; FIRMWARE_VERSION_LEN] {
+    response([firmware_version_word(version)])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frame::{self, MAX_WORDS};
+    use crate::parse;
+    use crate::Error;
+    use proptest::collection::vec;
+    use proptest::prelude::*;
+
+    type Result<T> = core::result::Result<T, Error<()>>;
+
+    fn raw() -> impl Strategy<Value = Sen5xDataRaw> {
+        (any::<[u16; 4]>(), any::<[i16; 4]>()).prop_map(|(pm, [rh, t, voc, nox])| Sen5xDataRaw {
+            pm1_0: pm[0],
+            pm2_5: pm[1],
+            pm4_0: pm[2],
+            pm10_0: pm[3],
+            humidity: rh,
+            temperature: t,
+            voc_index: voc,
+            nox_index: nox,
+        })
+    }
+
+    /// Test that the model reproduces the known-good frames
+    #[test]
+    fn test_known_frames() {
+        for vector in crate::test_vectors::MEASUREMENTS {
+            assert_eq!(measurement(&vector.raw), vector.frame, "{}", vector.name);
+        }
+        assert_eq!(data_ready(true), [0x00, 0x01, 0xB0]);
+    }
+
+    proptest! {
+        /// Test that decoding an encoded measurement yields the original values
+        #[test]
+        fn test_measurement_round_trip(data in raw()) {
+            let decoded: Result<_> = parse::measurement(&measurement(&data));
+            prop_assert_eq!(decoded, Ok(data));
+        }
+
+        /// Test that decoding the other encoded responses yields the original values
+        #[test]
+        fn test_response_round_trip(
+            ready in any::<bool>(),
+            text in any::<[u8; 32]>(),
+            bits in any::<u32>(),
+            version in any::<u8>(),
+        ) {
+            prop_assert_eq!(parse::data_ready::<()>(&data_ready(ready)), Ok(ready));
+            prop_assert_eq!(parse::product_name::<()>(&ascii(&text)), Ok(text));
+            let serial = SerialNumber::from_bytes(text);
+            prop_assert_eq!(parse::serial_number::<()>(&serial_number(&serial)), Ok(serial));
+            let status = DeviceStatus::from_bits(bits);
+            prop_assert_eq!(parse::device_status::<()>(&device_status(status)), Ok(status));
+            prop_assert_eq!(parse::firmware_version::<()>(&firmware_version(version)), Ok(version));
+        }
+
+        /// Test that any words survive framing and that a corrupted CRC is located
+        #[test]
+        fn test_words_round_trip(words in vec(any::<u16>(), 0..=MAX_WORDS), flip in any::<usize>()) {
+            let mut bytes = [0u8; MAX_WORDS * 3];
+            let len = encode_words(&words, &mut bytes);
+            prop_assert_eq!(len, words.len() * 3);
+            let mut decoded = [0u16; MAX_WORDS];
+            let result: Result<_> = frame::decode(&bytes[..len], &mut decoded[..words.len()]);
+            prop_assert_eq!(result, Ok(()));
+            prop_assert_eq!(&decoded[..words.len()], &words[..]);
+
+            if let Some(word) = flip.checked_rem(words.len()) {
+                bytes[word * 3 + 2] ^= 0xFF;
+                let result: Result<_> = frame::decode(&bytes[..len], &mut decoded[..words.len()]);
+                prop_assert_eq!(result, Err(Error::Crc { word }));
+            }
+        }
+    }
+}
@@ -0,0 +1,257 @@
+//! Several sensors behind an I²C multiplexer.
+//!
+//! All SEN5x sensors use the same address, so rigs with more than one sensor put them on separate
+//! channels of a multiplexer such as the TCA9548A. [`MultiSen5x`] drives them through a single
+//! [`Sen5x`] and a closure selecting the channel of a sensor. What the driver tracks about a
+//! sensor, e.g. whether it is measuring, its firmware version and its faults, is kept per sensor.
+//! [`MultiSen5x::poll`] checks one sensor per call in turn, which staggers the data-ready polls
+//! over the signal update interval.
+//!
+//! ```
+//! # use embedded_hal_mock::eh1::{delay::NoopDelay, i2c::Mock};
+//! use embedded_hal::i2c::I2c;
+//! use sen5x_rs::multi::MultiSen5x;
+//! use sen5x_rs::Sen5x;
+//!
+//! # let mut mock = Mock::new(&[]);
+//! # let i2c = mock.clone();
+//! const MUX_ADDRESS: u8 = 0x70;
+//! let sensor = Sen5x::new(i2c, NoopDelay);
+//! let mut sensors = MultiSen5x::<_, _, _, 4>::new(sensor, |i2c: &mut Mock, id: usize| {
+//!     i2c.write(MUX_ADDRESS, &[1 << id])
+//! });
+//! # mock.done();
+//! ```
+use embedded_hal::{delay::DelayNs, i2c::I2c};
+
+use crate::errors::bus_error;
+use crate::sen5x::SensorState;
+use crate::types::Sen5xData;
+use crate::{Error, Sen5x};
+
+/// Round-robin manager of `N` sensors sharing one bus through a multiplexer.
+#[derive(Debug)]
+pub struct MultiSen5x<I2C, D, S, const N: usize> {
+    sensor: Sen5x<I2C, D>,
+    select: S,
+    selected: Option<usize>,
+    /// State of every sensor, that of the selected one is held by `sensor`.
+    states: [SensorState; N],
+    next: usize,
+}
+
+impl<I2C, D, S, E, const N: usize> MultiSen5x<I2C, D, S, N>
+where
+    I2C: I2c<Error = E>,
+    D: DelayNs,
+    S: FnMut(&mut I2C, usize) -> Result<(), E>,
+{
+    /// Create a manager driving the sensors through `sensor`.
+    ///
+    /// `select` is called with the bus and the id of a sensor, from 0 to `N - 1`, and has to route
+    /// the bus to that sensor.
+    pub fn new(sensor: Sen5x<I2C, D>, select: S) -> Self {
+        Self {
+            sensor,
+            select,
+            selected: None,
+            states: [SensorState::default(); N],
+            next: 0,
+        }
+    }
+
+    /// Route the bus to the sensor `id` and return the driver to issue commands to it.
+    pub fn select(&mut self, id: usize) -> Result<&mut Sen5x<I2C, D>, Error<E>> {
+        if id >= N {
+            return Err(Error::InvalidInput {
+                field: "sensor_id",
+                value: i64::try_from(id).unwrap_or(i64::MAX),
+            });
+        }
+        if self.selected != Some(id) {
+            if let Some(selected) = self.selected {
+                self.states[selected] = self.sensor.sensor_state();
+            }
+            // Forget the selection in case routing fails half way.
+            self.selected = None;
+            (self.select)(self.sensor.i2c_mut(), id).map_err(bus_error::<I2C>)?;
+            self.selected = Some(id);
+            self.sensor.set_sensor_state(self.states[id]);
+        }
+        Ok(&mut self.sensor)
+    }
+
+    /// Start the periodic measurement of every sensor.
+    pub fn start_measurement(&mut self) -> Result<(), Error<E>> {
+        (0..N).try_for_each(|id| self.select(id)?.start_measurement())
+    }
+
+    /// Stop the periodic measurement of every sensor.
+    pub fn stop_measurement(&mut self) -> Result<(), Error<E>> {
+        (0..N).try_for_each(|id| self.select(id)?.stop_measurement())
+    }
+
+    /// Check the next sensor in turn and read its measurement if new data is available.
+    ///
+    /// Call this at `N` times the rate at which the sensors should be read, e.g. every 250 ms for
+    /// four sensors. The turn advances even if reading the sensor fails, so one broken sensor
+    /// does not stall the others.
+    pub fn poll(&mut self) -> Result<Option<(usize, Sen5xData)>, Error<E>> {
+        let id = self.next;
+        self.next = (self.next + 1) % N.max(1);
+        let sensor = self.select(id)?;
+        if !sensor.data_ready_status()? {
+            return Ok(None);
+        }
+        sensor.measurement().map(|data| Some((id, data)))
+    }
+
+    /// The id of the sensor the bus is routed to, `None` before the first selection.
+    pub fn selected(&self) -> Option<usize> {
+        self.selected
+    }
+
+    /// Release the driver and the selection closure.
+    pub fn release(self) -> (Sen5x<I2C, D>, S) {
+        (self.sensor, self.select)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use embedded_hal_mock as hal;
+
+    use self::hal::eh1::delay::NoopDelay as DelayMock;
+    use self::hal::eh1::i2c::{Mock as I2cMock, Transaction};
+    use super::*;
+    use crate::commands::Command;
+    use crate::simulator::{SimDelay, Simulator};
+    use crate::test_vectors::{DATA_NOT_READY, DATA_READY, MEASUREMENTS};
+    use crate::types::DriverState;
+    use embedded_hal::i2c::{ErrorKind, ErrorType, Operation};
+
+    const ADDRESS: u8 = 0x69;
+    const MUX_ADDRESS: u8 = 0x70;
+
+    fn route(id: u8) -> Transaction {
+        Transaction::write(MUX_ADDRESS, vec![1 << id])
+    }
+
+    fn command(cmd: Command) -> Transaction {
+        let (cmd, _, _) = cmd.as_tuple();
+        Transaction::write(ADDRESS, cmd.to_be_bytes().to_vec())
+    }
+
+    /// Test that the sensors are polled in turn and their states are kept apart
+    #[test]
+    fn test_round_robin() {
+        let expectations = [
+            route(0),
+            command(Command::StartMeasurement),
+            route(1),
+            command(Command::StartMeasurement),
+            route(0),
+            command(Command::GetReadDataReadyStatus),
+            Transaction::read(ADDRESS, DATA_NOT_READY.to_vec()),
+            route(1),
+            command(Command::GetReadDataReadyStatus),
+            Transaction::read(ADDRESS, DATA_READY.to_vec()),
+            command(Command::ReadMeasurement),
+            Transaction::read(ADDRESS, MEASUREMENTS[0].frame.to_vec()),
+            route(0),
+            command(Command::StopMeasurement),
+        ];
+        let mut mock = I2cMock::new(&expectations);
+        let sensor = Sen5x::new(mock.clone(), DelayMock);
+        let mut sensors = MultiSen5x::<_, _, _, 2>::new(sensor, |i2c: &mut I2cMock, id: usize| {
+            i2c.write(MUX_ADDRESS, &[1 << id])
+        });
+        assert_eq!(sensors.selected(), None);
+        sensors.start_measurement().unwrap();
+        assert_eq!(sensors.poll().unwrap(), None);
+        assert_eq!(sensors.poll().unwrap(), Some((1, MEASUREMENTS[0].data)));
+        let sensor = sensors.select(0).unwrap();
+        assert!(sensor.is_measuring());
+        sensor.stop_measurement().unwrap();
+        // Selecting the sensor again does not route the bus again.
+        assert!(!sensors.select(0).unwrap().is_measuring());
+        assert_eq!(
+            sensors.select(2).err(),
+            Some(Error::InvalidInput {
+                field: "sensor_id",
+                value: 2
+            })
+        );
+        mock.done();
+    }
+
+    /// Sensors behind a multiplexer which routes the bus to one of them.
+    struct Mux {
+        sensors: [Simulator; 2],
+        selected: usize,
+    }
+
+    impl ErrorType for Mux {
+        type Error = ErrorKind;
+    }
+
+    impl I2c for Mux {
+        fn transaction(
+            &mut self,
+            address: u8,
+            operations: &mut [Operation<'_>],
+        ) -> Result<(), ErrorKind> {
+            self.sensors[self.selected].transaction(address, operations)
+        }
+    }
+
+    /// Advances the clocks of all sensors.
+    struct MuxDelay([SimDelay; 2]);
+
+    impl DelayNs for MuxDelay {
+        fn delay_ns(&mut self, ns: u32) {
+            self.0.iter_mut().for_each(|delay| delay.delay_ns(ns));
+        }
+    }
+
+    /// Test that the firmware version, faults and samples are tracked per sensor
+    #[test]
+    fn test_sensor_state() {
+        let sims = [Simulator::new(), Simulator::new()];
+        sims[0].set_firmware_version(1);
+        let mux = Mux {
+            sensors: sims.clone(),
+            selected: 0,
+        };
+        let sensor = Sen5x::new(mux, MuxDelay(sims.clone().map(|sim| sim.delay())));
+        let mut sensors = MultiSen5x::<_, _, _, 2>::new(sensor, |mux: &mut Mux, id: usize| {
+            mux.selected = id;
+            Ok(())
+        });
+
+        assert_eq!(sensors.select(0).unwrap().read_firmware_version(), Ok(1));
+        assert_eq!(sensors.select(1).unwrap().firmware_version(), None);
+        sensors.start_measurement().unwrap();
+        sims.iter().for_each(|sim| sim.advance_ms(1000));
+        let sensor = sensors.select(1).unwrap();
+        assert!(sensor.raw_values().is_ok());
+        sensor.measurement().unwrap();
+        assert_eq!(sensor.state(), DriverState::Measuring);
+
+        let sensor = sensors.select(0).unwrap();
+        assert_eq!(sensor.firmware_version(), Some(1));
+        assert_eq!(sensor.state(), DriverState::Starting);
+        assert_eq!(
+            sensor.raw_values(),
+            Err(Error::UnsupportedFirmware {
+                required: 2,
+                found: 1
+            })
+        );
+        sims[0].nack_transaction(0);
+        assert!(sensor.measurement().is_err());
+        assert_eq!(sensor.state(), DriverState::Error);
+        assert_eq!(sensors.select(1).unwrap().state(), DriverState::Measuring);
+        assert_eq!(sensors.select(0).unwrap().state(), DriverState::Error);
+    }
+}
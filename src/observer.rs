@@ -0,0 +1,240 @@
+//! Observers of new measurements.
+//!
+//! A [`MeasurementObserver`] registered with [`Sen5x::set_measurement_observer`] sees every
+//! sample read by [`Sen5x::measurement`], [`Sen5x::measure_blocking`],
+//! [`Sen5x::poll_measurement`], [`Sen5x::measurement_pm`] and [`Sen5x::measurement_channels`],
+//! and the errors of these reads, so a display, a logger and an alert engine can each consume the
+//! samples without restructuring the main loop. The asynchronous driver and the Embassy tasks
+//! report to an observer in the same way. An array of observers forwards every sample to each of
+//! them, without allocating.
+//!
+//! ```
+//! use core::sync::atomic::{AtomicU32, Ordering};
+//! # use embedded_hal_mock::eh1::{delay::NoopDelay, i2c::{Mock, Transaction}};
+//! use sen5x_rs::observer::MeasurementObserver;
+//! use sen5x_rs::types::Sen5xData;
+//! use sen5x_rs::Sen5x;
+//!
+//! static SAMPLES: AtomicU32 = AtomicU32::new(0);
+//!
+//! fn count(_: &Sen5xData) {
+//!     SAMPLES.fetch_add(1, Ordering::Relaxed);
+//! }
+//!
+//! fn show(data: &Sen5xData) {
+//!     // Update the display with `data`.
+//! }
+//!
+//! static OBSERVERS: [&dyn MeasurementObserver; 2] = [&count, &show];
+//!
+//! # let frame = vec![
+//! #     0x00, 0x12, 0xA0, 0x00, 0x16, 0x64, 0x00, 0x18, 0x7B, 0x00, 0x1A, 0x19, 0x15, 0x8A, 0x39,
+//! #     0x11, 0x81, 0x50, 0x01, 0x68, 0x77, 0x00, 0x0A, 0x5A,
+//! # ];
+//! # let mut i2c = Mock::new(&[
+//! #     Transaction::write(0x69, vec![0x00, 0x21]),
+//! #     Transaction::write(0x69, vec![0x03, 0xC4]),
+//! #     Transaction::read(0x69, frame),
+//! # ]);
+//! let mut sensor = Sen5x::new(i2c.clone(), NoopDelay);
+//! sensor.set_measurement_observer(Some(&OBSERVERS));
+//! sensor.start_measurement().unwrap();
+//! sensor.measurement().unwrap();
+//! assert_eq!(SAMPLES.load(Ordering::Relaxed), 1);
+//! # i2c.done();
+//! ```
+use core::fmt;
+
+use crate::measurement::Measurement;
+use crate::types::Sen5xData;
+use crate::Error;
+#[cfg(doc)]
+use crate::Sen5x;
+
+/// Receives every new sample of a driver.
+///
+/// Called synchronously before the sample is returned, so it should return quickly and must not
+/// block.
+pub trait MeasurementObserver: Sync {
+    /// Handle the new sample `data`.
+    fn on_measurement(&self, data: &Sen5xData);
+
+    /// Handle a new sample holding only the channels read by [`Sen5x::measurement_channels`].
+    ///
+    /// By default passed to [`MeasurementObserver::on_measurement`] if all mass concentrations
+    /// were read.
+    fn on_channels(&self, measurement: &Measurement) {
+        if let Some(data) = measurement.to_sen5x_data() {
+            self.on_measurement(&data);
+        }
+    }
+
+    /// Handle a failed read of a sample, with the error of the bus left out. Ignored by default.
+    fn on_error(&self, error: &Error<()>) {
+        let _ = error;
+    }
+}
+
+impl<F: Fn(&Sen5xData) + Sync> MeasurementObserver for F {
+    fn on_measurement(&self, data: &Sen5xData) {
+        self(data);
+    }
+}
+
+impl<const N: usize> MeasurementObserver for [&dyn MeasurementObserver; N] {
+    fn on_measurement(&self, data: &Sen5xData) {
+        for observer in self {
+            observer.on_measurement(data);
+        }
+    }
+
+    fn on_channels(&self, measurement: &Measurement) {
+        for observer in self {
+            observer.on_channels(measurement);
+        }
+    }
+
+    fn on_error(&self, error: &Error<()>) {
+        for observer in self {
+            observer.on_error(error);
+        }
+    }
+}
+
+/// Report the sample of `result`, with `sample`, or its error to `observer`.
+pub(crate) fn notify<T, E>(
+    observer: Option<&dyn MeasurementObserver>,
+    result: &Result<T, Error<E>>,
+    sample: impl FnOnce(&dyn MeasurementObserver, &T),
+) {
+    if let Some(observer) = observer {
+        match result {
+            Ok(value) => sample(observer, value),
+            Err(err) => observer.on_error(&err.without_bus()),
+        }
+    }
+}
+
+impl fmt::Debug for dyn MeasurementObserver {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("MeasurementObserver")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulator::Simulator;
+    use crate::Sen5x;
+    use core::sync::atomic::{AtomicU32, Ordering};
+
+    static FIRST: AtomicU32 = AtomicU32::new(0);
+    static SECOND: AtomicU32 = AtomicU32::new(0);
+
+    fn first(_: &Sen5xData) {
+        FIRST.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn second(data: &Sen5xData) {
+        assert!(data.pm.pm2_5 > 0.0);
+        SECOND.fetch_add(1, Ordering::Relaxed);
+    }
+
+    static OBSERVERS: [&dyn MeasurementObserver; 2] = [&first, &second];
+
+    /// Test that every sample read is forwarded to all observers
+    #[test]
+    fn test_measurement_observer() {
+        let sim = Simulator::new();
+        let mut sensor = Sen5x::new(sim.clone(), sim.delay());
+        sensor.set_measurement_observer(Some(&OBSERVERS));
+        sensor.start_measurement().unwrap();
+        sensor.measure_blocking().unwrap();
+        let sample = (0..100).find_map(|_| {
+            sim.advance_ms(20);
+            sensor.poll_measurement().unwrap()
+        });
+        assert!(sample.is_some());
+        assert_eq!(FIRST.load(Ordering::Relaxed), 2);
+        assert_eq!(SECOND.load(Ordering::Relaxed), 2);
+
+        sensor.set_measurement_observer(None);
+        sensor.measurement().unwrap();
+        assert_eq!(FIRST.load(Ordering::Relaxed), 2);
+    }
+
+    static SAMPLES: AtomicU32 = AtomicU32::new(0);
+    static CHANNELS: AtomicU32 = AtomicU32::new(0);
+    static ERRORS: AtomicU32 = AtomicU32::new(0);
+
+    /// Counts the samples, the channel reads and the errors.
+    struct Counter;
+
+    impl MeasurementObserver for Counter {
+        fn on_measurement(&self, _: &Sen5xData) {
+            SAMPLES.fetch_add(1, Ordering::Relaxed);
+        }
+
+        fn on_channels(&self, _: &Measurement) {
+            CHANNELS.fetch_add(1, Ordering::Relaxed);
+        }
+
+        fn on_error(&self, error: &Error<()>) {
+            assert!(matches!(error, Error::Nack(()) | Error::NotAllowed));
+            ERRORS.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Test that the partial reads and the failed reads are reported
+    #[test]
+    fn test_partial_reads_and_errors() {
+        use crate::measurement::{Channel, ChannelMask};
+        use crate::types::DriverState;
+
+        let sim = Simulator::new();
+        let mut sensor = Sen5x::new(sim.clone(), sim.delay());
+        sensor.set_measurement_observer(Some(&Counter));
+        assert!(sensor.measure_blocking().is_err());
+        assert_eq!(ERRORS.load(Ordering::Relaxed), 1);
+
+        sensor.start_measurement().unwrap();
+        sim.advance_ms(1000);
+        sensor.set_channel_mask(ChannelMask::NONE);
+        sensor.measurement_channels().unwrap();
+        assert_eq!(CHANNELS.load(Ordering::Relaxed), 0);
+        assert_eq!(sensor.state(), DriverState::Starting);
+        sensor.set_channel_mask(ChannelMask::of(&[Channel::Pm2_5]));
+        sensor.measurement_channels().unwrap();
+        assert_eq!(CHANNELS.load(Ordering::Relaxed), 1);
+        sensor.measurement_pm().unwrap();
+        assert_eq!(SAMPLES.load(Ordering::Relaxed), 1);
+
+        sim.nack_transaction(0);
+        assert!(sensor.measurement().is_err());
+        assert_eq!(ERRORS.load(Ordering::Relaxed), 2);
+        assert_eq!(SAMPLES.load(Ordering::Relaxed), 1);
+    }
+
+    /// Test that a mask with all mass concentrations is passed on as a sample by default
+    #[test]
+    fn test_channels_default() {
+        static PM: AtomicU32 = AtomicU32::new(0);
+
+        fn count(data: &Sen5xData) {
+            assert_eq!(data.env, None);
+            PM.fetch_add(1, Ordering::Relaxed);
+        }
+
+        let observer: &dyn MeasurementObserver = &count;
+        let pm = Measurement {
+            pm1_0: Some(1.0),
+            pm2_5: Some(2.0),
+            pm4_0: Some(3.0),
+            pm10_0: Some(4.0),
+            ..Measurement::default()
+        };
+        observer.on_channels(&pm);
+        observer.on_channels(&Measurement::default());
+        assert_eq!(PM.load(Ordering::Relaxed), 1);
+    }
+}
@@ -0,0 +1,280 @@
+//! Pure decoding functions for sensor responses.
+//!
+//! The functions take the exact bytes read from the sensor, including the CRC byte following each
+//! word, and do not touch the bus. They never panic on malformed input.
+//!
+//! The sizes of the frames are checked against the commands at compile time, and the frames are
+//! only accessed through iterators and chunks, so no input can index out of bounds.
+#![cfg_attr(not(test), deny(clippy::indexing_slicing))]
+use crate::commands::Command;
+use crate::crc;
+use crate::frame::{response_len, MAX_WORDS};
+use crate::measurement::Measurement;
+use crate::types::{
+    DeviceStatus, DeviceVariant, ParticulateMatter, RawValues, Sen5xDataRaw, SerialNumber,
+};
+use crate::Error;
+
+/// Bytes of a `Read Measured Values` response.
+pub const MEASUREMENT_LEN: usize = response_len(Command::ReadMeasurement);
+/// Bytes of the mass concentrations at the start of a `Read Measured Values` response.
+pub const MEASUREMENT_PM_LEN: usize = 4 * 3;
+/// Bytes of a `Read Data-Ready Flag` response.
+pub const DATA_READY_LEN: usize = response_len(Command::GetReadDataReadyStatus);
+/// Bytes of a `Read Product Name` or `Read Serial Number` response.
+pub const ASCII_LEN: usize = response_len(Command::ReadProductName);
+/// Bytes of a `Read Device Status` response.
+pub const DEVICE_STATUS_LEN: usize = response_len(Command::ReadDeviceStatus);
+/// Bytes of a `Read Firmware Version` response.
+pub const FIRMWARE_VERSION_LEN: usize = response_len(Command::ReadFirmwareVersion);
+/// Bytes of a `Read Measured Raw Values` response.
+pub const RAW_VALUES_LEN: usize = response_len(Command::ReadRawValues);
+
+// The decoders interpret as many words as the commands respond with.
+const _: () = {
+    assert!(MEASUREMENT_LEN == 8 * 3);
+    assert!(MEASUREMENT_PM_LEN <= MEASUREMENT_LEN);
+    assert!(DATA_READY_LEN == 3);
+    assert!(ASCII_LEN == 16 * 3);
+    assert!(response_len(Command::GetSerialNumber) == ASCII_LEN);
+    assert!(DEVICE_STATUS_LEN == 2 * 3);
+    assert!(response_len(Command::ReadAndClearDeviceStatus) == DEVICE_STATUS_LEN);
+    assert!(FIRMWARE_VERSION_LEN == 3);
+    assert!(RAW_VALUES_LEN == 4 * 3);
+    assert!(MEASUREMENT_LEN <= MAX_WORDS * 3 && ASCII_LEN <= MAX_WORDS * 3);
+};
+
+/// Validate the CRC of every word in `frame` and return the words.
+pub(crate) fn words<E, const N: usize>(frame: &[u8]) -> Result<[u16; N], Error<E>> {
+    if frame.len() != N * 3 {
+        return Err(Error::WrongBufferSize);
+    }
+    let mut words = [0u16; N];
+    for (index, (word, chunk)) in words.iter_mut().zip(frame.chunks_exact(3)).enumerate() {
+        *word = checked_word(chunk, index)?;
+    }
+    Ok(words)
+}
+
+/// Validate the CRC of the word at `index` of a response, held in the 3 bytes of `chunk`.
+pub(crate) fn checked_word<E>(chunk: &[u8], index: usize) -> Result<u16, Error<E>> {
+    match *chunk {
+        [hi, lo, checksum] if crc::crc(&[hi, lo]) == checksum => Ok(u16::from_be_bytes([hi, lo])),
+        [_, _, _] => Err(Error::Crc { word: index }),
+        _ => Err(Error::WrongBufferSize),
+    }
+}
+
+/// Interpret the words of a `Read Measured Values` response.
+#[cfg(any(feature = "driver", feature = "ffi", test))]
+pub(crate) fn measurement_words(values: [u16; 8]) -> Sen5xDataRaw {
+    Sen5xDataRaw {
+        pm1_0: values[0],
+        pm2_5: values[1],
+        pm4_0: values[2],
+        pm10_0: values[3],
+        humidity: values[4].cast_signed(),
+        temperature: values[5].cast_signed(),
+        voc_index: values[6].cast_signed(),
+        nox_index: values[7].cast_signed(),
+    }
+}
+
+/// Interpret the word of a `Read Data-Ready Flag` response.
+pub(crate) fn data_ready_words([status]: [u16; 1]) -> bool {
+    // 7FF is the last 11 bytes. If they are all zeroes, then data isn't ready.
+    (status & 0x7FF) != 0
+}
+
+/// Interpret the words of a 16 word ASCII string response.
+pub(crate) fn ascii_words(words: [u16; 16]) -> [u8; 32] {
+    let mut bytes = [0u8; 32];
+    for (pair, word) in bytes.chunks_exact_mut(2).zip(words) {
+        pair.copy_from_slice(&word.to_be_bytes());
+    }
+    bytes
+}
+
+/// Interpret the word of a `Read Firmware Version` response.
+pub(crate) fn firmware_version_words([version]: [u16; 1]) -> u8 {
+    version.to_be_bytes()[0]
+}
+
+/// Interpret the words of a `Read Device Status` response.
+pub(crate) fn device_status_words([hi, lo]: [u16; 2]) -> DeviceStatus {
+    DeviceStatus::from_bits(u32::from(hi) << 16 | u32::from(lo))
+}
+
+/// Decode a `Read Measured Values` response.
+///
+/// Validates the CRC and extracts each word in a single pass over the frame, stopping at the first
+/// word with a mismatching CRC.
+pub fn measurement<E>(frame: &[u8; MEASUREMENT_LEN]) -> Result<Sen5xDataRaw, Error<E>> {
+    let mut chunks = frame.chunks_exact(3).enumerate();
+    let mut next = || match chunks.next() {
+        Some((index, chunk)) => checked_word(chunk, index),
+        None => Err(Error::WrongBufferSize),
+    };
+    // Fields are initialized in the order they are written, matching the frame.
+    Ok(Sen5xDataRaw {
+        pm1_0: next()?,
+        pm2_5: next()?,
+        pm4_0: next()?,
+        pm10_0: next()?,
+        humidity: next()?.cast_signed(),
+        temperature: next()?.cast_signed(),
+        voc_index: next()?.cast_signed(),
+        nox_index: next()?.cast_signed(),
+    })
+}
+
+/// Decode the first four words of a `Read Measured Values` response, holding the PM values.
+pub fn measurement_pm<E>(frame: &[u8; MEASUREMENT_PM_LEN]) -> Result<ParticulateMatter, Error<E>> {
+    words(frame).map(ParticulateMatter::from_words)
+}
+
+/// Decode the measurement response of `variant`, laid out as described by
+/// [`DeviceVariant::layout`].
+pub fn measurement_of<E>(variant: DeviceVariant, frame: &[u8]) -> Result<Measurement, Error<E>> {
+    let len = variant.layout().channels.len();
+    if frame.len() != len * 3 {
+        return Err(Error::WrongBufferSize);
+    }
+    let mut words = [0u16; MAX_WORDS];
+    let words = words.get_mut(..len).ok_or(Error::WrongBufferSize)?;
+    for (index, (word, chunk)) in words.iter_mut().zip(frame.chunks_exact(3)).enumerate() {
+        *word = checked_word(chunk, index)?;
+    }
+    Ok(Measurement::decode(variant, words))
+}
+
+/// Decode a `Read Data-Ready Flag` response.
+pub fn data_ready<E>(frame: &[u8; DATA_READY_LEN]) -> Result<bool, Error<E>> {
+    words(frame).map(data_ready_words)
+}
+
+/// Decode a `Read Product Name` response.
+pub fn product_name<E>(frame: &[u8; ASCII_LEN]) -> Result<[u8; 32], Error<E>> {
+    words(frame).map(ascii_words)
+}
+
+/// Decode a `Read Serial Number` response.
+pub fn serial_number<E>(frame: &[u8; ASCII_LEN]) -> Result<SerialNumber, Error<E>> {
+    words(frame).map(ascii_words).map(SerialNumber::from_bytes)
+}
+
+/// Decode a `Read Device Status` or `Read And Clear Device Status` response.
+pub fn device_status<E>(frame: &[u8; DEVICE_STATUS_LEN]) -> Result<DeviceStatus, Error<E>> {
+    words(frame).map(device_status_words)
+}
+
+/// Decode a `Read Firmware Version` response.
+pub fn firmware_version<E>(frame: &[u8; FIRMWARE_VERSION_LEN]) -> Result<u8, Error<E>> {
+    words(frame).map(firmware_version_words)
+}
+
+/// Decode a `Read Measured Raw Values` response.
+pub fn raw_values<E>(frame: &[u8; RAW_VALUES_LEN]) -> Result<RawValues, Error<E>> {
+    words(frame).map(RawValues::from_words)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type Result<T> = core::result::Result<T, Error<()>>;
+
+    /// Test decoding of a measurement frame
+    #[test]
+    fn test_measurement() {
+        let frame = [
+            0x00, 0x12, 0xA0, 0x00, 0x16, 0x64, 0x00, 0x18, 0x7B, 0x00, 0x1A, 0x19, 0x15, 0x8A,
+            0x39, 0x11, 0x81, 0x50, 0x01, 0x68, 0x77, 0x00, 0x0A, 0x5A,
+        ];
+        let data: Result<_> = measurement(&frame);
+        let data = data.unwrap();
+        assert_eq!(data.pm2_5, 22);
+        assert_eq!(data.nox_index, 10);
+    }
+
+    /// Test that the PM values decode from the start of a measurement frame
+    #[test]
+    fn test_measurement_pm() {
+        for vector in crate::test_vectors::MEASUREMENTS {
+            let pm: Result<_> = measurement_pm(vector.frame[..12].try_into().unwrap());
+            let pm = pm.unwrap();
+            assert_eq!(
+                [pm.pm1_0, pm.pm2_5, pm.pm4_0, pm.pm10_0],
+                [
+                    vector.data.pm.pm1_0,
+                    vector.data.pm.pm2_5,
+                    vector.data.pm.pm4_0,
+                    vector.data.pm.pm10_0
+                ]
+            );
+            assert_eq!(pm, vector.data.pm);
+        }
+    }
+
+    /// Test that the field table of the command matches the decoded measurement
+    #[test]
+    fn test_measurement_fields() {
+        use crate::commands::Command;
+        use crate::fields::FieldId;
+        use crate::types::Sen5xData;
+
+        for vector in crate::test_vectors::MEASUREMENTS {
+            let words: [u16; 8] = words::<(), 8>(&vector.frame).unwrap();
+            let fields = Command::ReadMeasurement.spec().fields;
+            let values: [f32; 8] = core::array::from_fn(|i| fields[i].value(words[i]));
+            let data = Sen5xData::from(measurement_words(words));
+            for (value, id) in values.into_iter().zip(FieldId::ALL) {
+                assert_eq!(Some(value), data.value(id));
+            }
+        }
+    }
+
+    /// Test that the frame of a variant decodes with its layout
+    #[test]
+    fn test_measurement_of() {
+        for vector in crate::test_vectors::MEASUREMENTS {
+            let data: Result<_> = measurement_of(DeviceVariant::Sen55, &vector.frame);
+            assert_eq!(data.unwrap().pm2_5, Some(vector.data.pm.pm2_5));
+        }
+        let result: Result<_> = measurement_of(
+            DeviceVariant::Sen66,
+            &crate::test_vectors::MEASUREMENTS[0].frame,
+        );
+        assert_eq!(result, Err(Error::WrongBufferSize));
+
+        let mut frame = crate::test_vectors::MEASUREMENTS[0].frame;
+        frame[5 * 3 + 2] ^= 1;
+        let result: Result<_> = measurement_of(DeviceVariant::Sen55, &frame);
+        assert_eq!(result, Err(Error::Crc { word: 5 }));
+    }
+
+    /// Test that a corrupted CRC is reported
+    #[test]
+    fn test_crc_error() {
+        let frame = [0xbe, 0xef, 0x00];
+        let result: Result<_> = data_ready(&frame);
+        assert_eq!(result, Err(Error::Crc { word: 0 }));
+
+        let mut frame = crate::test_vectors::MEASUREMENTS[0].frame;
+        frame[23] ^= 1;
+        let result: Result<_> = measurement(&frame);
+        assert_eq!(result, Err(Error::Crc { word: 7 }));
+    }
+
+    /// Test that a frame not matching the number of words is rejected
+    #[test]
+    fn test_wrong_buffer_size() {
+        let result: Result<[u16; 2]> = words(&[0xbe, 0xef, 0x92]);
+        assert_eq!(result, Err(Error::WrongBufferSize));
+        for len in 0..MEASUREMENT_LEN {
+            let frame = [0u8; MEASUREMENT_LEN];
+            let result = measurement_of::<()>(DeviceVariant::Sen55, &frame[..len]);
+            assert_eq!(result, Err(Error::WrongBufferSize));
+        }
+    }
+}
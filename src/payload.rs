@@ -0,0 +1,306 @@
+//! Compact, standardized payloads of measurements and status for gateways.
+//!
+//! With the `postcard` feature, [`Sen5xData`] and [`DeviceStatus`] encode to and decode from
+//! [postcard](https://docs.rs/postcard), with the `cbor` feature to and from CBOR. The payloads
+//! are built in a fixed-size [`Payload`], so sensor nodes need no allocator, and gateways decode
+//! them into the same types.
+//!
+//! ```
+//! # #[cfg(feature = "postcard")] {
+//! use sen5x_rs::types::{ParticulateMatter, Sen5xData};
+//!
+//! let data = Sen5xData {
+//!     pm: ParticulateMatter { pm1_0: 1.8, pm2_5: 2.2, pm4_0: 2.4, pm10_0: 2.6 },
+//!     env: None,
+//!     gas: None,
+//! };
+//! let payload = data.to_postcard().unwrap();
+//! assert_eq!(payload.as_bytes().len(), 18);
+//! assert_eq!(Sen5xData::from_postcard(payload.as_bytes()), Ok(data));
+//! # }
+//! ```
+use crate::types::{DeviceStatus, Sen5xData};
+
+#[cfg(feature = "cbor")]
+use crate::types::{Environment, GasIndices, ParticulateMatter};
+
+/// Size of the largest payload.
+pub const MAX_SIZE: usize = 48;
+
+/// The bytes of an encoded value.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Payload {
+    bytes: [u8; MAX_SIZE],
+    len: usize,
+}
+
+impl Payload {
+    /// The bytes to transmit.
+    pub fn as_bytes(&self) -> &[u8] {
+        self.bytes.get(..self.len).unwrap_or(&self.bytes)
+    }
+
+    fn encode(write: impl FnOnce(&mut [u8]) -> Option<usize>) -> Result<Self, PayloadError> {
+        let mut bytes = [0u8; MAX_SIZE];
+        let len = write(&mut bytes).ok_or(PayloadError::Encode)?;
+        Ok(Self { bytes, len })
+    }
+}
+
+/// Failure to encode or decode a payload.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum PayloadError {
+    /// The value does not fit into a [`Payload`].
+    Encode,
+    /// The bytes are not a valid encoding of the value.
+    Decode,
+}
+
+#[cfg(feature = "postcard")]
+fn to_postcard(value: &impl serde::Serialize, buf: &mut [u8]) -> Option<usize> {
+    postcard::to_slice(value, buf).ok().map(|bytes| bytes.len())
+}
+
+#[cfg(feature = "cbor")]
+fn to_cbor(value: &impl minicbor::Encode<()>, buf: &mut [u8]) -> Option<usize> {
+    let mut cursor = minicbor::encode::write::Cursor::new(buf);
+    minicbor::encode(value, &mut cursor).ok()?;
+    Some(cursor.position())
+}
+
+impl Sen5xData {
+    /// Encode the data with postcard.
+    #[cfg(feature = "postcard")]
+    pub fn to_postcard(&self) -> Result<Payload, PayloadError> {
+        Payload::encode(|buf| to_postcard(self, buf))
+    }
+
+    /// Decode data encoded with [`Sen5xData::to_postcard`].
+    #[cfg(feature = "postcard")]
+    pub fn from_postcard(bytes: &[u8]) -> Result<Self, PayloadError> {
+        postcard::from_bytes(bytes).map_err(|_| PayloadError::Decode)
+    }
+
+    /// Encode the data as CBOR.
+    ///
+    /// The data is an array of the mass concentrations, the ambient conditions and the gas
+    /// indices, each of them an array of their values in the order of their fields or `null` if
+    /// not measured.
+    #[cfg(feature = "cbor")]
+    pub fn to_cbor(&self) -> Result<Payload, PayloadError> {
+        Payload::encode(|buf| to_cbor(self, buf))
+    }
+
+    /// Decode data encoded with [`Sen5xData::to_cbor`].
+    #[cfg(feature = "cbor")]
+    pub fn from_cbor(bytes: &[u8]) -> Result<Self, PayloadError> {
+        minicbor::decode(bytes).map_err(|_| PayloadError::Decode)
+    }
+}
+
+impl DeviceStatus {
+    /// Encode the status with postcard.
+    #[cfg(feature = "postcard")]
+    pub fn to_postcard(&self) -> Result<Payload, PayloadError> {
+        Payload::encode(|buf| to_postcard(self, buf))
+    }
+
+    /// Decode a status encoded with [`DeviceStatus::to_postcard`].
+    #[cfg(feature = "postcard")]
+    pub fn from_postcard(bytes: &[u8]) -> Result<Self, PayloadError> {
+        postcard::from_bytes(bytes).map_err(|_| PayloadError::Decode)
+    }
+
+    /// Encode the status as CBOR, the register value as unsigned integer.
+    #[cfg(feature = "cbor")]
+    pub fn to_cbor(&self) -> Result<Payload, PayloadError> {
+        Payload::encode(|buf| to_cbor(self, buf))
+    }
+
+    /// Decode a status encoded with [`DeviceStatus::to_cbor`].
+    #[cfg(feature = "cbor")]
+    pub fn from_cbor(bytes: &[u8]) -> Result<Self, PayloadError> {
+        minicbor::decode(bytes).map_err(|_| PayloadError::Decode)
+    }
+}
+
+#[cfg(feature = "cbor")]
+mod cbor {
+    use minicbor::decode::{self, Decoder};
+    use minicbor::encode::{self, Encoder, Write};
+    use minicbor::{Decode, Encode};
+
+    use super::*;
+
+    /// Check that the next item is an array of `len` items.
+    fn array(d: &mut Decoder<'_>, len: u64) -> Result<(), decode::Error> {
+        match d.array()? {
+            Some(n) if n == len => Ok(()),
+            _ => Err(decode::Error::message("unexpected array length")),
+        }
+    }
+
+    impl<C> Encode<C> for ParticulateMatter {
+        fn encode<W: Write>(
+            &self,
+            e: &mut Encoder<W>,
+            _: &mut C,
+        ) -> Result<(), encode::Error<W::Error>> {
+            e.array(4)?
+                .f32(self.pm1_0)?
+                .f32(self.pm2_5)?
+                .f32(self.pm4_0)?
+                .f32(self.pm10_0)?;
+            Ok(())
+        }
+    }
+
+    impl<C> Decode<'_, C> for ParticulateMatter {
+        fn decode(d: &mut Decoder<'_>, _: &mut C) -> Result<Self, decode::Error> {
+            array(d, 4)?;
+            Ok(Self {
+                pm1_0: d.f32()?,
+                pm2_5: d.f32()?,
+                pm4_0: d.f32()?,
+                pm10_0: d.f32()?,
+            })
+        }
+    }
+
+    impl<C> Encode<C> for Environment {
+        fn encode<W: Write>(
+            &self,
+            e: &mut Encoder<W>,
+            _: &mut C,
+        ) -> Result<(), encode::Error<W::Error>> {
+            e.array(2)?.f32(self.humidity)?.f32(self.temperature)?;
+            Ok(())
+        }
+    }
+
+    impl<C> Decode<'_, C> for Environment {
+        fn decode(d: &mut Decoder<'_>, _: &mut C) -> Result<Self, decode::Error> {
+            array(d, 2)?;
+            Ok(Self {
+                humidity: d.f32()?,
+                temperature: d.f32()?,
+            })
+        }
+    }
+
+    impl<C> Encode<C> for GasIndices {
+        fn encode<W: Write>(
+            &self,
+            e: &mut Encoder<W>,
+            ctx: &mut C,
+        ) -> Result<(), encode::Error<W::Error>> {
+            e.array(2)?
+                .f32(self.voc_index)?
+                .encode_with(self.nox_index, ctx)?;
+            Ok(())
+        }
+    }
+
+    impl<C> Decode<'_, C> for GasIndices {
+        fn decode(d: &mut Decoder<'_>, ctx: &mut C) -> Result<Self, decode::Error> {
+            array(d, 2)?;
+            Ok(Self {
+                voc_index: d.f32()?,
+                nox_index: d.decode_with(ctx)?,
+            })
+        }
+    }
+
+    impl<C> Encode<C> for Sen5xData {
+        fn encode<W: Write>(
+            &self,
+            e: &mut Encoder<W>,
+            ctx: &mut C,
+        ) -> Result<(), encode::Error<W::Error>> {
+            e.array(3)?
+                .encode_with(self.pm, ctx)?
+                .encode_with(self.env, ctx)?
+                .encode_with(self.gas, ctx)?;
+            Ok(())
+        }
+    }
+
+    impl<C> Decode<'_, C> for Sen5xData {
+        fn decode(d: &mut Decoder<'_>, ctx: &mut C) -> Result<Self, decode::Error> {
+            array(d, 3)?;
+            Ok(Self {
+                pm: d.decode_with(ctx)?,
+                env: d.decode_with(ctx)?,
+                gas: d.decode_with(ctx)?,
+            })
+        }
+    }
+
+    impl<C> Encode<C> for DeviceStatus {
+        fn encode<W: Write>(
+            &self,
+            e: &mut Encoder<W>,
+            _: &mut C,
+        ) -> Result<(), encode::Error<W::Error>> {
+            e.u32(self.bits())?;
+            Ok(())
+        }
+    }
+
+    impl<C> Decode<'_, C> for DeviceStatus {
+        fn decode(d: &mut Decoder<'_>, _: &mut C) -> Result<Self, decode::Error> {
+            d.u32().map(Self::from_bits)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_vectors::MEASUREMENTS;
+
+    /// Test that measurements and status round-trip through postcard
+    #[cfg(feature = "postcard")]
+    #[test]
+    fn test_postcard() {
+        for vector in MEASUREMENTS {
+            let payload = vector.data.to_postcard().unwrap();
+            assert_eq!(
+                Sen5xData::from_postcard(payload.as_bytes()),
+                Ok(vector.data)
+            );
+        }
+        let status = DeviceStatus::from_bits(DeviceStatus::FAN_CLEANING);
+        let payload = status.to_postcard().unwrap();
+        assert_eq!(payload.as_bytes(), [0x80, 0x80, 0x20]);
+        assert_eq!(DeviceStatus::from_postcard(payload.as_bytes()), Ok(status));
+        assert_eq!(Sen5xData::from_postcard(&[0x00]), Err(PayloadError::Decode));
+    }
+
+    /// Test that measurements and status round-trip through CBOR
+    #[cfg(feature = "cbor")]
+    #[test]
+    fn test_cbor() {
+        for vector in MEASUREMENTS {
+            let payload = vector.data.to_cbor().unwrap();
+            assert_eq!(Sen5xData::from_cbor(payload.as_bytes()), Ok(vector.data));
+        }
+        let data = Sen5xData {
+            env: None,
+            gas: None,
+            ..MEASUREMENTS[0].data
+        };
+        let payload = data.to_cbor().unwrap();
+        assert_eq!(payload.as_bytes()[..2], [0x83, 0x84]);
+        assert_eq!(payload.as_bytes()[22..], [0xF6, 0xF6]);
+
+        let status = DeviceStatus::from_bits(DeviceStatus::FAN_CLEANING);
+        let payload = status.to_cbor().unwrap();
+        assert_eq!(payload.as_bytes(), [0x1A, 0x00, 0x08, 0x00, 0x00]);
+        assert_eq!(DeviceStatus::from_cbor(payload.as_bytes()), Ok(status));
+        assert_eq!(
+            Sen5xData::from_cbor(&payload.as_bytes()[..3]),
+            Err(PayloadError::Decode)
+        );
+    }
+}
@@ -0,0 +1,294 @@
+//! Versioned, checksummed containers for data persisted across restarts.
+//!
+//! A blob consists of a header identifying its content and layout version, the payload and a
+//! CRC-32 of everything preceding it:
+//!
+//! | Bytes | Content                    |
+//! |-------|----------------------------|
+//! | 2     | Magic `b"S5"`              |
+//! | 1     | Kind of the content        |
+//! | 1     | Layout version             |
+//! | n     | Payload                    |
+//! | 4     | CRC-32 (little endian)     |
+//!
+//! Blobs written by older versions of this crate are still decoded, while blobs of unknown kinds
+//! or versions are rejected instead of being misinterpreted.
+#![cfg_attr(not(test), deny(clippy::indexing_slicing))]
+use crate::types::{
+    AutoCleanInterval, Config, InvalidValue, NoxTuningParameters, TemperatureCompensation,
+    VocAlgorithmState, VocTuningParameters,
+};
+
+/// Identifies a blob written by this crate.
+const MAGIC: [u8; 2] = *b"S5";
+
+/// Size of the header preceding the payload.
+const HEADER_SIZE: usize = 4;
+
+/// Size of the checksum following the payload.
+const CHECKSUM_SIZE: usize = 4;
+
+/// Kind of a blob containing a [`VocAlgorithmState`].
+const KIND_VOC_ALGORITHM_STATE: u8 = 1;
+
+/// Kind of a blob containing a [`Config`].
+const KIND_CONFIG: u8 = 2;
+
+/// Errors when encoding or decoding a blob.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum BlobError {
+    /// The blob is shorter or longer than its content requires
+    WrongSize,
+    /// The blob was not written by this crate, e.g. erased flash
+    BadMagic,
+    /// The blob contains a different kind of data
+    WrongKind(u8),
+    /// The blob was written with a layout this version does not know
+    UnsupportedVersion(u8),
+    /// The checksum does not match the content
+    Checksum,
+    /// A parameter is outside of the range accepted by the sensor
+    InvalidInput {
+        /// Name of the offending parameter.
+        field: &'static str,
+        /// The rejected value, in the unit sent to the sensor.
+        value: i64,
+    },
+}
+
+impl From<InvalidValue> for BlobError {
+    fn from(InvalidValue { field, value }: InvalidValue) -> Self {
+        BlobError::InvalidInput { field, value }
+    }
+}
+
+/// CRC-32 (IEEE 802.3) of `data`.
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+    for byte in data {
+        crc ^= u32::from(*byte);
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+/// The blob of `payload` with the header and checksum.
+fn seal<const PAYLOAD: usize, const BLOB: usize>(
+    kind: u8,
+    version: u8,
+    payload: &[u8; PAYLOAD],
+) -> [u8; BLOB] {
+    const { assert!(BLOB == HEADER_SIZE + PAYLOAD + CHECKSUM_SIZE) };
+    let header = [MAGIC[0], MAGIC[1], kind, version];
+    let mut blob = [0u8; BLOB];
+    for (byte, value) in blob.iter_mut().zip(header.iter().chain(payload)) {
+        *byte = *value;
+    }
+    if let Some((content, checksum)) = blob.split_at_mut_checked(HEADER_SIZE + PAYLOAD) {
+        checksum.copy_from_slice(&crc32(content).to_le_bytes());
+    }
+    blob
+}
+
+/// Validate the header and checksum of `blob` and return its version and payload.
+fn open(blob: &[u8], kind: u8) -> Result<(u8, &[u8]), BlobError> {
+    let (content, checksum) = blob
+        .len()
+        .checked_sub(CHECKSUM_SIZE)
+        .and_then(|len| blob.split_at_checked(len))
+        .ok_or(BlobError::WrongSize)?;
+    let [magic @ .., blob_kind, version] = content
+        .first_chunk::<HEADER_SIZE>()
+        .copied()
+        .ok_or(BlobError::WrongSize)?;
+    if magic != MAGIC {
+        return Err(BlobError::BadMagic);
+    }
+    if crc32(content).to_le_bytes() != checksum {
+        return Err(BlobError::Checksum);
+    }
+    if blob_kind != kind {
+        return Err(BlobError::WrongKind(blob_kind));
+    }
+    Ok((version, content.get(HEADER_SIZE..).unwrap_or_default()))
+}
+
+/// Write the big endian bytes of `words` to `out` starting at `offset`, returning the offset
+/// following them.
+fn put_words(out: &mut [u8], offset: usize, words: &[u16]) -> usize {
+    let bytes = words.iter().flat_map(|word| word.to_be_bytes());
+    for (byte, value) in out.iter_mut().skip(offset).zip(bytes) {
+        *byte = value;
+    }
+    offset + 2 * words.len()
+}
+
+/// Read `N` big endian words from `bytes` starting at `offset`, zero past the end of `bytes`.
+fn get_words<const N: usize>(bytes: &[u8], offset: usize) -> [u16; N] {
+    let mut words = [0u16; N];
+    let pairs = bytes.get(offset..).unwrap_or_default().as_chunks::<2>().0;
+    for (word, &pair) in words.iter_mut().zip(pairs) {
+        *word = u16::from_be_bytes(pair);
+    }
+    words
+}
+
+impl VocAlgorithmState {
+    /// Current layout version of the blob.
+    pub const BLOB_VERSION: u8 = 1;
+
+    /// Size of the blob returned by [`VocAlgorithmState::to_blob`].
+    pub const BLOB_SIZE: usize = HEADER_SIZE + 8 + CHECKSUM_SIZE;
+
+    /// Encode the state as a versioned, checksummed blob.
+    pub fn to_blob(&self) -> [u8; Self::BLOB_SIZE] {
+        seal(
+            KIND_VOC_ALGORITHM_STATE,
+            Self::BLOB_VERSION,
+            self.as_bytes(),
+        )
+    }
+
+    /// Decode a blob previously returned by [`VocAlgorithmState::to_blob`].
+    pub fn from_blob(blob: &[u8]) -> Result<Self, BlobError> {
+        match open(blob, KIND_VOC_ALGORITHM_STATE)? {
+            (1, payload) => {
+                let bytes = payload.try_into().map_err(|_| BlobError::WrongSize)?;
+                Ok(Self::from_bytes(bytes))
+            }
+            (version, _) => Err(BlobError::UnsupportedVersion(version)),
+        }
+    }
+}
+
+impl Config {
+    /// Current layout version of the blob.
+    pub const BLOB_VERSION: u8 = 1;
+
+    /// Size of the payload of the current layout version.
+    const PAYLOAD_SIZE: usize = 2 * (2 + 6 + 6 + 3);
+
+    /// Size of the blob returned by [`Config::to_blob`].
+    pub const BLOB_SIZE: usize = HEADER_SIZE + Self::PAYLOAD_SIZE + CHECKSUM_SIZE;
+
+    /// Encode the configuration as a versioned, checksummed blob.
+    ///
+    /// Returns `BlobError::InvalidInput` for parameters which could not be written to the sensor.
+    pub fn to_blob(&self) -> Result<[u8; Self::BLOB_SIZE], BlobError> {
+        let mut payload = [0u8; Self::PAYLOAD_SIZE];
+        let offset = put_words(&mut payload, 0, &self.auto_clean_interval.to_words()?);
+        let offset = put_words(&mut payload, offset, &self.voc_tuning.to_words()?);
+        let offset = put_words(&mut payload, offset, &self.nox_tuning.to_words()?);
+        let offset = put_words(
+            &mut payload,
+            offset,
+            &self.temperature_compensation.to_words()?,
+        );
+        debug_assert_eq!(offset, Self::PAYLOAD_SIZE);
+        Ok(seal(KIND_CONFIG, Self::BLOB_VERSION, &payload))
+    }
+
+    /// Decode a blob previously returned by [`Config::to_blob`].
+    pub fn from_blob(blob: &[u8]) -> Result<Self, BlobError> {
+        match open(blob, KIND_CONFIG)? {
+            (1, payload) if payload.len() == Self::PAYLOAD_SIZE => Ok(Self {
+                auto_clean_interval: AutoCleanInterval::from_words(get_words(payload, 0)),
+                voc_tuning: VocTuningParameters::from_words(get_words(payload, 4)),
+                nox_tuning: NoxTuningParameters::from_words(get_words(payload, 16)),
+                temperature_compensation: TemperatureCompensation::from_words(get_words(
+                    payload, 28,
+                )),
+            }),
+            (1, _) => Err(BlobError::WrongSize),
+            (version, _) => Err(BlobError::UnsupportedVersion(version)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::time::Duration;
+
+    /// Test the CRC-32 against the standard check value
+    #[test]
+    fn test_crc32() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+    }
+
+    /// Test that blobs survive a round trip
+    #[test]
+    fn test_round_trip() {
+        let state = VocAlgorithmState::from_bytes([1, 2, 3, 4, 5, 6, 7, 8]);
+        assert_eq!(VocAlgorithmState::from_blob(&state.to_blob()), Ok(state));
+
+        let config = Config {
+            auto_clean_interval: AutoCleanInterval::Every(Duration::from_secs(86_400)),
+            temperature_compensation: TemperatureCompensation {
+                offset: -2.5,
+                slope: 0.0,
+                time_constant: 60,
+            },
+            ..Config::default()
+        };
+        assert_eq!(Config::from_blob(&config.to_blob().unwrap()), Ok(config));
+    }
+
+    /// Test that damaged or foreign blobs are rejected
+    #[test]
+    fn test_rejected_blobs() {
+        let state = VocAlgorithmState::default();
+        let mut blob = state.to_blob();
+
+        assert_eq!(
+            VocAlgorithmState::from_blob(&[0xFF; VocAlgorithmState::BLOB_SIZE]),
+            Err(BlobError::BadMagic)
+        );
+        assert_eq!(
+            VocAlgorithmState::from_blob(&blob[..3]),
+            Err(BlobError::WrongSize)
+        );
+        assert_eq!(
+            Config::from_blob(&blob),
+            Err(BlobError::WrongKind(KIND_VOC_ALGORITHM_STATE))
+        );
+
+        blob[5] ^= 1;
+        assert_eq!(
+            VocAlgorithmState::from_blob(&blob),
+            Err(BlobError::Checksum)
+        );
+
+        let future: [u8; VocAlgorithmState::BLOB_SIZE] =
+            seal(KIND_VOC_ALGORITHM_STATE, 2, state.as_bytes());
+        assert_eq!(
+            VocAlgorithmState::from_blob(&future),
+            Err(BlobError::UnsupportedVersion(2))
+        );
+    }
+
+    /// Test that configurations the sensor would reject are not persisted
+    #[test]
+    fn test_invalid_config() {
+        let config = Config {
+            voc_tuning: VocTuningParameters {
+                gain_factor: 0,
+                ..VocTuningParameters::DATASHEET_DEFAULT
+            },
+            ..Config::default()
+        };
+        assert_eq!(
+            config.to_blob(),
+            Err(BlobError::InvalidInput {
+                field: "gain_factor",
+                value: 0
+            })
+        );
+    }
+}
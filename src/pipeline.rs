@@ -0,0 +1,409 @@
+//! Composable processing of measurements.
+//!
+//! Most applications post-process the samples: smooth them, calibrate them against a reference
+//! instrument, correct the particulate matter for the water taken up at high humidity and compute
+//! an air quality index. A [`Pipeline`] chains such [`Stage`]s and applies them to every sample
+//! in order. Stages are provided for an exponential moving average ([`Ema`]), a linear
+//! [`Calibration`], the [`HygroscopicCorrection`], the [`UsAqi`] and the
+//! [`Smoother`](crate::smoothing::Smoother); closures are stages as well. A pipeline built from
+//! a fixed list of samples, e.g. from [`test_vectors`](crate::test_vectors), is unit tested with
+//! [`Pipeline::process_all`] without a sensor.
+//!
+//! ```
+//! use core::time::Duration;
+//! use sen5x_rs::fields::FieldId;
+//! use sen5x_rs::pipeline::{Calibration, HygroscopicCorrection, Pipeline, UsAqi};
+//! # let data = sen5x_rs::types::Sen5xData::sample();
+//!
+//! let mut pipeline = Pipeline::new()
+//!     .then(Calibration::new().with_linear(FieldId::Temperature, 1.0, -1.5))
+//!     .then(HygroscopicCorrection::new())
+//!     .then(UsAqi::new());
+//! let processed = pipeline.process(&data, Duration::from_secs(1));
+//! assert!(processed.pm.pm2_5 < data.pm.pm2_5);
+//! // The last stage is the second field of the chain.
+//! assert_eq!(pipeline.stages().1.value(), Some(9));
+//! ```
+use core::time::Duration;
+
+use crate::fields::FieldId;
+use crate::smoothing::Smoother;
+use crate::types::Sen5xData;
+
+/// A processing step applied to every sample.
+pub trait Stage {
+    /// Process `data` measured `dt` after the previous sample in place.
+    fn process(&mut self, data: &mut Sen5xData, dt: Duration);
+}
+
+impl<F: FnMut(&mut Sen5xData, Duration)> Stage for F {
+    fn process(&mut self, data: &mut Sen5xData, dt: Duration) {
+        self(data, dt);
+    }
+}
+
+/// The empty stage, leaving the samples unchanged.
+impl Stage for () {
+    fn process(&mut self, _: &mut Sen5xData, _: Duration) {}
+}
+
+/// Two stages applied one after the other.
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub struct Chain<A, B>(pub A, pub B);
+
+impl<A: Stage, B: Stage> Stage for Chain<A, B> {
+    fn process(&mut self, data: &mut Sen5xData, dt: Duration) {
+        self.0.process(data, dt);
+        self.1.process(data, dt);
+    }
+}
+
+impl Stage for Smoother {
+    fn process(&mut self, data: &mut Sen5xData, dt: Duration) {
+        *data = self.filter(data, dt);
+    }
+}
+
+/// A chain of stages applied to every sample.
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub struct Pipeline<S> {
+    stages: S,
+}
+
+impl Pipeline<()> {
+    /// A pipeline without stages.
+    pub const fn new() -> Self {
+        Self { stages: () }
+    }
+}
+
+impl<S: Stage> Pipeline<S> {
+    /// Append `stage`, applied after the previous stages.
+    pub fn then<T: Stage>(self, stage: T) -> Pipeline<Chain<S, T>> {
+        Pipeline {
+            stages: Chain(self.stages, stage),
+        }
+    }
+
+    /// The stages, the last one appended in the second field of the outer [`Chain`].
+    pub fn stages(&self) -> &S {
+        &self.stages
+    }
+
+    /// Mutable access to the stages, e.g. to reconfigure one of them.
+    pub fn stages_mut(&mut self) -> &mut S {
+        &mut self.stages
+    }
+
+    /// Process `data` measured `dt` after the previous sample.
+    pub fn process(&mut self, data: &Sen5xData, dt: Duration) -> Sen5xData {
+        let mut data = *data;
+        self.stages.process(&mut data, dt);
+        data
+    }
+
+    /// Process the consecutive `samples`, measured `dt` apart.
+    pub fn process_all<'a>(
+        &'a mut self,
+        samples: impl IntoIterator<Item = Sen5xData> + 'a,
+        dt: Duration,
+    ) -> impl Iterator<Item = Sen5xData> + 'a {
+        samples.into_iter().map(move |data| self.process(&data, dt))
+    }
+}
+
+/// An exponential moving average of every value.
+///
+/// The weight of a sample is `dt / (tau + dt)` with the time constant `tau`, so irregularly spaced
+/// samples are averaged consistently. Values which were not measured restart their average.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Ema {
+    time_constant: Duration,
+    averages: [Option<f32>; 8],
+}
+
+impl Ema {
+    /// An average with the time constant `time_constant`, zero to pass the values through.
+    pub const fn new(time_constant: Duration) -> Self {
+        Self {
+            time_constant,
+            averages: [None; 8],
+        }
+    }
+}
+
+impl Stage for Ema {
+    fn process(&mut self, data: &mut Sen5xData, dt: Duration) {
+        let (tau, dt) = (self.time_constant.as_secs_f32(), dt.as_secs_f32());
+        let weight = if tau > 0.0 { dt / (tau + dt) } else { 1.0 };
+        for id in FieldId::ALL {
+            let average = &mut self.averages[id as usize];
+            match data.value_mut(id) {
+                Some(value) => {
+                    let next = average.map_or(*value, |avg| avg + weight * (*value - avg));
+                    *average = Some(next);
+                    *value = next;
+                }
+                None => *average = None,
+            }
+        }
+    }
+}
+
+/// A linear correction `value * gain + offset` of every value, e.g. fitted against a reference
+/// instrument.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Calibration([(f32, f32); 8]);
+
+impl Default for Calibration {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Calibration {
+    /// A calibration leaving every value unchanged.
+    pub const fn new() -> Self {
+        Self([(1.0, 0.0); 8])
+    }
+
+    /// Correct the value `id` to `value * gain + offset`.
+    pub const fn with_linear(mut self, id: FieldId, gain: f32, offset: f32) -> Self {
+        self.0[id as usize] = (gain, offset);
+        self
+    }
+}
+
+impl Stage for Calibration {
+    fn process(&mut self, data: &mut Sen5xData, _: Duration) {
+        for id in FieldId::ALL {
+            if let Some(value) = data.value_mut(id) {
+                let (gain, offset) = self.0[id as usize];
+                *value = *value * gain + offset;
+            }
+        }
+    }
+}
+
+/// Correction of the particulate matter for the growth of the particles by water uptake at high
+/// humidity, following the κ-Köhler theory.
+///
+/// The mass concentrations are divided by `1 + κ / 1.65 / (100 / RH - 1)`, with the
+/// hygroscopicity `κ` of the aerosol and the humidity `RH` [%] of the same sample. Samples
+/// without humidity are left unchanged.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct HygroscopicCorrection {
+    kappa: f32,
+    max_humidity: f32,
+}
+
+impl Default for HygroscopicCorrection {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HygroscopicCorrection {
+    /// Default hygroscopicity, typical for continental aerosol.
+    pub const DEFAULT_KAPPA: f32 = 0.4;
+
+    /// Default humidity above which the correction is not increased further [%RH], as the
+    /// growth diverges towards saturation.
+    pub const DEFAULT_MAX_HUMIDITY: f32 = 95.0;
+
+    /// A correction with the default hygroscopicity.
+    pub const fn new() -> Self {
+        Self {
+            kappa: Self::DEFAULT_KAPPA,
+            max_humidity: Self::DEFAULT_MAX_HUMIDITY,
+        }
+    }
+
+    /// Use the hygroscopicity `kappa`, e.g. fitted against a reference instrument.
+    pub const fn with_kappa(self, kappa: f32) -> Self {
+        Self { kappa, ..self }
+    }
+
+    /// Limit the humidity used for the correction to `max_humidity` [%RH].
+    pub const fn with_max_humidity(self, max_humidity: f32) -> Self {
+        Self {
+            max_humidity,
+            ..self
+        }
+    }
+
+    /// The factor by which the particles grew at the humidity `humidity` [%RH].
+    pub fn growth(&self, humidity: f32) -> f32 {
+        let humidity = humidity.clamp(0.0, self.max_humidity.min(99.0));
+        if humidity <= 0.0 {
+            return 1.0;
+        }
+        1.0 + self.kappa / 1.65 / (100.0 / humidity - 1.0)
+    }
+}
+
+impl Stage for HygroscopicCorrection {
+    fn process(&mut self, data: &mut Sen5xData, _: Duration) {
+        let Some(env) = data.env else {
+            return;
+        };
+        let growth = self.growth(env.humidity);
+        for id in [
+            FieldId::Pm1_0,
+            FieldId::Pm2_5,
+            FieldId::Pm4_0,
+            FieldId::Pm10_0,
+        ] {
+            if let Some(value) = data.value_mut(id) {
+                *value /= growth;
+            }
+        }
+    }
+}
+
+/// Breakpoints of an AQI: upper concentration and index of each category.
+type Breakpoints = [(f32, u16); 6];
+
+/// Breakpoints of PM2.5 of the US EPA since 2024 [μg/m³], truncated to 0.1 μg/m³.
+const PM2_5_BREAKPOINTS: Breakpoints = [
+    (9.0, 50),
+    (35.4, 100),
+    (55.4, 150),
+    (125.4, 200),
+    (225.4, 300),
+    (325.4, 500),
+];
+
+/// Breakpoints of PM10 of the US EPA [μg/m³], truncated to 1 μg/m³.
+const PM10_BREAKPOINTS: Breakpoints = [
+    (54.0, 50),
+    (154.0, 100),
+    (254.0, 150),
+    (354.0, 200),
+    (424.0, 300),
+    (604.0, 500),
+];
+
+/// The index of `concentration` truncated to `1 / scale`, interpolated between the
+/// `breakpoints`, whose categories start `1 / scale` above the end of the previous one.
+fn aqi(breakpoints: &Breakpoints, scale: f32, concentration: f32) -> u16 {
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)] // truncating on purpose
+    let steps = (concentration.max(0.0) * scale) as u32;
+    #[allow(clippy::cast_precision_loss)] // exact below 2^24 steps
+    let concentration = steps as f32 / scale;
+    let mut low = (0.0, 0);
+    for &(high, index) in breakpoints {
+        if concentration <= high {
+            let (c_low, i_low) = low;
+            let slope = f32::from(index - i_low) / (high - c_low);
+            let aqi = f32::from(i_low) + slope * (concentration - c_low).max(0.0);
+            #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)] // between 0 and 500
+            let aqi = (aqi + 0.5) as u16;
+            return aqi;
+        }
+        low = (high + 1.0 / scale, index + 1);
+    }
+    500
+}
+
+/// The US AQI of the PM2.5 concentration `pm2_5` [μg/m³].
+pub fn pm2_5_aqi(pm2_5: f32) -> u16 {
+    aqi(&PM2_5_BREAKPOINTS, 10.0, pm2_5)
+}
+
+/// The US AQI of the PM10 concentration `pm10` [μg/m³].
+pub fn pm10_aqi(pm10: f32) -> u16 {
+    aqi(&PM10_BREAKPOINTS, 1.0, pm10)
+}
+
+/// The US AQI of `data`, the higher one of PM2.5 and PM10.
+///
+/// The EPA defines the AQI on 24 h averages, e.g. from an [`Ema`] earlier in the pipeline; of
+/// single samples it is an instantaneous estimate.
+pub fn us_aqi(data: &Sen5xData) -> u16 {
+    pm2_5_aqi(data.pm.pm2_5).max(pm10_aqi(data.pm.pm10_0))
+}
+
+/// Computes the [`us_aqi`] of every sample, leaving the sample unchanged.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub struct UsAqi {
+    value: Option<u16>,
+}
+
+impl UsAqi {
+    /// A stage which has not seen a sample yet.
+    pub const fn new() -> Self {
+        Self { value: None }
+    }
+
+    /// The AQI of the last sample.
+    pub fn value(&self) -> Option<u16> {
+        self.value
+    }
+}
+
+impl Stage for UsAqi {
+    fn process(&mut self, data: &mut Sen5xData, _: Duration) {
+        self.value = Some(us_aqi(data));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_vectors::MEASUREMENTS;
+
+    const SECOND: Duration = Duration::from_secs(1);
+
+    /// Test the breakpoints of the US AQI
+    #[test]
+    fn test_us_aqi() {
+        assert_eq!(pm2_5_aqi(0.0), 0);
+        assert_eq!(pm2_5_aqi(9.0), 50);
+        assert_eq!(pm2_5_aqi(9.09), 50);
+        assert_eq!(pm2_5_aqi(9.1), 51);
+        assert_eq!(pm2_5_aqi(35.4), 100);
+        assert_eq!(pm2_5_aqi(55.5), 151);
+        assert_eq!(pm2_5_aqi(1000.0), 500);
+        assert_eq!(pm10_aqi(54.9), 50);
+        assert_eq!(pm10_aqi(155.0), 101);
+        assert_eq!(pm10_aqi(-1.0), 0);
+
+        let mut data = MEASUREMENTS[0].data;
+        data.pm.pm2_5 = 5.0;
+        data.pm.pm10_0 = 200.0;
+        assert_eq!(us_aqi(&data), pm10_aqi(200.0));
+    }
+
+    /// Test that the stages are applied in order to every sample
+    #[test]
+    fn test_pipeline() {
+        let data = MEASUREMENTS[0].data;
+        let mut pipeline = Pipeline::new()
+            .then(Calibration::new().with_linear(FieldId::Pm2_5, 2.0, 1.0))
+            .then(|data: &mut Sen5xData, _: Duration| data.pm.pm1_0 = 0.0);
+        let processed = pipeline.process(&data, SECOND);
+        assert_eq!(processed.pm.pm2_5, data.pm.pm2_5 * 2.0 + 1.0);
+        assert_eq!(processed.pm.pm1_0, 0.0);
+        assert_eq!(processed.env, data.env);
+
+        let mut ema = Pipeline::new().then(Ema::new(SECOND));
+        let mut step = data;
+        step.pm.pm10_0 += 10.0;
+        step.gas = None;
+        let averaged: std::vec::Vec<_> = ema.process_all([data, step, data], SECOND).collect();
+        assert_eq!(averaged[0], data);
+        assert_eq!(averaged[1].pm.pm10_0, data.pm.pm10_0 + 5.0);
+        assert_eq!(averaged[1].gas, None);
+        assert_eq!(averaged[2].pm.pm10_0, data.pm.pm10_0 + 2.5);
+        assert_eq!(averaged[2].gas, data.gas);
+
+        let correction = HygroscopicCorrection::new();
+        assert_eq!(correction.growth(0.0), 1.0);
+        assert!((correction.growth(50.0) - (1.0 + 0.4 / 1.65)).abs() < 1e-6);
+        assert_eq!(correction.growth(100.0), correction.growth(95.0));
+        let mut dry = data;
+        dry.env = None;
+        assert_eq!(Pipeline::new().then(correction).process(&dry, SECOND), dry);
+    }
+}
@@ -0,0 +1,20 @@
+//! Re-exports of the commonly used items.
+//!
+//! ```
+//! use sen5x_rs::prelude::*;
+//! ```
+#[cfg(feature = "async")]
+pub use crate::asynch::Sen5xAsync;
+pub use crate::fields::FieldId;
+pub use crate::measurement::Measurement;
+#[cfg(feature = "driver")]
+pub use crate::sen6x::{
+    AutomaticSelfCalibration, Co2Compensation, Sen60, Sen63c, Sen66, Sen68, Sen6x,
+};
+pub use crate::types::{
+    AutoCleanInterval, DeviceInfo, DeviceStatus, DeviceVariant, DriverState, Environment,
+    GasIndices, ParticulateMatter, Sen5xData, Sen5xDataRaw, SerialNumber, TemperatureCompensation,
+};
+pub use crate::{Error, Result};
+#[cfg(feature = "driver")]
+pub use crate::{NoDelay, Sen5x};
@@ -0,0 +1,188 @@
+//! Prometheus text exposition of measurements, e.g. for a gateway serving `/metrics`.
+//!
+//! Every measured value is rendered as a gauge named after the value and its unit, e.g.
+//! `sen5x_pm2_5_ug_m3`, with the labels of the [`Prometheus`] layout. Values which were not
+//! measured are left out.
+//!
+//! ```
+//! use core::fmt::Write;
+//! use sen5x_rs::prometheus::Prometheus;
+//! # let data = sen5x_rs::types::Sen5xData::sample();
+//!
+//! let labels = [("room", "kitchen")];
+//! let prometheus = Prometheus::new().with_labels(&labels);
+//! let mut body = String::new();
+//! write!(body, "{}", prometheus.render(&data))?;
+//! assert!(body.contains("# TYPE sen5x_pm2_5_ug_m3 gauge\nsen5x_pm2_5_ug_m3{room=\"kitchen\"} 2.2\n"));
+//! # Ok::<(), core::fmt::Error>(())
+//! ```
+use core::fmt;
+
+use crate::csv::decimals;
+use crate::fields::FieldId;
+use crate::types::Sen5xData;
+
+/// Layout of the exposition.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Prometheus<'a> {
+    prefix: &'a str,
+    labels: &'a [(&'a str, &'a str)],
+    metadata: bool,
+}
+
+impl Default for Prometheus<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a> Prometheus<'a> {
+    /// Metrics prefixed with `sen5x`, without labels and with `HELP` and `TYPE` lines.
+    pub const fn new() -> Self {
+        Self {
+            prefix: "sen5x",
+            labels: &[],
+            metadata: true,
+        }
+    }
+
+    /// Prefix the metric names with `prefix` instead of `sen5x`.
+    pub const fn with_prefix(self, prefix: &'a str) -> Self {
+        Self { prefix, ..self }
+    }
+
+    /// Add the `(name, value)` pairs of `labels` to every sample, e.g. to tell sensors apart.
+    pub const fn with_labels(self, labels: &'a [(&'a str, &'a str)]) -> Self {
+        Self { labels, ..self }
+    }
+
+    /// Leave out the `HELP` and `TYPE` lines, e.g. for the samples of a further sensor served
+    /// at the same endpoint.
+    pub const fn without_metadata(self) -> Self {
+        Self {
+            metadata: false,
+            ..self
+        }
+    }
+
+    /// The exposition of the values of `data`.
+    pub fn render<'b, F>(&'b self, data: &'b Sen5xData<F>) -> Exposition<'b, F> {
+        Exposition {
+            prometheus: self,
+            data,
+        }
+    }
+
+    /// The name of the metric of `id`.
+    pub fn metric(&self, id: FieldId) -> Metric<'_> {
+        Metric {
+            prefix: self.prefix,
+            id,
+        }
+    }
+}
+
+/// The unit suffix of the metric of `id`, following the Prometheus naming conventions.
+const fn suffix(id: FieldId) -> &'static str {
+    match id {
+        FieldId::Pm1_0 | FieldId::Pm2_5 | FieldId::Pm4_0 | FieldId::Pm10_0 => "_ug_m3",
+        FieldId::Humidity => "_percent",
+        FieldId::Temperature => "_celsius",
+        FieldId::VocIndex | FieldId::NoxIndex => "",
+    }
+}
+
+/// Name of a metric, returned by [`Prometheus::metric`].
+#[derive(Debug, Copy, Clone)]
+pub struct Metric<'a> {
+    prefix: &'a str,
+    id: FieldId,
+}
+
+impl fmt::Display for Metric<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}_{}{}", self.prefix, self.id.name(), suffix(self.id))
+    }
+}
+
+/// Exposition of a measurement, returned by [`Prometheus::render`].
+#[derive(Debug, Copy, Clone)]
+pub struct Exposition<'a, F = f32> {
+    prometheus: &'a Prometheus<'a>,
+    data: &'a Sen5xData<F>,
+}
+
+impl<F: Copy + fmt::Display> fmt::Display for Exposition<'_, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let prometheus = self.prometheus;
+        for id in FieldId::ALL {
+            let Some(value) = self.data.value(id) else {
+                continue;
+            };
+            let metric = prometheus.metric(id);
+            if prometheus.metadata {
+                writeln!(f, "# HELP {metric} {}", id.label())?;
+                writeln!(f, "# TYPE {metric} gauge")?;
+            }
+            write!(f, "{metric}")?;
+            let mut separator = '{';
+            for (name, value) in prometheus.labels {
+                write!(f, "{separator}{name}=\"{}\"", Escaped(value))?;
+                separator = ',';
+            }
+            if !prometheus.labels.is_empty() {
+                f.write_str("}")?;
+            }
+            writeln!(f, " {value:.*}", decimals(id))?;
+        }
+        Ok(())
+    }
+}
+
+/// A label value escaped for the text exposition format.
+struct Escaped<'a>(&'a str);
+
+impl fmt::Display for Escaped<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for c in self.0.chars() {
+            match c {
+                '"' => f.write_str("\\\"")?,
+                '\\' => f.write_str("\\\\")?,
+                '\n' => f.write_str("\\n")?,
+                c => write!(f, "{c}")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_vectors::MEASUREMENTS;
+
+    /// Test that measured values are rendered as gauges with escaped labels
+    #[test]
+    fn test_render() {
+        let data = Sen5xData {
+            gas: None,
+            ..MEASUREMENTS[0].data
+        };
+        let labels = [("room", "a \"b\"\n"), ("id", "1")];
+        let prometheus = Prometheus::new().with_prefix("air").with_labels(&labels);
+        let body = prometheus.render(&data).to_string();
+        assert!(body.starts_with(
+            "# HELP air_pm1_0_ug_m3 PM1.0\n# TYPE air_pm1_0_ug_m3 gauge\n\
+             air_pm1_0_ug_m3{room=\"a \\\"b\\\"\\n\",id=\"1\"} 1.8\n"
+        ));
+        assert!(body.contains("air_temperature_celsius{"));
+        assert!(!body.contains("voc_index"));
+
+        let body = Prometheus::new()
+            .without_metadata()
+            .render(&MEASUREMENTS[0].data)
+            .to_string();
+        assert_eq!(body.lines().count(), 8);
+        assert!(body.ends_with("sen5x_humidity_percent 55.14\nsen5x_temperature_celsius 22.405\nsen5x_voc_index 36.0\nsen5x_nox_index 1.0\n"));
+    }
+}
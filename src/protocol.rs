@@ -0,0 +1,224 @@
+//! The I²C protocol shared by Sensirion sensors.
+//!
+//! Sensirion sensors such as the SEN5x, SEN6x, SVM41 or SGP4x take a 16-bit command code followed
+//! by argument words, need a command specific execution time and respond with words, each followed
+//! by a CRC byte. [`Interface`] implements this pattern once, and a [`Frame`] describes a command
+//! with the number of argument and response words in its type, so drivers for related devices only
+//! need to list their commands.
+//!
+//! ```
+//! # use embedded_hal_mock::eh1::{delay::NoopDelay, i2c::{Mock, Transaction}};
+//! use sen5x_rs::protocol::{Frame, Interface};
+//!
+//! /// `Get Serial Number` of an SGP4x, without arguments and responding with 3 words.
+//! const GET_SERIAL_NUMBER: Frame<0, 3> = Frame::new(0x3682, 1);
+//!
+//! # let mut mock = Mock::new(&[
+//! #     Transaction::write(0x59, vec![0x36, 0x82]),
+//! #     Transaction::read(0x59, vec![0xBE, 0xEF, 0x92, 0xBE, 0xEF, 0x92, 0xBE, 0xEF, 0x92]),
+//! # ]);
+//! # let i2c = mock.clone();
+//! let mut sgp4x = Interface::new(i2c, NoopDelay, 0x59);
+//! let serial = sgp4x.execute(GET_SERIAL_NUMBER, [])?;
+//! assert_eq!(serial, [0xBEEF; 3]);
+//! # mock.done();
+//! # Ok::<(), sen5x_rs::Error<embedded_hal::i2c::ErrorKind>>(())
+//! ```
+#![cfg_attr(not(test), deny(clippy::indexing_slicing))]
+use embedded_hal::{delay::DelayNs, i2c::I2c};
+
+use crate::errors::bus_error;
+use crate::frame;
+use crate::Error;
+
+/// Largest number of words written or read at once.
+pub const MAX_WORDS: usize = frame::MAX_WORDS;
+
+/// A command taking `ARGS` argument words and responding with `RESPONSE` words.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Frame<const ARGS: usize, const RESPONSE: usize> {
+    /// The 16-bit command code.
+    pub code: u16,
+    /// Execution time [ms].
+    pub execution_time_ms: u32,
+}
+
+impl<const ARGS: usize, const RESPONSE: usize> Frame<ARGS, RESPONSE> {
+    /// Describe a command.
+    pub const fn new(code: u16, execution_time_ms: u32) -> Self {
+        const {
+            assert!(
+                ARGS <= MAX_WORDS && RESPONSE <= MAX_WORDS,
+                "frames hold at most 16 words"
+            )
+        };
+        Self {
+            code,
+            execution_time_ms,
+        }
+    }
+}
+
+/// Write `code` followed by `words`, each protected by a CRC.
+pub(crate) fn write<I2C: I2c>(
+    i2c: &mut I2C,
+    address: u8,
+    code: u16,
+    words: &[u16],
+) -> Result<(), Error<I2C::Error>> {
+    let bytes = frame::encode(code, words)?;
+    i2c.write(address, bytes.as_bytes())
+        .map_err(bus_error::<I2C>)
+}
+
+/// Read `words.len()` words, validating the CRC of each of them.
+pub(crate) fn read<I2C: I2c>(
+    i2c: &mut I2C,
+    address: u8,
+    words: &mut [u16],
+) -> Result<(), Error<I2C::Error>> {
+    let mut buf = [0u8; MAX_WORDS * 3];
+    let bytes = buf
+        .get_mut(..words.len() * 3)
+        .ok_or(Error::WrongBufferSize)?;
+    i2c.read(address, bytes).map_err(bus_error::<I2C>)?;
+    frame::decode(bytes, words)
+}
+
+/// Write `code` and read `words.len()` words in a single transaction with a repeated start.
+///
+/// Only possible for commands which respond without an execution time.
+pub(crate) fn write_read<I2C: I2c>(
+    i2c: &mut I2C,
+    address: u8,
+    code: u16,
+    words: &mut [u16],
+) -> Result<(), Error<I2C::Error>> {
+    let mut buf = [0u8; MAX_WORDS * 3];
+    let bytes = buf
+        .get_mut(..words.len() * 3)
+        .ok_or(Error::WrongBufferSize)?;
+    i2c.write_read(address, &code.to_be_bytes(), bytes)
+        .map_err(bus_error::<I2C>)?;
+    frame::decode(bytes, words)
+}
+
+/// A Sensirion sensor at an address of an I²C bus.
+#[derive(Debug)]
+pub struct Interface<I2C, D> {
+    i2c: I2C,
+    delay: D,
+    address: u8,
+}
+
+impl<I2C, D, E> Interface<I2C, D>
+where
+    I2C: I2c<Error = E>,
+    D: DelayNs,
+{
+    /// Create an interface to the sensor at `address`.
+    pub fn new(i2c: I2C, delay: D, address: u8) -> Self {
+        Self {
+            i2c,
+            delay,
+            address,
+        }
+    }
+
+    /// The I2C address of the sensor.
+    pub fn address(&self) -> u8 {
+        self.address
+    }
+
+    /// Mutable access to the underlying I²C bus.
+    pub fn i2c_mut(&mut self) -> &mut I2C {
+        &mut self.i2c
+    }
+
+    /// Mutable access to the underlying delay provider.
+    pub fn delay_mut(&mut self) -> &mut D {
+        &mut self.delay
+    }
+
+    /// Release the bus and the delay provider.
+    pub fn release(self) -> (I2C, D) {
+        (self.i2c, self.delay)
+    }
+
+    /// Issue `frame` with `args`, wait for its execution time and read back the response.
+    ///
+    /// Reads without arguments and execution time are issued as a single transaction.
+    pub fn execute<const ARGS: usize, const RESPONSE: usize>(
+        &mut self,
+        frame: Frame<ARGS, RESPONSE>,
+        args: [u16; ARGS],
+    ) -> Result<[u16; RESPONSE], Error<E>> {
+        let mut words = [0u16; RESPONSE];
+        if ARGS == 0 && RESPONSE > 0 && frame.execution_time_ms == 0 {
+            write_read(&mut self.i2c, self.address, frame.code, &mut words)?;
+            return Ok(words);
+        }
+        self.write(frame.code, &args)?;
+        self.delay_ms(frame.execution_time_ms);
+        if RESPONSE > 0 {
+            self.read(&mut words)?;
+        }
+        Ok(words)
+    }
+
+    /// Write the command `code` followed by `words` without waiting for its execution.
+    pub fn write(&mut self, code: u16, words: &[u16]) -> Result<(), Error<E>> {
+        write(&mut self.i2c, self.address, code, words)
+    }
+
+    /// Read `words.len()` words of a response, validating the CRC of each of them.
+    pub fn read(&mut self, words: &mut [u16]) -> Result<(), Error<E>> {
+        read(&mut self.i2c, self.address, words)
+    }
+
+    /// Wait for `ms` milliseconds, skipped for zero.
+    pub fn delay_ms(&mut self, ms: u32) {
+        if ms > 0 {
+            self.delay.delay_ms(ms);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use embedded_hal_mock as hal;
+
+    use self::hal::eh1::delay::NoopDelay as DelayMock;
+    use self::hal::eh1::i2c::{Mock as I2cMock, Transaction};
+    use super::*;
+
+    /// Test that arguments and responses are framed with a CRC per word
+    #[test]
+    fn test_execute() {
+        let expectations = [
+            Transaction::write(0x69, vec![0x80, 0x04, 0x00, 0x00, 0x81, 0xBE, 0xEF, 0x92]),
+            Transaction::write(0x69, vec![0x80, 0x04]),
+            Transaction::read(0x69, vec![0x00, 0x00, 0x81, 0xBE, 0xEF, 0x92]),
+            Transaction::write(0x69, vec![0xD1, 0x00]),
+            Transaction::read(0x69, vec![0x02, 0x00, 0x00]),
+            Transaction::write_read(0x69, vec![0x02, 0x02], vec![0x00, 0x01, 0xB0]),
+        ];
+        let mut mock = I2cMock::new(&expectations);
+        let mut sensor = Interface::new(mock.clone(), DelayMock, 0x69);
+        assert_eq!(
+            sensor.execute(Frame::<2, 0>::new(0x8004, 20), [0, 0xBEEF]),
+            Ok([])
+        );
+        assert_eq!(
+            sensor.execute(Frame::<0, 2>::new(0x8004, 20), []),
+            Ok([0, 0xBEEF])
+        );
+        assert_eq!(
+            sensor.execute(Frame::<0, 1>::new(0xD100, 20), []),
+            Err(Error::Crc { word: 0 })
+        );
+        assert_eq!(sensor.execute(Frame::<0, 1>::new(0x0202, 0), []), Ok([1]));
+        assert_eq!(sensor.write(0x8004, &[0; 17]), Err(Error::WrongBufferSize));
+        mock.done();
+    }
+}
@@ -0,0 +1,253 @@
+//! Deferred execution of commands requested from interrupt handlers.
+//!
+//! Commands take up to several milliseconds on the bus, too long for an interrupt handler. A
+//! [`CommandQueue`] lets one context, e.g. a button interrupt, enqueue [`Request`]s, which the
+//! main loop executes between measurements with [`CommandQueue::execute`]. The queue is lock-free
+//! and can be placed in a `static`; it supports a single producer and a single consumer.
+//!
+//! ```
+//! # use embedded_hal_mock::eh1::{delay::NoopDelay, i2c::{Mock, Transaction}};
+//! use sen5x_rs::queue::{CommandQueue, Request};
+//!
+//! static QUEUE: CommandQueue<4> = CommandQueue::new();
+//!
+//! // In the interrupt handler.
+//! let ticket = QUEUE.enqueue(Request::StartFanCleaning).unwrap();
+//!
+//! // In the main loop.
+//! # let mut mock = Mock::new(&[
+//! #     Transaction::write(0x69, vec![0x00, 0x21]),
+//! #     Transaction::write(0x69, vec![0x56, 0x07]),
+//! # ]);
+//! # let mut sensor = sen5x_rs::Sen5x::new(mock.clone(), NoopDelay);
+//! # sensor.start_measurement().unwrap();
+//! QUEUE.execute(&mut sensor, |_, result| assert!(result.is_ok()));
+//! assert!(QUEUE.is_complete(ticket));
+//! # mock.done();
+//! ```
+use core::sync::atomic::{AtomicU32, AtomicU8, AtomicUsize, Ordering};
+
+use embedded_hal::{delay::DelayNs, i2c::I2c};
+
+use crate::types::AutoCleanInterval;
+use crate::{Error, Sen5x};
+
+/// A command to execute later.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Request {
+    /// Start the measurement.
+    StartMeasurement,
+    /// Stop the measurement.
+    StopMeasurement,
+    /// Start the fan cleaning.
+    StartFanCleaning,
+    /// Set the interval of the periodic fan cleaning [s], `0` disables it.
+    SetAutoCleanInterval(u32),
+}
+
+impl Request {
+    fn encode(self) -> (u8, u32) {
+        match self {
+            Self::StartMeasurement => (0, 0),
+            Self::StopMeasurement => (1, 0),
+            Self::StartFanCleaning => (2, 0),
+            Self::SetAutoCleanInterval(secs) => (3, secs),
+        }
+    }
+
+    fn decode(tag: u8, arg: u32) -> Self {
+        match tag {
+            0 => Self::StartMeasurement,
+            1 => Self::StopMeasurement,
+            2 => Self::StartFanCleaning,
+            _ => Self::SetAutoCleanInterval(arg),
+        }
+    }
+
+    /// Execute the request.
+    pub fn execute<I2C, D, E>(self, sensor: &mut Sen5x<I2C, D>) -> Result<(), Error<E>>
+    where
+        I2C: I2c<Error = E>,
+        D: DelayNs,
+    {
+        match self {
+            Self::StartMeasurement => sensor.start_measurement(),
+            Self::StopMeasurement => sensor.stop_measurement(),
+            Self::StartFanCleaning => sensor.start_fan_cleaning(),
+            Self::SetAutoCleanInterval(secs) => {
+                sensor.set_auto_clean_interval(AutoCleanInterval::from_secs(secs))
+            }
+        }
+    }
+}
+
+/// Identifies an enqueued request, to check whether it was executed.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Ticket(usize);
+
+#[derive(Debug)]
+struct Slot {
+    tag: AtomicU8,
+    arg: AtomicU32,
+}
+
+/// A lock-free queue of up to `N` requests.
+#[derive(Debug)]
+pub struct CommandQueue<const N: usize> {
+    slots: [Slot; N],
+    /// Number of requests enqueued so far, wrapping.
+    enqueued: AtomicUsize,
+    /// Number of requests executed so far, wrapping.
+    executed: AtomicUsize,
+}
+
+impl<const N: usize> Default for CommandQueue<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> CommandQueue<N> {
+    /// Create an empty queue.
+    pub const fn new() -> Self {
+        const {
+            assert!(
+                N > 0 && N <= usize::MAX / 2,
+                "the queue holds at least one request"
+            )
+        };
+        Self {
+            slots: [const {
+                Slot {
+                    tag: AtomicU8::new(0),
+                    arg: AtomicU32::new(0),
+                }
+            }; N],
+            enqueued: AtomicUsize::new(0),
+            executed: AtomicUsize::new(0),
+        }
+    }
+
+    /// Enqueue `request`, returning it back if the queue is full.
+    ///
+    /// Must only be called from one context at a time.
+    pub fn enqueue(&self, request: Request) -> Result<Ticket, Request> {
+        let enqueued = self.enqueued.load(Ordering::Relaxed);
+        let executed = self.executed.load(Ordering::Acquire);
+        if enqueued.wrapping_sub(executed) >= N {
+            return Err(request);
+        }
+        let slot = &self.slots[enqueued % N];
+        let (tag, arg) = request.encode();
+        slot.tag.store(tag, Ordering::Relaxed);
+        slot.arg.store(arg, Ordering::Relaxed);
+        self.enqueued
+            .store(enqueued.wrapping_add(1), Ordering::Release);
+        Ok(Ticket(enqueued))
+    }
+
+    /// Number of requests waiting for execution.
+    pub fn len(&self) -> usize {
+        let executed = self.executed.load(Ordering::Acquire);
+        self.enqueued.load(Ordering::Acquire).wrapping_sub(executed)
+    }
+
+    /// Whether no request is waiting for execution.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Whether the request of `ticket` was executed, successfully or not.
+    pub fn is_complete(&self, ticket: Ticket) -> bool {
+        let pending = self.enqueued.load(Ordering::Acquire).wrapping_sub(ticket.0);
+        let executed = self.executed.load(Ordering::Acquire).wrapping_sub(ticket.0);
+        // Both counters are past the ticket, unless it was not executed yet.
+        executed > 0 && executed <= pending
+    }
+
+    /// Take the oldest request without marking it as executed.
+    fn peek(&self) -> Option<(Ticket, Request)> {
+        let executed = self.executed.load(Ordering::Relaxed);
+        if self.enqueued.load(Ordering::Acquire) == executed {
+            return None;
+        }
+        let slot = &self.slots[executed % N];
+        let request = Request::decode(
+            slot.tag.load(Ordering::Relaxed),
+            slot.arg.load(Ordering::Relaxed),
+        );
+        Some((Ticket(executed), request))
+    }
+
+    /// Execute all pending requests in order, calling `done` with the result of each of them.
+    ///
+    /// Must only be called from one context at a time. Returns the number of executed requests.
+    pub fn execute<I2C, D, E>(
+        &self,
+        sensor: &mut Sen5x<I2C, D>,
+        mut done: impl FnMut(Ticket, Result<(), Error<E>>),
+    ) -> usize
+    where
+        I2C: I2c<Error = E>,
+        D: DelayNs,
+    {
+        let mut count = 0;
+        while let Some((ticket, request)) = self.peek() {
+            let result = request.execute(sensor);
+            self.executed
+                .store(ticket.0.wrapping_add(1), Ordering::Release);
+            done(ticket, result);
+            count += 1;
+        }
+        count
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use embedded_hal::i2c::ErrorKind;
+    use embedded_hal_mock as hal;
+
+    use self::hal::eh1::delay::NoopDelay as DelayMock;
+    use self::hal::eh1::i2c::{Mock as I2cMock, Transaction};
+    use super::*;
+
+    /// Test that requests execute in order and report their completion
+    #[test]
+    fn test_execute() {
+        let expectations = [
+            Transaction::write(0x69, vec![0x00, 0x21]),
+            Transaction::write(0x69, vec![0x80, 0x04, 0x00, 0x00, 0x81, 0x00, 0x3C, 0x39]),
+            Transaction::write(0x69, vec![0x56, 0x07]).with_error(ErrorKind::Other),
+        ];
+        let mut mock = I2cMock::new(&expectations);
+        let mut sensor = Sen5x::new(mock.clone(), DelayMock);
+        let queue = CommandQueue::<2>::new();
+
+        let start = queue.enqueue(Request::StartMeasurement).unwrap();
+        let interval = queue.enqueue(Request::SetAutoCleanInterval(60)).unwrap();
+        assert_eq!(
+            queue.enqueue(Request::StartFanCleaning),
+            Err(Request::StartFanCleaning)
+        );
+        assert_eq!(queue.len(), 2);
+        assert!(!queue.is_complete(start));
+
+        let mut results = vec![];
+        assert_eq!(
+            queue.execute(&mut sensor, |ticket, result| results.push((ticket, result))),
+            2
+        );
+        assert_eq!(results, [(start, Ok(())), (interval, Ok(()))]);
+        assert!(queue.is_complete(start) && queue.is_complete(interval));
+
+        let cleaning = queue.enqueue(Request::StartFanCleaning).unwrap();
+        assert!(!queue.is_complete(cleaning));
+        queue.execute(&mut sensor, |_, result| {
+            assert!(matches!(result, Err(Error::I2c(_))))
+        });
+        assert!(queue.is_complete(cleaning));
+        assert!(queue.is_empty());
+        mock.done();
+    }
+}
@@ -0,0 +1,210 @@
+//! Gradual changes of the temperature compensation.
+//!
+//! Writing a new temperature offset makes the reported temperature and humidity jump. The sensor
+//! can blend the change in itself: [`smoothed`] sets the time constant of the parameters, so the
+//! sensor moves to the new offset over the given period. Where the time constant is used for
+//! other purposes, an [`OffsetRamp`] moves the offset and slope in equal steps instead. Like
+//! [`StateSaver`](crate::saver::StateSaver), it is fed with the time elapsed since the last call.
+//!
+//! ```
+//! use core::time::Duration;
+//! use sen5x_rs::ramp::OffsetRamp;
+//! use sen5x_rs::types::TemperatureCompensation;
+//!
+//! let target = TemperatureCompensation {
+//!     offset: -2.0,
+//!     ..TemperatureCompensation::DATASHEET_DEFAULT
+//! };
+//! let mut ramp = OffsetRamp::new(
+//!     TemperatureCompensation::DATASHEET_DEFAULT,
+//!     target,
+//!     Duration::from_secs(600),
+//! )
+//! .with_steps(4);
+//! assert_eq!(ramp.advance(Duration::from_secs(100)), None);
+//! assert_eq!(ramp.advance(Duration::from_secs(50)).unwrap().offset, -0.5);
+//! assert_eq!(ramp.advance(Duration::from_secs(450)), Some(target));
+//! assert!(ramp.is_done());
+//! ```
+use core::time::Duration;
+
+use embedded_hal::{delay::DelayNs, i2c::I2c};
+
+use crate::types::TemperatureCompensation;
+use crate::{Error, Sen5x};
+
+/// `target` with the time constant set, so the sensor blends the change over `period`.
+///
+/// Periods longer than the largest time constant of about 18 hours are shortened to it.
+pub fn smoothed(target: TemperatureCompensation, period: Duration) -> TemperatureCompensation {
+    TemperatureCompensation {
+        time_constant: u16::try_from(period.as_secs()).unwrap_or(u16::MAX),
+        ..target
+    }
+}
+
+/// Moves the temperature compensation from one set of parameters to another in equal steps.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct OffsetRamp {
+    from: TemperatureCompensation,
+    to: TemperatureCompensation,
+    period: Duration,
+    steps: u32,
+    elapsed: Duration,
+    /// The last step returned.
+    step: u32,
+}
+
+impl OffsetRamp {
+    /// Default number of steps.
+    pub const DEFAULT_STEPS: u32 = 10;
+
+    /// A ramp from `from`, e.g. the current parameters, to `to` within `period`.
+    ///
+    /// The time constant of `to` is used for every step.
+    pub fn new(
+        from: TemperatureCompensation,
+        to: TemperatureCompensation,
+        period: impl Into<Duration>,
+    ) -> Self {
+        Self {
+            from,
+            to,
+            period: period.into(),
+            steps: Self::DEFAULT_STEPS,
+            elapsed: Duration::ZERO,
+            step: 0,
+        }
+    }
+
+    /// Use `steps` steps instead of [`Self::DEFAULT_STEPS`], at least one.
+    pub fn with_steps(self, steps: u32) -> Self {
+        Self {
+            steps: steps.max(1),
+            ..self
+        }
+    }
+
+    /// The parameters of `step`.
+    fn at(&self, step: u32) -> TemperatureCompensation {
+        if step >= self.steps {
+            return self.to;
+        }
+        #[allow(clippy::cast_precision_loss)] // exact for any reasonable number of steps
+        let progress = step as f32 / self.steps as f32;
+        let blend = |from: f32, to: f32| from + (to - from) * progress;
+        TemperatureCompensation {
+            offset: blend(self.from.offset, self.to.offset),
+            slope: blend(self.from.slope, self.to.slope),
+            time_constant: self.to.time_constant,
+        }
+    }
+
+    /// The parameters of the last step returned, `from` before the first one.
+    pub fn current(&self) -> TemperatureCompensation {
+        if self.step == 0 {
+            self.from
+        } else {
+            self.at(self.step)
+        }
+    }
+
+    /// Whether the target was reached.
+    pub fn is_done(&self) -> bool {
+        self.step >= self.steps
+    }
+
+    /// Add `elapsed` time and return the parameters to write if a new step was reached.
+    pub fn advance(&mut self, elapsed: impl Into<Duration>) -> Option<TemperatureCompensation> {
+        if self.is_done() {
+            return None;
+        }
+        self.elapsed = self.elapsed.saturating_add(elapsed.into());
+        let step = if self.elapsed >= self.period {
+            self.steps
+        } else {
+            // Below `steps` as the elapsed time is below the period.
+            let step = self.elapsed.as_nanos() * u128::from(self.steps) / self.period.as_nanos();
+            u32::try_from(step).unwrap_or(self.steps)
+        };
+        if step == self.step {
+            return None;
+        }
+        self.step = step;
+        Some(self.at(step))
+    }
+
+    /// Add `elapsed` time and write the parameters to `sensor` if a new step was reached.
+    /// Returns whether they were written.
+    ///
+    /// The temperature compensation can only be written in idle mode, e.g. between the
+    /// measurements of a duty cycle. On errors the step is written on the next call.
+    pub fn tick<I2C, D, E>(
+        &mut self,
+        elapsed: impl Into<Duration>,
+        sensor: &mut Sen5x<I2C, D>,
+    ) -> Result<bool, Error<E>>
+    where
+        I2C: I2c<Error = E>,
+        D: DelayNs,
+    {
+        let previous = self.step;
+        let Some(params) = self.advance(elapsed) else {
+            return Ok(false);
+        };
+        if let Err(e) = sensor.set_temperature_compensation(params) {
+            self.step = previous;
+            return Err(e);
+        }
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulator::Simulator;
+
+    fn offset(offset: f32) -> TemperatureCompensation {
+        TemperatureCompensation {
+            offset,
+            ..TemperatureCompensation::DATASHEET_DEFAULT
+        }
+    }
+
+    /// Test that the ramp steps through the parameters and writes them to the sensor
+    #[test]
+    fn test_ramp() {
+        assert_eq!(
+            smoothed(offset(1.0), Duration::from_secs(300)).time_constant,
+            300
+        );
+        assert_eq!(
+            smoothed(offset(1.0), Duration::from_secs(100_000)).time_constant,
+            u16::MAX
+        );
+
+        let mut ramp =
+            OffsetRamp::new(offset(1.0), offset(3.0), Duration::from_secs(10)).with_steps(0);
+        assert_eq!(ramp.current(), offset(1.0));
+        assert_eq!(ramp.advance(Duration::from_secs(9)), None);
+        assert_eq!(ramp.advance(Duration::from_secs(1)), Some(offset(3.0)));
+        assert_eq!(ramp.advance(Duration::from_secs(1)), None);
+
+        let sim = Simulator::new();
+        let mut sensor = Sen5x::new(sim.clone(), sim.delay());
+        let mut ramp = OffsetRamp::new(offset(0.0), offset(-1.0), Duration::from_secs(10));
+        assert!(ramp.tick(Duration::from_secs(3), &mut sensor).unwrap());
+        assert_eq!(ramp.current(), offset(-0.3));
+        assert_eq!(sensor.temperature_compensation().unwrap(), offset(-0.3));
+        assert!(!ramp.tick(Duration::from_millis(500), &mut sensor).unwrap());
+
+        sensor.start_measurement().unwrap();
+        assert!(ramp.tick(Duration::from_secs(1), &mut sensor).is_err());
+        assert_eq!(ramp.current(), offset(-0.3));
+        sensor.stop_measurement().unwrap();
+        assert!(ramp.tick(Duration::from_secs(10), &mut sensor).unwrap());
+        assert!(ramp.is_done());
+        assert_eq!(sensor.temperature_compensation().unwrap(), offset(-1.0));
+    }
+}
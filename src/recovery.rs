@@ -0,0 +1,264 @@
+//! Recovery of an I²C bus held low by the sensor.
+//!
+//! A reset or brown-out of the controller in the middle of a read can leave the sensor waiting
+//! for clock pulses to shift out the rest of a byte, holding SDA low and blocking the bus. The
+//! standard recovery drives SCL as a GPIO for up to nine pulses until the sensor releases SDA and
+//! ends with a stop condition. [`Sen5x::recover_bus`] performs it with [`BusPins`], implemented
+//! for a pair of `embedded-hal` pins ([`GpioPins`]) and closures ([`FnPins`]), switches the pins
+//! back to I²C with [`BusPins::release`] and probes the sensor afterwards.
+//!
+//! The pins have to be in open-drain GPIO mode during the recovery. [`Sen5x::recover_bus`]
+//! assumes that the pins can be controlled while the driver owns the I²C peripheral, i.e. that
+//! the function of the pins is switched at runtime, e.g. with the flexible or dynamic pins of the
+//! HAL or by writing the registers in the closures. With HALs encoding the function of a pin in
+//! its type, the I²C peripheral owns its pins: take the driver apart with [`Sen5x::release`],
+//! free the pins from the peripheral, call [`clock_out`] and create the peripheral and the driver
+//! again instead.
+//!
+//! ```
+//! # use embedded_hal_mock::eh1::delay::NoopDelay;
+//! use sen5x_rs::recovery::{clock_out, FnPins};
+//!
+//! let mut pins = FnPins {
+//!     scl: |_high: bool| { /* drive or release SCL */ },
+//!     sda: |_high: bool| { /* drive or release SDA */ },
+//!     sda_is_high: || true,
+//!     release: || { /* switch both lines back to I2C */ },
+//! };
+//! // SDA was not held low, only the stop condition is sent.
+//! assert_eq!(clock_out(&mut pins, &mut NoopDelay), Ok(Some(0)));
+//! ```
+use core::convert::Infallible;
+
+use embedded_hal::delay::DelayNs;
+use embedded_hal::digital::{self, InputPin, OutputPin};
+use embedded_hal::i2c::I2c;
+
+use crate::Sen5x;
+
+/// Clock pulses needed to shift out the rest of a byte and its acknowledge bit.
+pub const RECOVERY_PULSES: u32 = 9;
+
+/// Half of the clock period of the recovery pulses, 100 kHz as in standard mode [µs].
+const HALF_PERIOD_US: u32 = 5;
+
+/// GPIO control of the bus lines. Setting a line high releases it, open-drain.
+pub trait BusPins {
+    /// Error of the pins.
+    type Error;
+
+    /// Drive SCL low or release it.
+    fn set_scl(&mut self, high: bool) -> Result<(), Self::Error>;
+
+    /// Drive SDA low or release it.
+    fn set_sda(&mut self, high: bool) -> Result<(), Self::Error>;
+
+    /// Whether SDA is high, i.e. not held low by a device.
+    fn sda_is_high(&mut self) -> Result<bool, Self::Error>;
+
+    /// Switch the lines back to the I²C peripheral after the recovery.
+    fn release(&mut self) -> Result<(), Self::Error>;
+}
+
+/// Bus lines as `embedded-hal` pins, SDA configured as open-drain input and output.
+///
+/// The pins switch their function at runtime: `release` switches them back to I²C.
+#[derive(Debug)]
+pub struct GpioPins<SCL, SDA, REL> {
+    /// The clock line.
+    pub scl: SCL,
+    /// The data line.
+    pub sda: SDA,
+    /// Switches both lines back to I²C.
+    pub release: REL,
+}
+
+impl<SCL, SDA, REL> BusPins for GpioPins<SCL, SDA, REL>
+where
+    SCL: OutputPin,
+    SDA: OutputPin + InputPin,
+    REL: FnMut(&mut SCL, &mut SDA),
+{
+    type Error = digital::ErrorKind;
+
+    fn set_scl(&mut self, high: bool) -> Result<(), Self::Error> {
+        self.scl
+            .set_state(high.into())
+            .map_err(|e| digital::Error::kind(&e))
+    }
+
+    fn set_sda(&mut self, high: bool) -> Result<(), Self::Error> {
+        self.sda
+            .set_state(high.into())
+            .map_err(|e| digital::Error::kind(&e))
+    }
+
+    fn sda_is_high(&mut self) -> Result<bool, Self::Error> {
+        self.sda.is_high().map_err(|e| digital::Error::kind(&e))
+    }
+
+    fn release(&mut self) -> Result<(), Self::Error> {
+        (self.release)(&mut self.scl, &mut self.sda);
+        Ok(())
+    }
+}
+
+/// Bus lines controlled by closures, e.g. writing the GPIO registers directly.
+#[derive(Debug)]
+pub struct FnPins<SCL, SDA, READ, REL> {
+    /// Drives SCL low or releases it.
+    pub scl: SCL,
+    /// Drives SDA low or releases it.
+    pub sda: SDA,
+    /// Whether SDA is high.
+    pub sda_is_high: READ,
+    /// Switches both lines back to I²C.
+    pub release: REL,
+}
+
+impl<SCL, SDA, READ, REL> BusPins for FnPins<SCL, SDA, READ, REL>
+where
+    SCL: FnMut(bool),
+    SDA: FnMut(bool),
+    READ: FnMut() -> bool,
+    REL: FnMut(),
+{
+    type Error = Infallible;
+
+    fn set_scl(&mut self, high: bool) -> Result<(), Infallible> {
+        (self.scl)(high);
+        Ok(())
+    }
+
+    fn set_sda(&mut self, high: bool) -> Result<(), Infallible> {
+        (self.sda)(high);
+        Ok(())
+    }
+
+    fn sda_is_high(&mut self) -> Result<bool, Infallible> {
+        Ok((self.sda_is_high)())
+    }
+
+    fn release(&mut self) -> Result<(), Infallible> {
+        (self.release)();
+        Ok(())
+    }
+}
+
+/// Outcome of a bus recovery.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct RecoveryReport {
+    /// Clock pulses until SDA was released, `0` if it was not held low.
+    pub pulses: u32,
+    /// Whether SDA was released.
+    pub released: bool,
+    /// Whether the sensor responded afterwards.
+    pub responding: bool,
+}
+
+/// Clock the bus until SDA is released and send a stop condition. Returns the pulses needed, or
+/// `None` if SDA is still held low after [`RECOVERY_PULSES`].
+pub fn clock_out<P: BusPins>(
+    pins: &mut P,
+    delay: &mut impl DelayNs,
+) -> Result<Option<u32>, P::Error> {
+    pins.set_sda(true)?;
+    pins.set_scl(true)?;
+    delay.delay_us(HALF_PERIOD_US);
+    let mut pulses = 0;
+    while !pins.sda_is_high()? {
+        if pulses == RECOVERY_PULSES {
+            return Ok(None);
+        }
+        pins.set_scl(false)?;
+        delay.delay_us(HALF_PERIOD_US);
+        pins.set_scl(true)?;
+        delay.delay_us(HALF_PERIOD_US);
+        pulses += 1;
+    }
+    // A stop condition, SDA rising while SCL is high, resets the state machine of the sensor.
+    pins.set_scl(false)?;
+    pins.set_sda(false)?;
+    delay.delay_us(HALF_PERIOD_US);
+    pins.set_scl(true)?;
+    delay.delay_us(HALF_PERIOD_US);
+    pins.set_sda(true)?;
+    delay.delay_us(HALF_PERIOD_US);
+    Ok(Some(pulses))
+}
+
+impl<I2C, D, E> Sen5x<I2C, D>
+where
+    I2C: I2c<Error = E>,
+    D: DelayNs,
+{
+    /// Free a bus held low by the sensor with `pins`, switch them back to I²C with
+    /// [`BusPins::release`] and probe the sensor by reading its product name. Fails only if
+    /// controlling the pins fails.
+    ///
+    /// The pins are released even if SDA is still held low.
+    ///
+    /// The sensor may have been reset along with the controller, use [`Sen5x::sync_state`] to
+    /// find out whether it is still measuring.
+    pub fn recover_bus<P: BusPins>(&mut self, pins: &mut P) -> Result<RecoveryReport, P::Error> {
+        let pulses = clock_out(pins, self.delay_mut())?;
+        pins.release()?;
+        let responding = self.product_name().is_ok();
+        Ok(RecoveryReport {
+            pulses: pulses.unwrap_or(RECOVERY_PULSES),
+            released: pulses.is_some(),
+            responding,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulator::Simulator;
+    use std::cell::{Cell, RefCell};
+
+    /// Test that the bus is clocked until SDA is released and the sensor probed
+    #[test]
+    fn test_recover_bus() {
+        let sim = Simulator::new();
+        let mut sensor = Sen5x::new(sim.clone(), sim.delay());
+        let held = Cell::new(3);
+        let scl = RefCell::new(Vec::new());
+        let mut pins = FnPins {
+            scl: |high: bool| {
+                if !high && held.get() > 0 {
+                    held.set(held.get() - 1);
+                }
+                scl.borrow_mut().push(high);
+            },
+            sda: |_: bool| {},
+            sda_is_high: || held.get() == 0,
+            release: || sim.set_address(0x69),
+        };
+        // The sensor cannot be reached while the lines are GPIOs.
+        sim.set_address(0x10);
+        let report = sensor.recover_bus(&mut pins).unwrap();
+        assert_eq!(
+            report,
+            RecoveryReport {
+                pulses: 3,
+                released: true,
+                responding: true
+            }
+        );
+        // Released, three pulses and the stop condition.
+        assert_eq!(scl.borrow().len(), 1 + 3 * 2 + 2);
+
+        let mut stuck = FnPins {
+            scl: |_: bool| {},
+            sda: |_: bool| {},
+            sda_is_high: || false,
+            release: || {},
+        };
+        sim.nack_transaction(0);
+        let report = sensor.recover_bus(&mut stuck).unwrap();
+        assert!(!report.released && !report.responding);
+        assert_eq!(report.pulses, RECOVERY_PULSES);
+    }
+}
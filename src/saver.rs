@@ -0,0 +1,167 @@
+//! Periodic saving of the VOC algorithm state.
+//!
+//! [`StateSaver`] is fed with the time elapsed since the last call, e.g. from the measurement
+//! loop, and reads the VOC algorithm state whenever the configured interval passed. The state is
+//! handed to a [`StateSink`], which can be a closure or, with the `embedded-storage` feature, a
+//! `VocStateStorage`.
+use core::time::Duration;
+
+use embedded_hal::{delay::DelayNs, i2c::I2c};
+
+use crate::types::VocAlgorithmState;
+use crate::{Error, Sen5x};
+
+/// Receives the VOC algorithm state whenever it is due to be saved.
+pub trait StateSink {
+    /// Error returned when the state could not be saved.
+    type Error;
+
+    /// Save `state`, e.g. as the blob returned by [`VocAlgorithmState::to_blob`].
+    fn save(&mut self, state: &VocAlgorithmState) -> Result<(), Self::Error>;
+}
+
+impl<F, SE> StateSink for F
+where
+    F: FnMut(&VocAlgorithmState) -> Result<(), SE>,
+{
+    type Error = SE;
+
+    fn save(&mut self, state: &VocAlgorithmState) -> Result<(), SE> {
+        self(state)
+    }
+}
+
+#[cfg(feature = "embedded-storage")]
+impl<F> StateSink for crate::storage::VocStateStorage<F>
+where
+    F: embedded_storage::nor_flash::NorFlash,
+{
+    type Error = crate::storage::StorageError<F::Error>;
+
+    fn save(&mut self, state: &VocAlgorithmState) -> Result<(), Self::Error> {
+        crate::storage::VocStateStorage::save(self, state)
+    }
+}
+
+/// Errors when saving the VOC algorithm state.
+#[derive(Debug, PartialEq)]
+pub enum SaveError<E, S> {
+    /// Reading the state from the sensor failed
+    Sensor(Error<E>),
+    /// The sink failed to save the state
+    Sink(S),
+}
+
+/// Triggers saving the VOC algorithm state at a fixed interval of elapsed time.
+#[derive(Debug, Copy, Clone)]
+pub struct StateSaver {
+    interval: Duration,
+    elapsed: Duration,
+}
+
+impl StateSaver {
+    /// Interval recommended by Sensirion, one hour.
+    pub const DEFAULT_INTERVAL: Duration = Duration::from_secs(3600);
+
+    /// Save the state every `interval`.
+    pub fn new(interval: impl Into<Duration>) -> Self {
+        Self {
+            interval: interval.into(),
+            elapsed: Duration::ZERO,
+        }
+    }
+
+    /// Add `elapsed` time and return whether saving the state is due.
+    pub fn advance(&mut self, elapsed: impl Into<Duration>) -> bool {
+        self.elapsed = self.elapsed.saturating_add(elapsed.into());
+        self.is_due()
+    }
+
+    /// Whether saving the state is due.
+    pub fn is_due(&self) -> bool {
+        self.elapsed >= self.interval
+    }
+
+    /// Restart the interval, e.g. after the state was saved by other means.
+    pub fn reset(&mut self) {
+        self.elapsed = Duration::ZERO;
+    }
+
+    /// Add `elapsed` time and save the state to `sink` if due. Returns whether it was saved.
+    ///
+    /// On errors the state stays due and is saved on the next call.
+    pub fn tick<I2C, D, E, S>(
+        &mut self,
+        elapsed: impl Into<Duration>,
+        sensor: &mut Sen5x<I2C, D>,
+        sink: &mut S,
+    ) -> Result<bool, SaveError<E, S::Error>>
+    where
+        I2C: I2c<Error = E>,
+        D: DelayNs,
+        S: StateSink,
+    {
+        if !self.advance(elapsed) {
+            return Ok(false);
+        }
+        let state = sensor.voc_algorithm_state().map_err(SaveError::Sensor)?;
+        sink.save(&state).map_err(SaveError::Sink)?;
+        self.reset();
+        Ok(true)
+    }
+}
+
+impl Default for StateSaver {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_INTERVAL)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulator::Simulator;
+
+    /// Test that the state is handed to the sink once per interval
+    #[test]
+    fn test_cadence() {
+        let sim = Simulator::new();
+        let mut sensor = Sen5x::new(sim.clone(), sim.delay());
+        sensor.start_measurement().unwrap();
+
+        let mut saved = 0;
+        let mut sink = |_: &VocAlgorithmState| -> Result<(), ()> {
+            saved += 1;
+            Ok(())
+        };
+        let mut saver = StateSaver::new(Duration::from_secs(60));
+        for _ in 0..150 {
+            saver
+                .tick(Duration::from_secs(1), &mut sensor, &mut sink)
+                .unwrap();
+        }
+        assert_eq!(saved, 2);
+    }
+
+    /// Test that a failed save is retried on the next tick
+    #[test]
+    fn test_retry() {
+        let sim = Simulator::new();
+        let mut sensor = Sen5x::new(sim.clone(), sim.delay());
+        let mut saver = StateSaver::new(Duration::from_secs(10));
+
+        let mut failing = |_: &VocAlgorithmState| Err("flash busy");
+        assert_eq!(
+            saver.tick(Duration::from_secs(10), &mut sensor, &mut failing),
+            Err(SaveError::Sink("flash busy"))
+        );
+        assert!(saver.is_due());
+
+        let mut working = |_: &VocAlgorithmState| Ok::<(), ()>(());
+        assert_eq!(
+            saver.tick(Duration::ZERO, &mut sensor, &mut working),
+            Ok(true)
+        );
+        assert!(!saver.is_due());
+    }
+}
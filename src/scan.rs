@@ -0,0 +1,297 @@
+//! Detection of address conflicts on the bus.
+//!
+//! The SEN5x and the SPS30 share the address 0x69, and I²C cannot tell two devices apart which
+//! acknowledge the same address: both drive the bus at once, which shows up as sporadic CRC errors
+//! rather than a clear failure. [`scan_bus`] probes the addresses of related Sensirion sensors and
+//! reads the serial number at 0x69 several times. Corrupted or differing responses reveal a
+//! conflict, and [`ScanReport::guidance`] tells what to do about it.
+//!
+//! ```
+//! # use embedded_hal_mock::eh1::{delay::NoopDelay, i2c::{Mock, Transaction}};
+//! # use embedded_hal::i2c::{ErrorKind, NoAcknowledgeSource};
+//! use sen5x_rs::scan::{scan_bus, Guidance};
+//!
+//! # let nack = ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address);
+//! # let mut expectations: Vec<_> = [0x44, 0x59, 0x62, 0x6A, 0x6B, 0x6C, 0x70]
+//! #     .into_iter()
+//! #     .map(|address| Transaction::write(address, vec![]).with_error(nack))
+//! #     .collect();
+//! # expectations.extend([Transaction::write(0x69, vec![0xD0, 0x33]).with_error(nack)]);
+//! # let mut i2c = Mock::new(&expectations);
+//! let report = scan_bus(&mut i2c, &mut NoopDelay);
+//! assert_eq!(report.responding().count(), 0);
+//! assert_eq!(report.guidance(), Some(Guidance::NotFound));
+//! # i2c.done();
+//! ```
+use embedded_hal::delay::DelayNs;
+use embedded_hal::i2c::{ErrorKind, I2c};
+
+use crate::commands::{Command, GET_SERIAL_NUMBER_MS};
+use crate::parse;
+use crate::types::SerialNumber;
+
+/// The address of the SEN5x and the SPS30.
+pub const SENSOR_ADDRESS: u8 = 0x69;
+
+/// Times the serial number is read to detect a conflict.
+pub const PROBE_READS: u8 = 3;
+
+/// A device often found on the bus of a SEN5x.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct KnownDevice {
+    /// The 7-bit address.
+    pub address: u8,
+    /// The devices using the address.
+    pub name: &'static str,
+}
+
+/// Devices probed by [`scan_bus`], in the order they are probed.
+pub const KNOWN_DEVICES: [KnownDevice; 8] = [
+    KnownDevice {
+        address: 0x44,
+        name: "SHT4x",
+    },
+    KnownDevice {
+        address: 0x59,
+        name: "SGP4x",
+    },
+    KnownDevice {
+        address: 0x62,
+        name: "SCD4x",
+    },
+    KnownDevice {
+        address: 0x6A,
+        name: "SVM4x",
+    },
+    KnownDevice {
+        address: 0x6B,
+        name: "SEN6x",
+    },
+    KnownDevice {
+        address: 0x6C,
+        name: "SEN60",
+    },
+    KnownDevice {
+        address: 0x70,
+        name: "I²C multiplexer",
+    },
+    KnownDevice {
+        address: SENSOR_ADDRESS,
+        name: "SEN5x or SPS30",
+    },
+];
+
+/// Outcome of reading the serial number at [`SENSOR_ADDRESS`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SensorProbe {
+    /// No device acknowledged the address.
+    Absent,
+    /// Every read returned the same valid serial number.
+    Single(SerialNumber),
+    /// The reads were corrupted or differed, likely several devices responding at once.
+    Conflict {
+        /// Reads which failed on the bus or had an invalid CRC.
+        crc_errors: u8,
+        /// Valid reads differing from the first valid one.
+        mismatches: u8,
+    },
+}
+
+/// What to do about the result of a scan.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Guidance {
+    /// Several devices respond at [`SENSOR_ADDRESS`]: put them behind an I²C multiplexer or on
+    /// separate buses.
+    AddressConflict,
+    /// Only the multiplexer responds: select the channel of the sensor before using it.
+    SelectMuxChannel,
+    /// Nothing responds: check the wiring, the pull-up resistors and the 5 V supply.
+    NotFound,
+}
+
+impl Guidance {
+    /// A short explanation for the user.
+    pub fn message(&self) -> &'static str {
+        match self {
+            Self::AddressConflict => {
+                "several devices respond at 0x69, use an I2C multiplexer or separate buses"
+            }
+            Self::SelectMuxChannel => "only the multiplexer responds, select the sensor channel",
+            Self::NotFound => "no device responds, check the wiring, pull-ups and 5 V supply",
+        }
+    }
+}
+
+/// Outcome of [`scan_bus`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ScanReport {
+    /// Bit `i` set if the `i`-th of the [`KNOWN_DEVICES`] responded.
+    responding: u8,
+    /// Outcome of reading the serial number.
+    pub sensor: SensorProbe,
+}
+
+impl ScanReport {
+    /// Whether a device responded at `address`, `false` for addresses which were not probed.
+    pub fn responds(&self, address: u8) -> bool {
+        self.responding().any(|device| device.address == address)
+    }
+
+    /// The known devices which responded.
+    pub fn responding(&self) -> impl Iterator<Item = &'static KnownDevice> + '_ {
+        KNOWN_DEVICES
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| self.responding & (1 << i) != 0)
+            .map(|(_, device)| device)
+    }
+
+    /// What to do, `None` if a single sensor responds at [`SENSOR_ADDRESS`].
+    pub fn guidance(&self) -> Option<Guidance> {
+        match self.sensor {
+            SensorProbe::Single(_) => None,
+            SensorProbe::Conflict { .. } => Some(Guidance::AddressConflict),
+            SensorProbe::Absent if self.responds(0x70) => Some(Guidance::SelectMuxChannel),
+            SensorProbe::Absent => Some(Guidance::NotFound),
+        }
+    }
+}
+
+/// Whether `error` means that no device acknowledged the address.
+fn is_absent(error: &impl embedded_hal::i2c::Error) -> bool {
+    matches!(error.kind(), ErrorKind::NoAcknowledge(_))
+}
+
+/// Probe the [`KNOWN_DEVICES`] on `i2c` and read the serial number at [`SENSOR_ADDRESS`]
+/// [`PROBE_READS`] times, e.g. before creating the driver.
+///
+/// The other addresses are probed with an empty write. Bus errors other than a missing
+/// acknowledge count as a response, as a device is driving the bus.
+pub fn scan_bus<I2C: I2c>(i2c: &mut I2C, delay: &mut impl DelayNs) -> ScanReport {
+    let mut responding = 0;
+    for (i, device) in KNOWN_DEVICES.iter().enumerate() {
+        if device.address == SENSOR_ADDRESS {
+            continue;
+        }
+        if !i2c.write(device.address, &[]).is_err_and(|e| is_absent(&e)) {
+            responding |= 1 << i;
+        }
+    }
+    let sensor = probe_sensor(i2c, delay);
+    if sensor != SensorProbe::Absent {
+        responding |= 1 << (KNOWN_DEVICES.len() - 1);
+    }
+    ScanReport { responding, sensor }
+}
+
+/// Read the serial number at [`SENSOR_ADDRESS`] [`PROBE_READS`] times and compare the results.
+fn probe_sensor<I2C: I2c>(i2c: &mut I2C, delay: &mut impl DelayNs) -> SensorProbe {
+    let code = Command::GetSerialNumber.spec().code;
+    let mut first = None;
+    let (mut crc_errors, mut mismatches) = (0, 0);
+    for _ in 0..PROBE_READS {
+        let mut frame = [0u8; 48];
+        match i2c.write(SENSOR_ADDRESS, &code.to_be_bytes()) {
+            Err(e) if is_absent(&e) && first.is_none() && crc_errors == 0 => {
+                return SensorProbe::Absent;
+            }
+            Err(_) => {
+                crc_errors += 1;
+                continue;
+            }
+            Ok(()) => {}
+        }
+        delay.delay_ms(GET_SERIAL_NUMBER_MS);
+        if i2c.read(SENSOR_ADDRESS, &mut frame).is_err() {
+            crc_errors += 1;
+            continue;
+        }
+        match (parse::serial_number::<()>(&frame), first) {
+            (Err(_), _) => crc_errors += 1,
+            (Ok(serial), None) => first = Some(serial),
+            (Ok(serial), Some(expected)) if serial != expected => mismatches += 1,
+            (Ok(_), Some(_)) => {}
+        }
+    }
+    match first {
+        Some(serial) if crc_errors == 0 && mismatches == 0 => SensorProbe::Single(serial),
+        _ => SensorProbe::Conflict {
+            crc_errors,
+            mismatches,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulator::Simulator;
+    use embedded_hal::i2c::NoAcknowledgeSource;
+    use embedded_hal_mock::eh1::delay::NoopDelay;
+    use embedded_hal_mock::eh1::i2c::{Mock, Transaction};
+    use std::vec;
+    use std::vec::Vec;
+
+    /// Test that a single sensor is found and no guidance is given
+    #[test]
+    fn test_scan_single() {
+        let mut sim = Simulator::new();
+        sim.set_serial_number("1A2B3C4D5E6F7A8B");
+        let mut delay = sim.delay();
+        let report = scan_bus(&mut sim, &mut delay);
+        assert!(report.responds(SENSOR_ADDRESS));
+        assert_eq!(report.responding().count(), 1);
+        let SensorProbe::Single(serial) = report.sensor else {
+            panic!("expected a single sensor, got {:?}", report.sensor);
+        };
+        assert_eq!(serial.as_ascii(), Some("1A2B3C4D5E6F7A8B"));
+        assert_eq!(report.guidance(), None);
+    }
+
+    /// Test that a SEN60 is found at its own address
+    #[test]
+    fn test_scan_sen60() {
+        let mut sim = Simulator::new();
+        sim.set_address(0x6C);
+        let mut delay = sim.delay();
+        let report = scan_bus(&mut sim, &mut delay);
+        let names: Vec<_> = report.responding().map(|device| device.name).collect();
+        assert_eq!(names, ["SEN60"]);
+        assert_eq!(report.sensor, SensorProbe::Absent);
+    }
+
+    /// Test that corrupted and differing serial numbers are reported as a conflict
+    #[test]
+    fn test_scan_conflict() {
+        let nack = ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address);
+        let serial = |byte: u8| {
+            let word = [byte, byte, crate::crc::crc(&[byte, byte])];
+            word.repeat(16)
+        };
+        let mut corrupted = serial(b'A');
+        corrupted[2] ^= 0xFF;
+        let mut expectations: Vec<_> = KNOWN_DEVICES[..7]
+            .iter()
+            .map(|device| match device.address {
+                0x70 => Transaction::write(0x70, vec![]),
+                address => Transaction::write(address, vec![]).with_error(nack),
+            })
+            .collect();
+        for response in [serial(b'A'), corrupted, serial(b'B')] {
+            expectations.push(Transaction::write(SENSOR_ADDRESS, vec![0xD0, 0x33]));
+            expectations.push(Transaction::read(SENSOR_ADDRESS, response));
+        }
+        let mut i2c = Mock::new(&expectations);
+        let report = scan_bus(&mut i2c, &mut NoopDelay);
+        assert_eq!(
+            report.sensor,
+            SensorProbe::Conflict {
+                crc_errors: 1,
+                mismatches: 1
+            }
+        );
+        assert!(report.responds(0x70) && report.responds(SENSOR_ADDRESS));
+        assert_eq!(report.guidance(), Some(Guidance::AddressConflict));
+        i2c.done();
+    }
+}
@@ -1,17 +1,102 @@
+use core::time::Duration;
+
 use embedded_hal::{delay::DelayNs, i2c::I2c};
-use sensirion_i2c::i2c as sen_i2c;
 
-use crate::commands::Command;
-use crate::crc;
-use crate::types::{Sen5xData, Sen5xDataRaw};
+use crate::commands::{Command, CommandSpec, WaitStrategy};
+use crate::errors::bus_error;
+use crate::frame::MAX_WORDS;
+use crate::lifecycle::{LifecycleEvent, LifecycleObserver};
+use crate::measurement::{ChannelMask, Measurement};
+use crate::observer::{self, MeasurementObserver};
+use crate::parse;
+use crate::protocol;
+use crate::types::{
+    AutoCleanInterval, Capabilities, Config, ConfigDiff, DeviceInfo, DeviceStatus, DeviceVariant,
+    DriverState, NoxTuningParameters, ParticulateMatter, RawValues, Sen5xData, Sen5xDataRaw,
+    SerialNumber, ShutdownState, TemperatureCompensation, VocAlgorithmState, VocTuningParameters,
+};
 use crate::Error;
 
 /// The default I²C address of the SEN5X sensor.
 const _SEN5X_I2C_ADDRESS: u8 = 0x69;
 
+/// Time [`Sen5x::sync_state`] waits for the data-ready flag, just over one signal update interval.
+const SYNC_STATE_TIMEOUT_MS: u32 = 1100;
+
+/// Interval at which [`Sen5x::sync_state`] polls the data-ready flag.
+const SYNC_STATE_POLL_MS: u32 = 100;
+
+/// Interval at which [`Sen5x::measure_blocking`] polls the data-ready flag.
+const DATA_READY_POLL_MS: u32 = 50;
+
+/// Interval at which [`Sen5x::start_fan_cleaning_blocking`] polls the device status.
+const FAN_CLEANING_POLL_MS: u32 = 500;
+
+/// The I²C general call address.
+const GENERAL_CALL_ADDRESS: u8 = 0x00;
+
+/// The second byte of the I²C general call reset.
+const GENERAL_CALL_RESET: u8 = 0x06;
+
+/// `delay` in whole microseconds, rounded up and saturated.
+pub(crate) fn guard_us(delay: Duration) -> u32 {
+    let us = delay.as_micros() + u128::from(!delay.subsec_nanos().is_multiple_of(1_000));
+    u32::try_from(us).unwrap_or(u32::MAX)
+}
+
+/// Step of [`Sen5x::poll_measurement`], named after the next bus interaction.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+enum PollState {
+    /// Issue `Read Data-Ready Flag`.
+    #[default]
+    RequestDataReady,
+    /// Read the data-ready flag.
+    ReadDataReady,
+    /// Issue `Read Measured Values`.
+    RequestMeasurement,
+    /// Read the measured values.
+    ReadMeasurement,
+}
+
+/// The state of a sensor tracked by [`Sen5x`], as opposed to the configuration of the driver.
+#[derive(Debug, Copy, Clone, Default)]
+pub(crate) struct SensorState {
+    is_running: bool,
+    poll: PollState,
+    has_sample: bool,
+    cleaning: bool,
+    faulted: bool,
+    eeprom_writes: u32,
+    firmware: Option<u8>,
+}
+
+/// A delay provider which never waits, the default delay of [`Sen5x`].
+///
+/// A driver created with [`Sen5x::without_delay`] stores no delay and is named `Sen5x<I2C>`, e.g.
+/// in a `static` cell, for applications which space the commands themselves and advance the
+/// measurement with [`Sen5x::poll_measurement`]. Such a driver cannot wait for the execution
+/// time of reads, so blocking operations reading from the sensor return `Error::Unsupported`.
+///
+/// Only [`Sen5x::without_delay`] creates it, so the blocking operations of a driver never read
+/// without waiting:
+///
+/// ```compile_fail
+/// # use embedded_hal_mock::eh1::i2c::Mock;
+/// use sen5x_rs::{NoDelay, Sen5x};
+///
+/// # let i2c = Mock::new(&[]);
+/// let sensor = Sen5x::new(i2c, NoDelay);
+/// ```
+#[derive(Debug, PartialEq, Eq)]
+pub struct NoDelay(());
+
+impl DelayNs for NoDelay {
+    fn delay_ns(&mut self, _ns: u32) {}
+}
+
 /// SEN5x sensor instance. Use related methods to take measurements.
 #[derive(Debug, Default)]
-pub struct Sen5x<I2C, D> {
+pub struct Sen5x<I2C, D = NoDelay> {
     /// The concrete I²C device implementation.
     i2c: I2C,
     /// The concrete Delay implementation.
@@ -20,6 +105,57 @@ pub struct Sen5x<I2C, D> {
     is_running: bool,
     /// The I2C address of the sensor.
     address: u8,
+    /// How to wait for commands to finish executing.
+    wait: WaitStrategy,
+    /// Additional delay between the write and the read phase of a command [µs].
+    guard_us: u32,
+    /// Longest time blocking operations wait for the sensor, `None` to wait indefinitely.
+    timeout: Option<Duration>,
+    /// Next step of the non-blocking measurement.
+    poll: PollState,
+    /// Whether a sample was read since the measurement was started.
+    has_sample: bool,
+    /// Whether the fan cleaning was started and not reported as finished yet.
+    cleaning: bool,
+    /// Whether the last transaction failed.
+    faulted: bool,
+    /// Channels read by [`Sen5x::measurement_channels`].
+    channels: ChannelMask,
+    /// Writes to the non-volatile memory of the sensor performed by this instance.
+    eeprom_writes: u32,
+    /// Major firmware version, once read from the sensor.
+    firmware: Option<u8>,
+    /// Receives the lifecycle events.
+    observer: Option<&'static dyn LifecycleObserver>,
+    /// Receives the new samples.
+    measurement_observer: Option<&'static dyn MeasurementObserver>,
+    /// Whether the driver was created by [`Sen5x::without_delay`] and cannot wait for reads.
+    no_delay: bool,
+}
+
+impl<I2C, D> Sen5x<I2C, D> {
+    /// Default of the timeout of blocking operations, two signal update intervals.
+    pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(2);
+}
+
+impl<I2C, E> Sen5x<I2C>
+where
+    I2C: I2c<Error = E>,
+{
+    /// Create a new instance using the default I2C address which never delays.
+    ///
+    /// The commands are not waited for, as with [`WaitStrategy::Skip`], so the application has
+    /// to space them by their execution time, e.g. by calling [`Sen5x::poll_measurement`] every
+    /// 20 ms. Commands without response, e.g. [`Sen5x::start_measurement`], are written as usual.
+    /// The sensor does not acknowledge reads during the execution time of the command, so the
+    /// blocking operations reading a response, e.g. [`Sen5x::measurement`] or
+    /// [`Sen5x::serial_number`], fail with `Error::Unsupported` without accessing the bus.
+    pub fn without_delay(i2c: I2C) -> Self {
+        let mut sensor = Self::new(i2c, NoDelay(()));
+        sensor.set_wait_strategy(WaitStrategy::Skip);
+        sensor.no_delay = true;
+        sensor
+    }
 }
 
 impl<I2C, D, E> Sen5x<I2C, D>
@@ -34,6 +170,45 @@ where
             delay,
             is_running: false,
             address: _SEN5X_I2C_ADDRESS,
+            wait: WaitStrategy::Datasheet,
+            guard_us: 0,
+            timeout: Some(Self::DEFAULT_TIMEOUT),
+            poll: PollState::RequestDataReady,
+            has_sample: false,
+            cleaning: false,
+            faulted: false,
+            channels: ChannelMask::ALL,
+            eeprom_writes: 0,
+            firmware: None,
+            observer: None,
+            measurement_observer: None,
+            no_delay: false,
+        }
+    }
+
+    /// Create a new instance after verifying that the sensor is the `expected` variant.
+    ///
+    /// Fails with [`Error::WrongDevice`] if the product name does not match or is not a SEN5x,
+    /// since the SEN6x is driven by [`crate::sen6x::Sen6x`].
+    pub fn try_new(i2c: I2C, delay: D, expected: DeviceVariant) -> Result<Self, Error<E>> {
+        Self::new(i2c, delay).verified(expected)
+    }
+
+    /// Create a new instance using a custom I2C address after verifying that the sensor is the
+    /// `expected` variant.
+    pub fn try_with_i2c_address(
+        i2c: I2C,
+        delay: D,
+        address: u8,
+        expected: DeviceVariant,
+    ) -> Result<Self, Error<E>> {
+        Self::with_i2c_address(i2c, delay, address).verified(expected)
+    }
+
+    fn verified(mut self, expected: DeviceVariant) -> Result<Self, Error<E>> {
+        match self.device_variant()? {
+            Some(found) if found == expected && found.is_sen5x() => Ok(self),
+            found => Err(Error::WrongDevice { found }),
         }
     }
 
@@ -44,135 +219,860 @@ where
             delay,
             is_running: false,
             address,
+            wait: WaitStrategy::Datasheet,
+            guard_us: 0,
+            timeout: Some(Self::DEFAULT_TIMEOUT),
+            poll: PollState::RequestDataReady,
+            has_sample: false,
+            cleaning: false,
+            faulted: false,
+            channels: ChannelMask::ALL,
+            eeprom_writes: 0,
+            firmware: None,
+            observer: None,
+            measurement_observer: None,
+            no_delay: false,
         }
     }
 
+    /// Create a new instance using an I2C address validated at compile time.
+    ///
+    /// Fails to build if `ADDRESS` is not a 7-bit address outside the reserved ranges, e.g. for
+    /// production builds with the address fixed by the board.
+    ///
+    /// ```
+    /// # use embedded_hal_mock::eh1::{delay::NoopDelay, i2c::Mock};
+    /// # let mut i2c = Mock::new(&[]);
+    /// let sensor = sen5x_rs::Sen5x::with_const_address::<0x69>(i2c.clone(), NoopDelay);
+    /// assert_eq!(sensor.address(), 0x69);
+    /// # i2c.done();
+    /// ```
+    ///
+    /// ```compile_fail
+    /// # use embedded_hal_mock::eh1::{delay::NoopDelay, i2c::Mock};
+    /// # let i2c = Mock::new(&[]);
+    /// let sensor = sen5x_rs::Sen5x::with_const_address::<0x80>(i2c, NoopDelay);
+    /// ```
+    pub fn with_const_address<const ADDRESS: u8>(i2c: I2C, delay: D) -> Self {
+        const {
+            assert!(
+                ADDRESS >= 0x08 && ADDRESS <= 0x77,
+                "not a valid 7-bit I2C address"
+            )
+        };
+        Self::with_i2c_address(i2c, delay, ADDRESS)
+    }
+
+    /// The I2C address of the sensor.
+    pub fn address(&self) -> u8 {
+        self.address
+    }
+
+    /// Mutable access to the underlying I²C bus, e.g. for bus recovery.
+    pub fn i2c_mut(&mut self) -> &mut I2C {
+        &mut self.i2c
+    }
+
+    /// Mutable access to the underlying delay provider.
+    pub fn delay_mut(&mut self) -> &mut D {
+        &mut self.delay
+    }
+
+    /// Release the bus and the delay provider.
+    pub fn release(self) -> (I2C, D) {
+        (self.i2c, self.delay)
+    }
+
+    /// Report the lifecycle events of the driver to `observer`, `None` to stop reporting them.
+    pub fn set_observer(&mut self, observer: Option<&'static dyn LifecycleObserver>) {
+        self.observer = observer;
+    }
+
+    /// Forward every new sample to `observer`, `None` to stop forwarding them.
+    pub fn set_measurement_observer(&mut self, observer: Option<&'static dyn MeasurementObserver>) {
+        self.measurement_observer = observer;
+    }
+
+    /// Set how the driver waits for commands to finish executing.
+    pub fn set_wait_strategy(&mut self, wait: WaitStrategy) {
+        self.wait = wait;
+    }
+
+    /// Wait for `delay` in addition to the execution time between writing a command and reading
+    /// its response, rounded up to whole microseconds.
+    ///
+    /// Some I²C masters and long cables need a gap between transactions longer than the minimum
+    /// given in the datasheet. There is no guard delay by default.
+    pub fn set_guard_delay(&mut self, delay: impl Into<Duration>) {
+        self.guard_us = guard_us(delay.into());
+    }
+
+    /// The additional delay between writing a command and reading its response.
+    pub fn guard_delay(&self) -> Duration {
+        Duration::from_micros(self.guard_us.into())
+    }
+
+    /// Set the longest time blocking operations like [`Sen5x::measure_blocking`] wait for the
+    /// sensor before returning `Error::Timeout`, `None` to wait indefinitely.
+    ///
+    /// The time is measured by adding up the delays of the driver, so it is a lower bound of the
+    /// time actually spent. Defaults to [`Sen5x::DEFAULT_TIMEOUT`].
+    pub fn set_timeout(&mut self, timeout: Option<Duration>) {
+        self.timeout = timeout;
+    }
+
+    /// The timeout of blocking operations.
+    pub fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
     /// Start periodic measurement, signal update interval is 1 second.
     pub fn start_measurement(&mut self) -> Result<(), Error<E>> {
         self.write_command(Command::StartMeasurement)?;
         self.is_running = true;
+        self.poll = PollState::RequestDataReady;
+        self.has_sample = false;
+        self.cleaning = false;
+        self.emit(LifecycleEvent::Started);
         Ok(())
     }
 
+    /// Stop periodic measurement and return to idle mode.
+    pub fn stop_measurement(&mut self) -> Result<(), Error<E>> {
+        self.write_command(Command::StopMeasurement)?;
+        self.is_running = false;
+        self.cleaning = false;
+        self.emit(LifecycleEvent::Stopped);
+        Ok(())
+    }
+
+    /// Stop the measurement and capture everything needed to resume it later.
+    ///
+    /// Reads the VOC algorithm state, stops the measurement, reads the configuration and reads and
+    /// clears the device status. Pass the result to [`Sen5x::resume`] after a restart.
+    pub fn shutdown(&mut self) -> Result<ShutdownState, Error<E>> {
+        let voc_state = self.voc_algorithm_state()?;
+        if self.is_running {
+            self.stop_measurement()?;
+        }
+        let config = self.config()?;
+        let status = self.device_status_and_clear()?;
+        Ok(ShutdownState {
+            voc_state,
+            config,
+            status,
+        })
+    }
+
+    /// Restore the configuration and VOC algorithm state captured by [`Sen5x::shutdown`] and
+    /// start the measurement.
+    pub fn resume(&mut self, state: &ShutdownState) -> Result<(), Error<E>> {
+        if self.is_running {
+            self.stop_measurement()?;
+        }
+        self.set_config(&state.config)?;
+        self.set_voc_algorithm_state(state.voc_state)?;
+        self.start_measurement()
+    }
+
     /// The reinit command reinitializes the sensor by reloading user settings from EEPROM.
     pub fn reinit(&mut self) -> Result<(), Error<E>> {
         self.write_command(Command::Reinit)?;
+        self.is_running = false;
+        self.cleaning = false;
+        self.emit(LifecycleEvent::Reinitialized);
         Ok(())
     }
 
-    /// Get 48-bit serial number.
-    pub fn serial_number(&mut self) -> Result<u64, Error<E>> {
-        let mut buf = [0; 9];
-        self.delayed_read_cmd(Command::GetSerialNumber, &mut buf)?;
-        let serial = u64::from(buf[0]) << 40
-            | u64::from(buf[1]) << 32
-            | u64::from(buf[3]) << 24
-            | u64::from(buf[4]) << 16
-            | u64::from(buf[6]) << 8
-            | u64::from(buf[7]);
+    /// Whether the driver believes periodic measurement is running.
+    ///
+    /// This is tracked by the driver and can get out of sync with the sensor, e.g. after a power
+    /// glitch. Use [`Sen5x::sync_state`] to query the sensor.
+    pub fn is_measuring(&self) -> bool {
+        self.is_running
+    }
+
+    /// What the driver believes the sensor is doing.
+    ///
+    /// Tracked from the commands issued and responses read, like [`Sen5x::is_measuring`].
+    pub fn state(&self) -> DriverState {
+        if self.faulted {
+            DriverState::Error
+        } else if !self.is_running {
+            DriverState::Idle
+        } else if self.cleaning {
+            DriverState::Cleaning
+        } else if !self.has_sample {
+            DriverState::Starting
+        } else {
+            DriverState::Measuring
+        }
+    }
+
+    /// What the driver tracks about the sensor, e.g. to switch between sensors on a multiplexed
+    /// bus.
+    pub(crate) fn sensor_state(&self) -> SensorState {
+        SensorState {
+            is_running: self.is_running,
+            poll: self.poll,
+            has_sample: self.has_sample,
+            cleaning: self.cleaning,
+            faulted: self.faulted,
+            eeprom_writes: self.eeprom_writes,
+            firmware: self.firmware,
+        }
+    }
+
+    /// Replace what the driver tracks about the sensor, the inverse of [`Sen5x::sensor_state`].
+    pub(crate) fn set_sensor_state(&mut self, state: SensorState) {
+        let SensorState {
+            is_running,
+            poll,
+            has_sample,
+            cleaning,
+            faulted,
+            eeprom_writes,
+            firmware,
+        } = state;
+        self.is_running = is_running;
+        self.poll = poll;
+        self.has_sample = has_sample;
+        self.cleaning = cleaning;
+        self.faulted = faulted;
+        self.eeprom_writes = eeprom_writes;
+        self.firmware = firmware;
+    }
 
-        Ok(serial)
+    /// Infer whether periodic measurement is running from the sensor and update the driver state.
+    ///
+    /// In measurement mode the sensor sets the data-ready flag at least once per signal update
+    /// interval, while in idle mode the flag stays cleared, so the flag is polled for slightly
+    /// longer than one interval. This blocks for up to 1.1 s. Returns whether the sensor is
+    /// measuring, bus errors are returned as they are.
+    pub fn sync_state(&mut self) -> Result<bool, Error<E>> {
+        let mut waited = 0;
+        let measuring = loop {
+            if self.data_ready_status()? {
+                break true;
+            }
+            // Idle mode is only told apart by the flag not being set within the timeout.
+            if waited >= SYNC_STATE_TIMEOUT_MS {
+                break false;
+            }
+            self.delay.delay_ms(SYNC_STATE_POLL_MS);
+            waited += SYNC_STATE_POLL_MS;
+        };
+        self.is_running = measuring;
+        self.faulted = false;
+        Ok(measuring)
+    }
+
+    /// Reset the sensor using the I²C general call reset.
+    ///
+    /// Useful to recover the sensor when it does not respond at its own address. Note that every
+    /// device on the bus supporting the general call reset is reset as well.
+    pub fn general_call_reset(&mut self) -> Result<(), Error<E>> {
+        let result = self
+            .i2c
+            .write(GENERAL_CALL_ADDRESS, &[GENERAL_CALL_RESET])
+            .map_err(bus_error::<I2C>);
+        self.track(result)?;
+        self.is_running = false;
+        self.cleaning = false;
+        self.wait_for(Command::Reinit);
+        self.emit(LifecycleEvent::Reinitialized);
+        Ok(())
+    }
+
+    /// Get the serial number.
+    pub fn serial_number(&mut self) -> Result<SerialNumber, Error<E>> {
+        self.read_frame(Command::GetSerialNumber)
+            .map(parse::ascii_words)
+            .map(SerialNumber::from_bytes)
     }
 
     /// Get 48-bit serial number.
     pub fn product_name(&mut self) -> Result<[u8; 32], Error<E>> {
-        let mut buf = [0; 48];
-        self.delayed_read_cmd(Command::ReadProductName, &mut buf)?;
-
-        let mut bytes = [0u8; 32];
-        for i in 0..16 {
-            let hi = buf[i * 3 + 0];
-            let lo = buf[i * 3 + 1];
-            let crc = buf[i * 3 + 2];
-            if crc::crc(&[hi, lo]) != crc {
-                return Err(Error::Crc);
-            }
-            bytes[i * 2 + 0] = hi;
-            bytes[i * 2 + 1] = lo;
-        }
+        self.read_frame(Command::ReadProductName)
+            .map(parse::ascii_words)
+    }
 
-        Ok(bytes)
+    /// Identify the variant from the product name, `None` if it is not a SEN5x.
+    pub fn device_variant(&mut self) -> Result<Option<DeviceVariant>, Error<E>> {
+        self.product_name()
+            .map(|name| DeviceVariant::from_product_name(&name))
+    }
+
+    /// Read the product name, serial number and firmware version of the sensor.
+    pub fn identify(&mut self) -> Result<DeviceInfo, Error<E>> {
+        let product_name = self.product_name()?;
+        Ok(DeviceInfo {
+            product_name,
+            serial_number: self.serial_number()?,
+            firmware_version: self.read_firmware_version()?,
+            variant: DeviceVariant::from_product_name(&product_name),
+        })
+    }
+
+    /// Read what the sensor supports from its product name and firmware version.
+    ///
+    /// Fails with [`Error::WrongDevice`] if the product name is not a SEN5x.
+    pub fn capabilities(&mut self) -> Result<Capabilities, Error<E>> {
+        let variant = match self.device_variant()? {
+            Some(variant) if variant.is_sen5x() => variant,
+            found => return Err(Error::WrongDevice { found }),
+        };
+        Ok(Capabilities::new(variant, self.read_firmware_version()?))
     }
 
     /// Read firmware version.
+    ///
+    /// The version is cached, so commands which the firmware does not support fail with
+    /// [`Error::UnsupportedFirmware`] instead of a NACK of the sensor.
     pub fn read_firmware_version(&mut self) -> Result<u8, Error<E>> {
-        let mut buf = [0u8; 3];
-        self.delayed_read_cmd(Command::ReadFirmwareVersion, &mut buf)?;
-        let [fw, res, crc] = buf;
-        if crc::crc(&[fw, res]) != crc {
-            return Err(Error::Crc);
+        let version = self
+            .read_frame(Command::ReadFirmwareVersion)
+            .map(parse::firmware_version_words)?;
+        self.firmware = Some(version);
+        Ok(version)
+    }
+
+    /// The major firmware version read last, `None` if it was not read yet.
+    pub fn firmware_version(&self) -> Option<u8> {
+        self.firmware
+    }
+
+    /// Start the fan cleaning manually. Only possible while measuring.
+    ///
+    /// The cleaning takes about 10 s, measurements taken in the meantime are not valid.
+    pub fn start_fan_cleaning(&mut self) -> Result<(), Error<E>> {
+        self.write_command(Command::StartFanCleaning)?;
+        self.cleaning = true;
+        self.emit(LifecycleEvent::CleaningStarted);
+        Ok(())
+    }
+
+    /// Start the fan cleaning and wait until it completed.
+    ///
+    /// Returns `Error::Timeout` if the cleaning is still active after `timeout`.
+    pub fn start_fan_cleaning_blocking(
+        &mut self,
+        timeout: impl Into<Duration>,
+    ) -> Result<(), Error<E>> {
+        let timeout = timeout.into();
+        self.start_fan_cleaning()?;
+        let mut waited = Duration::ZERO;
+        while self.is_fan_cleaning()? {
+            if waited >= timeout {
+                return Err(Error::Timeout);
+            }
+            self.delay.delay_ms(FAN_CLEANING_POLL_MS);
+            waited = waited.saturating_add(Duration::from_millis(FAN_CLEANING_POLL_MS.into()));
+        }
+        Ok(())
+    }
+
+    /// Read the interval of the periodic fan cleaning.
+    pub fn auto_clean_interval(&mut self) -> Result<AutoCleanInterval, Error<E>> {
+        self.read_frame(Command::AutoCleaningInterval)
+            .map(AutoCleanInterval::from_words)
+    }
+
+    /// Set the interval of the periodic fan cleaning.
+    ///
+    /// Returns `Error::InvalidInput` for intervals shorter than 1 s or longer than `u32::MAX`
+    /// seconds.
+    ///
+    /// The interval is stored in the EEPROM of the sensor, prefer
+    /// [`Sen5x::update_auto_clean_interval`] to avoid needless writes.
+    pub fn set_auto_clean_interval(&mut self, interval: AutoCleanInterval) -> Result<(), Error<E>> {
+        let words = interval.to_words()?;
+        self.write_eeprom_words(Command::AutoCleaningInterval, &words)
+    }
+
+    /// Set the interval of the periodic fan cleaning unless the sensor already uses it. Returns
+    /// whether it was written.
+    pub fn update_auto_clean_interval(
+        &mut self,
+        interval: AutoCleanInterval,
+    ) -> Result<bool, Error<E>> {
+        let words = interval.to_words()?;
+        self.update_eeprom_words(Command::AutoCleaningInterval, words)
+    }
+
+    /// Number of writes to the EEPROM of the sensor performed by this instance.
+    ///
+    /// The EEPROM endures a limited number of writes, so firmware can log the count or alert when
+    /// it grows unexpectedly, e.g. because the configuration is written on every boot.
+    pub fn eeprom_writes(&self) -> u32 {
+        self.eeprom_writes
+    }
+
+    fn write_eeprom_words(&mut self, cmd: Command, words: &[u16]) -> Result<(), Error<E>> {
+        self.write_words(cmd, words)?;
+        self.eeprom_writes = self.eeprom_writes.saturating_add(1);
+        Ok(())
+    }
+
+    /// Write `words` to the EEPROM unless the sensor returns them already.
+    fn update_eeprom_words<const N: usize>(
+        &mut self,
+        cmd: Command,
+        words: [u16; N],
+    ) -> Result<bool, Error<E>> {
+        if self.read_frame::<N>(cmd)? == words {
+            return Ok(false);
         }
-        Ok(fw)
+        self.write_eeprom_words(cmd, &words)?;
+        Ok(true)
+    }
+
+    /// Read the tuning parameters of the VOC algorithm. Only available in idle mode.
+    pub fn voc_tuning_parameters(&mut self) -> Result<VocTuningParameters, Error<E>> {
+        self.read_frame(Command::VocTuningParameters)
+            .map(VocTuningParameters::from_words)
+    }
+
+    /// Set the tuning parameters of the VOC algorithm. Only available in idle mode.
+    ///
+    /// Returns `Error::InvalidInput` for parameters outside of the range given in the datasheet.
+    pub fn set_voc_tuning_parameters(
+        &mut self,
+        params: VocTuningParameters,
+    ) -> Result<(), Error<E>> {
+        let words = params.to_words()?;
+        self.write_words(Command::VocTuningParameters, &words)
+    }
+
+    /// Read the tuning parameters of the NOx algorithm. Only available in idle mode.
+    pub fn nox_tuning_parameters(&mut self) -> Result<NoxTuningParameters, Error<E>> {
+        self.read_frame(Command::NoxTuningParameters)
+            .map(NoxTuningParameters::from_words)
+    }
+
+    /// Set the tuning parameters of the NOx algorithm. Only available in idle mode.
+    ///
+    /// Returns `Error::InvalidInput` for parameters outside of the range given in the datasheet.
+    pub fn set_nox_tuning_parameters(
+        &mut self,
+        params: NoxTuningParameters,
+    ) -> Result<(), Error<E>> {
+        let words = params.to_words()?;
+        self.write_words(Command::NoxTuningParameters, &words)
+    }
+
+    /// Read the temperature compensation parameters. Only available in idle mode.
+    pub fn temperature_compensation(&mut self) -> Result<TemperatureCompensation, Error<E>> {
+        self.read_frame(Command::TemperatureCompensation)
+            .map(TemperatureCompensation::from_words)
+    }
+
+    /// Set the temperature compensation parameters. Only available in idle mode.
+    ///
+    /// Returns `Error::InvalidInput` if the offset or slope do not fit the resolution used by the
+    /// sensor.
+    pub fn set_temperature_compensation(
+        &mut self,
+        params: TemperatureCompensation,
+    ) -> Result<(), Error<E>> {
+        let words = params.to_words()?;
+        self.write_words(Command::TemperatureCompensation, &words)
+    }
+
+    /// Read the state of the VOC algorithm.
+    ///
+    /// The state can be stored and restored with [`Sen5x::set_voc_algorithm_state`] after a
+    /// restart to skip the initial learning phase.
+    pub fn voc_algorithm_state(&mut self) -> Result<VocAlgorithmState, Error<E>> {
+        self.read_frame(Command::VocAlgorithmState)
+            .map(VocAlgorithmState::from_words)
+    }
+
+    /// Restore the state of the VOC algorithm. Only available in idle mode.
+    pub fn set_voc_algorithm_state(&mut self, state: VocAlgorithmState) -> Result<(), Error<E>> {
+        self.write_words(Command::VocAlgorithmState, &state.to_words())
+    }
+
+    /// Read all configurable parameters. Only available in idle mode.
+    pub fn config(&mut self) -> Result<Config, Error<E>> {
+        Ok(Config {
+            auto_clean_interval: self.auto_clean_interval()?,
+            voc_tuning: self.voc_tuning_parameters()?,
+            nox_tuning: self.nox_tuning_parameters()?,
+            temperature_compensation: self.temperature_compensation()?,
+        })
+    }
+
+    /// Write all configurable parameters. Only available in idle mode.
+    ///
+    /// All parameters are validated before anything is written to the sensor. The fan cleaning
+    /// interval is stored in the EEPROM and only written if it differs from the current one.
+    pub fn set_config(&mut self, config: &Config) -> Result<(), Error<E>> {
+        let auto_clean_interval = config.auto_clean_interval.to_words()?;
+        let voc_tuning = config.voc_tuning.to_words()?;
+        let nox_tuning = config.nox_tuning.to_words()?;
+        let temperature_compensation = config.temperature_compensation.to_words()?;
+        self.update_eeprom_words(Command::AutoCleaningInterval, auto_clean_interval)?;
+        self.write_words(Command::VocTuningParameters, &voc_tuning)?;
+        self.write_words(Command::NoxTuningParameters, &nox_tuning)?;
+        self.write_words(Command::TemperatureCompensation, &temperature_compensation)
+    }
+
+    /// Read all configurable parameters and compare them with `expected`. Only available in idle
+    /// mode.
+    ///
+    /// A non-empty diff indicates a sensor which lost its settings or was swapped.
+    pub fn verify_config(&mut self, expected: &Config) -> Result<ConfigDiff, Error<E>> {
+        Ok(expected.diff(&self.config()?))
+    }
+
+    /// Whether the fan cleaning is currently active.
+    pub fn is_fan_cleaning(&mut self) -> Result<bool, Error<E>> {
+        Ok(self.device_status()?.fan_cleaning())
+    }
+
+    /// Read the device status register without clearing it.
+    pub fn device_status(&mut self) -> Result<DeviceStatus, Error<E>> {
+        self.read_frame(Command::ReadDeviceStatus)
+            .map(parse::device_status_words)
+            .inspect(|status| self.cleaning = status.fan_cleaning())
+    }
+
+    /// Read the device status register and clear it in the same transaction.
+    ///
+    /// Flags raised between reading and clearing cannot get lost, unlike with a separate read and
+    /// clear.
+    pub fn device_status_and_clear(&mut self) -> Result<DeviceStatus, Error<E>> {
+        self.read_frame(Command::ReadAndClearDeviceStatus)
+            .map(parse::device_status_words)
+            .inspect(|status| self.cleaning = status.fan_cleaning())
     }
 
     /// Read raw sensor data.
     pub fn measurement_raw(&mut self) -> Result<Sen5xDataRaw, Error<E>> {
-        let mut buf = [0; 24];
-        self.delayed_read_cmd(Command::ReadMeasurement, &mut buf)?;
-
-        let mut values = [0u16; 8];
-        for value_idx in 0..8 {
-            let hi = buf[value_idx * 3 + 0];
-            let lo = buf[value_idx * 3 + 1];
-            let crc = buf[value_idx * 3 + 2];
-            if crc::crc(&[hi, lo]) != crc {
-                return Err(Error::Crc);
-            }
-            values[value_idx] = u16::from_be_bytes([hi, lo]);
-        }
-
-        Ok(Sen5xDataRaw {
-            pm1_0: values[0],
-            pm2_5: values[1],
-            pm4_0: values[2],
-            pm10_0: values[3],
-            humidity: values[4],
-            temperature: values[5],
-            voc_index: values[6],
-            nox_index: values[7],
-        })
+        let mut frame = [0u8; parse::MEASUREMENT_LEN];
+        self.read_bytes(Command::ReadMeasurement, &mut frame)?;
+        let data = self.track(parse::measurement(&frame))?;
+        self.has_sample = true;
+        Ok(data)
     }
 
     /// Read converted sensor data.
     pub fn measurement(&mut self) -> Result<Sen5xData, Error<E>> {
-        let data = self.measurement_raw()?;
-        Ok(Sen5xData {
-            pm1_0: data.pm1_0 as f32 / 10f32,
-            pm2_5: data.pm2_5 as f32 / 10f32,
-            pm4_0: data.pm4_0 as f32 / 10f32,
-            pm10_0: data.pm10_0 as f32 / 10f32,
-            temperature: data.temperature as f32 / 200f32,
-            humidity: data.humidity as f32 / 100f32,
-            voc_index: data.voc_index as f32 / 10f32,
-            nox_index: data.nox_index as f32 / 10f32,
-        })
+        let result = self.measurement_raw().map(Sen5xData::from);
+        self.observe(&result, |observer, data| observer.on_measurement(data));
+        result
+    }
+
+    /// Select the channels read by [`Sen5x::measurement_channels`].
+    pub fn set_channel_mask(&mut self, mask: ChannelMask) {
+        self.channels = mask;
+    }
+
+    /// The channels read by [`Sen5x::measurement_channels`].
+    pub fn channel_mask(&self) -> ChannelMask {
+        self.channels
+    }
+
+    /// Read the channels selected by [`Sen5x::set_channel_mask`], the others are `None`.
+    ///
+    /// The response is read up to the last word of the mask and only the words of the mask are
+    /// validated and converted.
+    /// A mask without SEN5x channels reads nothing and returns an empty measurement.
+    pub fn measurement_channels(&mut self) -> Result<Measurement, Error<E>> {
+        let mask = self.channels;
+        let len = DeviceVariant::Sen55.layout().words_for(mask) * 3;
+        if len == 0 {
+            return Ok(Measurement::default());
+        }
+        let result = self.read_channels(mask, len);
+        if result.is_ok() {
+            self.has_sample = true;
+        }
+        self.observe(&result, |observer, measurement| {
+            observer.on_channels(measurement);
+        });
+        result
+    }
+
+    /// Read the first `len` bytes of the measurement and decode the channels of `mask`.
+    fn read_channels(&mut self, mask: ChannelMask, len: usize) -> Result<Measurement, Error<E>> {
+        let mut frame = [0u8; parse::MEASUREMENT_LEN];
+        let frame = frame.get_mut(..len).ok_or(Error::WrongBufferSize)?;
+        self.read_bytes(Command::ReadMeasurement, frame)?;
+        let result = Measurement::decode_masked(DeviceVariant::Sen55, frame, mask);
+        self.track(result)
+    }
+
+    /// Read only the mass concentrations, skipping the remaining values of the measurement.
+    ///
+    /// Reads the first 4 of the 8 words of `Read Measured Values`, half of the response, e.g. for
+    /// the SEN50 which does not measure the other values.
+    pub fn measurement_pm(&mut self) -> Result<ParticulateMatter, Error<E>> {
+        let result = self
+            .read_frame(Command::ReadMeasurement)
+            .map(ParticulateMatter::from_words);
+        if result.is_ok() {
+            self.has_sample = true;
+        }
+        self.observe(&result, |observer, &pm| {
+            observer.on_measurement(&Sen5xData {
+                pm,
+                env: None,
+                gas: None,
+            });
+        });
+        result
+    }
+
+    /// Wait until new measurement data is available and read it.
+    ///
+    /// Returns `Error::NotAllowed` if the measurement is not running and `Error::Timeout` if no
+    /// data got ready within the timeout set by [`Sen5x::set_timeout`].
+    pub fn measure_blocking(&mut self) -> Result<Sen5xData, Error<E>> {
+        let result = self.wait_data_ready();
+        self.observe(&result, |_, ()| {});
+        result?;
+        self.measurement()
+    }
+
+    /// Poll the data-ready flag until it is set, for [`Sen5x::measure_blocking`].
+    fn wait_data_ready(&mut self) -> Result<(), Error<E>> {
+        if !self.is_running {
+            return Err(Error::NotAllowed);
+        }
+        let poll_ms = DATA_READY_POLL_MS
+            .saturating_add(self.wait.delay_ms(Command::GetReadDataReadyStatus))
+            .saturating_add(self.guard_us / 1000);
+        let mut waited = Duration::ZERO;
+        while !self.data_ready_status()? {
+            if self.timeout.is_some_and(|timeout| waited >= timeout) {
+                return Err(Error::Timeout);
+            }
+            self.delay.delay_ms(DATA_READY_POLL_MS);
+            waited = waited.saturating_add(Duration::from_millis(poll_ms.into()));
+        }
+        Ok(())
+    }
+
+    /// Advance the measurement without waiting, returning the sample once a new one was read.
+    ///
+    /// Every call performs at most one I²C transaction and never delays, e.g. for superloops.
+    /// Consecutive calls have to be spaced by the execution time of the commands (20 ms); a
+    /// response the sensor is still preparing is not acknowledged and read again by the next call.
+    /// Returns `Error::NotAllowed` if the measurement is not running.
+    pub fn poll_measurement(&mut self) -> Result<Option<Sen5xData>, Error<E>> {
+        let result = self.poll_step();
+        self.observe(&result, |observer, data| {
+            if let Some(data) = data {
+                observer.on_measurement(data);
+            }
+        });
+        result
+    }
+
+    /// Perform the next step of [`Sen5x::poll_measurement`].
+    fn poll_step(&mut self) -> Result<Option<Sen5xData>, Error<E>> {
+        if !self.is_running {
+            return Err(Error::NotAllowed);
+        }
+        let state = self.poll;
+        // Start over after an error.
+        self.poll = PollState::RequestDataReady;
+        match state {
+            PollState::RequestDataReady => {
+                self.request(Command::GetReadDataReadyStatus)?;
+                self.poll = PollState::ReadDataReady;
+                Ok(None)
+            }
+            PollState::ReadDataReady => {
+                let mut frame = [0u8; parse::DATA_READY_LEN];
+                if !self.poll_response(state, &mut frame)? {
+                    return Ok(None);
+                }
+                if self.track(parse::data_ready(&frame))? {
+                    self.poll = PollState::RequestMeasurement;
+                }
+                Ok(None)
+            }
+            PollState::RequestMeasurement => {
+                self.request(Command::ReadMeasurement)?;
+                self.poll = PollState::ReadMeasurement;
+                Ok(None)
+            }
+            PollState::ReadMeasurement => {
+                let mut frame = [0u8; parse::MEASUREMENT_LEN];
+                if !self.poll_response(state, &mut frame)? {
+                    return Ok(None);
+                }
+                let data = Sen5xData::from(self.track(parse::measurement(&frame))?);
+                self.has_sample = true;
+                Ok(Some(data))
+            }
+        }
+    }
+
+    /// Write `cmd` without waiting for its execution.
+    fn request(&mut self, cmd: Command) -> Result<(), Error<E>> {
+        let result = protocol::write(&mut self.i2c, self.address, cmd.spec().code, &[]);
+        self.track(result)
+    }
+
+    /// Read the response of a command issued by [`Sen5x::poll_measurement`], `false` if the sensor
+    /// did not acknowledge because it is still executing the command.
+    fn poll_response(&mut self, state: PollState, frame: &mut [u8]) -> Result<bool, Error<E>> {
+        match self.i2c.read(self.address, frame).map_err(bus_error::<I2C>) {
+            Ok(()) => Ok(true),
+            Err(Error::Nack(_)) => {
+                self.poll = state;
+                Ok(false)
+            }
+            Err(err) => self.track(Err(err)),
+        }
+    }
+
+    /// Read the humidity and temperature before the compensation and the raw signals of the gas
+    /// sensors. Only possible while measuring and with firmware version 2 or newer.
+    pub fn raw_values(&mut self) -> Result<RawValues, Error<E>> {
+        self.read_frame(Command::ReadRawValues)
+            .map(RawValues::from_words)
     }
 
     /// Check whether new measurement data is available for read-out.
     pub fn data_ready_status(&mut self) -> Result<bool, Error<E>> {
-        let mut buf = [0; 3];
-        self.delayed_read_cmd(Command::GetReadDataReadyStatus, &mut buf)?;
-        let status = u16::from_be_bytes([buf[0], buf[1]]);
+        self.read_frame(Command::GetReadDataReadyStatus)
+            .map(parse::data_ready_words)
+    }
 
-        // 7FF is the last 11 bytes. If they are all zeroes, then data isn't ready.
-        let ready = (status & 0x7FF) != 0;
-        Ok(ready)
+    /// Fail if the cached firmware version is too old for `spec`.
+    fn check_firmware(&self, spec: &CommandSpec) -> Result<(), Error<E>> {
+        match self.firmware {
+            Some(found) if found < spec.min_firmware => Err(Error::UnsupportedFirmware {
+                required: spec.min_firmware,
+                found,
+            }),
+            _ => Ok(()),
+        }
     }
 
     /// Writes commands without additional arguments.
     fn write_command(&mut self, cmd: Command) -> Result<(), Error<E>> {
-        let (command, delay, _allowed_if_running) = cmd.as_tuple();
-        sen_i2c::write_command_u16(&mut self.i2c, self.address, command).map_err(Error::I2c)?;
-        self.delay.delay_ms(delay);
+        let spec = cmd.spec();
+        self.check_firmware(&spec)?;
+        if !spec.availability.allows(self.is_running) {
+            return Err(Error::NotAllowed);
+        }
+        let result = protocol::write(&mut self.i2c, self.address, spec.code, &[]);
+        self.track(result)?;
+        self.wait_for(cmd);
         Ok(())
     }
 
-    /// Command for reading values from the sensor.
-    fn delayed_read_cmd(&mut self, cmd: Command, data: &mut [u8]) -> Result<(), Error<E>> {
-        self.write_command(cmd)?;
-        let _ = sen_i2c::read_words_with_crc(&mut self.i2c, self.address, data).map_err(Error::I2c);
+    /// Writes a command followed by `words` arguments, each protected by a CRC.
+    fn write_words(&mut self, cmd: Command, words: &[u16]) -> Result<(), Error<E>> {
+        let spec = cmd.spec();
+        if words.len() != spec.argument_words {
+            return Err(Error::WrongBufferSize);
+        }
+        self.check_firmware(&spec)?;
+        if !spec.write_availability.allows(self.is_running) {
+            return Err(Error::NotAllowed);
+        }
+        let result = protocol::write(&mut self.i2c, self.address, spec.code, words);
+        self.track(result)?;
+        self.wait_for(cmd);
         Ok(())
     }
+
+    /// Record whether `result` failed on the bus or with a corrupted response.
+    fn track<T>(&mut self, result: Result<T, Error<E>>) -> Result<T, Error<E>> {
+        let faulted = self.faulted;
+        self.faulted = match &result {
+            Ok(_) => false,
+            Err(err) => err.i2c_error().is_some() || matches!(err, Error::Crc { .. }),
+        };
+        if faulted && result.is_ok() {
+            self.emit(LifecycleEvent::RecoveredAfterFault);
+        }
+        result
+    }
+
+    /// Forward the new sample of `result`, with `sample`, or its error to the measurement observer.
+    fn observe<T>(
+        &self,
+        result: &Result<T, Error<E>>,
+        sample: impl FnOnce(&dyn MeasurementObserver, &T),
+    ) {
+        observer::notify(self.measurement_observer, result, sample);
+    }
+
+    /// Report `event` to the observer.
+    fn emit(&self, event: LifecycleEvent) {
+        if let Some(observer) = self.observer {
+            observer.on_event(event);
+        }
+    }
+
+    /// Wait for `cmd` to finish executing according to the wait strategy.
+    fn wait_for(&mut self, cmd: Command) {
+        let delay = self.wait.delay_ms(cmd);
+        if delay > 0 {
+            self.delay.delay_ms(delay);
+        }
+    }
+
+    /// Wait for the guard delay before reading a response.
+    fn guard(&mut self) {
+        if self.guard_us > 0 {
+            self.delay.delay_us(self.guard_us);
+        }
+    }
+
+    /// Issue `cmd` and read its raw response into `buf`, validating the CRC of every word.
+    ///
+    /// `buf` has to hold a multiple of 3 bytes (two data bytes and a CRC byte per word) and must not
+    /// be longer than the response of the command, otherwise `Error::WrongBufferSize` is returned.
+    /// A shorter buffer reads only the first words of the response.
+    pub fn read_raw(&mut self, cmd: Command, buf: &mut [u8]) -> Result<(), Error<E>> {
+        let words = buf.len() / 3;
+        if !buf.len().is_multiple_of(3) || words == 0 || words > cmd.response_words() {
+            return Err(Error::WrongBufferSize);
+        }
+        self.read_bytes(cmd, buf)?;
+        for (index, chunk) in buf.chunks_exact(3).enumerate() {
+            let result = parse::checked_word::<E>(chunk, index);
+            self.track(result)?;
+        }
+        Ok(())
+    }
+
+    /// Issue `cmd` and read back `WORDS` words, validating the CRC of each of them.
+    fn read_frame<const WORDS: usize>(&mut self, cmd: Command) -> Result<[u16; WORDS], Error<E>> {
+        const { assert!(WORDS <= MAX_WORDS, "responses hold at most 16 words") };
+        if WORDS == 0 || WORDS > cmd.response_words() {
+            return Err(Error::WrongBufferSize);
+        }
+        let mut buf = [0u8; MAX_WORDS * 3];
+        let frame = buf.get_mut(..WORDS * 3).ok_or(Error::WrongBufferSize)?;
+        self.read_bytes(cmd, frame)?;
+        let result = parse::words(frame);
+        self.track(result)
+    }
+
+    /// Issue `cmd` and read its response into `buf` without validating it.
+    ///
+    /// Every SEN5x read has an execution time during which the sensor does not acknowledge, so the
+    /// command and the response are always separate transactions, unlike the reads without
+    /// execution time combined by [`protocol::Interface::execute`].
+    fn read_bytes(&mut self, cmd: Command, buf: &mut [u8]) -> Result<(), Error<E>> {
+        if self.no_delay && cmd.execution_time_ms() > 0 {
+            return Err(Error::Unsupported);
+        }
+        self.write_command(cmd)?;
+        self.guard();
+        let result = self.i2c.read(self.address, buf).map_err(bus_error::<I2C>);
+        self.track(result)
+    }
 }
 
 #[cfg(test)]
@@ -192,7 +1092,11 @@ mod tests {
             Transaction::write(_SEN5X_I2C_ADDRESS, cmd.to_be_bytes().to_vec()),
             Transaction::read(
                 _SEN5X_I2C_ADDRESS,
-                vec![0xbe, 0xef, 0x92, 0xbe, 0xef, 0x92, 0xbe, 0xef, 0x92],
+                [
+                    vec![0xbe, 0xef, 0x92, 0xbe, 0xef, 0x92, 0xbe, 0xef, 0x92],
+                    [0x00, 0x00, 0x81].repeat(13),
+                ]
+                .concat(),
             ),
         ];
         let mut mock = I2cMock::new(&expectations);
@@ -200,10 +1104,29 @@ mod tests {
         // Act
         let serial = sensor.serial_number().unwrap();
         // Assert
-        assert_eq!(serial, 0xbeefbeefbeef);
+        assert_eq!(serial.as_u64(), 0xbeefbeefbeef);
+        assert_eq!(serial.as_ascii(), None);
+        assert_eq!(std::format!("{serial:x}"), "beefbeefbeef");
         mock.done();
     }
 
+    /// Test the ASCII representation of the serial number
+    #[test]
+    fn test_serial_number_ascii() {
+        use crate::simulator::Simulator;
+
+        let sim = Simulator::new();
+        sim.set_serial_number("1A2B3C4D5E6F7A8B");
+        let mut sensor = Sen5x::new(sim.clone(), sim.delay());
+        let serial = sensor.serial_number().unwrap();
+        assert_eq!(serial.as_ascii(), Some("1A2B3C4D5E6F7A8B"));
+        assert_eq!(std::format!("{serial}"), "1A2B3C4D5E6F7A8B");
+        assert_eq!(
+            std::format!("{serial:x}"),
+            "31413242334334443545364637413842"
+        );
+    }
+
     /// Test the measurement function
     #[test]
     fn test_measurement() {
@@ -221,12 +1144,869 @@ mod tests {
         ];
         let mut mock = I2cMock::new(&expectations);
         let mut sensor = Sen5x::new(mock.clone(), DelayMock);
+        sensor.is_running = true;
         // Act
         let data = sensor.measurement().unwrap();
         // Assert
-        assert_eq!(data.pm2_5, 2.200_f32);
-        assert_eq!(data.temperature, 22.405_f32);
-        assert_eq!(data.humidity, 55.14_f32);
+        assert_eq!(data.pm.pm2_5, 2.200_f32);
+        assert_eq!(data.env.unwrap().temperature, 22.405_f32);
+        assert_eq!(data.env.unwrap().humidity, 55.14_f32);
         mock.done()
     }
+
+    /// Test the address and bus accessors
+    #[test]
+    fn test_accessors() {
+        let expectations = [Transaction::write(0x6A, vec![0x06])];
+        let mut mock = I2cMock::new(&expectations);
+        let mut sensor = Sen5x::with_i2c_address(mock.clone(), DelayMock, 0x6A);
+        assert_eq!(sensor.address(), 0x6A);
+        sensor.i2c_mut().write(0x6A, &[0x06]).unwrap();
+        sensor.delay_mut().delay_ms(1);
+        mock.done();
+    }
+
+    /// Test that the general call reset returns the sensor to idle mode
+    #[test]
+    fn test_general_call_reset() {
+        use crate::simulator::{Mode, Simulator};
+
+        let sim = Simulator::new();
+        let mut sensor = Sen5x::new(sim.clone(), sim.delay());
+        sensor.start_measurement().unwrap();
+        assert_eq!(sim.mode(), Mode::Measuring);
+        sensor.general_call_reset().unwrap();
+        assert_eq!(sim.mode(), Mode::Idle);
+    }
+
+    /// Test that the driver state follows the sensor after an external reset
+    #[test]
+    fn test_sync_state() {
+        use crate::simulator::{Mode, Simulator};
+
+        let sim = Simulator::new();
+        let mut sensor = Sen5x::new(sim.clone(), sim.delay());
+        assert!(!sensor.is_measuring());
+        sensor.start_measurement().unwrap();
+        assert!(sensor.is_measuring());
+        assert!(sensor.sync_state().unwrap());
+
+        // Reset behind the driver's back.
+        sensor.i2c_mut().write(0x00, &[0x06]).unwrap();
+        sim.advance_ms(100);
+        assert!(sensor.is_measuring());
+        assert!(!sensor.sync_state().unwrap());
+        assert!(!sensor.is_measuring());
+        assert_eq!(sim.mode(), Mode::Idle);
+
+        // A bus error is not mistaken for idle mode.
+        sim.nack_transaction(0);
+        assert!(matches!(sensor.sync_state(), Err(Error::Nack(_))));
+
+        sensor.start_measurement().unwrap();
+        sensor.stop_measurement().unwrap();
+        assert!(!sensor.is_measuring());
+    }
+
+    /// Test reading and clearing the device status
+    #[test]
+    fn test_device_status() {
+        use crate::simulator::Simulator;
+
+        let sim = Simulator::new();
+        let mut sensor = Sen5x::new(sim.clone(), sim.delay());
+        sim.raise_status(DeviceStatus::LASER_FAILURE);
+        let status = sensor.device_status().unwrap();
+        assert!(status.laser_failure());
+        assert!(status.has_error());
+        assert_eq!(sensor.device_status_and_clear().unwrap(), status);
+        assert_eq!(sensor.device_status().unwrap(), DeviceStatus::default());
+    }
+
+    /// Test waiting for the fan cleaning to complete
+    #[test]
+    fn test_fan_cleaning() {
+        use crate::simulator::Simulator;
+
+        let sim = Simulator::new();
+        let mut sensor = Sen5x::new(sim.clone(), sim.delay());
+        sensor.start_measurement().unwrap();
+        assert!(!sensor.is_fan_cleaning().unwrap());
+        assert_eq!(
+            sensor.start_fan_cleaning_blocking(Duration::from_secs(5)),
+            Err(Error::Timeout)
+        );
+        assert!(sensor.is_fan_cleaning().unwrap());
+
+        sim.advance_ms(10_000);
+        let start = sim.now_ms();
+        sensor
+            .start_fan_cleaning_blocking(Duration::from_secs(15))
+            .unwrap();
+        assert!(!sensor.is_fan_cleaning().unwrap());
+        assert!(sim.now_ms() - start >= 10_000);
+
+        sensor.start_fan_cleaning_blocking(Duration::MAX).unwrap();
+    }
+
+    /// Test reading and writing the auto cleaning interval
+    #[test]
+    fn test_auto_clean_interval() {
+        use crate::simulator::Simulator;
+
+        let sim = Simulator::new();
+        let mut sensor = Sen5x::new(sim.clone(), sim.delay());
+        assert_eq!(
+            sensor.auto_clean_interval().unwrap(),
+            AutoCleanInterval::Every(Duration::from_secs(604_800))
+        );
+        sensor
+            .set_auto_clean_interval(AutoCleanInterval::Every(Duration::from_secs(86_400)))
+            .unwrap();
+        assert_eq!(
+            sensor.auto_clean_interval().unwrap(),
+            AutoCleanInterval::Every(Duration::from_secs(86_400))
+        );
+        sensor
+            .set_auto_clean_interval(AutoCleanInterval::Disabled)
+            .unwrap();
+        assert_eq!(
+            sensor.auto_clean_interval().unwrap(),
+            AutoCleanInterval::Disabled
+        );
+        sensor
+            .set_auto_clean_interval(AutoCleanInterval::from_secs(u32::MAX))
+            .unwrap();
+        assert_eq!(
+            sensor.auto_clean_interval().unwrap().as_secs(),
+            Some(u32::MAX)
+        );
+        assert_eq!(
+            sensor.set_auto_clean_interval(AutoCleanInterval::Every(Duration::MAX)),
+            Err(Error::InvalidInput {
+                field: "auto_clean_interval",
+                value: i64::MAX
+            })
+        );
+        assert_eq!(
+            sensor.set_auto_clean_interval(AutoCleanInterval::Every(Duration::from_millis(500))),
+            Err(Error::InvalidInput {
+                field: "auto_clean_interval",
+                value: 0
+            })
+        );
+    }
+
+    /// Test that unchanged EEPROM parameters are not written again
+    #[test]
+    fn test_eeprom_writes() {
+        use crate::simulator::Simulator;
+
+        let sim = Simulator::new();
+        let mut sensor = Sen5x::new(sim.clone(), sim.delay());
+        let daily = AutoCleanInterval::Every(Duration::from_secs(86_400));
+        assert!(sensor.update_auto_clean_interval(daily).unwrap());
+        assert!(!sensor.update_auto_clean_interval(daily).unwrap());
+        assert_eq!(sensor.eeprom_writes(), 1);
+
+        sensor.set_auto_clean_interval(daily).unwrap();
+        assert_eq!(sensor.eeprom_writes(), 2);
+        let config = Config {
+            auto_clean_interval: daily,
+            ..Config::default()
+        };
+        sensor.set_config(&config).unwrap();
+        assert_eq!(sensor.eeprom_writes(), 2);
+        sensor.set_config(&Config::default()).unwrap();
+        assert_eq!(sensor.eeprom_writes(), 3);
+        assert_eq!(sensor.config().unwrap(), Config::default());
+    }
+
+    /// Test that fugit durations are accepted for intervals and timeouts
+    #[cfg(feature = "fugit")]
+    #[test]
+    fn test_fugit_durations() {
+        use crate::commands::WaitStrategy;
+        use crate::simulator::Simulator;
+        use fugit::{HoursDurationU32, MicrosDurationU32, MinutesDurationU32, SecsDurationU32};
+
+        assert_eq!(
+            AutoCleanInterval::from(HoursDurationU32::from_ticks(2)),
+            AutoCleanInterval::Every(Duration::from_secs(7200))
+        );
+        assert_eq!(
+            WaitStrategy::fixed(MicrosDurationU32::from_ticks(1500))
+                .delay_ms(Command::ReadMeasurement),
+            2
+        );
+
+        let sim = Simulator::new();
+        let mut sensor = Sen5x::new(sim.clone(), sim.delay());
+        sensor.start_measurement().unwrap();
+        sensor
+            .start_fan_cleaning_blocking(SecsDurationU32::from_ticks(15))
+            .unwrap();
+        sensor
+            .set_auto_clean_interval(AutoCleanInterval::every(MinutesDurationU32::from_ticks(1)))
+            .unwrap();
+        assert_eq!(
+            sensor.auto_clean_interval().unwrap(),
+            AutoCleanInterval::Every(Duration::from_secs(60))
+        );
+    }
+
+    /// Test reading and writing the algorithm tuning and temperature compensation parameters
+    #[test]
+    fn test_tuning_parameters() {
+        use crate::simulator::Simulator;
+
+        let sim = Simulator::new();
+        let mut sensor = Sen5x::new(sim.clone(), sim.delay());
+        let voc = VocTuningParameters {
+            index_offset: 100,
+            learning_time_offset_hours: 24,
+            learning_time_gain_hours: 24,
+            gating_max_duration_minutes: 0,
+            std_initial: 50,
+            gain_factor: 230,
+        };
+        sensor.set_voc_tuning_parameters(voc).unwrap();
+        assert_eq!(sensor.voc_tuning_parameters().unwrap(), voc);
+        assert_eq!(
+            sensor.set_voc_tuning_parameters(VocTuningParameters {
+                index_offset: 251,
+                ..voc
+            }),
+            Err(Error::InvalidInput {
+                field: "index_offset",
+                value: 251
+            })
+        );
+
+        let nox = sensor.nox_tuning_parameters().unwrap();
+        assert_eq!(nox.index_offset, 1);
+        assert_eq!(
+            sensor.set_nox_tuning_parameters(NoxTuningParameters {
+                std_initial: 10,
+                ..nox
+            }),
+            Err(Error::InvalidInput {
+                field: "std_initial",
+                value: 10
+            })
+        );
+
+        let compensation = TemperatureCompensation {
+            offset: -1.5,
+            slope: 0.01,
+            time_constant: 600,
+        };
+        sensor.set_temperature_compensation(compensation).unwrap();
+        assert_eq!(sensor.temperature_compensation().unwrap(), compensation);
+        assert_eq!(
+            sensor.set_temperature_compensation(TemperatureCompensation {
+                offset: 200.0,
+                ..compensation
+            }),
+            Err(Error::InvalidInput {
+                field: "offset",
+                value: 40000
+            })
+        );
+        for offset in [f32::NAN, f32::INFINITY, f32::NEG_INFINITY] {
+            assert!(matches!(
+                sensor.set_temperature_compensation(TemperatureCompensation {
+                    offset,
+                    ..compensation
+                }),
+                Err(Error::InvalidInput {
+                    field: "offset",
+                    ..
+                })
+            ));
+        }
+    }
+
+    /// Test that the datasheet defaults match the parameters of a freshly reset sensor
+    #[test]
+    fn test_datasheet_defaults() {
+        use crate::simulator::Simulator;
+
+        let sim = Simulator::new();
+        let mut sensor = Sen5x::new(sim.clone(), sim.delay());
+        assert_eq!(
+            sensor.auto_clean_interval().unwrap(),
+            AutoCleanInterval::default()
+        );
+        assert_eq!(
+            sensor.voc_tuning_parameters().unwrap(),
+            VocTuningParameters::default()
+        );
+        assert_eq!(
+            sensor.nox_tuning_parameters().unwrap(),
+            NoxTuningParameters::default()
+        );
+        assert_eq!(
+            sensor.temperature_compensation().unwrap(),
+            TemperatureCompensation::default()
+        );
+
+        sensor
+            .set_voc_tuning_parameters(VocTuningParameters {
+                gain_factor: 100,
+                ..VocTuningParameters::DATASHEET_DEFAULT
+            })
+            .unwrap();
+        sensor
+            .set_voc_tuning_parameters(VocTuningParameters::DATASHEET_DEFAULT)
+            .unwrap();
+        assert_eq!(
+            sensor.voc_tuning_parameters().unwrap(),
+            VocTuningParameters::DATASHEET_DEFAULT
+        );
+    }
+
+    /// Test that the configuration and VOC algorithm state can be restored
+    #[test]
+    fn test_restore_config_and_state() {
+        use crate::simulator::Simulator;
+
+        let sim = Simulator::new();
+        let mut sensor = Sen5x::new(sim.clone(), sim.delay());
+        let config = Config {
+            auto_clean_interval: AutoCleanInterval::Disabled,
+            ..Config::default()
+        };
+        sensor.set_config(&config).unwrap();
+        assert_eq!(sensor.config().unwrap(), config);
+
+        let state = VocAlgorithmState::from_bytes([1, 2, 3, 4, 5, 6, 7, 8]);
+        sensor.set_voc_algorithm_state(state).unwrap();
+        sensor.start_measurement().unwrap();
+        assert_eq!(sensor.voc_algorithm_state().unwrap(), state);
+    }
+
+    /// Test that parameters differing from the expected configuration are reported
+    #[test]
+    fn test_verify_config() {
+        use crate::simulator::Simulator;
+        use crate::types::ConfigParameter;
+
+        let sim = Simulator::new();
+        let mut sensor = Sen5x::new(sim.clone(), sim.delay());
+        let mut expected = Config {
+            temperature_compensation: TemperatureCompensation {
+                offset: 1.2345,
+                ..TemperatureCompensation::DATASHEET_DEFAULT
+            },
+            ..Config::default()
+        };
+        sensor.set_config(&expected).unwrap();
+        // The offset read back is rounded to 0.005 °C.
+        assert!(sensor.verify_config(&expected).unwrap().is_empty());
+
+        expected.auto_clean_interval = AutoCleanInterval::Disabled;
+        expected.nox_tuning.index_offset = 2;
+        let diff = sensor.verify_config(&expected).unwrap();
+        assert!(!diff.is_empty());
+        assert!(diff.differs(ConfigParameter::NoxTuning));
+        assert_eq!(
+            diff.iter().collect::<Vec<_>>(),
+            [
+                ConfigParameter::AutoCleanInterval,
+                ConfigParameter::NoxTuning
+            ]
+        );
+        assert_eq!(diff.actual, sensor.config().unwrap());
+    }
+
+    /// Test that the state captured on shutdown is restored on resume
+    #[test]
+    fn test_shutdown_and_resume() {
+        use crate::simulator::Simulator;
+
+        let sim = Simulator::new();
+        let mut sensor = Sen5x::new(sim.clone(), sim.delay());
+        let config = Config {
+            auto_clean_interval: AutoCleanInterval::Disabled,
+            ..Config::default()
+        };
+        let voc_state = VocAlgorithmState::from_bytes([8, 7, 6, 5, 4, 3, 2, 1]);
+        sensor.set_config(&config).unwrap();
+        sensor.set_voc_algorithm_state(voc_state).unwrap();
+        sensor.start_measurement().unwrap();
+        sim.raise_status(DeviceStatus::FAN_SPEED_WARNING);
+
+        let state = sensor.shutdown().unwrap();
+        assert!(!sensor.is_measuring());
+        assert_eq!(state.voc_state, voc_state);
+        assert_eq!(state.config, config);
+        assert!(state.status.fan_speed_warning());
+        assert!(!sensor.device_status().unwrap().fan_speed_warning());
+
+        sensor.general_call_reset().unwrap();
+        sensor.resume(&state).unwrap();
+        assert!(sensor.is_measuring());
+        assert_eq!(sensor.voc_algorithm_state().unwrap(), voc_state);
+        sensor.stop_measurement().unwrap();
+        assert_eq!(sensor.config().unwrap(), config);
+    }
+
+    /// Test that idle-only commands are rejected while measuring
+    #[test]
+    fn test_not_allowed_while_measuring() {
+        use crate::simulator::Simulator;
+
+        let sim = Simulator::new();
+        let mut sensor = Sen5x::new(sim.clone(), sim.delay());
+        sensor.start_measurement().unwrap();
+        let transactions = sim.transactions();
+        assert_eq!(sensor.start_measurement(), Err(Error::NotAllowed));
+        assert_eq!(sensor.voc_tuning_parameters(), Err(Error::NotAllowed));
+        assert_eq!(
+            sensor.set_voc_algorithm_state(VocAlgorithmState::default()),
+            Err(Error::NotAllowed)
+        );
+        assert_eq!(sim.transactions(), transactions);
+
+        sensor.voc_algorithm_state().unwrap();
+        sensor.serial_number().unwrap();
+        sensor
+            .set_auto_clean_interval(AutoCleanInterval::Disabled)
+            .unwrap();
+    }
+
+    /// Test that the commands of measurement mode are not sent while idle
+    #[test]
+    fn test_not_allowed_while_idle() {
+        use crate::simulator::Simulator;
+
+        let sim = Simulator::new();
+        let mut sensor = Sen5x::new(sim.clone(), sim.delay());
+        assert_eq!(sensor.measurement(), Err(Error::NotAllowed));
+        assert_eq!(sensor.measurement_raw(), Err(Error::NotAllowed));
+        assert_eq!(sensor.start_fan_cleaning(), Err(Error::NotAllowed));
+        assert_eq!(sensor.stop_measurement(), Err(Error::NotAllowed));
+        assert_eq!(sim.transactions(), 0);
+
+        sensor.start_measurement().unwrap();
+        sim.advance_ms(1000);
+        sensor.measurement().unwrap();
+        sensor.stop_measurement().unwrap();
+    }
+
+    /// Test that negative temperatures are decoded
+    #[test]
+    fn test_negative_temperature() {
+        use crate::simulator::Simulator;
+
+        let sim = Simulator::new();
+        let mut sensor = Sen5x::new(sim.clone(), sim.delay());
+        let mut raw = crate::test_vectors::MEASUREMENTS[0].raw;
+        raw.temperature = -2000;
+        sim.set_measurement(raw);
+        sensor.start_measurement().unwrap();
+        sim.advance_ms(1000);
+        assert_eq!(
+            sensor.measurement().unwrap().env.unwrap().temperature,
+            -10.0
+        );
+        assert_eq!(sensor.measurement_raw().unwrap().temperature, -2000);
+    }
+
+    /// Test that bus errors while reading a response are reported
+    #[test]
+    fn test_read_error() {
+        use crate::simulator::Simulator;
+
+        let sim = Simulator::new();
+        let mut sensor = Sen5x::new(sim.clone(), sim.delay());
+        sim.nack_transaction(1);
+        assert!(matches!(sensor.product_name(), Err(Error::Nack(_))));
+        assert_eq!(&sensor.product_name().unwrap()[..5], b"SEN55");
+    }
+
+    /// Test that bus errors are classified by their kind
+    #[test]
+    fn test_bus_error_kinds() {
+        use embedded_hal::i2c::{ErrorKind, NoAcknowledgeSource};
+
+        let start = [0x00, 0x21].to_vec();
+        let expectations = [
+            Transaction::write(0x69, start.clone())
+                .with_error(ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address)),
+            Transaction::write(0x69, start.clone()).with_error(ErrorKind::ArbitrationLoss),
+            Transaction::write(0x69, start.clone()).with_error(ErrorKind::Bus),
+            Transaction::write(0x69, start).with_error(ErrorKind::Overrun),
+        ];
+        let mut mock = I2cMock::new(&expectations);
+        let mut sensor = Sen5x::new(mock.clone(), DelayMock);
+        let nack = sensor.start_measurement().unwrap_err();
+        assert!(matches!(nack, Error::Nack(_)));
+        assert!(nack.i2c_error().is_some());
+        assert!(matches!(
+            sensor.start_measurement(),
+            Err(Error::ArbitrationLoss(ErrorKind::ArbitrationLoss))
+        ));
+        assert!(matches!(sensor.start_measurement(), Err(Error::Bus(_))));
+        assert!(matches!(
+            sensor.start_measurement(),
+            Err(Error::I2c(ErrorKind::Overrun))
+        ));
+        assert_eq!(Error::<ErrorKind>::Crc { word: 0 }.i2c_error(), None);
+        mock.done();
+    }
+
+    /// Test the buffer size validation of raw reads
+    #[test]
+    fn test_read_raw() {
+        use crate::simulator::Simulator;
+
+        let sim = Simulator::new();
+        let mut sensor = Sen5x::new(sim.clone(), sim.delay());
+        let mut buf = [0u8; 6];
+        assert_eq!(
+            sensor.read_raw(Command::ReadFirmwareVersion, &mut buf),
+            Err(Error::WrongBufferSize)
+        );
+        assert_eq!(
+            sensor.read_raw(Command::ReadFirmwareVersion, &mut buf[..2]),
+            Err(Error::WrongBufferSize)
+        );
+        sensor.read_raw(Command::ReadProductName, &mut buf).unwrap();
+        assert_eq!([buf[0], buf[1], buf[3], buf[4]], *b"SEN5");
+    }
+
+    /// Test that the wait strategy controls the delay after a command
+    #[test]
+    fn test_wait_strategy() {
+        use crate::simulator::Simulator;
+
+        let sim = Simulator::new();
+        let mut sensor = Sen5x::new(sim.clone(), sim.delay());
+        sensor.start_measurement().unwrap();
+        assert_eq!(sim.now_ms(), 50);
+
+        sensor.set_wait_strategy(WaitStrategy::Skip);
+        sensor.reinit().unwrap();
+        assert_eq!(sim.now_ms(), 50);
+
+        sim.advance_ms(100);
+        sensor.set_wait_strategy(WaitStrategy::Custom(|cmd| cmd.execution_time_ms() * 2));
+        sensor.start_measurement().unwrap();
+        assert_eq!(sim.now_ms(), 250);
+    }
+
+    /// Test that reads wait for the execution time between the command and the response
+    #[test]
+    fn test_read_execution_time() {
+        use crate::simulator::Simulator;
+
+        let sim = Simulator::new();
+        let mut sensor = Sen5x::new(sim.clone(), sim.delay());
+        let (transactions, now) = (sim.transactions(), sim.now_ms());
+        assert!(!sensor.data_ready_status().unwrap());
+        assert_eq!(sim.transactions(), transactions + 2);
+        assert_eq!(sim.now_ms(), now + 20);
+    }
+
+    /// Test that only the words up to the last channel of the mask are read
+    #[test]
+    fn test_measurement_channels() {
+        use crate::measurement::Channel;
+        use crate::test_vectors::MEASUREMENTS;
+
+        let vector = &MEASUREMENTS[0];
+        let expectations = [
+            Transaction::write(0x69, vec![0x03, 0xC4]),
+            Transaction::read(0x69, vector.frame[..6].to_vec()),
+        ];
+        let mut mock = I2cMock::new(&expectations);
+        let mut sensor = Sen5x::new(mock.clone(), DelayMock);
+        assert_eq!(sensor.channel_mask(), ChannelMask::ALL);
+        sensor.set_channel_mask(ChannelMask::of(&[Channel::Pm2_5]));
+        sensor.is_running = true;
+        let measurement = sensor.measurement_channels().unwrap();
+        assert_eq!(measurement.pm2_5, Some(vector.data.pm.pm2_5));
+        assert_eq!(measurement.pm1_0, None);
+        mock.done();
+    }
+
+    /// Test that polling advances by one transaction per call
+    #[test]
+    fn test_poll_measurement() {
+        use crate::test_vectors::{DATA_NOT_READY, DATA_READY, MEASUREMENTS};
+        use embedded_hal::i2c::{ErrorKind, NoAcknowledgeSource};
+
+        let nack = ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address);
+        let expectations = [
+            Transaction::write(0x69, vec![0x02, 0x02]),
+            Transaction::read(0x69, DATA_NOT_READY.to_vec()),
+            Transaction::write(0x69, vec![0x02, 0x02]),
+            Transaction::read(0x69, vec![0; 3]).with_error(nack),
+            Transaction::read(0x69, DATA_READY.to_vec()),
+            Transaction::write(0x69, vec![0x03, 0xC4]),
+            Transaction::read(0x69, MEASUREMENTS[0].frame.to_vec()),
+        ];
+        let mut mock = I2cMock::new(&expectations);
+        let mut sensor = Sen5x::new(mock.clone(), DelayMock);
+        assert_eq!(sensor.poll_measurement(), Err(Error::NotAllowed));
+        sensor.is_running = true;
+        for _ in 0..6 {
+            assert_eq!(sensor.poll_measurement(), Ok(None));
+        }
+        assert_eq!(sensor.poll_measurement(), Ok(Some(MEASUREMENTS[0].data)));
+        mock.done();
+    }
+
+    /// Test that a driver without delay is named without a delay type and polls measurements
+    #[test]
+    fn test_without_delay() {
+        use crate::simulator::Simulator;
+
+        let sim = Simulator::new();
+        let mut sensor: Sen5x<Simulator> = Sen5x::without_delay(sim.clone());
+        sim.advance_ms(100);
+        sensor.start_measurement().unwrap();
+        sim.advance_ms(u64::from(Command::StartMeasurement.execution_time_ms()));
+        let sample = (0..200).find_map(|_| {
+            sim.advance_ms(20);
+            sensor.poll_measurement().unwrap()
+        });
+        assert!(sample.is_some());
+        assert_eq!(core::mem::size_of::<NoDelay>(), 0);
+
+        // Reads waiting for the execution time are rejected without accessing the bus.
+        let transactions = sim.transactions();
+        assert_eq!(sensor.serial_number(), Err(Error::Unsupported));
+        assert_eq!(sensor.product_name(), Err(Error::Unsupported));
+        assert_eq!(sensor.data_ready_status(), Err(Error::Unsupported));
+        assert_eq!(sensor.measurement(), Err(Error::Unsupported));
+        assert_eq!(sensor.measure_blocking(), Err(Error::Unsupported));
+        assert_eq!(sim.transactions(), transactions);
+        assert!(sensor.is_measuring());
+        sensor.stop_measurement().unwrap();
+    }
+
+    /// Test that the driver state follows the commands and responses
+    #[test]
+    fn test_state() {
+        use crate::simulator::Simulator;
+
+        let sim = Simulator::new();
+        let mut sensor = Sen5x::new(sim.clone(), sim.delay());
+        assert_eq!(sensor.state(), DriverState::Idle);
+        sensor.start_measurement().unwrap();
+        assert_eq!(sensor.state(), DriverState::Starting);
+        sim.advance_ms(1000);
+        sensor.measurement().unwrap();
+        assert_eq!(sensor.state(), DriverState::Measuring);
+        sensor.start_fan_cleaning().unwrap();
+        assert_eq!(sensor.state(), DriverState::Cleaning);
+        sim.corrupt_next_crc(0);
+        assert!(sensor.device_status().is_err());
+        assert_eq!(sensor.state(), DriverState::Error);
+        sim.advance_ms(10_000);
+        assert!(!sensor.is_fan_cleaning().unwrap());
+        assert_eq!(sensor.state(), DriverState::Measuring);
+        sensor.stop_measurement().unwrap();
+        assert_eq!(sensor.state(), DriverState::Idle);
+    }
+
+    /// Test reading only the PM values of a measurement
+    #[test]
+    fn test_measurement_pm() {
+        use crate::simulator::Simulator;
+        use crate::test_vectors::MEASUREMENTS;
+
+        let sim = Simulator::new();
+        let mut sensor = Sen5x::new(sim.clone(), sim.delay());
+        sensor.start_measurement().unwrap();
+        sim.advance_ms(1000);
+        let pm = sensor.measurement_pm().unwrap();
+        assert_eq!(pm.pm2_5, MEASUREMENTS[0].data.pm.pm2_5);
+        assert_eq!(pm.pm10_0, MEASUREMENTS[0].data.pm.pm10_0);
+        assert!(!sensor.data_ready_status().unwrap());
+    }
+
+    /// Test waiting for a measurement with a timeout
+    #[test]
+    fn test_measure_blocking() {
+        use crate::simulator::Simulator;
+        use crate::test_vectors::MEASUREMENTS;
+
+        let sim = Simulator::new();
+        let mut sensor = Sen5x::new(sim.clone(), sim.delay());
+        assert_eq!(sensor.measure_blocking(), Err(Error::NotAllowed));
+        assert_eq!(sensor.timeout(), Some(Duration::from_secs(2)));
+
+        sensor.start_measurement().unwrap();
+        let start = sim.now_ms();
+        assert_eq!(sensor.measure_blocking().unwrap(), MEASUREMENTS[0].data);
+        assert!((1000..1200).contains(&(sim.now_ms() - start)));
+
+        sensor.set_timeout(Some(Duration::from_millis(300)));
+        let start = sim.now_ms();
+        assert_eq!(sensor.measure_blocking(), Err(Error::Timeout));
+        assert!((300..500).contains(&(sim.now_ms() - start)));
+
+        sensor.set_timeout(None);
+        sensor.measure_blocking().unwrap();
+    }
+
+    /// Test that the guard delay separates the write and the read phase
+    #[test]
+    fn test_guard_delay() {
+        use crate::simulator::Simulator;
+
+        let sim = Simulator::new();
+        let mut sensor = Sen5x::new(sim.clone(), sim.delay());
+        assert_eq!(sensor.guard_delay(), Duration::ZERO);
+        sensor.read_firmware_version().unwrap();
+        let execution_ms = sim.now_ms();
+
+        sensor.set_guard_delay(Duration::from_nanos(2_999_001));
+        assert_eq!(sensor.guard_delay(), Duration::from_micros(3000));
+        sensor.read_firmware_version().unwrap();
+        assert_eq!(sim.now_ms(), 2 * execution_ms + 3);
+        // Commands without response are not delayed.
+        let start = sim.now_ms();
+        sensor.start_measurement().unwrap();
+        assert_eq!(sim.now_ms() - start, 50);
+
+        sensor.set_guard_delay(Duration::MAX);
+        assert_eq!(sensor.guard_delay(), Duration::from_micros(u32::MAX.into()));
+    }
+
+    /// Test that the constructors verifying the variant reject other devices
+    #[test]
+    fn test_try_new() {
+        use crate::simulator::Simulator;
+
+        let sim = Simulator::new();
+        let sensor = Sen5x::try_new(sim.clone(), sim.delay(), DeviceVariant::Sen55);
+        assert!(sensor.is_ok());
+        let sensor = Sen5x::try_new(sim.clone(), sim.delay(), DeviceVariant::Sen54);
+        assert_eq!(
+            sensor.err(),
+            Some(Error::WrongDevice {
+                found: Some(DeviceVariant::Sen55)
+            })
+        );
+
+        sim.set_product_name("SEN66");
+        let sensor = Sen5x::try_new(sim.clone(), sim.delay(), DeviceVariant::Sen66);
+        assert_eq!(
+            sensor.err(),
+            Some(Error::WrongDevice {
+                found: Some(DeviceVariant::Sen66)
+            })
+        );
+
+        sim.set_product_name("SVM41");
+        let sensor =
+            Sen5x::try_with_i2c_address(sim.clone(), sim.delay(), 0x69, DeviceVariant::Sen55);
+        assert_eq!(sensor.err(), Some(Error::WrongDevice { found: None }));
+    }
+
+    /// Test reading the identification in one call
+    #[test]
+    fn test_identify() {
+        use crate::simulator::Simulator;
+
+        let sim = Simulator::new();
+        let mut sensor = Sen5x::new(sim.clone(), sim.delay());
+        let info = sensor.identify().unwrap();
+        assert_eq!(info.product_name_str(), Some("SEN55"));
+        assert_eq!(info.variant, Some(DeviceVariant::Sen55));
+        assert_eq!(info.serial_number, sensor.serial_number().unwrap());
+        assert_eq!(
+            info.firmware_version,
+            sensor.read_firmware_version().unwrap()
+        );
+    }
+
+    /// Test that commands too new for the cached firmware version are rejected
+    #[test]
+    fn test_firmware_gating() {
+        use crate::simulator::Simulator;
+
+        let sim = Simulator::new();
+        let mut sensor = Sen5x::new(sim.clone(), sim.delay());
+        sensor.start_measurement().unwrap();
+        let raw = sensor.raw_values().unwrap();
+        assert_eq!(
+            raw.temperature,
+            sensor.measurement().unwrap().env.map(|env| env.temperature)
+        );
+        assert!(raw.voc_ticks.is_some() && raw.nox_ticks.is_some());
+
+        // An old sensor NACKs the command until its version is known.
+        sim.set_firmware_version(1);
+        assert!(matches!(sensor.raw_values(), Err(Error::Nack(_))));
+        assert_eq!(sensor.firmware_version(), None);
+        assert_eq!(sensor.read_firmware_version().unwrap(), 1);
+        assert_eq!(sensor.firmware_version(), Some(1));
+        let transactions = sim.transactions();
+        assert_eq!(
+            sensor.raw_values(),
+            Err(Error::UnsupportedFirmware {
+                required: 2,
+                found: 1
+            })
+        );
+        assert_eq!(sim.transactions(), transactions);
+        assert!(!Capabilities::new(DeviceVariant::Sen55, 1).supports(Command::ReadRawValues));
+        assert!(Capabilities::new(DeviceVariant::Sen55, 2).supports(Command::ReadRawValues));
+
+        assert_eq!(
+            RawValues::from_words([0x7FFF, 0x7FFF, 0xFFFF, 0xFFFF]),
+            RawValues {
+                humidity: None,
+                temperature: None,
+                voc_ticks: None,
+                nox_ticks: None
+            }
+        );
+    }
+
+    /// Test that the capabilities follow the variant of the connected sensor
+    #[test]
+    fn test_capabilities() {
+        use crate::measurement::Channel;
+        use crate::simulator::Simulator;
+
+        let sim = Simulator::new();
+        let mut sensor = Sen5x::new(sim.clone(), sim.delay());
+        sim.set_firmware_version(2);
+        let sen55 = sensor.capabilities().unwrap();
+        assert_eq!(sen55, Capabilities::new(DeviceVariant::Sen55, 2));
+        assert!(sen55.nox_tuning() && sen55.fan_cleaning() && !sen55.co2());
+        assert_eq!(sen55.channels().len(), 8);
+
+        sim.set_product_name("SEN54");
+        let sen54 = sensor.capabilities().unwrap();
+        assert!(sen54.voc_tuning() && !sen54.nox_tuning());
+        assert!(!sen54.measures(Channel::NoxIndex));
+
+        sim.set_product_name("SEN50");
+        let sen50 = sensor.capabilities().unwrap();
+        assert!(!sen50.temperature_compensation() && !sen50.voc_tuning());
+        assert_eq!(sen50.channels().len(), 4);
+
+        sim.set_product_name("SEN66");
+        assert_eq!(
+            sensor.capabilities(),
+            Err(Error::WrongDevice {
+                found: Some(DeviceVariant::Sen66)
+            })
+        );
+        assert!(Capabilities::new(DeviceVariant::Sen66, 2).co2());
+
+        sim.set_product_name("SPS30");
+        assert_eq!(
+            sensor.capabilities(),
+            Err(Error::WrongDevice { found: None })
+        );
+    }
 }
@@ -0,0 +1,943 @@
+//! Driver for the SEN6x series.
+//!
+//! The SEN6x share the I²C protocol, the CRC and many commands with the SEN5x, but use another
+//! address, lay out the measurement differently and add channels such as CO₂. [`Sen6x`] is
+//! generic over a [`Variant`] describing these differences, e.g. [`Sen66`]. Commands which only
+//! some variants support are only implemented for them, e.g. the CO₂ commands for variants
+//! implementing [`Co2Variant`]. The SEN60 uses other command codes and supports fewer commands,
+//! the missing ones fail with `Error::Unsupported`.
+//!
+//! [Datasheet](https://sensirion.com/media/documents/FAFC548D/6731FFFA/Sensirion_Datasheet_SEN6x.pdf)
+use core::marker::PhantomData;
+
+use embedded_hal::{delay::DelayNs, i2c::I2c};
+
+use crate::commands::{Availability, CommandSpec};
+use crate::measurement::Measurement;
+use crate::parse;
+use crate::protocol::{Interface, MAX_WORDS};
+use crate::types::{
+    DeviceVariant, Environment, GasIndices, ParticulateMatter, Sen5xData, SerialNumber,
+    VocAlgorithmState,
+};
+use crate::Error;
+
+/// Measurements the forced recalibration requires before, 3 minutes at the signal update interval.
+pub const FRC_SAMPLES: u32 = 180;
+
+/// Longest time [`Sen6x::perform_forced_recalibration`] waits for the measurements [ms], the
+/// signal update interval for each of the [`FRC_SAMPLES`] with a margin of 10 s.
+const FRC_TIMEOUT_MS: u32 = FRC_SAMPLES * 1000 + 10_000;
+
+/// Interval at which blocking operations poll the data-ready flag [ms].
+const DATA_READY_POLL_MS: u32 = 100;
+
+/// Words of a response, the words following the response are zero.
+pub type Words = [u16; MAX_WORDS];
+
+/// Commands shared by the SEN6x variants.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Command {
+    /// Start continuous measurement, signal update interval is 1 second.
+    StartMeasurement,
+    /// Stop the measurement and return to idle mode.
+    StopMeasurement,
+    /// Is data ready for read-out?
+    GetDataReady,
+    /// Read the product name, e.g. `SEN66`.
+    GetProductName,
+    /// Read the serial number.
+    GetSerialNumber,
+    /// Read the major and minor firmware version.
+    GetVersion,
+    /// Reset the sensor, equivalent to a power cycle.
+    DeviceReset,
+    /// Run the fan at maximum speed for 10 seconds. Only possible in idle mode.
+    StartFanCleaning,
+    /// Recalibrate the CO₂ sensor to a reference concentration.
+    ForcedCo2Recalibration,
+    /// Reads or writes whether the automatic self calibration of the CO₂ sensor is enabled.
+    Co2AutomaticSelfCalibration,
+    /// Reads or writes the ambient pressure used to compensate the CO₂ concentration [hPa].
+    AmbientPressure,
+    /// Reads or writes the altitude used to compensate the CO₂ concentration [m].
+    SensorAltitude,
+    /// Reads or writes the state of the VOC algorithm.
+    VocAlgorithmState,
+}
+
+impl Command {
+    /// The datasheet description of the command.
+    pub const fn spec(self) -> CommandSpec {
+        use Availability::*;
+
+        match self {
+            Self::StartMeasurement => CommandSpec::send(0x0021, 50, Idle),
+            Self::StopMeasurement => CommandSpec::send(0x0104, 1000, Measuring),
+            Self::GetDataReady => CommandSpec::read(0x0202, 20, 1, Measuring),
+            Self::GetProductName => CommandSpec::read(0xD014, 20, 16, Always),
+            Self::GetSerialNumber => CommandSpec::read(0xD033, 20, 16, Always),
+            Self::GetVersion => CommandSpec::read(0xD100, 20, 1, Always),
+            Self::DeviceReset => CommandSpec::send(0xD304, 1200, Idle),
+            Self::StartFanCleaning => CommandSpec::send(0x5607, 20, Idle),
+            Self::ForcedCo2Recalibration => CommandSpec::read(0x6707, 500, 1, Idle).write(1, Idle),
+            Self::Co2AutomaticSelfCalibration => {
+                CommandSpec::read(0x6711, 20, 1, Idle).write(1, Idle)
+            }
+            Self::AmbientPressure => CommandSpec::read(0x6720, 20, 1, Always).write(1, Always),
+            Self::SensorAltitude => CommandSpec::read(0x6736, 20, 1, Idle).write(1, Idle),
+            Self::VocAlgorithmState => CommandSpec::read(0x6181, 20, 4, Always).write(4, Idle),
+        }
+    }
+}
+
+/// Describes how a member of the SEN6x series differs from the others.
+pub trait Variant {
+    /// The variant, whose product name the sensor reports.
+    const VARIANT: DeviceVariant;
+    /// The default I²C address.
+    const ADDRESS: u8;
+    /// The command reading the measured values, following the layout of the variant.
+    const READ_MEASUREMENT: CommandSpec = CommandSpec::read(
+        Self::VARIANT.layout().code,
+        20,
+        Self::VARIANT.layout().channels.len(),
+        Availability::Measuring,
+    );
+    /// Values of a measurement.
+    type Data: Copy;
+
+    /// The description of a shared command for this variant, `None` if it does not support it.
+    fn command(cmd: Command) -> Option<CommandSpec> {
+        Some(cmd.spec())
+    }
+
+    /// Convert the words of the measurement response.
+    fn decode(words: &Words) -> Self::Data;
+}
+
+/// A variant measuring the CO₂ concentration.
+pub trait Co2Variant: Variant {}
+
+/// A variant measuring the VOC and NOx index.
+pub trait GasVariant: Variant {}
+
+/// Whether `word` is the value an unsigned channel reports while it is not yet available.
+fn known(word: u16) -> Option<u16> {
+    (word != u16::MAX).then_some(word)
+}
+
+/// Convert words in the layout of a SEN5x measurement: PM, humidity, temperature, VOC and NOx
+/// index.
+fn sen5x_part(words: [u16; 8]) -> Sen5xData {
+    Sen5xData::from(parse::measurement_words(words))
+}
+
+/// SEN66: PM, humidity, temperature, VOC, NOx index and CO₂.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Sen66;
+
+/// Values of a SEN66 measurement, values which are not available yet are `None`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Sen66Data {
+    /// Mass concentrations.
+    pub pm: ParticulateMatter,
+    /// Humidity and temperature.
+    pub env: Option<Environment>,
+    /// VOC and NOx index.
+    pub gas: Option<GasIndices>,
+    /// CO₂ concentration [ppm], not available during the first seconds of the measurement.
+    pub co2: Option<u16>,
+}
+
+impl Variant for Sen66 {
+    const VARIANT: DeviceVariant = DeviceVariant::Sen66;
+    const ADDRESS: u8 = 0x6B;
+    type Data = Sen66Data;
+
+    fn decode(words: &Words) -> Sen66Data {
+        let &[pm1_0, pm2_5, pm4_0, pm10_0, humidity, temperature, voc, nox, co2, ..] = words;
+        let Sen5xData { pm, env, gas } =
+            sen5x_part([pm1_0, pm2_5, pm4_0, pm10_0, humidity, temperature, voc, nox]);
+        Sen66Data {
+            pm,
+            env,
+            gas,
+            co2: known(co2),
+        }
+    }
+}
+
+impl Co2Variant for Sen66 {}
+
+impl GasVariant for Sen66 {}
+
+/// SEN63C: PM, humidity, temperature and CO₂, without VOC and NOx index.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Sen63c;
+
+/// Values of a SEN63C measurement, values which are not available yet are `None`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Sen63cData {
+    /// Mass concentrations.
+    pub pm: ParticulateMatter,
+    /// Humidity and temperature.
+    pub env: Option<Environment>,
+    /// CO₂ concentration [ppm], not available during the first seconds of the measurement.
+    pub co2: Option<u16>,
+}
+
+impl Variant for Sen63c {
+    const VARIANT: DeviceVariant = DeviceVariant::Sen63c;
+    const ADDRESS: u8 = 0x6B;
+    type Data = Sen63cData;
+
+    fn decode(words: &Words) -> Sen63cData {
+        let &[pm1_0, pm2_5, pm4_0, pm10_0, humidity, temperature, co2, ..] = words;
+        let unknown = i16::MAX.cast_unsigned();
+        let Sen5xData { pm, env, .. } = sen5x_part([
+            pm1_0,
+            pm2_5,
+            pm4_0,
+            pm10_0,
+            humidity,
+            temperature,
+            unknown,
+            unknown,
+        ]);
+        Sen63cData {
+            pm,
+            env,
+            co2: known(co2),
+        }
+    }
+}
+
+impl Co2Variant for Sen63c {}
+
+/// SEN68: PM, humidity, temperature, VOC, NOx index and formaldehyde.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Sen68;
+
+/// Values of a SEN68 measurement, values which are not available yet are `None`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Sen68Data {
+    /// Mass concentrations.
+    pub pm: ParticulateMatter,
+    /// Humidity and temperature.
+    pub env: Option<Environment>,
+    /// VOC and NOx index.
+    pub gas: Option<GasIndices>,
+    /// Formaldehyde (HCHO) concentration [ppb], not available during the first minutes of the
+    /// measurement.
+    pub hcho: Option<f32>,
+}
+
+impl Variant for Sen68 {
+    const VARIANT: DeviceVariant = DeviceVariant::Sen68;
+    const ADDRESS: u8 = 0x6B;
+    type Data = Sen68Data;
+
+    fn decode(words: &Words) -> Sen68Data {
+        let &[pm1_0, pm2_5, pm4_0, pm10_0, humidity, temperature, voc, nox, hcho, ..] = words;
+        let Sen5xData { pm, env, gas } =
+            sen5x_part([pm1_0, pm2_5, pm4_0, pm10_0, humidity, temperature, voc, nox]);
+        Sen68Data {
+            pm,
+            env,
+            gas,
+            // Scaled by 10 like the PM values.
+            hcho: known(hcho).map(|hcho| f32::from(hcho) / 10.0),
+        }
+    }
+}
+
+impl GasVariant for Sen68 {}
+
+/// SEN60: PM only, with a reduced command set using other command codes.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Sen60;
+
+impl Variant for Sen60 {
+    const VARIANT: DeviceVariant = DeviceVariant::Sen60;
+    const ADDRESS: u8 = 0x6C;
+    type Data = ParticulateMatter;
+
+    fn command(cmd: Command) -> Option<CommandSpec> {
+        use Availability::*;
+
+        match cmd {
+            Command::StartMeasurement => Some(CommandSpec::send(0x2152, 50, Idle)),
+            Command::StopMeasurement => Some(CommandSpec::send(0x3F86, 1000, Measuring)),
+            Command::GetDataReady => Some(CommandSpec::read(0xE4B8, 20, 1, Measuring)),
+            // A 48-bit number instead of a string.
+            Command::GetSerialNumber => Some(CommandSpec::read(0x3682, 20, 3, Idle)),
+            Command::DeviceReset => Some(CommandSpec::send(0x3F8D, 1200, Idle)),
+            Command::StartFanCleaning => Some(CommandSpec::send(0x3730, 20, Idle)),
+            _ => None,
+        }
+    }
+
+    fn decode(&[pm1_0, pm2_5, pm4_0, pm10_0, ..]: &Words) -> ParticulateMatter {
+        ParticulateMatter::from_words([pm1_0, pm2_5, pm4_0, pm10_0])
+    }
+}
+
+/// Automatic self calibration of the CO₂ sensor, stored in the EEPROM of the sensor.
+///
+/// The self calibration assumes that the sensor is exposed to fresh air of about 400 ppm
+/// regularly, e.g. once a week. Disable it for sensors which never are.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum AutomaticSelfCalibration {
+    /// Recalibrate automatically, the factory default.
+    #[default]
+    Enabled,
+    /// Do not recalibrate automatically.
+    Disabled,
+}
+
+/// Values used to compensate the CO₂ concentration, `None` if not set.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub struct Co2Compensation {
+    /// Ambient pressure [hPa], overrides the altitude.
+    pub ambient_pressure: Option<u16>,
+    /// Altitude of the sensor [m].
+    pub altitude: Option<u16>,
+}
+
+/// Driver of a sensor of the SEN6x series.
+#[derive(Debug)]
+pub struct Sen6x<I2C, D, V> {
+    bus: Interface<I2C, D>,
+    is_running: bool,
+    samples: u32,
+    compensation: Co2Compensation,
+    variant: PhantomData<V>,
+}
+
+impl<I2C, D, E, V> Sen6x<I2C, D, V>
+where
+    I2C: I2c<Error = E>,
+    D: DelayNs,
+    V: Variant,
+{
+    /// Create a new instance using the default I2C address of the variant.
+    pub fn new(i2c: I2C, delay: D) -> Self {
+        Self::with_i2c_address(i2c, delay, V::ADDRESS)
+    }
+
+    /// Create a new instance using a custom I2C address.
+    pub fn with_i2c_address(i2c: I2C, delay: D, address: u8) -> Self {
+        Self {
+            bus: Interface::new(i2c, delay, address),
+            is_running: false,
+            samples: 0,
+            compensation: Co2Compensation::default(),
+            variant: PhantomData,
+        }
+    }
+
+    /// The I2C address of the sensor.
+    pub fn address(&self) -> u8 {
+        self.bus.address()
+    }
+
+    /// Start continuous measurement.
+    pub fn start_measurement(&mut self) -> Result<(), Error<E>> {
+        self.write(Self::spec(Command::StartMeasurement)?, &[])?;
+        self.is_running = true;
+        self.samples = 0;
+        Ok(())
+    }
+
+    /// Stop the measurement and return to idle mode.
+    pub fn stop_measurement(&mut self) -> Result<(), Error<E>> {
+        self.write(Self::spec(Command::StopMeasurement)?, &[])?;
+        self.is_running = false;
+        Ok(())
+    }
+
+    /// Whether the driver believes the measurement is running.
+    pub fn is_measuring(&self) -> bool {
+        self.is_running
+    }
+
+    /// Check whether new measurement data is available for read-out.
+    pub fn data_ready_status(&mut self) -> Result<bool, Error<E>> {
+        let [ready, ..] = self.read(Self::spec(Command::GetDataReady)?)?;
+        Ok(parse::data_ready_words([ready]))
+    }
+
+    /// Read the measured values.
+    pub fn measurement(&mut self) -> Result<V::Data, Error<E>> {
+        let words = self.read(V::READ_MEASUREMENT)?;
+        self.samples = self.samples.saturating_add(1);
+        Ok(V::decode(&words))
+    }
+
+    /// Read the measured values into the channels shared by all variants.
+    pub fn measurement_unified(&mut self) -> Result<Measurement, Error<E>> {
+        let words = self.read(V::READ_MEASUREMENT)?;
+        self.samples = self.samples.saturating_add(1);
+        Ok(Measurement::decode(V::VARIANT, &words))
+    }
+
+    /// Number of measurements read since the measurement was started.
+    pub fn samples(&self) -> u32 {
+        self.samples
+    }
+
+    /// Read the product name, a null-terminated ASCII string.
+    pub fn product_name(&mut self) -> Result<[u8; 32], Error<E>> {
+        self.read_ascii(Self::spec(Command::GetProductName)?)
+    }
+
+    /// Read the serial number.
+    ///
+    /// The SEN60 reports a 48-bit number instead of a string, which is converted to its hex
+    /// digits, e.g. `31394A072C85`.
+    pub fn serial_number(&mut self) -> Result<SerialNumber, Error<E>> {
+        let spec = Self::spec(Command::GetSerialNumber)?;
+        if V::VARIANT == DeviceVariant::Sen60 {
+            let words = self.read(spec)?;
+            let words = words.get(..spec.response_words).unwrap_or_default();
+            return Ok(SerialNumber::from_hex_words(words));
+        }
+        self.read_ascii(spec).map(SerialNumber::from_bytes)
+    }
+
+    /// Read the major and minor firmware version.
+    pub fn firmware_version(&mut self) -> Result<(u8, u8), Error<E>> {
+        let [version, ..] = self.read(Self::spec(Command::GetVersion)?)?;
+        let [major, minor] = version.to_be_bytes();
+        Ok((major, minor))
+    }
+
+    /// Reset the sensor. Only possible in idle mode.
+    ///
+    /// The CO₂ compensation set through this driver is sent again after the reset.
+    pub fn device_reset(&mut self) -> Result<(), Error<E>> {
+        self.write(Self::spec(Command::DeviceReset)?, &[])?;
+        let Co2Compensation {
+            ambient_pressure,
+            altitude,
+        } = self.compensation;
+        // Only set for the variants measuring CO₂.
+        if let Some(meters) = altitude {
+            self.write(Self::spec(Command::SensorAltitude)?, &[meters])?;
+        }
+        if let Some(hpa) = ambient_pressure {
+            self.write(Self::spec(Command::AmbientPressure)?, &[hpa])?;
+        }
+        Ok(())
+    }
+
+    /// Start the fan cleaning, which takes 10 seconds. Only possible in idle mode.
+    pub fn start_fan_cleaning(&mut self) -> Result<(), Error<E>> {
+        self.write(Self::spec(Command::StartFanCleaning)?, &[])
+    }
+
+    /// The description of `cmd` for the variant.
+    fn spec(cmd: Command) -> Result<CommandSpec, Error<E>> {
+        V::command(cmd).ok_or(Error::Unsupported)
+    }
+
+    /// Issue the command and read back its response, validating the CRC of every word.
+    fn read(&mut self, spec: CommandSpec) -> Result<Words, Error<E>> {
+        self.write(spec, &[])?;
+        self.read_response(spec)
+    }
+
+    /// Read the response of the command issued last.
+    fn read_response(&mut self, spec: CommandSpec) -> Result<Words, Error<E>> {
+        let mut words = [0u16; MAX_WORDS];
+        let len = spec.response_words.min(MAX_WORDS);
+        let response = words.get_mut(..len).ok_or(Error::WrongBufferSize)?;
+        self.bus.read(response)?;
+        Ok(words)
+    }
+
+    /// Issue the command and read back a 32 character ASCII string.
+    fn read_ascii(&mut self, spec: CommandSpec) -> Result<[u8; 32], Error<E>> {
+        self.read(spec).map(parse::ascii_words)
+    }
+
+    /// Write the command followed by `words` arguments, each protected by a CRC.
+    fn write(&mut self, spec: CommandSpec, words: &[u16]) -> Result<(), Error<E>> {
+        let availability = if words.is_empty() {
+            spec.availability
+        } else {
+            spec.write_availability
+        };
+        if !availability.allows(self.is_running) {
+            return Err(Error::NotAllowed);
+        }
+        self.bus.write(spec.code, words)?;
+        self.bus.delay_ms(spec.execution_time_ms);
+        Ok(())
+    }
+}
+
+impl<I2C, D, E, V> Sen6x<I2C, D, V>
+where
+    I2C: I2c<Error = E>,
+    D: DelayNs,
+    V: Co2Variant,
+{
+    /// Run the forced recalibration procedure of the datasheet and return the correction applied
+    /// [ppm].
+    ///
+    /// Measures until [`FRC_SAMPLES`] measurements were read since the start of the measurement,
+    /// starting it if needed, stops the measurement and recalibrates the CO₂ sensor to the
+    /// reference concentration `target_ppm`. The sensor has to be in an environment with a
+    /// stable CO₂ concentration of `target_ppm` during the whole procedure, which blocks for
+    /// about 3 minutes. The measurement stays stopped afterwards.
+    ///
+    /// Returns `Error::Timeout`, leaving the measurement running, if the measurements are not
+    /// ready within 3 minutes and 10 seconds, e.g. because the sensor was reset or the measurement
+    /// was stopped in the meantime.
+    pub fn perform_forced_recalibration(&mut self, target_ppm: u16) -> Result<i16, Error<E>> {
+        if !self.is_running {
+            self.start_measurement()?;
+        }
+        let mut waited = 0;
+        while self.samples < FRC_SAMPLES {
+            if self.data_ready_status()? {
+                self.measurement()?;
+            } else if waited >= FRC_TIMEOUT_MS {
+                return Err(Error::Timeout);
+            } else {
+                self.bus.delay_ms(DATA_READY_POLL_MS);
+                waited += DATA_READY_POLL_MS;
+            }
+        }
+        // Stopping takes the second the sensor has to idle before the recalibration.
+        self.stop_measurement()?;
+        self.forced_co2_recalibration(target_ppm)
+    }
+
+    /// Recalibrate the CO₂ sensor to the reference concentration `target_ppm` and return the
+    /// correction applied [ppm].
+    ///
+    /// Only possible in idle mode, after the sensor measured for at least 3 minutes in an
+    /// environment with a stable CO₂ concentration and waited 1 second after stopping. Returns
+    /// `Error::Internal` if the sensor failed to recalibrate.
+    pub fn forced_co2_recalibration(&mut self, target_ppm: u16) -> Result<i16, Error<E>> {
+        let spec = Self::spec(Command::ForcedCo2Recalibration)?;
+        self.write(spec, &[target_ppm])?;
+        let [correction, ..] = self.read_response(spec)?;
+        if correction == u16::MAX {
+            return Err(Error::Internal);
+        }
+        Ok((correction ^ 0x8000).cast_signed())
+    }
+
+    /// Enable or disable the automatic self calibration of the CO₂ sensor. Only possible in idle
+    /// mode.
+    ///
+    /// The setting is written to the EEPROM of the sensor, which endures a limited number of
+    /// writes. Configure it once, e.g. with
+    /// [`Sen6x::ensure_co2_automatic_self_calibration`], instead of on every start.
+    pub fn set_co2_automatic_self_calibration(
+        &mut self,
+        asc: AutomaticSelfCalibration,
+    ) -> Result<(), Error<E>> {
+        self.write(
+            Self::spec(Command::Co2AutomaticSelfCalibration)?,
+            &[u16::from(asc == AutomaticSelfCalibration::Enabled)],
+        )
+    }
+
+    /// Whether the automatic self calibration of the CO₂ sensor is enabled. Only possible in idle
+    /// mode.
+    pub fn co2_automatic_self_calibration(&mut self) -> Result<AutomaticSelfCalibration, Error<E>> {
+        let [enabled, ..] = self.read(Self::spec(Command::Co2AutomaticSelfCalibration)?)?;
+        Ok(if enabled & 0xFF != 0 {
+            AutomaticSelfCalibration::Enabled
+        } else {
+            AutomaticSelfCalibration::Disabled
+        })
+    }
+
+    /// Set the automatic self calibration of the CO₂ sensor only if it differs from `asc`,
+    /// sparing the EEPROM a write. Returns whether it was written. Only possible in idle mode.
+    pub fn ensure_co2_automatic_self_calibration(
+        &mut self,
+        asc: AutomaticSelfCalibration,
+    ) -> Result<bool, Error<E>> {
+        if self.co2_automatic_self_calibration()? == asc {
+            return Ok(false);
+        }
+        self.set_co2_automatic_self_calibration(asc)?;
+        Ok(true)
+    }
+
+    /// Set the ambient pressure used to compensate the CO₂ concentration, from 700 to 1200 hPa.
+    ///
+    /// Overrides the compensation by the sensor altitude. Possible while measuring, e.g. to pass
+    /// the reading of a barometer periodically. The sensor forgets the value on a reset, see
+    /// [`Sen6x::co2_compensation`].
+    pub fn set_ambient_pressure(&mut self, hpa: u16) -> Result<(), Error<E>> {
+        if !(700..=1200).contains(&hpa) {
+            return Err(Error::InvalidInput {
+                field: "ambient_pressure",
+                value: hpa.into(),
+            });
+        }
+        self.write(Self::spec(Command::AmbientPressure)?, &[hpa])?;
+        self.compensation.ambient_pressure = Some(hpa);
+        Ok(())
+    }
+
+    /// The ambient pressure used to compensate the CO₂ concentration [hPa].
+    pub fn ambient_pressure(&mut self) -> Result<u16, Error<E>> {
+        let [hpa, ..] = self.read(Self::spec(Command::AmbientPressure)?)?;
+        Ok(hpa)
+    }
+
+    /// Set the altitude of the sensor used to compensate the CO₂ concentration, from 0 to
+    /// 3000 m. Only possible in idle mode.
+    ///
+    /// The sensor forgets the value on a reset, see [`Sen6x::co2_compensation`].
+    pub fn set_altitude(&mut self, meters: u16) -> Result<(), Error<E>> {
+        if meters > 3000 {
+            return Err(Error::InvalidInput {
+                field: "altitude",
+                value: meters.into(),
+            });
+        }
+        self.write(Self::spec(Command::SensorAltitude)?, &[meters])?;
+        self.compensation.altitude = Some(meters);
+        Ok(())
+    }
+
+    /// The altitude of the sensor used to compensate the CO₂ concentration [m].
+    pub fn altitude(&mut self) -> Result<u16, Error<E>> {
+        let [meters, ..] = self.read(Self::spec(Command::SensorAltitude)?)?;
+        Ok(meters)
+    }
+
+    /// The compensation set through this driver.
+    ///
+    /// The sensor keeps the ambient pressure and the altitude in volatile memory only.
+    /// [`Sen6x::device_reset`] sends the values again, after a power cycle call
+    /// [`Sen6x::restore_co2_compensation`], or store the values and pass them to
+    /// [`Sen6x::set_co2_compensation`] after a restart of the application.
+    pub fn co2_compensation(&self) -> Co2Compensation {
+        self.compensation
+    }
+
+    /// Send the values of `compensation` which are set to the sensor. Only possible in idle
+    /// mode if the altitude is set.
+    pub fn set_co2_compensation(&mut self, compensation: Co2Compensation) -> Result<(), Error<E>> {
+        if let Some(meters) = compensation.altitude {
+            self.set_altitude(meters)?;
+        }
+        if let Some(hpa) = compensation.ambient_pressure {
+            self.set_ambient_pressure(hpa)?;
+        }
+        Ok(())
+    }
+
+    /// Send the compensation set through this driver again, e.g. after the sensor lost power.
+    pub fn restore_co2_compensation(&mut self) -> Result<(), Error<E>> {
+        self.set_co2_compensation(self.compensation)
+    }
+}
+
+impl<I2C, D, E, V> Sen6x<I2C, D, V>
+where
+    I2C: I2c<Error = E>,
+    D: DelayNs,
+    V: GasVariant,
+{
+    /// Read the state of the VOC algorithm, e.g. to restore it after a restart.
+    pub fn voc_algorithm_state(&mut self) -> Result<VocAlgorithmState, Error<E>> {
+        let [a, b, c, d, ..] = self.read(Self::spec(Command::VocAlgorithmState)?)?;
+        Ok(VocAlgorithmState::from_words([a, b, c, d]))
+    }
+
+    /// Restore the state of the VOC algorithm. Only possible in idle mode.
+    pub fn set_voc_algorithm_state(&mut self, state: VocAlgorithmState) -> Result<(), Error<E>> {
+        self.write(Self::spec(Command::VocAlgorithmState)?, &state.to_words())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use embedded_hal_mock as hal;
+
+    use self::hal::eh1::delay::NoopDelay as DelayMock;
+    use self::hal::eh1::i2c::{Mock as I2cMock, Transaction};
+    use super::*;
+    use crate::test_vectors::{DATA_NOT_READY, DATA_READY, MEASUREMENTS};
+
+    const ADDRESS: u8 = 0x6B;
+
+    fn command(spec: CommandSpec, args: &[u8]) -> Transaction {
+        Transaction::write(ADDRESS, [&spec.code.to_be_bytes()[..], args].concat())
+    }
+
+    /// Test reading a SEN66 measurement
+    #[test]
+    fn test_sen66_measurement() {
+        let frame = [&MEASUREMENTS[0].frame[..], &[0x03, 0x52, 0xB0]].concat();
+        let expectations = [
+            command(Command::StartMeasurement.spec(), &[]),
+            command(Command::GetDataReady.spec(), &[]),
+            Transaction::read(ADDRESS, DATA_READY.to_vec()),
+            command(Sen66::READ_MEASUREMENT, &[]),
+            Transaction::read(ADDRESS, frame),
+            command(Sen66::READ_MEASUREMENT, &[]),
+            Transaction::read(
+                ADDRESS,
+                [&MEASUREMENTS[0].frame[..], &[0xFF, 0xFF, 0xAC]].concat(),
+            ),
+            command(Sen66::READ_MEASUREMENT, &[]),
+            Transaction::read(
+                ADDRESS,
+                [&MEASUREMENTS[0].frame[..], &[0x03, 0x52, 0xB0]].concat(),
+            ),
+        ];
+        let mut mock = I2cMock::new(&expectations);
+        let mut sensor = Sen6x::<_, _, Sen66>::new(mock.clone(), DelayMock);
+        sensor.start_measurement().unwrap();
+        assert!(sensor.data_ready_status().unwrap());
+        let data = sensor.measurement().unwrap();
+        let expected = MEASUREMENTS[0].data;
+        assert_eq!(
+            data,
+            Sen66Data {
+                pm: expected.pm,
+                env: expected.env,
+                gas: expected.gas,
+                co2: Some(850),
+            }
+        );
+        assert_eq!(sensor.measurement().unwrap().co2, None);
+        let unified = sensor.measurement_unified().unwrap();
+        assert_eq!(unified.co2, Some(850.0));
+        assert_eq!(unified.pm2_5, Some(expected.pm.pm2_5));
+        assert_eq!(unified.hcho, None);
+        mock.done();
+    }
+
+    /// Test reading a SEN63C measurement, which lacks the gas indices
+    #[test]
+    fn test_sen63c_measurement() {
+        let frame = [&MEASUREMENTS[0].frame[..18], &[0x03, 0x52, 0xB0]].concat();
+        let expectations = [
+            command(Sen63c::READ_MEASUREMENT, &[]),
+            Transaction::read(ADDRESS, frame),
+            command(Command::ForcedCo2Recalibration.spec(), &[0x01, 0xA4, 0x4D]),
+            Transaction::read(ADDRESS, vec![0x7F, 0xCE, 0x7B]),
+        ];
+        let mut mock = I2cMock::new(&expectations);
+        let mut sensor = Sen6x::<_, _, Sen63c>::new(mock.clone(), DelayMock);
+        sensor.is_running = true;
+        assert_eq!(
+            sensor.measurement().unwrap(),
+            Sen63cData {
+                pm: MEASUREMENTS[0].data.pm,
+                env: MEASUREMENTS[0].data.env,
+                co2: Some(850),
+            }
+        );
+        sensor.is_running = false;
+        assert_eq!(sensor.forced_co2_recalibration(420), Ok(-50));
+        mock.done();
+    }
+
+    /// Test reading a SEN68 measurement and the VOC algorithm state
+    #[test]
+    fn test_sen68() {
+        let frame = [&MEASUREMENTS[0].frame[..], &[0x00, 0x7B, 0x93]].concat();
+        let state = VocAlgorithmState::from_bytes([1, 2, 3, 4, 5, 6, 7, 8]);
+        let state_frame = vec![
+            0x01, 0x02, 0x17, 0x03, 0x04, 0x68, 0x05, 0x06, 0x50, 0x07, 0x08, 0x96,
+        ];
+        let expectations = [
+            command(Sen68::READ_MEASUREMENT, &[]),
+            Transaction::read(ADDRESS, frame),
+            command(Command::VocAlgorithmState.spec(), &[]),
+            Transaction::read(ADDRESS, state_frame.clone()),
+            command(Command::VocAlgorithmState.spec(), &state_frame),
+        ];
+        let mut mock = I2cMock::new(&expectations);
+        let mut sensor = Sen6x::<_, _, Sen68>::new(mock.clone(), DelayMock);
+        sensor.is_running = true;
+        let data = sensor.measurement().unwrap();
+        assert_eq!(data.hcho, Some(12.3));
+        assert_eq!(data.gas, MEASUREMENTS[0].data.gas);
+        assert_eq!(sensor.voc_algorithm_state().unwrap(), state);
+        sensor.is_running = false;
+        sensor.set_voc_algorithm_state(state).unwrap();
+        mock.done();
+    }
+
+    /// Test the address and command codes of the SEN60
+    #[test]
+    fn test_sen60() {
+        const SEN60_ADDRESS: u8 = 0x6C;
+        let expectations = [
+            Transaction::write(SEN60_ADDRESS, vec![0x36, 0x82]),
+            Transaction::read(
+                SEN60_ADDRESS,
+                vec![0xBE, 0xEF, 0x92, 0xBE, 0xEF, 0x92, 0xBE, 0xEF, 0x92],
+            ),
+            Transaction::write(SEN60_ADDRESS, vec![0x21, 0x52]),
+            Transaction::write(SEN60_ADDRESS, vec![0xE4, 0xB8]),
+            Transaction::read(SEN60_ADDRESS, DATA_READY.to_vec()),
+            Transaction::write(SEN60_ADDRESS, vec![0xEC, 0x05]),
+            Transaction::read(SEN60_ADDRESS, MEASUREMENTS[0].frame[..12].to_vec()),
+        ];
+        let mut mock = I2cMock::new(&expectations);
+        let mut sensor = Sen6x::<_, _, Sen60>::new(mock.clone(), DelayMock);
+        assert_eq!(
+            sensor.serial_number().unwrap().as_ascii(),
+            Some("BEEFBEEFBEEF")
+        );
+        assert_eq!(sensor.product_name(), Err(Error::Unsupported));
+        sensor.start_measurement().unwrap();
+        assert!(sensor.data_ready_status().unwrap());
+        assert_eq!(sensor.measurement().unwrap(), MEASUREMENTS[0].data.pm);
+        mock.done();
+    }
+
+    /// Test that the binary serial of the SEN60 is converted word by word, even if its bytes look
+    /// like ASCII
+    #[test]
+    fn test_sen60_serial_number() {
+        let mut frame = [0u8; 9];
+        crate::model::encode_words(&[0x3139, 0x4A07, 0x2C85], &mut frame);
+        let expectations = [
+            Transaction::write(0x6C, vec![0x36, 0x82]),
+            Transaction::read(0x6C, frame.to_vec()),
+        ];
+        let mut mock = I2cMock::new(&expectations);
+        let mut sensor = Sen6x::<_, _, Sen60>::new(mock.clone(), DelayMock);
+        let serial = sensor.serial_number().unwrap();
+        assert_eq!(serial.as_ascii(), Some("31394A072C85"));
+        assert_eq!(serial.to_string(), "31394A072C85");
+        mock.done();
+    }
+
+    /// Test the CO₂ calibration and compensation commands
+    #[test]
+    fn test_sen66_co2() {
+        let expectations = [
+            command(Command::ForcedCo2Recalibration.spec(), &[0x01, 0xA4, 0x4D]),
+            Transaction::read(ADDRESS, vec![0x7F, 0xCE, 0x7B]),
+            command(Command::ForcedCo2Recalibration.spec(), &[0x01, 0xA4, 0x4D]),
+            Transaction::read(ADDRESS, vec![0xFF, 0xFF, 0xAC]),
+            command(
+                Command::Co2AutomaticSelfCalibration.spec(),
+                &[0x00, 0x01, 0xB0],
+            ),
+            command(Command::Co2AutomaticSelfCalibration.spec(), &[]),
+            Transaction::read(ADDRESS, vec![0x00, 0x01, 0xB0]),
+            command(Command::StartMeasurement.spec(), &[]),
+            command(Command::AmbientPressure.spec(), &[0x03, 0xF5, 0xDB]),
+        ];
+        let mut mock = I2cMock::new(&expectations);
+        let mut sensor = Sen6x::<_, _, Sen66>::new(mock.clone(), DelayMock);
+        assert_eq!(sensor.forced_co2_recalibration(420), Ok(-50));
+        assert_eq!(sensor.forced_co2_recalibration(420), Err(Error::Internal));
+        sensor
+            .set_co2_automatic_self_calibration(AutomaticSelfCalibration::Enabled)
+            .unwrap();
+        assert_eq!(
+            sensor.ensure_co2_automatic_self_calibration(AutomaticSelfCalibration::Enabled),
+            Ok(false)
+        );
+        sensor.start_measurement().unwrap();
+        sensor.set_ambient_pressure(1013).unwrap();
+        assert_eq!(
+            sensor.set_ambient_pressure(650),
+            Err(Error::InvalidInput {
+                field: "ambient_pressure",
+                value: 650
+            })
+        );
+        assert_eq!(sensor.set_altitude(500), Err(Error::NotAllowed));
+        mock.done();
+    }
+
+    /// Test that the forced recalibration measures for the required time first
+    #[test]
+    fn test_perform_forced_recalibration() {
+        let frame = [&MEASUREMENTS[0].frame[..], &[0x03, 0x52, 0xB0]].concat();
+        let mut expectations = vec![command(Command::StartMeasurement.spec(), &[])];
+        for _ in 0..FRC_SAMPLES {
+            expectations.extend([
+                command(Command::GetDataReady.spec(), &[]),
+                Transaction::read(ADDRESS, DATA_NOT_READY.to_vec()),
+                command(Command::GetDataReady.spec(), &[]),
+                Transaction::read(ADDRESS, DATA_READY.to_vec()),
+                command(Sen66::READ_MEASUREMENT, &[]),
+                Transaction::read(ADDRESS, frame.clone()),
+            ]);
+        }
+        expectations.extend([
+            command(Command::StopMeasurement.spec(), &[]),
+            command(Command::ForcedCo2Recalibration.spec(), &[0x01, 0xA4, 0x4D]),
+            Transaction::read(ADDRESS, vec![0x7F, 0xCE, 0x7B]),
+        ]);
+        let mut mock = I2cMock::new(&expectations);
+        let mut sensor = Sen6x::<_, _, Sen66>::new(mock.clone(), DelayMock);
+        assert_eq!(sensor.perform_forced_recalibration(420), Ok(-50));
+        assert_eq!(sensor.samples(), FRC_SAMPLES);
+        assert!(!sensor.is_measuring());
+        mock.done();
+    }
+
+    /// Test that the forced recalibration gives up if the measurements never get ready
+    #[test]
+    fn test_perform_forced_recalibration_timeout() {
+        let mut expectations = vec![command(Command::StartMeasurement.spec(), &[])];
+        for _ in 0..=FRC_TIMEOUT_MS / DATA_READY_POLL_MS {
+            expectations.extend([
+                command(Command::GetDataReady.spec(), &[]),
+                Transaction::read(ADDRESS, DATA_NOT_READY.to_vec()),
+            ]);
+        }
+        let mut mock = I2cMock::new(&expectations);
+        let mut sensor = Sen6x::<_, _, Sen66>::new(mock.clone(), DelayMock);
+        assert_eq!(
+            sensor.perform_forced_recalibration(420),
+            Err(Error::Timeout)
+        );
+        assert_eq!(sensor.samples(), 0);
+        assert!(sensor.is_measuring());
+        mock.done();
+    }
+
+    /// Test that the CO₂ compensation is remembered and sent again after a reset
+    #[test]
+    fn test_co2_compensation() {
+        let expectations = [
+            command(Command::SensorAltitude.spec(), &[0x01, 0xF4, 0x33]),
+            command(Command::AmbientPressure.spec(), &[0x03, 0xF5, 0xDB]),
+            command(Command::DeviceReset.spec(), &[]),
+            command(Command::SensorAltitude.spec(), &[0x01, 0xF4, 0x33]),
+            command(Command::AmbientPressure.spec(), &[0x03, 0xF5, 0xDB]),
+        ];
+        let mut mock = I2cMock::new(&expectations);
+        let mut sensor = Sen6x::<_, _, Sen66>::new(mock.clone(), DelayMock);
+        assert_eq!(
+            sensor.set_altitude(3001),
+            Err(Error::InvalidInput {
+                field: "altitude",
+                value: 3001
+            })
+        );
+        let compensation = Co2Compensation {
+            ambient_pressure: Some(1013),
+            altitude: Some(500),
+        };
+        sensor.set_co2_compensation(compensation).unwrap();
+        assert_eq!(sensor.co2_compensation(), compensation);
+        sensor.device_reset().unwrap();
+        mock.done();
+    }
+}
@@ -0,0 +1,101 @@
+//! Implementations of the `embedded-sensors-hal` traits.
+//!
+//! Generic code consuming temperature and humidity sensors through these traits can use the SEN5x
+//! interchangeably with other sensors. Every call reads a complete measurement, so the periodic
+//! measurement has to be running and a new sample available, see [`Sen5x::data_ready_status`].
+use embedded_hal::{delay::DelayNs, i2c::I2c};
+use embedded_sensors_hal::humidity::{Percentage, RelativeHumiditySensor};
+use embedded_sensors_hal::sensor::{self, ErrorKind, ErrorType};
+use embedded_sensors_hal::temperature::{DegreesCelsius, TemperatureSensor};
+
+use crate::{Error, Sen5x};
+
+impl<E> sensor::Error for Error<E>
+where
+    E: core::fmt::Debug,
+{
+    fn kind(&self) -> ErrorKind {
+        match self {
+            Error::I2c(_) | Error::Nack(_) | Error::ArbitrationLoss(_) | Error::Bus(_) => {
+                ErrorKind::Peripheral
+            }
+            Error::NotAllowed | Error::Timeout | Error::Unavailable => ErrorKind::NotReady,
+            Error::InvalidInput { .. } => ErrorKind::InvalidInput,
+            _ => ErrorKind::Other,
+        }
+    }
+}
+
+impl<I2C, D, E> ErrorType for Sen5x<I2C, D>
+where
+    I2C: I2c<Error = E>,
+    D: DelayNs,
+    E: core::fmt::Debug,
+{
+    type Error = Error<E>;
+}
+
+impl<I2C, D, E> TemperatureSensor for Sen5x<I2C, D>
+where
+    I2C: I2c<Error = E>,
+    D: DelayNs,
+    E: core::fmt::Debug,
+{
+    fn temperature(&mut self) -> Result<DegreesCelsius, Self::Error> {
+        self.measurement()?
+            .env
+            .map(|env| env.temperature)
+            .ok_or(Error::Unavailable)
+    }
+}
+
+impl<I2C, D, E> RelativeHumiditySensor for Sen5x<I2C, D>
+where
+    I2C: I2c<Error = E>,
+    D: DelayNs,
+    E: core::fmt::Debug,
+{
+    fn relative_humidity(&mut self) -> Result<Percentage, Self::Error> {
+        self.measurement()?
+            .env
+            .map(|env| env.humidity)
+            .ok_or(Error::Unavailable)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulator::Simulator;
+    use crate::test_vectors::MEASUREMENTS;
+
+    /// Read temperature and humidity through generic code
+    fn read<S: TemperatureSensor + RelativeHumiditySensor>(
+        sensor: &mut S,
+    ) -> Result<(f32, f32), S::Error> {
+        Ok((sensor.temperature()?, sensor.relative_humidity()?))
+    }
+
+    /// Test the traits against the simulator
+    #[test]
+    fn test_traits() {
+        let sim = Simulator::new();
+        let mut sensor = Sen5x::new(sim.clone(), sim.delay());
+        sensor.start_measurement().unwrap();
+        sim.advance_ms(1000);
+        let env = MEASUREMENTS[0].data.env.unwrap();
+        assert_eq!(read(&mut sensor).unwrap(), (env.temperature, env.humidity));
+    }
+
+    /// Test the mapping of errors to their kinds
+    #[test]
+    fn test_error_kind() {
+        use embedded_sensors_hal::sensor::Error as _;
+
+        assert_eq!(Error::I2c(()).kind(), ErrorKind::Peripheral);
+        assert_eq!(Error::Nack(()).kind(), ErrorKind::Peripheral);
+        assert_eq!(Error::<()>::Timeout.kind(), ErrorKind::NotReady);
+        assert_eq!(Error::<()>::Unavailable.kind(), ErrorKind::NotReady);
+        assert_eq!(Error::<()>::Crc { word: 0 }.kind(), ErrorKind::Other);
+    }
+}
@@ -0,0 +1,178 @@
+//! Sequence numbers of the samples and detection of the missed ones.
+//!
+//! The sensor provides a new sample every signal update interval. A [`Sequencer`], fed with the
+//! time elapsed since the previous sample like [`StateSaver`](crate::saver::StateSaver), numbers
+//! the samples by the interval they belong to, so the sequence number skips the intervals of
+//! samples which were missed, e.g. while the bus was busy. Consumers downstream quantify the
+//! sample loss from the sequence numbers alone, without timestamps. [`Sequencer::stamp_at`]
+//! takes the time of reading instead, e.g. from a [`Clock`](crate::clock::Clock), and the
+//! Embassy tasks `run_channel_sequenced` and `run_watch_sequenced` publish sequenced samples.
+//!
+//! ```
+//! use core::time::Duration;
+//! use sen5x_rs::sequence::Sequencer;
+//!
+//! let mut sequencer = Sequencer::new();
+//! assert_eq!(sequencer.stamp("a", Duration::ZERO).sequence, 0);
+//! assert_eq!(sequencer.stamp("b", Duration::from_millis(1010)).sequence, 1);
+//! let late = sequencer.stamp("c", Duration::from_millis(3020));
+//! assert_eq!((late.sequence, late.missed), (4, 2));
+//! assert_eq!(sequencer.missed(), 2);
+//! ```
+use core::time::Duration;
+
+/// A sample with its sequence number.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Sequenced<T> {
+    /// Number of the signal update interval of the sample, wrapping around.
+    pub sequence: u32,
+    /// Intervals missed since the previous sample.
+    pub missed: u32,
+    /// The sample.
+    pub sample: T,
+}
+
+/// Numbers consecutive samples and counts the missed ones.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Sequencer {
+    interval: Duration,
+    /// Sequence number of the next sample, `None` before the first one.
+    next: Option<u32>,
+    /// Time the previous sample was read at, for [`Sequencer::stamp_at`].
+    last: Option<Duration>,
+    received: u64,
+    missed: u64,
+}
+
+impl Default for Sequencer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Sequencer {
+    /// Signal update interval of the sensor in measurement mode.
+    pub const SIGNAL_UPDATE_INTERVAL: Duration = Duration::from_secs(1);
+
+    /// A sequencer expecting a sample every [`Self::SIGNAL_UPDATE_INTERVAL`].
+    pub const fn new() -> Self {
+        Self::with_interval(Self::SIGNAL_UPDATE_INTERVAL)
+    }
+
+    /// A sequencer expecting a sample every `interval`, e.g. the period of a duty cycle.
+    pub const fn with_interval(interval: Duration) -> Self {
+        Self {
+            interval,
+            next: None,
+            last: None,
+            received: 0,
+            missed: 0,
+        }
+    }
+
+    /// Number `sample`, read `elapsed` after the previous one.
+    ///
+    /// The elapsed time is rounded to whole intervals, so jitter below half an interval is not
+    /// reported as a gap. The first sample after creating or resetting the sequencer is number 0.
+    ///
+    /// A sample read less than half an interval after the previous one belongs to the same
+    /// interval: it repeats the sequence number of the previous sample and is not counted as
+    /// received, so consumers can drop it as a duplicate.
+    pub fn stamp<T>(&mut self, sample: T, elapsed: Duration) -> Sequenced<T> {
+        let interval = self.interval.as_nanos().max(1);
+        let (sequence, missed) = match self.next {
+            None => (0, 0),
+            Some(next) => match (elapsed.as_nanos() + interval / 2) / interval {
+                0 => {
+                    return Sequenced {
+                        sequence: next.wrapping_sub(1),
+                        missed: 0,
+                        sample,
+                    };
+                }
+                intervals => {
+                    let missed = u32::try_from(intervals - 1).unwrap_or(u32::MAX);
+                    (next.wrapping_add(missed), missed)
+                }
+            },
+        };
+        self.next = Some(sequence.wrapping_add(1));
+        self.received += 1;
+        self.missed += u64::from(missed);
+        Sequenced {
+            sequence,
+            missed,
+            sample,
+        }
+    }
+
+    /// Number `sample`, read at `now` on a monotonic clock.
+    ///
+    /// Like [`Sequencer::stamp`], with the time elapsed since the previous call.
+    pub fn stamp_at<T>(&mut self, sample: T, now: Duration) -> Sequenced<T> {
+        let elapsed = now.saturating_sub(self.last.unwrap_or(now));
+        self.last = Some(now);
+        self.stamp(sample, elapsed)
+    }
+
+    /// Samples numbered, without the repeated ones.
+    pub fn received(&self) -> u64 {
+        self.received
+    }
+
+    /// Samples missed in total.
+    pub fn missed(&self) -> u64 {
+        self.missed
+    }
+
+    /// Fraction of the samples missed, `0.0` before the first one.
+    pub fn loss(&self) -> f32 {
+        let total = self.received + self.missed;
+        if total == 0 {
+            return 0.0;
+        }
+        #[allow(clippy::cast_precision_loss)] // a ratio, approximate for huge counts
+        let loss = self.missed as f32 / total as f32;
+        loss
+    }
+
+    /// Start over at sequence number 0, e.g. after restarting the measurement.
+    pub fn reset(&mut self) {
+        *self = Self::with_interval(self.interval);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test that missed intervals advance the sequence number and are counted
+    #[test]
+    fn test_sequencer() {
+        let ms = Duration::from_millis;
+        let mut sequencer = Sequencer::new();
+        assert_eq!(sequencer.loss(), 0.0);
+        assert_eq!(sequencer.stamp((), ms(5000)).sequence, 0);
+        assert_eq!(sequencer.stamp((), ms(1400)).missed, 0);
+        assert_eq!(sequencer.stamp((), ms(600)).sequence, 2);
+        // A sample read twice within one interval repeats the sequence number.
+        let repeated = sequencer.stamp((), ms(10));
+        assert_eq!((repeated.sequence, repeated.missed), (2, 0));
+        let late = sequencer.stamp((), ms(1600));
+        assert_eq!((late.sequence, late.missed), (4, 1));
+        assert_eq!((sequencer.received(), sequencer.missed()), (4, 1));
+        assert!((sequencer.loss() - 1.0 / 5.0).abs() < 1e-6);
+
+        let mut wrapping = Sequencer::with_interval(ms(10));
+        wrapping.next = Some(u32::MAX);
+        assert_eq!(wrapping.stamp((), ms(10)).sequence, u32::MAX);
+        assert_eq!(wrapping.stamp((), ms(20)).sequence, 1);
+
+        sequencer.reset();
+        assert_eq!(sequencer.stamp_at((), ms(7000)).sequence, 0);
+        assert_eq!(sequencer.stamp_at((), ms(8000)).sequence, 1);
+        let late = sequencer.stamp_at((), ms(11000));
+        assert_eq!((late.sequence, late.missed), (4, 2));
+        assert_eq!(sequencer.received(), 3);
+    }
+}
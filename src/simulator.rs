@@ -0,0 +1,875 @@
+//! Software model of a SEN5x sensor for host-side testing.
+//!
+//! [`Simulator`] implements the `embedded-hal` [`I2c`] trait and emulates the command set of the
+//! sensor: the idle/measurement state machine, command execution times, CRC-protected responses and
+//! the device status register. Together with [`SimDelay`] the whole driver can be exercised
+//! end-to-end without hardware. Faults such as NACKs, corrupted CRCs or a late data-ready flag can be
+//! injected to exercise error handling. The [`tcp`] module exposes a simulator over a socket so that an
+//! application running in another process can talk to it.
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::time::Instant;
+
+use embedded_hal::delay::DelayNs;
+use embedded_hal::i2c::{ErrorKind, ErrorType, I2c, NoAcknowledgeSource, Operation};
+
+use crate::clock::Clock;
+use crate::crc;
+use crate::model;
+use crate::types::Sen5xDataRaw;
+
+/// The default I²C address of the simulated sensor.
+pub const DEFAULT_ADDRESS: u8 = 0x69;
+
+/// I²C general call address.
+const GENERAL_CALL_ADDRESS: u8 = 0x00;
+
+/// Second byte of the I²C general call reset.
+const GENERAL_CALL_RESET: u8 = 0x06;
+
+/// Interval at which the simulated sensor produces a new sample [ms].
+const SAMPLE_INTERVAL_MS: u64 = 1000;
+
+/// Duration of a simulated fan cleaning cycle [ms].
+const FAN_CLEANING_MS: u64 = 10_000;
+
+/// Device status bit indicating that the fan cleaning is active.
+const STATUS_FAN_CLEANING: u32 = 1 << 19;
+
+/// Default tuning parameters of the VOC algorithm.
+const DEFAULT_VOC_TUNING: [u16; 6] = [100, 12, 12, 180, 50, 230];
+
+/// Default tuning parameters of the NOx algorithm.
+const DEFAULT_NOX_TUNING: [u16; 6] = [1, 12, 12, 720, 50, 230];
+
+/// Raw signal of the VOC sensor reported with the raw values [ticks].
+const RAW_VOC_TICKS: u16 = 27_500;
+
+/// Raw signal of the NOx sensor reported with the raw values [ticks].
+const RAW_NOX_TICKS: u16 = 16_000;
+
+/// Operating mode of the simulated sensor.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Mode {
+    /// Idle mode, the sensor accepts configuration commands.
+    Idle,
+    /// Periodic measurement is running.
+    Measuring,
+}
+
+/// Faults injected into the simulated sensor.
+#[derive(Debug, Default)]
+struct Faults {
+    /// Index of the transaction to be rejected with a NACK.
+    nack_transaction: Option<u64>,
+    /// Index of the word whose CRC is corrupted in the next response.
+    corrupt_crc_word: Option<usize>,
+    /// Additional time before a new sample is flagged as ready [ms].
+    data_ready_delay_ms: u64,
+}
+
+/// Where the simulated sensor takes the current time from.
+#[derive(Debug)]
+enum TimeSource {
+    /// Time advances only through [`SimDelay`].
+    Virtual(u64),
+    /// Time follows the host clock.
+    Wall(Instant),
+}
+
+/// A response prepared by the last command, ready to be read out.
+#[derive(Debug)]
+struct Response {
+    data: [u8; 48],
+    len: usize,
+}
+
+#[derive(Debug)]
+struct State {
+    address: u8,
+    time: TimeSource,
+    mode: Mode,
+    measuring_since: u64,
+    samples_read: u64,
+    fan_cleaning_until: Option<u64>,
+    status: u32,
+    /// Time until which the last command is still executing [ms].
+    busy_until: u64,
+    response: Option<Response>,
+    /// Number of transactions handled so far.
+    transactions: u64,
+    faults: Faults,
+    measurement: Sen5xDataRaw,
+    product_name: [u8; 32],
+    serial_number: [u8; 32],
+    firmware_version: u8,
+    /// Interval of the periodic fan cleaning [s].
+    auto_cleaning_interval: u32,
+    /// Tuning parameters of the VOC algorithm.
+    voc_tuning: [u16; 6],
+    /// Tuning parameters of the NOx algorithm.
+    nox_tuning: [u16; 6],
+    /// Temperature compensation parameters.
+    temperature_compensation: [u16; 3],
+    /// State of the VOC algorithm.
+    voc_state: [u16; 4],
+}
+
+impl State {
+    fn now(&self) -> u64 {
+        match &self.time {
+            TimeSource::Virtual(ms) => *ms,
+            TimeSource::Wall(start) => {
+                u64::try_from(start.elapsed().as_millis()).unwrap_or(u64::MAX)
+            }
+        }
+    }
+
+    fn samples_produced(&self) -> u64 {
+        match self.mode {
+            Mode::Idle => 0,
+            Mode::Measuring => {
+                let elapsed = self.now() - self.measuring_since;
+                elapsed.saturating_sub(self.faults.data_ready_delay_ms) / SAMPLE_INTERVAL_MS
+            }
+        }
+    }
+
+    fn fan_cleaning(&mut self) -> bool {
+        match self.fan_cleaning_until {
+            Some(until) if self.now() < until => true,
+            Some(_) => {
+                self.fan_cleaning_until = None;
+                false
+            }
+            None => false,
+        }
+    }
+
+    fn status(&mut self) -> u32 {
+        if self.fan_cleaning() {
+            self.status | STATUS_FAN_CLEANING
+        } else {
+            self.status & !STATUS_FAN_CLEANING
+        }
+    }
+
+    fn respond(&mut self, words: &[u16]) {
+        let mut data = [0u8; 48];
+        let len = model::encode_words(words, &mut data);
+        if let Some(word) = self.faults.corrupt_crc_word.take() {
+            if let Some(crc) = data.get_mut(word * 3 + 2) {
+                *crc ^= 0xFF;
+            }
+        }
+        self.response = Some(Response { data, len });
+    }
+
+    fn respond_ascii(&mut self, text: [u8; 32]) {
+        self.respond(&model::ascii_words(&text));
+    }
+
+    fn reset(&mut self) {
+        self.mode = Mode::Idle;
+        self.status = 0;
+        self.fan_cleaning_until = None;
+        self.samples_read = 0;
+        self.response = None;
+        self.voc_tuning = DEFAULT_VOC_TUNING;
+        self.nox_tuning = DEFAULT_NOX_TUNING;
+        self.temperature_compensation = [0; 3];
+        self.voc_state = [0; 4];
+    }
+
+    fn write(&mut self, bytes: &[u8]) -> Result<(), ErrorKind> {
+        let nack = ErrorKind::NoAcknowledge(NoAcknowledgeSource::Data);
+        let ([hi, lo], args) = match bytes {
+            [hi, lo, args @ ..] => ([*hi, *lo], args),
+            // Only the address is acknowledged, e.g. when probing the bus.
+            [] => return Ok(()),
+            _ => return Err(nack),
+        };
+        if !args.len().is_multiple_of(3) || args.len() > 48 {
+            return Err(nack);
+        }
+        let mut words = [0u16; 16];
+        for (word, chunk) in words.iter_mut().zip(args.chunks_exact(3)) {
+            if crc::crc(&chunk[..2]) != chunk[2] {
+                return Err(nack);
+            }
+            *word = u16::from_be_bytes([chunk[0], chunk[1]]);
+        }
+        let args = &words[..args.len() / 3];
+        // The sensor does not accept new commands while executing one.
+        if self.now() < self.busy_until {
+            return Err(nack);
+        }
+        self.response = None;
+        let measuring = self.mode == Mode::Measuring;
+
+        let execution_ms = match u16::from_be_bytes([hi, lo]) {
+            // Start measurement
+            0x0021 if !measuring => {
+                self.mode = Mode::Measuring;
+                self.measuring_since = self.now();
+                self.samples_read = 0;
+                50
+            }
+            // Stop measurement
+            0x0104 => {
+                self.mode = Mode::Idle;
+                self.fan_cleaning_until = None;
+                200
+            }
+            // Read data-ready flag
+            0x0202 => {
+                let ready = self.samples_produced() > self.samples_read && !self.fan_cleaning();
+                self.respond(&[u16::from(ready)]);
+                20
+            }
+            // Read measured values
+            0x03C4 if measuring => {
+                self.samples_read = self.samples_produced();
+                self.respond(&model::measurement_words(&self.measurement));
+                20
+            }
+            // Read measured raw values
+            0x03D2 if measuring && self.firmware_version >= 2 => {
+                let m = &self.measurement;
+                // The raw values read like compensated values with typical gas sensor signals.
+                self.respond(&[
+                    m.humidity.cast_unsigned(),
+                    m.temperature.cast_unsigned(),
+                    RAW_VOC_TICKS,
+                    RAW_NOX_TICKS,
+                ]);
+                20
+            }
+            // Start fan cleaning
+            0x5607 if measuring => {
+                self.fan_cleaning_until = Some(self.now() + FAN_CLEANING_MS);
+                20
+            }
+            // Read product name
+            0xD014 => {
+                self.respond_ascii(self.product_name);
+                20
+            }
+            // Read serial number
+            0xD033 => {
+                self.respond_ascii(self.serial_number);
+                20
+            }
+            // Read firmware version
+            0xD100 => {
+                self.respond(&[model::firmware_version_word(self.firmware_version)]);
+                20
+            }
+            // Read device status
+            0xD206 => {
+                let status = self.status();
+                self.respond(&model::device_status_words(status));
+                20
+            }
+            // Read and clear device status
+            0xD210 => {
+                let status = self.status();
+                self.status = 0;
+                self.respond(&model::device_status_words(status));
+                20
+            }
+            // Read auto cleaning interval
+            0x8004 if args.is_empty() => {
+                let interval = self.auto_cleaning_interval;
+                self.respond(&split(interval));
+                20
+            }
+            // Write auto cleaning interval
+            0x8004 if args.len() == 2 => {
+                self.auto_cleaning_interval = u32::from(args[0]) << 16 | u32::from(args[1]);
+                20
+            }
+            // Read temperature compensation parameters
+            0x60B2 if !measuring && args.is_empty() => {
+                let words = self.temperature_compensation;
+                self.respond(&words);
+                20
+            }
+            // Write temperature compensation parameters
+            0x60B2 if !measuring && args.len() == 3 => {
+                self.temperature_compensation.copy_from_slice(args);
+                20
+            }
+            // Read VOC algorithm tuning parameters
+            0x60D0 if !measuring && args.is_empty() => {
+                let words = self.voc_tuning;
+                self.respond(&words);
+                20
+            }
+            // Write VOC algorithm tuning parameters
+            0x60D0 if !measuring && args.len() == 6 => {
+                self.voc_tuning.copy_from_slice(args);
+                20
+            }
+            // Read NOx algorithm tuning parameters
+            0x60E1 if !measuring && args.is_empty() => {
+                let words = self.nox_tuning;
+                self.respond(&words);
+                20
+            }
+            // Write NOx algorithm tuning parameters
+            0x60E1 if !measuring && args.len() == 6 => {
+                self.nox_tuning.copy_from_slice(args);
+                20
+            }
+            // Read VOC algorithm state
+            0x6181 if args.is_empty() => {
+                let words = self.voc_state;
+                self.respond(&words);
+                20
+            }
+            // Write VOC algorithm state
+            0x6181 if !measuring && args.len() == 4 => {
+                self.voc_state.copy_from_slice(args);
+                20
+            }
+            // Device reset
+            0xD304 => {
+                self.reset();
+                100
+            }
+            _ => return Err(nack),
+        };
+        self.busy_until = self.now() + execution_ms;
+        Ok(())
+    }
+
+    fn read(&mut self, buf: &mut [u8]) -> Result<(), ErrorKind> {
+        let nack = ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address);
+        if self.now() < self.busy_until {
+            // Still executing, the response stays available for a later read.
+            return Err(nack);
+        }
+        match self.response.take() {
+            Some(response) if buf.len() <= response.len => {
+                buf.copy_from_slice(&response.data[..buf.len()]);
+                Ok(())
+            }
+            _ => Err(nack),
+        }
+    }
+}
+
+/// Split `value` into the two words sent by the sensor.
+fn split(value: u32) -> [u16; 2] {
+    let [b0, b1, b2, b3] = value.to_be_bytes();
+    [u16::from_be_bytes([b0, b1]), u16::from_be_bytes([b2, b3])]
+}
+
+/// Copy `text` into a null-padded 32 byte buffer.
+fn ascii(text: &str) -> [u8; 32] {
+    let mut buf = [0u8; 32];
+    let len = text.len().min(31);
+    buf[..len].copy_from_slice(&text.as_bytes()[..len]);
+    buf
+}
+
+/// Simulated SEN5x sensor.
+///
+/// Clones share the same sensor state, so a clone can be handed to the driver while the test keeps
+/// another one to inspect or modify the sensor.
+#[derive(Debug, Clone)]
+pub struct Simulator {
+    state: Rc<RefCell<State>>,
+}
+
+impl Default for Simulator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Simulator {
+    /// Create a simulated SEN55 whose clock is advanced by [`SimDelay`].
+    pub fn new() -> Self {
+        Self::with_time_source(TimeSource::Virtual(0))
+    }
+
+    /// Create a simulated SEN55 whose clock follows the host clock.
+    ///
+    /// Use this when the driver does not use the [`SimDelay`] of this simulator, e.g. when the
+    /// simulator is served over [`tcp`].
+    pub fn with_wall_clock() -> Self {
+        Self::with_time_source(TimeSource::Wall(Instant::now()))
+    }
+
+    fn with_time_source(time: TimeSource) -> Self {
+        Self {
+            state: Rc::new(RefCell::new(State {
+                address: DEFAULT_ADDRESS,
+                time,
+                mode: Mode::Idle,
+                measuring_since: 0,
+                samples_read: 0,
+                fan_cleaning_until: None,
+                status: 0,
+                busy_until: 0,
+                response: None,
+                transactions: 0,
+                faults: Faults::default(),
+                measurement: Sen5xDataRaw {
+                    pm1_0: 18,
+                    pm2_5: 22,
+                    pm4_0: 24,
+                    pm10_0: 26,
+                    humidity: 5514,
+                    temperature: 4481,
+                    voc_index: 360,
+                    nox_index: 10,
+                },
+                product_name: ascii("SEN55"),
+                serial_number: ascii("1A2B3C4D5E6F7A8B"),
+                firmware_version: 2,
+                auto_cleaning_interval: 604_800,
+                voc_tuning: DEFAULT_VOC_TUNING,
+                nox_tuning: DEFAULT_NOX_TUNING,
+                temperature_compensation: [0; 3],
+                voc_state: [0; 4],
+            })),
+        }
+    }
+
+    /// Set the I²C address the simulated sensor responds to.
+    pub fn set_address(&self, address: u8) {
+        self.state.borrow_mut().address = address;
+    }
+
+    /// Set the product name reported by the sensor.
+    pub fn set_product_name(&self, name: &str) {
+        self.state.borrow_mut().product_name = ascii(name);
+    }
+
+    /// Set the serial number reported by the sensor.
+    pub fn set_serial_number(&self, serial: &str) {
+        self.state.borrow_mut().serial_number = ascii(serial);
+    }
+
+    /// Set the firmware version reported by the sensor.
+    pub fn set_firmware_version(&self, version: u8) {
+        self.state.borrow_mut().firmware_version = version;
+    }
+
+    /// Set the raw values returned by subsequent measurements.
+    pub fn set_measurement(&self, measurement: Sen5xDataRaw) {
+        self.state.borrow_mut().measurement = measurement;
+    }
+
+    /// Number of I²C transactions handled so far.
+    pub fn transactions(&self) -> u64 {
+        self.state.borrow().transactions
+    }
+
+    /// Set bits in the device status register.
+    pub fn raise_status(&self, bits: u32) {
+        self.state.borrow_mut().status |= bits;
+    }
+
+    /// Reject the `n`-th transaction from now on (`0` being the next one) with a NACK.
+    pub fn nack_transaction(&self, n: u64) {
+        let mut state = self.state.borrow_mut();
+        state.faults.nack_transaction = Some(state.transactions + n);
+    }
+
+    /// Corrupt the CRC of word `word` in the next response.
+    pub fn corrupt_next_crc(&self, word: usize) {
+        self.state.borrow_mut().faults.corrupt_crc_word = Some(word);
+    }
+
+    /// Delay setting the data-ready flag by `ms` after each sample interval.
+    pub fn delay_data_ready(&self, ms: u64) {
+        self.state.borrow_mut().faults.data_ready_delay_ms = ms;
+    }
+
+    /// Remove all injected faults.
+    pub fn clear_faults(&self) {
+        self.state.borrow_mut().faults = Faults::default();
+    }
+
+    /// Current operating mode.
+    pub fn mode(&self) -> Mode {
+        self.state.borrow().mode
+    }
+
+    /// Current simulated time [ms].
+    pub fn now_ms(&self) -> u64 {
+        self.state.borrow().now()
+    }
+
+    /// Advance the virtual clock. Has no effect on a simulator using the wall clock.
+    pub fn advance_ms(&self, ms: u64) {
+        if let TimeSource::Virtual(now) = &mut self.state.borrow_mut().time {
+            *now += ms;
+        }
+    }
+
+    /// A delay provider advancing the clock of this simulator instead of sleeping.
+    pub fn delay(&self) -> SimDelay {
+        SimDelay {
+            simulator: self.clone(),
+        }
+    }
+}
+
+/// The simulated time, so time-based code can run against the virtual clock.
+impl Clock for Simulator {
+    fn now(&self) -> core::time::Duration {
+        core::time::Duration::from_millis(self.now_ms())
+    }
+}
+
+impl ErrorType for Simulator {
+    type Error = ErrorKind;
+}
+
+impl I2c for Simulator {
+    fn transaction(
+        &mut self,
+        address: u8,
+        operations: &mut [Operation<'_>],
+    ) -> Result<(), Self::Error> {
+        let mut state = self.state.borrow_mut();
+        let index = state.transactions;
+        state.transactions += 1;
+        if state.faults.nack_transaction == Some(index) {
+            state.faults.nack_transaction = None;
+            return Err(ErrorKind::NoAcknowledge(NoAcknowledgeSource::Data));
+        }
+        if let (GENERAL_CALL_ADDRESS, [Operation::Write([GENERAL_CALL_RESET])]) =
+            (address, &*operations)
+        {
+            state.reset();
+            state.busy_until = state.now() + 100;
+            return Ok(());
+        }
+        if address != state.address {
+            return Err(ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address));
+        }
+        for operation in operations {
+            match operation {
+                Operation::Write(bytes) => state.write(bytes)?,
+                Operation::Read(buf) => state.read(buf)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "async")]
+impl embedded_hal_async::i2c::I2c for Simulator {
+    async fn transaction(
+        &mut self,
+        address: u8,
+        operations: &mut [Operation<'_>],
+    ) -> Result<(), Self::Error> {
+        I2c::transaction(self, address, operations)
+    }
+}
+
+/// Delay provider advancing the virtual clock of a [`Simulator`].
+#[derive(Debug, Clone)]
+pub struct SimDelay {
+    simulator: Simulator,
+}
+
+impl DelayNs for SimDelay {
+    fn delay_ns(&mut self, ns: u32) {
+        self.simulator.advance_ms(u64::from(ns.div_ceil(1_000_000)));
+    }
+
+    fn delay_ms(&mut self, ms: u32) {
+        self.simulator.advance_ms(u64::from(ms));
+    }
+}
+
+#[cfg(feature = "async")]
+impl embedded_hal_async::delay::DelayNs for SimDelay {
+    async fn delay_ns(&mut self, ns: u32) {
+        DelayNs::delay_ns(self, ns)
+    }
+
+    async fn delay_ms(&mut self, ms: u32) {
+        DelayNs::delay_ms(self, ms)
+    }
+}
+
+/// Serve a [`Simulator`] over TCP.
+///
+/// Every request starts with an operation byte (`0` = write, `1` = read), the 7-bit address and a
+/// big-endian `u16` length. Writes are followed by the payload. The server answers with a status
+/// byte (`0` = ok, `1` = address NACK, `2` = data NACK, `3` = other error) followed by the read
+/// data for successful reads.
+pub mod tcp {
+    use std::io::{self, Read, Write};
+    use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+
+    use embedded_hal::i2c::{ErrorKind, ErrorType, I2c, NoAcknowledgeSource, Operation};
+
+    use super::Simulator;
+
+    const OP_WRITE: u8 = 0;
+    const OP_READ: u8 = 1;
+
+    fn status(result: Result<(), ErrorKind>) -> u8 {
+        match result {
+            Ok(()) => 0,
+            Err(ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address)) => 1,
+            Err(ErrorKind::NoAcknowledge(_)) => 2,
+            Err(_) => 3,
+        }
+    }
+
+    fn error(status: u8) -> Result<(), ErrorKind> {
+        match status {
+            0 => Ok(()),
+            1 => Err(ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address)),
+            2 => Err(ErrorKind::NoAcknowledge(NoAcknowledgeSource::Data)),
+            _ => Err(ErrorKind::Other),
+        }
+    }
+
+    /// Handle requests of a single client until it disconnects.
+    pub fn serve_connection(mut stream: TcpStream, simulator: &mut Simulator) -> io::Result<()> {
+        let mut header = [0u8; 4];
+        loop {
+            match stream.read_exact(&mut header) {
+                Ok(()) => {}
+                Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(()),
+                Err(e) => return Err(e),
+            }
+            let [op, address, hi, lo] = header;
+            let mut data = vec![0u8; usize::from(u16::from_be_bytes([hi, lo]))];
+            match op {
+                OP_WRITE => {
+                    stream.read_exact(&mut data)?;
+                    let result = simulator.write(address, &data);
+                    stream.write_all(&[status(result)])?;
+                }
+                OP_READ => {
+                    let result = simulator.read(address, &mut data);
+                    stream.write_all(&[status(result)])?;
+                    if result.is_ok() {
+                        stream.write_all(&data)?;
+                    }
+                }
+                _ => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "unknown operation",
+                    ))
+                }
+            }
+        }
+    }
+
+    /// Accept clients one after another and serve them from the same simulator.
+    pub fn serve(listener: TcpListener, mut simulator: Simulator) -> io::Result<()> {
+        for stream in listener.incoming() {
+            serve_connection(stream?, &mut simulator)?;
+        }
+        Ok(())
+    }
+
+    /// I²C bus talking to a simulator served over TCP.
+    #[derive(Debug)]
+    pub struct TcpI2c {
+        stream: TcpStream,
+    }
+
+    impl TcpI2c {
+        /// Connect to a simulator served with [`serve`].
+        pub fn connect<A: ToSocketAddrs>(addr: A) -> io::Result<Self> {
+            let stream = TcpStream::connect(addr)?;
+            stream.set_nodelay(true)?;
+            Ok(Self { stream })
+        }
+
+        fn request(&mut self, op: u8, address: u8, len: usize) -> io::Result<()> {
+            let len = u16::try_from(len)
+                .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "transfer too long"))?;
+            let [hi, lo] = len.to_be_bytes();
+            self.stream.write_all(&[op, address, hi, lo])
+        }
+
+        fn status(&mut self) -> io::Result<u8> {
+            let mut status = [0u8];
+            self.stream.read_exact(&mut status)?;
+            Ok(status[0])
+        }
+
+        fn write_io(&mut self, address: u8, bytes: &[u8]) -> io::Result<u8> {
+            self.request(OP_WRITE, address, bytes.len())?;
+            self.stream.write_all(bytes)?;
+            self.status()
+        }
+
+        fn read_io(&mut self, address: u8, buf: &mut [u8]) -> io::Result<u8> {
+            self.request(OP_READ, address, buf.len())?;
+            let status = self.status()?;
+            if status == 0 {
+                self.stream.read_exact(buf)?;
+            }
+            Ok(status)
+        }
+    }
+
+    impl ErrorType for TcpI2c {
+        type Error = ErrorKind;
+    }
+
+    impl I2c for TcpI2c {
+        fn transaction(
+            &mut self,
+            address: u8,
+            operations: &mut [Operation<'_>],
+        ) -> Result<(), Self::Error> {
+            for operation in operations {
+                let status = match operation {
+                    Operation::Write(bytes) => self.write_io(address, bytes),
+                    Operation::Read(buf) => self.read_io(address, buf),
+                };
+                error(status.map_err(|_| ErrorKind::Other)?)?;
+            }
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Sen5x;
+
+    /// Run a full measurement cycle against the simulator
+    #[test]
+    fn test_measurement_cycle() {
+        let sim = Simulator::new();
+        let mut sensor = Sen5x::new(sim.clone(), sim.delay());
+
+        sensor.start_measurement().unwrap();
+        assert_eq!(sim.mode(), Mode::Measuring);
+        assert!(!sensor.data_ready_status().unwrap());
+
+        sim.advance_ms(SAMPLE_INTERVAL_MS);
+        assert!(sensor.data_ready_status().unwrap());
+        let data = sensor.measurement().unwrap();
+        assert_eq!(data.pm.pm2_5, 2.2_f32);
+        assert_eq!(data.env.unwrap().humidity, 55.14_f32);
+        assert!(!sensor.data_ready_status().unwrap());
+    }
+
+    /// Test that responses are only available after the execution time
+    #[test]
+    fn test_execution_time() {
+        let mut sim = Simulator::new();
+        let mut buf = [0u8; 3];
+        sim.write(DEFAULT_ADDRESS, &[0xD1, 0x00]).unwrap();
+        assert!(sim.read(DEFAULT_ADDRESS, &mut buf).is_err());
+        sim.advance_ms(20);
+        sim.read(DEFAULT_ADDRESS, &mut buf).unwrap();
+        assert_eq!(buf, [0x02, 0x00, crc::crc(&[0x02, 0x00])]);
+    }
+
+    /// Test that commands are rejected in the wrong mode or at the wrong address
+    #[test]
+    fn test_rejected_commands() {
+        let mut sim = Simulator::new();
+        // Read measured values is not available in idle mode.
+        assert!(sim.write(DEFAULT_ADDRESS, &[0x03, 0xC4]).is_err());
+        assert_eq!(
+            sim.write(0x42, &[0xD1, 0x00]),
+            Err(ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address))
+        );
+    }
+
+    /// Test the device status register and fan cleaning bit
+    #[test]
+    fn test_device_status() {
+        let mut sim = Simulator::new();
+        let mut buf = [0u8; 6];
+        sim.raise_status(1 << 5);
+        sim.write(DEFAULT_ADDRESS, &[0x00, 0x21]).unwrap();
+        sim.advance_ms(50);
+        sim.write(DEFAULT_ADDRESS, &[0x56, 0x07]).unwrap();
+        sim.advance_ms(20);
+        sim.write(DEFAULT_ADDRESS, &[0xD2, 0x10]).unwrap();
+        sim.advance_ms(20);
+        sim.read(DEFAULT_ADDRESS, &mut buf).unwrap();
+        assert_eq!([buf[0], buf[1], buf[3], buf[4]], [0x00, 0x08, 0x00, 0x20]);
+
+        sim.advance_ms(FAN_CLEANING_MS);
+        sim.write(DEFAULT_ADDRESS, &[0xD2, 0x06]).unwrap();
+        sim.advance_ms(20);
+        sim.read(DEFAULT_ADDRESS, &mut buf).unwrap();
+        assert_eq!([buf[0], buf[1], buf[3], buf[4]], [0; 4]);
+    }
+
+    /// Test NACK injection on a transaction
+    #[test]
+    fn test_nack_injection() {
+        let sim = Simulator::new();
+        let mut sensor = Sen5x::new(sim.clone(), sim.delay());
+        sim.nack_transaction(0);
+        assert!(matches!(
+            sensor.start_measurement(),
+            Err(crate::Error::Nack(_))
+        ));
+        sensor.start_measurement().unwrap();
+    }
+
+    /// Test CRC corruption of a response
+    #[test]
+    fn test_crc_corruption() {
+        let sim = Simulator::new();
+        let mut sensor = Sen5x::new(sim.clone(), sim.delay());
+        sensor.start_measurement().unwrap();
+        sim.advance_ms(SAMPLE_INTERVAL_MS);
+        sim.corrupt_next_crc(1);
+        assert_eq!(sensor.measurement(), Err(crate::Error::Crc { word: 1 }));
+    }
+
+    /// Test delaying the data-ready flag
+    #[test]
+    fn test_data_ready_delay() {
+        let sim = Simulator::new();
+        let mut sensor = Sen5x::new(sim.clone(), sim.delay());
+        sim.delay_data_ready(500);
+        sensor.start_measurement().unwrap();
+        sim.advance_ms(SAMPLE_INTERVAL_MS);
+        assert!(!sensor.data_ready_status().unwrap());
+        sim.advance_ms(500);
+        assert!(sensor.data_ready_status().unwrap());
+    }
+
+    /// Talk to the simulator through the TCP bridge
+    #[test]
+    fn test_tcp() {
+        use std::net::TcpListener;
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            tcp::serve_connection(stream, &mut Simulator::with_wall_clock()).unwrap();
+        });
+
+        let mut i2c = tcp::TcpI2c::connect(addr).unwrap();
+        let mut buf = [0u8; 3];
+        i2c.write(DEFAULT_ADDRESS, &[0xD1, 0x00]).unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        i2c.read(DEFAULT_ADDRESS, &mut buf).unwrap();
+        assert_eq!(buf[0], 0x02);
+        assert!(i2c.write(0x42, &[0xD1, 0x00]).is_err());
+        drop(i2c);
+        server.join().unwrap();
+    }
+}
@@ -0,0 +1,199 @@
+//! Smoothing of measurements for on-device displays.
+//!
+//! The [one euro filter](https://gery.casiez.net/1euro/) of [`OneEuro`] smooths a noisy value
+//! strongly while it is steady and follows it with little lag when it changes quickly, so
+//! displayed values are stable without trailing sudden changes like a moving average.
+//! [`Smoother`] filters every value of a measurement with parameters tuned for the channel.
+//!
+//! ```
+//! use core::time::Duration;
+//! use sen5x_rs::smoothing::Smoother;
+//! # let data = sen5x_rs::types::Sen5xData::sample();
+//!
+//! let mut smoother = Smoother::new();
+//! let shown = smoother.filter(&data, Duration::from_secs(1));
+//! assert_eq!(shown, data);
+//! ```
+use core::f32::consts::PI;
+use core::time::Duration;
+
+use crate::fields::FieldId;
+use crate::types::Sen5xData;
+
+/// A one euro filter of a single value.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct OneEuro {
+    min_cutoff: f32,
+    beta: f32,
+    d_cutoff: f32,
+    /// The last filtered value and its rate of change.
+    state: Option<(f32, f32)>,
+}
+
+impl OneEuro {
+    /// Parameters for the mass concentrations, following increases within a few samples.
+    pub const PM: Self = Self::new(0.1, 0.02, 1.0);
+    /// Parameters for the VOC and NOx index, which change slowly.
+    pub const GAS_INDEX: Self = Self::new(0.05, 0.005, 1.0);
+    /// Parameters for the humidity and temperature, which are steady already.
+    pub const AMBIENT: Self = Self::new(0.2, 0.1, 1.0);
+
+    /// A filter with the cutoff frequency `min_cutoff` [Hz] of a steady value, increasing by
+    /// `beta` times the rate of change of the value, estimated with the cutoff `d_cutoff` [Hz].
+    ///
+    /// Lower `min_cutoff` reduces the jitter, higher `beta` reduces the lag.
+    pub const fn new(min_cutoff: f32, beta: f32, d_cutoff: f32) -> Self {
+        Self {
+            min_cutoff,
+            beta,
+            d_cutoff,
+            state: None,
+        }
+    }
+
+    /// Filter `value` measured `dt` after the previous one.
+    ///
+    /// The first value after creating or resetting the filter is returned unchanged.
+    pub fn filter(&mut self, value: f32, dt: Duration) -> f32 {
+        let dt = dt.as_secs_f32();
+        match self.state {
+            Some((prev, prev_rate)) if dt > 0.0 => {
+                let rate = (value - prev) / dt;
+                let rate = prev_rate + alpha(self.d_cutoff, dt) * (rate - prev_rate);
+                let cutoff = self.min_cutoff + self.beta * rate.abs();
+                let filtered = prev + alpha(cutoff, dt) * (value - prev);
+                self.state = Some((filtered, rate));
+                filtered
+            }
+            Some((prev, _)) => prev,
+            None => {
+                self.state = Some((value, 0.0));
+                value
+            }
+        }
+    }
+
+    /// Forget the previous values, e.g. when the value became unavailable.
+    pub fn reset(&mut self) {
+        self.state = None;
+    }
+}
+
+/// Smoothing factor of a first order low-pass filter with the `cutoff` [Hz] sampled every `dt`.
+fn alpha(cutoff: f32, dt: f32) -> f32 {
+    let tau = 1.0 / (2.0 * PI * cutoff);
+    1.0 / (1.0 + tau / dt)
+}
+
+/// Smooths every value of a measurement.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Smoother([OneEuro; 8]);
+
+impl Default for Smoother {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Smoother {
+    /// Filters with the parameters tuned for each channel.
+    pub const fn new() -> Self {
+        Self([
+            OneEuro::PM,
+            OneEuro::PM,
+            OneEuro::PM,
+            OneEuro::PM,
+            OneEuro::AMBIENT,
+            OneEuro::AMBIENT,
+            OneEuro::GAS_INDEX,
+            OneEuro::GAS_INDEX,
+        ])
+    }
+
+    /// Use `filter` for the value `id`.
+    pub const fn with_filter(mut self, id: FieldId, filter: OneEuro) -> Self {
+        self.0[id as usize] = filter;
+        self
+    }
+
+    /// Filter the values of `data` measured `dt` after the previous measurement.
+    ///
+    /// Values which were not measured stay `None` and restart their filter.
+    pub fn filter(&mut self, data: &Sen5xData, dt: Duration) -> Sen5xData {
+        let mut filter = |id: FieldId, value: f32| self.0[id as usize].filter(value, dt);
+        let mut smoothed = *data;
+        smoothed.pm.pm1_0 = filter(FieldId::Pm1_0, data.pm.pm1_0);
+        smoothed.pm.pm2_5 = filter(FieldId::Pm2_5, data.pm.pm2_5);
+        smoothed.pm.pm4_0 = filter(FieldId::Pm4_0, data.pm.pm4_0);
+        smoothed.pm.pm10_0 = filter(FieldId::Pm10_0, data.pm.pm10_0);
+        if let Some(env) = &mut smoothed.env {
+            env.humidity = filter(FieldId::Humidity, env.humidity);
+            env.temperature = filter(FieldId::Temperature, env.temperature);
+        }
+        if let Some(gas) = &mut smoothed.gas {
+            gas.voc_index = filter(FieldId::VocIndex, gas.voc_index);
+            if let Some(nox_index) = &mut gas.nox_index {
+                *nox_index = filter(FieldId::NoxIndex, *nox_index);
+            }
+        }
+        for id in FieldId::ALL {
+            if data.value(id).is_none() {
+                self.0[id as usize].reset();
+            }
+        }
+        smoothed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECOND: Duration = Duration::from_secs(1);
+
+    /// Test that steady noise is damped and steps are followed
+    #[test]
+    fn test_one_euro() {
+        let mut filter = OneEuro::PM;
+        assert_eq!(filter.filter(10.0, SECOND), 10.0);
+        let mut max = 10.0f32;
+        for i in 0..20 {
+            let noise = if i % 2 == 0 { 1.0 } else { -1.0 };
+            max = max.max(filter.filter(10.0 + noise, SECOND));
+        }
+        assert!(max < 10.5);
+
+        let mut steps = 0;
+        while filter.filter(50.0, SECOND) < 45.0 {
+            steps += 1;
+        }
+        assert!(steps < 5);
+        assert_eq!(filter.filter(30.0, Duration::ZERO), filter.state.unwrap().0);
+
+        filter.reset();
+        assert_eq!(filter.filter(30.0, SECOND), 30.0);
+    }
+
+    /// Test that unavailable values restart their filter
+    #[test]
+    fn test_smoother() {
+        let data = crate::test_vectors::MEASUREMENTS[0].data;
+        let mut smoother = Smoother::new();
+        assert_eq!(smoother.filter(&data, SECOND), data);
+
+        let mut next = data;
+        next.pm.pm2_5 += 1.0;
+        next.gas = None;
+        let smoothed = smoother.filter(&next, SECOND);
+        assert!(smoothed.pm.pm2_5 > data.pm.pm2_5 && smoothed.pm.pm2_5 < next.pm.pm2_5);
+        assert_eq!(smoothed.gas, None);
+
+        let mut last = data;
+        last.gas = data.gas.map(|mut gas| {
+            gas.voc_index = 100.0;
+            gas
+        });
+        let smoothed = smoother.filter(&last, SECOND);
+        assert_eq!(smoothed.gas.map(|gas| gas.voc_index), Some(100.0));
+    }
+}
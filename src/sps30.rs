@@ -0,0 +1,286 @@
+//! Driver for the SPS30 particulate matter sensor.
+//!
+//! The SPS30 uses the same I²C conventions as the SEN5x and reports the same mass concentrations,
+//! so code migrating between the two can keep working with [`ParticulateMatter`]. It additionally
+//! reports number concentrations and the typical particle size.
+//!
+//! The measurement is started in the unsigned integer output format, in which the mass
+//! concentrations have a resolution of 1 µg/m³.
+//!
+//! [Datasheet](https://sensirion.com/media/documents/8600FF88/616542B5/Sensirion_PM_Sensors_Datasheet_SPS30.pdf)
+use embedded_hal::{delay::DelayNs, i2c::I2c};
+
+use crate::parse;
+use crate::protocol::{Frame, Interface};
+use crate::types::{DeviceStatus, ParticulateMatter, SerialNumber};
+use crate::Error;
+
+/// The I²C address of the SPS30.
+pub const ADDRESS: u8 = 0x69;
+
+/// Argument of `Start Measurement` selecting the unsigned integer output format.
+const FORMAT_U16: u16 = 0x0500;
+
+const START_MEASUREMENT: Frame<1, 0> = Frame::new(0x0010, 20);
+const STOP_MEASUREMENT: Frame<0, 0> = Frame::new(0x0104, 20);
+const READ_DATA_READY: Frame<0, 1> = Frame::new(0x0202, 0);
+const READ_MEASUREMENT: Frame<0, 10> = Frame::new(0x0300, 0);
+const SLEEP: Frame<0, 0> = Frame::new(0x1001, 5);
+const WAKE_UP: Frame<0, 0> = Frame::new(0x1103, 5);
+const START_FAN_CLEANING: Frame<0, 0> = Frame::new(0x5607, 0);
+const READ_AUTO_CLEANING_INTERVAL: Frame<0, 2> = Frame::new(0x8004, 0);
+const WRITE_AUTO_CLEANING_INTERVAL: Frame<2, 0> = Frame::new(0x8004, 20);
+const READ_PRODUCT_TYPE: Frame<0, 4> = Frame::new(0xD002, 0);
+const READ_SERIAL_NUMBER: Frame<0, 16> = Frame::new(0xD033, 0);
+const READ_VERSION: Frame<0, 1> = Frame::new(0xD100, 0);
+const READ_DEVICE_STATUS: Frame<0, 2> = Frame::new(0xD206, 0);
+const CLEAR_DEVICE_STATUS: Frame<0, 0> = Frame::new(0xD210, 5);
+const RESET: Frame<0, 0> = Frame::new(0xD304, 100);
+
+/// Number concentrations of particles [#/cm³].
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct NumberConcentrations {
+    /// Number Concentration PM0.5
+    pub nc0_5: f32,
+    /// Number Concentration PM1.0
+    pub nc1_0: f32,
+    /// Number Concentration PM2.5
+    pub nc2_5: f32,
+    /// Number Concentration PM4.0
+    pub nc4_0: f32,
+    /// Number Concentration PM10
+    pub nc10_0: f32,
+}
+
+/// Values of an SPS30 measurement.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Sps30Data {
+    /// Mass concentrations.
+    pub pm: ParticulateMatter,
+    /// Number concentrations.
+    pub number: NumberConcentrations,
+    /// Typical particle size [µm]
+    pub typical_particle_size: f32,
+}
+
+impl Sps30Data {
+    /// Convert the words of a measurement in the unsigned integer format.
+    fn from_words(
+        [pm1_0, pm2_5, pm4_0, pm10_0, nc0_5, nc1_0, nc2_5, nc4_0, nc10_0, size]: [u16; 10],
+    ) -> Self {
+        Self {
+            pm: ParticulateMatter {
+                pm1_0: pm1_0.into(),
+                pm2_5: pm2_5.into(),
+                pm4_0: pm4_0.into(),
+                pm10_0: pm10_0.into(),
+            },
+            number: NumberConcentrations {
+                nc0_5: nc0_5.into(),
+                nc1_0: nc1_0.into(),
+                nc2_5: nc2_5.into(),
+                nc4_0: nc4_0.into(),
+                nc10_0: nc10_0.into(),
+            },
+            // Reported in nm.
+            typical_particle_size: f32::from(size) / 1000.0,
+        }
+    }
+}
+
+/// SPS30 driver.
+#[derive(Debug)]
+pub struct Sps30<I2C, D> {
+    bus: Interface<I2C, D>,
+}
+
+impl<I2C, D, E> Sps30<I2C, D>
+where
+    I2C: I2c<Error = E>,
+    D: DelayNs,
+{
+    /// Create a new instance.
+    pub fn new(i2c: I2C, delay: D) -> Self {
+        Self {
+            bus: Interface::new(i2c, delay, ADDRESS),
+        }
+    }
+
+    /// Start the measurement, signal update interval is 1 second.
+    pub fn start_measurement(&mut self) -> Result<(), Error<E>> {
+        self.bus.execute(START_MEASUREMENT, [FORMAT_U16])?;
+        Ok(())
+    }
+
+    /// Stop the measurement and return to idle mode.
+    pub fn stop_measurement(&mut self) -> Result<(), Error<E>> {
+        self.bus.execute(STOP_MEASUREMENT, [])?;
+        Ok(())
+    }
+
+    /// Check whether new measurement data is available for read-out.
+    pub fn data_ready_status(&mut self) -> Result<bool, Error<E>> {
+        self.bus
+            .execute(READ_DATA_READY, [])
+            .map(parse::data_ready_words)
+    }
+
+    /// Read the measured values.
+    pub fn measurement(&mut self) -> Result<Sps30Data, Error<E>> {
+        self.bus
+            .execute(READ_MEASUREMENT, [])
+            .map(Sps30Data::from_words)
+    }
+
+    /// Enter the sleep mode. Only possible in idle mode.
+    pub fn sleep(&mut self) -> Result<(), Error<E>> {
+        self.bus.execute(SLEEP, [])?;
+        Ok(())
+    }
+
+    /// Leave the sleep mode.
+    pub fn wake_up(&mut self) -> Result<(), Error<E>> {
+        // The first command only activates the interface and is not acknowledged.
+        let _ = self.bus.execute(WAKE_UP, []);
+        self.bus.execute(WAKE_UP, [])?;
+        Ok(())
+    }
+
+    /// Start the fan cleaning, which takes 10 seconds. Only possible while measuring.
+    pub fn start_fan_cleaning(&mut self) -> Result<(), Error<E>> {
+        self.bus.execute(START_FAN_CLEANING, [])?;
+        Ok(())
+    }
+
+    /// Read the interval of the periodic fan cleaning [s].
+    pub fn auto_cleaning_interval(&mut self) -> Result<u32, Error<E>> {
+        let [hi, lo] = self.bus.execute(READ_AUTO_CLEANING_INTERVAL, [])?;
+        Ok(u32::from(hi) << 16 | u32::from(lo))
+    }
+
+    /// Set the interval of the periodic fan cleaning [s], `0` disables it.
+    pub fn set_auto_cleaning_interval(&mut self, seconds: u32) -> Result<(), Error<E>> {
+        let [hi1, hi0, lo1, lo0] = seconds.to_be_bytes();
+        self.bus.execute(
+            WRITE_AUTO_CLEANING_INTERVAL,
+            [
+                u16::from_be_bytes([hi1, hi0]),
+                u16::from_be_bytes([lo1, lo0]),
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Read the product type, `00080000` for the SPS30.
+    pub fn product_type(&mut self) -> Result<[u8; 8], Error<E>> {
+        let words = self.bus.execute(READ_PRODUCT_TYPE, [])?;
+        let mut bytes = [0u8; 8];
+        for (pair, word) in bytes.chunks_exact_mut(2).zip(words) {
+            pair.copy_from_slice(&word.to_be_bytes());
+        }
+        Ok(bytes)
+    }
+
+    /// Read the serial number.
+    pub fn serial_number(&mut self) -> Result<SerialNumber, Error<E>> {
+        self.bus
+            .execute(READ_SERIAL_NUMBER, [])
+            .map(parse::ascii_words)
+            .map(SerialNumber::from_bytes)
+    }
+
+    /// Read the major and minor firmware version.
+    pub fn firmware_version(&mut self) -> Result<(u8, u8), Error<E>> {
+        let [version] = self.bus.execute(READ_VERSION, [])?;
+        let [major, minor] = version.to_be_bytes();
+        Ok((major, minor))
+    }
+
+    /// Read the device status register, which uses the flags of the SEN5x for fan and laser.
+    pub fn device_status(&mut self) -> Result<DeviceStatus, Error<E>> {
+        let [hi, lo] = self.bus.execute(READ_DEVICE_STATUS, [])?;
+        Ok(DeviceStatus::from_bits(u32::from(hi) << 16 | u32::from(lo)))
+    }
+
+    /// Clear the device status register.
+    pub fn clear_device_status(&mut self) -> Result<(), Error<E>> {
+        self.bus.execute(CLEAR_DEVICE_STATUS, [])?;
+        Ok(())
+    }
+
+    /// Reset the sensor, equivalent to a power cycle.
+    pub fn reset(&mut self) -> Result<(), Error<E>> {
+        self.bus.execute(RESET, [])?;
+        Ok(())
+    }
+
+    /// Release the bus and the delay provider.
+    pub fn release(self) -> (I2C, D) {
+        self.bus.release()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use embedded_hal::i2c::ErrorKind;
+    use embedded_hal_mock as hal;
+
+    use self::hal::eh1::delay::NoopDelay as DelayMock;
+    use self::hal::eh1::i2c::{Mock as I2cMock, Transaction};
+    use super::*;
+    use crate::test_vectors::DATA_READY;
+
+    /// Test starting and reading a measurement in the integer format
+    #[test]
+    fn test_measurement() {
+        let expectations = [
+            Transaction::write(ADDRESS, vec![0x00, 0x10, 0x05, 0x00, 0xF6]),
+            Transaction::write_read(ADDRESS, vec![0x02, 0x02], DATA_READY.to_vec()),
+            Transaction::write_read(
+                ADDRESS,
+                vec![0x03, 0x00],
+                vec![
+                    0x00, 0x02, 0xE3, 0x00, 0x03, 0xD2, 0x00, 0x03, 0xD2, 0x00, 0x04, 0x45, 0x00,
+                    0x0A, 0x5A, 0x00, 0x0C, 0xFC, 0x00, 0x0D, 0xCD, 0x00, 0x0D, 0xCD, 0x00, 0x0D,
+                    0xCD, 0x02, 0x26, 0x78,
+                ],
+            ),
+        ];
+        let mut mock = I2cMock::new(&expectations);
+        let mut sensor = Sps30::new(mock.clone(), DelayMock);
+        sensor.start_measurement().unwrap();
+        assert!(sensor.data_ready_status().unwrap());
+        let data = sensor.measurement().unwrap();
+        assert_eq!(data.pm.pm2_5, 3.0);
+        assert_eq!(data.number.nc0_5, 10.0);
+        assert_eq!(data.number.nc10_0, 13.0);
+        assert_eq!(data.typical_particle_size, 0.55);
+        mock.done();
+    }
+
+    /// Test waking up, where the first command is not acknowledged
+    #[test]
+    fn test_wake_up() {
+        let expectations = [
+            Transaction::write(ADDRESS, vec![0x11, 0x03]).with_error(ErrorKind::Other),
+            Transaction::write(ADDRESS, vec![0x11, 0x03]),
+            Transaction::write(
+                ADDRESS,
+                vec![0x80, 0x04, 0x00, 0x09, 0x09, 0x3A, 0x80, 0xA7],
+            ),
+            Transaction::write_read(
+                ADDRESS,
+                vec![0xD0, 0x02],
+                vec![
+                    0x30, 0x30, 0xF6, 0x30, 0x38, 0x4F, 0x30, 0x30, 0xF6, 0x30, 0x30, 0xF6,
+                ],
+            ),
+        ];
+        let mut mock = I2cMock::new(&expectations);
+        let mut sensor = Sps30::new(mock.clone(), DelayMock);
+        sensor.wake_up().unwrap();
+        sensor.set_auto_cleaning_interval(604_800).unwrap();
+        assert_eq!(&sensor.product_type().unwrap(), b"00080000");
+        mock.done();
+    }
+}
@@ -0,0 +1,111 @@
+//! Storage of the driver in `static` memory, e.g. for RTIC resources and Embassy tasks.
+//!
+//! Tasks take the driver by `&'static mut`, so its type appears in the signature of the task.
+//! [`StaticSen5x`] names it with the bus type only, using the [`NoDelay`] of drivers advanced with
+//! [`Sen5x::poll_measurement`], and [`Sen5x::new_in`] creates the driver in place in
+//! uninitialized memory, such as the `&'static mut MaybeUninit` returned by
+//! `StaticCell::uninit`. Such a driver reads measurements only with `poll_measurement`, its
+//! blocking reads return `Error::Unsupported`. Tasks using the blocking operations store a driver
+//! with a delay instead, created with [`Sen5x::new_with_delay_in`] and named
+//! `StaticSen5x<I2C, D>`.
+//!
+//! ```
+//! use core::mem::MaybeUninit;
+//! # use embedded_hal_mock::eh1::i2c::Mock;
+//! use embedded_hal::i2c::I2c;
+//! use sen5x_rs::statics::StaticSen5x;
+//! use sen5x_rs::Sen5x;
+//!
+//! fn task<I2C: I2c>(sensor: StaticSen5x<I2C>) {
+//!     // Call `sensor.poll_measurement()` every 20 ms.
+//! }
+//!
+//! # let mut i2c = Mock::new(&[]);
+//! let slot = Box::leak(Box::new(MaybeUninit::uninit()));
+//! let sensor = Sen5x::new_in(slot, i2c.clone());
+//! task(sensor);
+//! # i2c.done();
+//! ```
+use core::mem::MaybeUninit;
+
+use embedded_hal::{delay::DelayNs, i2c::I2c};
+
+#[cfg(feature = "async")]
+use crate::asynch::Sen5xAsync;
+use crate::{NoDelay, Sen5x};
+
+/// A driver in `static` memory, as taken by a task.
+pub type StaticSen5x<I2C, D = NoDelay> = &'static mut Sen5x<I2C, D>;
+
+/// An asynchronous driver in `static` memory, as taken by a task.
+#[cfg(feature = "async")]
+pub type StaticSen5xAsync<I2C, D> = &'static mut Sen5xAsync<I2C, D>;
+
+impl<I2C, E> Sen5x<I2C>
+where
+    I2C: I2c<Error = E>,
+{
+    /// Create a new instance without delay in `slot`, like [`Sen5x::without_delay`].
+    pub fn new_in(slot: &mut MaybeUninit<Self>, i2c: I2C) -> &mut Self {
+        slot.write(Self::without_delay(i2c))
+    }
+}
+
+impl<I2C, D, E> Sen5x<I2C, D>
+where
+    I2C: I2c<Error = E>,
+    D: DelayNs,
+{
+    /// Create a new instance using the default I2C address in `slot`.
+    pub fn new_with_delay_in(slot: &mut MaybeUninit<Self>, i2c: I2C, delay: D) -> &mut Self {
+        slot.write(Self::new(i2c, delay))
+    }
+}
+
+#[cfg(feature = "async")]
+impl<I2C, D, E> Sen5xAsync<I2C, D>
+where
+    I2C: embedded_hal_async::i2c::I2c<Error = E>,
+    D: embedded_hal_async::delay::DelayNs,
+{
+    /// Create a new instance using the default I2C address in `slot`.
+    pub fn new_in(slot: &mut MaybeUninit<Self>, i2c: I2C, delay: D) -> &mut Self {
+        slot.write(Self::new(i2c, delay))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulator::Simulator;
+    use std::boxed::Box;
+
+    /// Test that drivers created in static memory read measurements
+    #[test]
+    fn test_new_in() {
+        use crate::commands::Command;
+        use crate::simulator::SimDelay;
+
+        let sim = Simulator::new();
+        let sensor: StaticSen5x<Simulator> =
+            Sen5x::new_in(Box::leak(Box::new(MaybeUninit::uninit())), sim.clone());
+        assert_eq!(sensor.address(), 0x69);
+        sensor.start_measurement().unwrap();
+        sim.advance_ms(u64::from(Command::StartMeasurement.execution_time_ms()));
+        let sample = (0..200).find_map(|_| {
+            sim.advance_ms(20);
+            sensor.poll_measurement().unwrap()
+        });
+        assert!(sample.is_some());
+
+        let sim = Simulator::new();
+        let slot = Box::leak(Box::new(MaybeUninit::uninit()));
+        let sensor: StaticSen5x<Simulator, SimDelay> =
+            Sen5x::new_with_delay_in(slot, sim.clone(), sim.delay());
+        sim.set_serial_number("1A2B3C4D5E6F7A8B");
+        let serial = sensor.serial_number().unwrap();
+        assert_eq!(serial.as_ascii(), Some("1A2B3C4D5E6F7A8B"));
+        sensor.start_measurement().unwrap();
+        assert!(sensor.measure_blocking().is_ok());
+    }
+}
@@ -0,0 +1,324 @@
+//! Persistence of the VOC algorithm state in NOR flash.
+//!
+//! The state is stored in two slots, each occupying one erase sector of the flash region. Every
+//! save goes to the slot not holding the most recent state, so a power loss while saving never
+//! destroys the last good copy, and the wear is spread over both sectors. Each record carries a
+//! sequence number to find the most recent slot at boot.
+use embedded_storage::nor_flash::NorFlash;
+
+use crate::types::VocAlgorithmState;
+
+/// Size of the sequence number and its complement preceding the blob.
+const SEQUENCE_SIZE: usize = 8;
+
+/// Size of a record before padding.
+const RECORD_SIZE: usize = SEQUENCE_SIZE + VocAlgorithmState::BLOB_SIZE;
+
+/// Largest supported record including the padding to the read and write granularity.
+const MAX_RECORD_SIZE: usize = 64;
+
+/// Number of slots used in the flash region.
+const SLOTS: u32 = 2;
+
+/// Errors when storing the VOC algorithm state.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum StorageError<F> {
+    /// The flash operation failed
+    Flash(F),
+    /// The region is not aligned to erase sectors, too small, or the flash granularity is too
+    /// coarse for the records
+    UnsupportedGeometry,
+}
+
+/// The VOC algorithm state stored in two erase sectors of a NOR flash starting at an offset.
+#[derive(Debug)]
+pub struct VocStateStorage<F> {
+    flash: F,
+    /// Start of the region of two erase sectors.
+    offset: u32,
+    /// Size of an erase sector.
+    sector_size: u32,
+    /// Slot and sequence number of the most recent record, if any.
+    latest: Option<(u32, u32)>,
+    /// Whether the slots were searched for the most recent record.
+    scanned: bool,
+}
+
+/// Round `len` up to a multiple of `granularity`.
+const fn round_up(len: usize, granularity: usize) -> usize {
+    len.div_ceil(granularity) * granularity
+}
+
+impl<F> VocStateStorage<F>
+where
+    F: NorFlash,
+{
+    /// Length of a record when read from the flash.
+    const READ_LEN: usize = round_up(RECORD_SIZE, F::READ_SIZE);
+
+    /// Length of a record when written to the flash.
+    const WRITE_LEN: usize = round_up(RECORD_SIZE, F::WRITE_SIZE);
+
+    /// Use two erase sectors of `flash` starting at `offset`, which must be sector aligned.
+    pub fn new(flash: F, offset: u32) -> Result<Self, StorageError<F::Error>> {
+        // The whole region has to be addressable with 32 bit offsets.
+        let sector_size =
+            u32::try_from(F::ERASE_SIZE).map_err(|_| StorageError::UnsupportedGeometry)?;
+        let fits = sector_size
+            .checked_mul(SLOTS)
+            .and_then(|len| offset.checked_add(len))
+            .is_some_and(|end| usize::try_from(end).is_ok_and(|end| end <= flash.capacity()));
+        if !fits
+            || !offset.is_multiple_of(sector_size)
+            || Self::READ_LEN.max(Self::WRITE_LEN) > MAX_RECORD_SIZE.min(F::ERASE_SIZE)
+        {
+            return Err(StorageError::UnsupportedGeometry);
+        }
+        Ok(Self {
+            flash,
+            offset,
+            sector_size,
+            latest: None,
+            scanned: false,
+        })
+    }
+
+    /// Release the flash.
+    pub fn release(self) -> F {
+        self.flash
+    }
+
+    /// Load the most recently saved state, `None` if no valid state was saved yet.
+    ///
+    /// Call once at boot and pass the state to [`crate::Sen5x::set_voc_algorithm_state`] before
+    /// starting the measurement.
+    pub fn load(&mut self) -> Result<Option<VocAlgorithmState>, StorageError<F::Error>> {
+        self.latest = None;
+        self.scanned = true;
+        let mut latest = None;
+        for slot in 0..SLOTS {
+            if let Some((sequence, state)) = self.read_slot(slot)? {
+                let newer = match latest {
+                    Some((_, latest_sequence, _)) => {
+                        sequence.wrapping_sub(latest_sequence) as i32 > 0
+                    }
+                    None => true,
+                };
+                if newer {
+                    latest = Some((slot, sequence, state));
+                }
+            }
+        }
+        Ok(latest.map(|(slot, sequence, state)| {
+            self.latest = Some((slot, sequence));
+            state
+        }))
+    }
+
+    /// Save `state` to the slot not holding the most recent state.
+    ///
+    /// Searches the slots first if [`Self::load`] was not called, so the saved state is never
+    /// shadowed by an older record with a higher sequence number.
+    pub fn save(&mut self, state: &VocAlgorithmState) -> Result<(), StorageError<F::Error>> {
+        if !self.scanned {
+            self.load()?;
+        }
+        let (slot, sequence) = match self.latest {
+            Some((slot, sequence)) => ((slot + 1) % SLOTS, sequence.wrapping_add(1)),
+            None => (0, 0),
+        };
+        let mut record = [0xFFu8; MAX_RECORD_SIZE];
+        record[..4].copy_from_slice(&sequence.to_le_bytes());
+        record[4..8].copy_from_slice(&(!sequence).to_le_bytes());
+        record[SEQUENCE_SIZE..RECORD_SIZE].copy_from_slice(&state.to_blob());
+
+        let start = self.slot_offset(slot);
+        self.flash
+            .erase(start, start + self.sector_size)
+            .map_err(StorageError::Flash)?;
+        self.flash
+            .write(start, &record[..Self::WRITE_LEN])
+            .map_err(StorageError::Flash)?;
+        self.latest = Some((slot, sequence));
+        Ok(())
+    }
+
+    /// Offset of `slot` in the flash.
+    fn slot_offset(&self, slot: u32) -> u32 {
+        // Checked in `new` to not overflow.
+        self.offset + slot * self.sector_size
+    }
+
+    /// Read the record in `slot`, `None` if it is erased or damaged.
+    fn read_slot(
+        &mut self,
+        slot: u32,
+    ) -> Result<Option<(u32, VocAlgorithmState)>, StorageError<F::Error>> {
+        let mut record = [0u8; MAX_RECORD_SIZE];
+        self.flash
+            .read(self.slot_offset(slot), &mut record[..Self::READ_LEN])
+            .map_err(StorageError::Flash)?;
+        let sequence = u32::from_le_bytes([record[0], record[1], record[2], record[3]]);
+        let check = u32::from_le_bytes([record[4], record[5], record[6], record[7]]);
+        if sequence != !check {
+            return Ok(None);
+        }
+        Ok(
+            VocAlgorithmState::from_blob(&record[SEQUENCE_SIZE..RECORD_SIZE])
+                .ok()
+                .map(|state| (sequence, state)),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use embedded_storage::nor_flash::{ErrorType, NorFlashErrorKind, ReadNorFlash};
+
+    /// NOR flash in memory, writes can only clear bits.
+    #[derive(Debug)]
+    struct Flash([u8; 1024]);
+
+    impl ErrorType for Flash {
+        type Error = NorFlashErrorKind;
+    }
+
+    impl ReadNorFlash for Flash {
+        const READ_SIZE: usize = 1;
+
+        fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Self::Error> {
+            let offset = offset as usize;
+            bytes.copy_from_slice(&self.0[offset..offset + bytes.len()]);
+            Ok(())
+        }
+
+        fn capacity(&self) -> usize {
+            self.0.len()
+        }
+    }
+
+    impl NorFlash for Flash {
+        const WRITE_SIZE: usize = 4;
+        const ERASE_SIZE: usize = 256;
+
+        fn erase(&mut self, from: u32, to: u32) -> Result<(), Self::Error> {
+            self.0[from as usize..to as usize].fill(0xFF);
+            Ok(())
+        }
+
+        fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Self::Error> {
+            let offset = offset as usize;
+            for (cell, byte) in self.0[offset..offset + bytes.len()].iter_mut().zip(bytes) {
+                *cell &= byte;
+            }
+            Ok(())
+        }
+    }
+
+    fn state(n: u8) -> VocAlgorithmState {
+        VocAlgorithmState::from_bytes([n; 8])
+    }
+
+    /// Test that the most recent state is restored and the slots alternate
+    #[test]
+    fn test_save_and_load() {
+        let mut storage = VocStateStorage::new(Flash([0xFF; 1024]), 256).unwrap();
+        assert_eq!(storage.load(), Ok(None));
+
+        storage.save(&state(1)).unwrap();
+        storage.save(&state(2)).unwrap();
+        storage.save(&state(3)).unwrap();
+
+        let mut storage = VocStateStorage::new(storage.release(), 256).unwrap();
+        assert_eq!(storage.load(), Ok(Some(state(3))));
+        storage.save(&state(4)).unwrap();
+        assert_eq!(storage.load(), Ok(Some(state(4))));
+    }
+
+    /// Test that saving without loading first supersedes the records already stored
+    #[test]
+    fn test_save_before_load() {
+        let mut storage = VocStateStorage::new(Flash([0xFF; 1024]), 0).unwrap();
+        storage.save(&state(1)).unwrap();
+        storage.save(&state(2)).unwrap();
+        storage.save(&state(3)).unwrap();
+
+        let mut storage = VocStateStorage::new(storage.release(), 0).unwrap();
+        storage.save(&state(4)).unwrap();
+        let mut storage = VocStateStorage::new(storage.release(), 0).unwrap();
+        assert_eq!(storage.load(), Ok(Some(state(4))));
+    }
+
+    /// Test that an interrupted save keeps the previous state
+    #[test]
+    fn test_interrupted_save() {
+        let mut storage = VocStateStorage::new(Flash([0xFF; 1024]), 0).unwrap();
+        storage.save(&state(1)).unwrap();
+        storage.save(&state(2)).unwrap();
+
+        // Damage the record in the second slot as if the write did not complete.
+        let mut flash = storage.release();
+        flash.0[256 + 20] = 0;
+        let mut storage = VocStateStorage::new(flash, 0).unwrap();
+        assert_eq!(storage.load(), Ok(Some(state(1))));
+    }
+
+    /// Test that unusable regions are rejected
+    #[test]
+    fn test_geometry() {
+        assert_eq!(
+            VocStateStorage::new(Flash([0xFF; 1024]), 128).unwrap_err(),
+            StorageError::UnsupportedGeometry
+        );
+        assert_eq!(
+            VocStateStorage::new(Flash([0xFF; 1024]), 768).unwrap_err(),
+            StorageError::UnsupportedGeometry
+        );
+    }
+
+    /// Flash larger than the 32 bit address space, only used to check the geometry.
+    #[derive(Debug)]
+    struct Unbounded;
+
+    impl ErrorType for Unbounded {
+        type Error = NorFlashErrorKind;
+    }
+
+    impl ReadNorFlash for Unbounded {
+        const READ_SIZE: usize = 1;
+
+        fn read(&mut self, _: u32, _: &mut [u8]) -> Result<(), Self::Error> {
+            Err(NorFlashErrorKind::Other)
+        }
+
+        fn capacity(&self) -> usize {
+            usize::MAX
+        }
+    }
+
+    impl NorFlash for Unbounded {
+        const WRITE_SIZE: usize = 1;
+        const ERASE_SIZE: usize = 4096;
+
+        fn erase(&mut self, _: u32, _: u32) -> Result<(), Self::Error> {
+            Err(NorFlashErrorKind::Other)
+        }
+
+        fn write(&mut self, _: u32, _: &[u8]) -> Result<(), Self::Error> {
+            Err(NorFlashErrorKind::Other)
+        }
+    }
+
+    /// Test that regions reaching past the 32 bit address space are rejected
+    #[test]
+    fn test_geometry_overflow() {
+        // The end of the region would be 2^32.
+        let last = u32::MAX - 2 * 4096 + 1;
+        assert_eq!(
+            VocStateStorage::new(Unbounded, last).unwrap_err(),
+            StorageError::UnsupportedGeometry
+        );
+        assert!(VocStateStorage::new(Unbounded, last - 4096).is_ok());
+    }
+}
@@ -0,0 +1,193 @@
+//! Known-good response frames and their decoded values.
+//!
+//! Every frame is the exact byte sequence read from the sensor, including the CRC byte following
+//! each word. The vectors can be used to validate other parsers or ports of this driver against
+//! the same data.
+use crate::types::{Environment, GasIndices, ParticulateMatter, Sen5xData, Sen5xDataRaw};
+
+/// A `Read Measured Values` response and its decoded contents.
+#[derive(Debug)]
+pub struct MeasurementVector {
+    /// Short description of the vector.
+    pub name: &'static str,
+    /// Response frame as read from the sensor.
+    pub frame: [u8; 24],
+    /// Raw values contained in the frame.
+    pub raw: Sen5xDataRaw,
+    /// Values converted to physical units.
+    pub data: Sen5xData,
+}
+
+/// `Read Measured Values` responses.
+pub const MEASUREMENTS: &[MeasurementVector] = &[
+    MeasurementVector {
+        name: "indoor air",
+        frame: [
+            0x00, 0x12, 0xA0, 0x00, 0x16, 0x64, 0x00, 0x18, 0x7B, 0x00, 0x1A, 0x19, 0x15, 0x8A,
+            0x39, 0x11, 0x81, 0x50, 0x01, 0x68, 0x77, 0x00, 0x0A, 0x5A,
+        ],
+        raw: Sen5xDataRaw {
+            pm1_0: 18,
+            pm2_5: 22,
+            pm4_0: 24,
+            pm10_0: 26,
+            humidity: 5514,
+            temperature: 4481,
+            voc_index: 360,
+            nox_index: 10,
+        },
+        data: Sen5xData::sample(),
+    },
+    MeasurementVector {
+        name: "all zero",
+        frame: [
+            0x00, 0x00, 0x81, 0x00, 0x00, 0x81, 0x00, 0x00, 0x81, 0x00, 0x00, 0x81, 0x00, 0x00,
+            0x81, 0x00, 0x00, 0x81, 0x00, 0x00, 0x81, 0x00, 0x00, 0x81,
+        ],
+        raw: Sen5xDataRaw {
+            pm1_0: 0,
+            pm2_5: 0,
+            pm4_0: 0,
+            pm10_0: 0,
+            humidity: 0,
+            temperature: 0,
+            voc_index: 0,
+            nox_index: 0,
+        },
+        data: Sen5xData {
+            pm: ParticulateMatter {
+                pm1_0: 0.0,
+                pm2_5: 0.0,
+                pm4_0: 0.0,
+                pm10_0: 0.0,
+            },
+            env: Some(Environment {
+                humidity: 0.0,
+                temperature: 0.0,
+            }),
+            gas: Some(GasIndices {
+                voc_index: 0.0,
+                nox_index: Some(0.0),
+            }),
+        },
+    },
+    MeasurementVector {
+        name: "polluted",
+        frame: [
+            0x03, 0xE8, 0xD4, 0x05, 0xDC, 0x8F, 0x06, 0xD6, 0x79, 0x07, 0x08, 0x96, 0x0B, 0xB8,
+            0x9D, 0x13, 0x88, 0x01, 0x03, 0xE8, 0xD4, 0x09, 0xC4, 0xC1,
+        ],
+        raw: Sen5xDataRaw {
+            pm1_0: 1000,
+            pm2_5: 1500,
+            pm4_0: 1750,
+            pm10_0: 1800,
+            humidity: 3000,
+            temperature: 5000,
+            voc_index: 1000,
+            nox_index: 2500,
+        },
+        data: Sen5xData {
+            pm: ParticulateMatter {
+                pm1_0: 100.0,
+                pm2_5: 150.0,
+                pm4_0: 175.0,
+                pm10_0: 180.0,
+            },
+            env: Some(Environment {
+                humidity: 30.0,
+                temperature: 25.0,
+            }),
+            gas: Some(GasIndices {
+                voc_index: 100.0,
+                nox_index: Some(250.0),
+            }),
+        },
+    },
+];
+
+/// `Read Data-Ready Flag` response with new data available.
+pub const DATA_READY: [u8; 3] = [0x00, 0x01, 0xB0];
+
+/// `Read Data-Ready Flag` response without new data.
+pub const DATA_NOT_READY: [u8; 3] = [0x00, 0x00, 0x81];
+
+/// `Read Product Name` response of a SEN55.
+pub const PRODUCT_NAME_SEN55: [u8; 48] = [
+    0x53, 0x45, 0x83, 0x4E, 0x35, 0x55, 0x35, 0x00, 0x44, 0x00, 0x00, 0x81, 0x00, 0x00, 0x81, 0x00,
+    0x00, 0x81, 0x00, 0x00, 0x81, 0x00, 0x00, 0x81, 0x00, 0x00, 0x81, 0x00, 0x00, 0x81, 0x00, 0x00,
+    0x81, 0x00, 0x00, 0x81, 0x00, 0x00, 0x81, 0x00, 0x00, 0x81, 0x00, 0x00, 0x81, 0x00, 0x00, 0x81,
+];
+
+/// `Read Firmware Version` response of firmware version 2.
+pub const FIRMWARE_VERSION_2: [u8; 3] = [0x02, 0x00, 0x58];
+
+#[cfg(all(test, feature = "driver"))]
+mod tests {
+    use embedded_hal_mock as hal;
+
+    use self::hal::eh1::delay::NoopDelay as DelayMock;
+    use self::hal::eh1::i2c::{Mock as I2cMock, Transaction};
+    use super::*;
+    use crate::commands::Command;
+    use crate::Sen5x;
+
+    const ADDRESS: u8 = 0x69;
+
+    fn expect(cmd: Command, frame: &[u8]) -> [Transaction; 2] {
+        let (cmd, _, _) = cmd.as_tuple();
+        [
+            Transaction::write(ADDRESS, cmd.to_be_bytes().to_vec()),
+            Transaction::read(ADDRESS, frame.to_vec()),
+        ]
+    }
+
+    /// Test that the driver decodes every measurement vector
+    #[test]
+    fn test_measurements() {
+        for vector in MEASUREMENTS {
+            let start = [Transaction::write(ADDRESS, vec![0x00, 0x21])];
+            let expectations = [
+                &start[..],
+                &expect(Command::ReadMeasurement, &vector.frame),
+                &expect(Command::ReadMeasurement, &vector.frame),
+            ]
+            .concat();
+            let mut mock = I2cMock::new(&expectations);
+            let mut sensor = Sen5x::new(mock.clone(), DelayMock);
+            sensor.start_measurement().unwrap();
+            assert_eq!(
+                sensor.measurement_raw().unwrap(),
+                vector.raw,
+                "{}",
+                vector.name
+            );
+            assert_eq!(
+                sensor.measurement().unwrap(),
+                vector.data,
+                "{}",
+                vector.name
+            );
+            mock.done();
+        }
+    }
+
+    /// Test that the driver decodes the status and identification vectors
+    #[test]
+    fn test_other_frames() {
+        let expectations = [
+            expect(Command::GetReadDataReadyStatus, &DATA_READY),
+            expect(Command::GetReadDataReadyStatus, &DATA_NOT_READY),
+            expect(Command::ReadProductName, &PRODUCT_NAME_SEN55),
+            expect(Command::ReadFirmwareVersion, &FIRMWARE_VERSION_2),
+        ]
+        .concat();
+        let mut mock = I2cMock::new(&expectations);
+        let mut sensor = Sen5x::new(mock.clone(), DelayMock);
+        assert!(sensor.data_ready_status().unwrap());
+        assert!(!sensor.data_ready_status().unwrap());
+        assert_eq!(&sensor.product_name().unwrap()[..6], b"SEN55\0");
+        assert_eq!(sensor.read_firmware_version().unwrap(), 2);
+        mock.done();
+    }
+}
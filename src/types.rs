@@ -1,24 +1,64 @@
+#![cfg_attr(not(test), deny(clippy::indexing_slicing))]
+use core::fmt;
+use core::ops::{Div, RangeInclusive};
+use core::time::Duration;
+
 /// SEN5x sensor data.
-pub struct Sen5xData {
+///
+/// Values the sensor does not measure, e.g. the humidity of a SEN50, or reports as unknown, e.g.
+/// the NOx index during the first seconds after starting the measurement, are `None`.
+///
+/// The values are `f32` by default. Host-side analytics aggregating many samples can use
+/// `Sen5xData<f64>`, created with [`Sen5xData::from_raw`].
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Sen5xData<F = f32> {
+    /// Mass concentrations of particulate matter
+    pub pm: ParticulateMatter<F>,
+    /// Humidity and temperature (SEN54 and SEN55 only)
+    pub env: Option<Environment<F>>,
+    /// VOC and NOx index (SEN54 and SEN55 only)
+    pub gas: Option<GasIndices<F>>,
+}
+
+/// Mass concentrations of particulate matter.
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ParticulateMatter<F = f32> {
     /// Mass Concentration PM1.0 [μg/m³]
-    pub pm1_0: f32,
+    pub pm1_0: F,
     /// Mass Concentration PM2.5 [μg/m³]
-    pub pm2_5: f32,
+    pub pm2_5: F,
     /// Mass Concentration PM4.0 [μg/m³]
-    pub pm4_0: f32,
+    pub pm4_0: F,
     /// Mass Concentration PM10 [μg/m³]
-    pub pm10_0: f32,
+    pub pm10_0: F,
+}
+
+/// Ambient conditions.
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Environment<F = f32> {
     /// Compensated Ambient Humidity [%RH]
-    pub humidity: f32,
+    pub humidity: F,
     /// Compensated Ambient Temperature [°C]
-    pub temperature: f32,
+    pub temperature: F,
+}
+
+/// Indices of the gas sensor.
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GasIndices<F = f32> {
     /// VOC Index
-    pub voc_index: f32,
-    /// NOx Index
-    pub nox_index: f32,
+    pub voc_index: F,
+    /// NOx Index (SEN55 only)
+    pub nox_index: Option<F>,
 }
 
 /// SEN5x sensor raw data.
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[repr(C)]
 pub struct Sen5xDataRaw {
     /// Mass Concentration PM1.0 [μg/m³] [×10]
     pub pm1_0: u16,
@@ -29,11 +69,886 @@ pub struct Sen5xDataRaw {
     /// Mass Concentration PM10.0 [μg/m³] [×10]
     pub pm10_0: u16,
     /// Compensated Ambient Temperature [°C] [×200]
-    pub temperature: u16,
+    pub temperature: i16,
     /// Compensated Ambient Humidity [%RH] [×100]
-    pub humidity: u16,
+    pub humidity: i16,
     /// VOC Index [×10]
-    pub voc_index: u16,
+    pub voc_index: i16,
     /// NOx Index [×10]
-    pub nox_index: u16,
+    pub nox_index: i16,
+}
+
+/// Value the sensor reports for unknown signed values.
+const UNKNOWN: i16 = i16::MAX;
+
+impl<F> Sen5xData<F>
+where
+    F: From<u16> + From<i16> + Div<Output = F>,
+{
+    /// Convert raw values to physical units in the precision of `F`.
+    pub fn from_raw(data: Sen5xDataRaw) -> Self {
+        let scaled = |value: i16, scale: u16| F::from(value) / F::from(scale);
+        let known = |value: i16| value != UNKNOWN;
+        Sen5xData {
+            pm: ParticulateMatter::from_words([data.pm1_0, data.pm2_5, data.pm4_0, data.pm10_0]),
+            env: (known(data.humidity) && known(data.temperature)).then(|| Environment {
+                humidity: scaled(data.humidity, 100),
+                temperature: scaled(data.temperature, 200),
+            }),
+            gas: known(data.voc_index).then(|| GasIndices {
+                voc_index: scaled(data.voc_index, 10),
+                nox_index: known(data.nox_index).then(|| scaled(data.nox_index, 10)),
+            }),
+        }
+    }
+}
+
+impl Sen5xData {
+    /// A measurement of indoor air, used by the examples in the documentation.
+    #[doc(hidden)]
+    pub const fn sample() -> Self {
+        Self {
+            pm: ParticulateMatter {
+                pm1_0: 1.8,
+                pm2_5: 2.2,
+                pm4_0: 2.4,
+                pm10_0: 2.6,
+            },
+            env: Some(Environment {
+                humidity: 55.14,
+                temperature: 22.405,
+            }),
+            gas: Some(GasIndices {
+                voc_index: 36.0,
+                nox_index: Some(1.0),
+            }),
+        }
+    }
+}
+
+impl From<Sen5xDataRaw> for Sen5xData {
+    fn from(data: Sen5xDataRaw) -> Self {
+        Self::from_raw(data)
+    }
+}
+
+impl<F> ParticulateMatter<F>
+where
+    F: From<u16> + Div<Output = F>,
+{
+    /// Convert the raw PM words of a measurement to [μg/m³].
+    pub(crate) fn from_words(words: [u16; 4]) -> Self {
+        let [pm1_0, pm2_5, pm4_0, pm10_0] = words.map(|word| F::from(word) / F::from(10));
+        Self {
+            pm1_0,
+            pm2_5,
+            pm4_0,
+            pm10_0,
+        }
+    }
+}
+
+/// The bytes of the null-terminated string `bytes` before the first null byte.
+fn until_nul(bytes: &[u8]) -> &[u8] {
+    bytes.split(|&b| b == 0).next().unwrap_or(bytes)
+}
+
+/// Serial number of a sensor, reported as a null-terminated ASCII string of up to 32 characters.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct SerialNumber([u8; 32]);
+
+impl SerialNumber {
+    /// Create a serial number from the 32 bytes reported by the sensor.
+    pub fn from_bytes(bytes: [u8; 32]) -> Self {
+        Self(bytes)
+    }
+
+    /// Create a serial number from a binary serial of `words`, e.g. the 48-bit number reported by
+    /// the SEN60, as the uppercase hex digits of the words in order.
+    pub fn from_hex_words(words: &[u16]) -> Self {
+        const DIGITS: &[u8; 16] = b"0123456789ABCDEF";
+        let digits = words.iter().flat_map(|&word| {
+            [12, 8, 4, 0].map(|shift| {
+                DIGITS
+                    .get(usize::from(word >> shift & 0xF))
+                    .copied()
+                    .unwrap_or_default()
+            })
+        });
+        let mut bytes = [0u8; 32];
+        for (byte, digit) in bytes.iter_mut().zip(digits) {
+            *byte = digit;
+        }
+        Self(bytes)
+    }
+
+    /// The bytes reported by the sensor, including the null padding.
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+
+    /// The serial number as printed on the device label, or `None` if it is not ASCII.
+    pub fn as_ascii(&self) -> Option<&str> {
+        let bytes = until_nul(&self.0);
+        if bytes.is_ascii() {
+            core::str::from_utf8(bytes).ok()
+        } else {
+            None
+        }
+    }
+
+    /// The first six bytes packed into a 48-bit number, as returned by earlier versions of this
+    /// driver.
+    pub fn as_u64(&self) -> u64 {
+        let [b0, b1, b2, b3, b4, b5, ..] = self.0;
+        [b0, b1, b2, b3, b4, b5]
+            .into_iter()
+            .fold(0u64, |serial, byte| serial << 8 | u64::from(byte))
+    }
+}
+
+impl fmt::Display for SerialNumber {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.as_ascii() {
+            Some(ascii) => f.write_str(ascii),
+            None => fmt::LowerHex::fmt(self, f),
+        }
+    }
+}
+
+impl fmt::LowerHex for SerialNumber {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let len = self.0.iter().rposition(|&b| b != 0).map_or(0, |i| i + 1);
+        for byte in self.0.get(..len).unwrap_or_default() {
+            write!(f, "{byte:02x}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Member of the SEN5x or SEN6x family, differing in the measured values.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DeviceVariant {
+    /// Particulate matter only.
+    Sen50,
+    /// Particulate matter, humidity, temperature and VOC index.
+    Sen54,
+    /// Particulate matter, humidity, temperature, VOC and NOx index.
+    Sen55,
+    /// Particulate matter only.
+    Sen60,
+    /// Particulate matter, humidity, temperature and CO₂.
+    Sen63c,
+    /// Particulate matter, humidity, temperature, VOC and NOx index.
+    Sen65,
+    /// Particulate matter, humidity, temperature, VOC index, NOx index and CO₂.
+    Sen66,
+    /// Particulate matter, humidity, temperature, VOC index, NOx index and formaldehyde.
+    Sen68,
+}
+
+impl DeviceVariant {
+    /// Identify the variant from the response of the `Read Product Name` command.
+    pub fn from_product_name(name: &[u8; 32]) -> Option<Self> {
+        match until_nul(name) {
+            b"SEN50" => Some(Self::Sen50),
+            b"SEN54" => Some(Self::Sen54),
+            b"SEN55" => Some(Self::Sen55),
+            b"SEN60" => Some(Self::Sen60),
+            b"SEN63C" => Some(Self::Sen63c),
+            b"SEN65" => Some(Self::Sen65),
+            b"SEN66" => Some(Self::Sen66),
+            b"SEN68" => Some(Self::Sen68),
+            _ => None,
+        }
+    }
+
+    /// The product name as reported by the sensor.
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Sen50 => "SEN50",
+            Self::Sen54 => "SEN54",
+            Self::Sen55 => "SEN55",
+            Self::Sen60 => "SEN60",
+            Self::Sen63c => "SEN63C",
+            Self::Sen65 => "SEN65",
+            Self::Sen66 => "SEN66",
+            Self::Sen68 => "SEN68",
+        }
+    }
+
+    /// Whether the variant is a SEN5x, supported by [`crate::Sen5x`].
+    pub const fn is_sen5x(self) -> bool {
+        matches!(self, Self::Sen50 | Self::Sen54 | Self::Sen55)
+    }
+}
+
+/// Identification of a sensor, e.g. for registering it in a fleet.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct DeviceInfo {
+    /// Product name as reported by the sensor, null-terminated.
+    pub product_name: [u8; 32],
+    /// Serial number of the sensor.
+    pub serial_number: SerialNumber,
+    /// Major firmware version.
+    pub firmware_version: u8,
+    /// Variant identified from the product name, `None` if it is not a SEN5x or SEN6x.
+    pub variant: Option<DeviceVariant>,
+}
+
+impl DeviceInfo {
+    /// The product name without the null padding, or `None` if it is not ASCII.
+    pub fn product_name_str(&self) -> Option<&str> {
+        let name = until_nul(&self.product_name);
+        if name.is_ascii() {
+            core::str::from_utf8(name).ok()
+        } else {
+            None
+        }
+    }
+}
+
+/// What a connected sensor supports, derived from its variant and firmware version.
+///
+/// Generic application code can use it to show only the values and settings the sensor has.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Capabilities {
+    /// Variant of the sensor.
+    pub variant: DeviceVariant,
+    /// Major firmware version.
+    pub firmware_version: u8,
+}
+
+impl Capabilities {
+    /// The capabilities of the `variant` with the major firmware version `firmware_version`.
+    pub const fn new(variant: DeviceVariant, firmware_version: u8) -> Self {
+        Self {
+            variant,
+            firmware_version,
+        }
+    }
+
+    /// The channels the sensor measures.
+    pub const fn channels(&self) -> crate::measurement::ChannelMask {
+        self.variant.channels()
+    }
+
+    /// Whether the sensor measures `channel`.
+    pub const fn measures(&self, channel: crate::measurement::Channel) -> bool {
+        self.channels().contains(channel)
+    }
+
+    /// Whether the fan can be cleaned, manually and periodically.
+    pub const fn fan_cleaning(&self) -> bool {
+        // Every variant has a fan.
+        true
+    }
+
+    /// Whether the VOC algorithm can be tuned and its state saved and restored.
+    pub const fn voc_tuning(&self) -> bool {
+        self.measures(crate::measurement::Channel::VocIndex)
+    }
+
+    /// Whether the NOx algorithm can be tuned.
+    pub const fn nox_tuning(&self) -> bool {
+        self.measures(crate::measurement::Channel::NoxIndex)
+    }
+
+    /// Whether the temperature compensation can be configured.
+    pub const fn temperature_compensation(&self) -> bool {
+        self.measures(crate::measurement::Channel::Temperature)
+    }
+
+    /// Whether the sensor measures the CO₂ concentration.
+    pub const fn co2(&self) -> bool {
+        self.measures(crate::measurement::Channel::Co2)
+    }
+
+    /// Whether the firmware of the sensor supports `cmd`.
+    pub const fn supports(&self, cmd: crate::commands::Command) -> bool {
+        self.firmware_version >= cmd.spec().min_firmware
+    }
+}
+
+/// Uncompensated values and raw signals of the gas sensors, e.g. for custom algorithms.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct RawValues {
+    /// Humidity before the temperature compensation [%RH], `None` if not measured.
+    pub humidity: Option<f32>,
+    /// Temperature before the temperature compensation [°C], `None` if not measured.
+    pub temperature: Option<f32>,
+    /// Raw signal of the VOC sensor [ticks], `None` if not measured.
+    pub voc_ticks: Option<u16>,
+    /// Raw signal of the NOx sensor [ticks], `None` if not measured.
+    pub nox_ticks: Option<u16>,
+}
+
+impl RawValues {
+    /// Decode the response of `Read Measured Raw Values`.
+    pub(crate) fn from_words([humidity, temperature, voc, nox]: [u16; 4]) -> Self {
+        let signed = |word: u16, scale: f32| {
+            (word != i16::MAX.cast_unsigned()).then(|| f32::from(word.cast_signed()) / scale)
+        };
+        let unsigned = |word: u16| (word != u16::MAX).then_some(word);
+        Self {
+            humidity: signed(humidity, 100.0),
+            temperature: signed(temperature, 200.0),
+            voc_ticks: unsigned(voc),
+            nox_ticks: unsigned(nox),
+        }
+    }
+}
+
+/// Content of the device status register.
+///
+/// Warning and error flags are sticky, they stay set until the register is cleared with
+/// `Sen5x::device_status_and_clear` or the sensor is reset.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DeviceStatus(u32);
+
+impl DeviceStatus {
+    /// Fan speed is out of range.
+    pub const FAN_SPEED_WARNING: u32 = 1 << 21;
+    /// Fan cleaning is active.
+    pub const FAN_CLEANING: u32 = 1 << 19;
+    /// Gas sensor error (SEN54 and SEN55 only).
+    pub const GAS_SENSOR_ERROR: u32 = 1 << 7;
+    /// Error in the internal communication with the RH/T sensor (SEN54 and SEN55 only).
+    pub const RHT_COMMUNICATION_ERROR: u32 = 1 << 6;
+    /// Laser is switched on and the current is out of range.
+    pub const LASER_FAILURE: u32 = 1 << 5;
+    /// Fan is switched on but its speed is 0 RPM or the fan is blocked.
+    pub const FAN_FAILURE: u32 = 1 << 4;
+
+    /// Mask of all error flags.
+    const ERRORS: u32 = Self::GAS_SENSOR_ERROR
+        | Self::RHT_COMMUNICATION_ERROR
+        | Self::LASER_FAILURE
+        | Self::FAN_FAILURE;
+
+    /// Create a status from the raw register value.
+    pub fn from_bits(bits: u32) -> Self {
+        Self(bits)
+    }
+
+    /// The raw register value.
+    pub fn bits(&self) -> u32 {
+        self.0
+    }
+
+    /// Whether all flags in `mask` are set.
+    pub fn contains(&self, mask: u32) -> bool {
+        self.0 & mask == mask
+    }
+
+    /// Fan speed is out of range.
+    pub fn fan_speed_warning(&self) -> bool {
+        self.contains(Self::FAN_SPEED_WARNING)
+    }
+
+    /// Fan cleaning is active.
+    pub fn fan_cleaning(&self) -> bool {
+        self.contains(Self::FAN_CLEANING)
+    }
+
+    /// Gas sensor error.
+    pub fn gas_sensor_error(&self) -> bool {
+        self.contains(Self::GAS_SENSOR_ERROR)
+    }
+
+    /// RH/T sensor communication error.
+    pub fn rht_communication_error(&self) -> bool {
+        self.contains(Self::RHT_COMMUNICATION_ERROR)
+    }
+
+    /// Laser failure.
+    pub fn laser_failure(&self) -> bool {
+        self.contains(Self::LASER_FAILURE)
+    }
+
+    /// Fan failure.
+    pub fn fan_failure(&self) -> bool {
+        self.contains(Self::FAN_FAILURE)
+    }
+
+    /// Whether any error flag is set.
+    pub fn has_error(&self) -> bool {
+        self.0 & Self::ERRORS != 0
+    }
+}
+
+#[cfg(feature = "fugit")]
+impl<const NOM: u64, const DENOM: u64> From<fugit::Duration<u32, NOM, DENOM>>
+    for AutoCleanInterval
+{
+    fn from(interval: fugit::Duration<u32, NOM, DENOM>) -> Self {
+        Self::every(interval)
+    }
+}
+
+#[cfg(feature = "fugit")]
+impl<const NOM: u64, const DENOM: u64> From<fugit::Duration<u64, NOM, DENOM>>
+    for AutoCleanInterval
+{
+    fn from(interval: fugit::Duration<u64, NOM, DENOM>) -> Self {
+        Self::every(interval)
+    }
+}
+
+/// Interval of the periodic fan cleaning.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum AutoCleanInterval {
+    /// The fan is never cleaned automatically.
+    Disabled,
+    /// The fan is cleaned periodically. The sensor supports intervals from 1 s up to `u32::MAX`
+    /// seconds, sub-second parts are truncated.
+    Every(Duration),
+}
+
+impl Default for AutoCleanInterval {
+    fn default() -> Self {
+        Self::DATASHEET_DEFAULT
+    }
+}
+
+impl AutoCleanInterval {
+    /// Interval used by the sensor after a factory reset, one week.
+    pub const DATASHEET_DEFAULT: Self = Self::Every(Duration::from_secs(604_800));
+
+    /// Clean the fan every `interval`.
+    pub fn every(interval: impl Into<Duration>) -> Self {
+        Self::Every(interval.into())
+    }
+
+    /// Create the interval from the value used by the sensor [s].
+    pub fn from_secs(secs: u32) -> Self {
+        match secs {
+            0 => Self::Disabled,
+            secs => Self::Every(Duration::from_secs(secs.into())),
+        }
+    }
+
+    /// The value used by the sensor [s], or `None` if the interval is out of range.
+    pub fn as_secs(&self) -> Option<u32> {
+        match self {
+            Self::Disabled => Some(0),
+            Self::Every(interval) => match u32::try_from(interval.as_secs()) {
+                Ok(0) | Err(_) => None,
+                Ok(secs) => Some(secs),
+            },
+        }
+    }
+
+    /// Validate the interval and encode it as sent to the sensor.
+    pub(crate) fn to_words(self) -> Result<[u16; 2], InvalidValue> {
+        let secs = self.as_secs().ok_or(InvalidValue {
+            field: "auto_clean_interval",
+            value: match self {
+                Self::Every(interval) => i64::try_from(interval.as_secs()).unwrap_or(i64::MAX),
+                Self::Disabled => 0,
+            },
+        })?;
+        let [b0, b1, b2, b3] = secs.to_be_bytes();
+        Ok([u16::from_be_bytes([b0, b1]), u16::from_be_bytes([b2, b3])])
+    }
+
+    /// Decode the interval read from the sensor.
+    pub(crate) fn from_words([hi, lo]: [u16; 2]) -> Self {
+        Self::from_secs(u32::from(hi) << 16 | u32::from(lo))
+    }
+}
+
+/// A parameter outside of the range accepted by the sensor.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) struct InvalidValue {
+    /// Name of the offending parameter.
+    pub field: &'static str,
+    /// The rejected value, in the unit sent to the sensor.
+    pub value: i64,
+}
+
+/// Check that `value` of `field` lies within `range`.
+fn in_range(
+    field: &'static str,
+    value: i16,
+    range: RangeInclusive<i16>,
+) -> Result<u16, InvalidValue> {
+    if range.contains(&value) {
+        Ok(value.cast_unsigned())
+    } else {
+        Err(InvalidValue {
+            field,
+            value: value.into(),
+        })
+    }
+}
+
+/// Scale `value` of `field` to the signed fixed point representation used by the sensor.
+fn scaled(field: &'static str, value: f32, scale: f32) -> Result<u16, InvalidValue> {
+    let scaled = value * scale;
+    // Float to integer casts saturate and map NaN to zero, which is rejected below.
+    #[allow(clippy::cast_possible_truncation)]
+    let rounded = (scaled + if scaled < 0.0 { -0.5 } else { 0.5 }) as i64;
+    match i16::try_from(rounded) {
+        Ok(value) if scaled.is_finite() => Ok(value.cast_unsigned()),
+        _ => Err(InvalidValue {
+            field,
+            value: rounded,
+        }),
+    }
+}
+
+/// Tuning parameters of the VOC algorithm.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct VocTuningParameters {
+    /// VOC index representing typical (average) conditions, 1 to 250.
+    pub index_offset: i16,
+    /// Time constant to estimate the offset from the history [h], 1 to 1000.
+    pub learning_time_offset_hours: i16,
+    /// Time constant to estimate the gain from the history [h], 1 to 1000.
+    pub learning_time_gain_hours: i16,
+    /// Maximum duration of gating, 0 disables the gating [min], 0 to 3000.
+    pub gating_max_duration_minutes: i16,
+    /// Initial estimate for the standard deviation, 10 to 5000.
+    pub std_initial: i16,
+    /// Gain factor to amplify or to attenuate the index output, 1 to 1000.
+    pub gain_factor: i16,
+}
+
+impl Default for VocTuningParameters {
+    fn default() -> Self {
+        Self::DATASHEET_DEFAULT
+    }
+}
+
+impl VocTuningParameters {
+    /// Parameters used by the sensor after a reset.
+    pub const DATASHEET_DEFAULT: Self = Self {
+        index_offset: 100,
+        learning_time_offset_hours: 12,
+        learning_time_gain_hours: 12,
+        gating_max_duration_minutes: 180,
+        std_initial: 50,
+        gain_factor: 230,
+    };
+
+    /// Validate the parameters and encode them as sent to the sensor.
+    pub(crate) fn to_words(self) -> Result<[u16; 6], InvalidValue> {
+        Ok([
+            in_range("index_offset", self.index_offset, 1..=250)?,
+            in_range(
+                "learning_time_offset_hours",
+                self.learning_time_offset_hours,
+                1..=1000,
+            )?,
+            in_range(
+                "learning_time_gain_hours",
+                self.learning_time_gain_hours,
+                1..=1000,
+            )?,
+            in_range(
+                "gating_max_duration_minutes",
+                self.gating_max_duration_minutes,
+                0..=3000,
+            )?,
+            in_range("std_initial", self.std_initial, 10..=5000)?,
+            in_range("gain_factor", self.gain_factor, 1..=1000)?,
+        ])
+    }
+
+    /// Decode the parameters read from the sensor.
+    pub(crate) fn from_words(words: [u16; 6]) -> Self {
+        let words = words.map(u16::cast_signed);
+        Self {
+            index_offset: words[0],
+            learning_time_offset_hours: words[1],
+            learning_time_gain_hours: words[2],
+            gating_max_duration_minutes: words[3],
+            std_initial: words[4],
+            gain_factor: words[5],
+        }
+    }
+}
+
+/// Tuning parameters of the NOx algorithm.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct NoxTuningParameters {
+    /// NOx index representing typical (average) conditions, 1 to 250.
+    pub index_offset: i16,
+    /// Time constant to estimate the offset from the history [h], 1 to 1000.
+    pub learning_time_offset_hours: i16,
+    /// Unused by the NOx algorithm, must be 12.
+    pub learning_time_gain_hours: i16,
+    /// Maximum duration of gating, 0 disables the gating [min], 0 to 3000.
+    pub gating_max_duration_minutes: i16,
+    /// Unused by the NOx algorithm, must be 50.
+    pub std_initial: i16,
+    /// Gain factor to amplify or to attenuate the index output, 1 to 1000.
+    pub gain_factor: i16,
+}
+
+impl Default for NoxTuningParameters {
+    fn default() -> Self {
+        Self::DATASHEET_DEFAULT
+    }
+}
+
+impl NoxTuningParameters {
+    /// Parameters used by the sensor after a reset.
+    pub const DATASHEET_DEFAULT: Self = Self {
+        index_offset: 1,
+        learning_time_offset_hours: 12,
+        learning_time_gain_hours: 12,
+        gating_max_duration_minutes: 720,
+        std_initial: 50,
+        gain_factor: 230,
+    };
+
+    /// Validate the parameters and encode them as sent to the sensor.
+    pub(crate) fn to_words(self) -> Result<[u16; 6], InvalidValue> {
+        Ok([
+            in_range("index_offset", self.index_offset, 1..=250)?,
+            in_range(
+                "learning_time_offset_hours",
+                self.learning_time_offset_hours,
+                1..=1000,
+            )?,
+            in_range(
+                "learning_time_gain_hours",
+                self.learning_time_gain_hours,
+                12..=12,
+            )?,
+            in_range(
+                "gating_max_duration_minutes",
+                self.gating_max_duration_minutes,
+                0..=3000,
+            )?,
+            in_range("std_initial", self.std_initial, 50..=50)?,
+            in_range("gain_factor", self.gain_factor, 1..=1000)?,
+        ])
+    }
+
+    /// Decode the parameters read from the sensor.
+    pub(crate) fn from_words(words: [u16; 6]) -> Self {
+        let words = words.map(u16::cast_signed);
+        Self {
+            index_offset: words[0],
+            learning_time_offset_hours: words[1],
+            learning_time_gain_hours: words[2],
+            gating_max_duration_minutes: words[3],
+            std_initial: words[4],
+            gain_factor: words[5],
+        }
+    }
+}
+
+/// Compensation of the temperature offset caused by the self-heating of the housing.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct TemperatureCompensation {
+    /// Constant temperature offset [°C], resolution of 0.005 °C.
+    pub offset: f32,
+    /// Temperature offset proportional to the measured temperature, resolution of 0.0001.
+    pub slope: f32,
+    /// Time constant of applying changed parameters, 0 applies them immediately [s].
+    pub time_constant: u16,
+}
+
+impl Default for TemperatureCompensation {
+    fn default() -> Self {
+        Self::DATASHEET_DEFAULT
+    }
+}
+
+impl TemperatureCompensation {
+    /// Parameters used by the sensor after a reset, no compensation at all.
+    pub const DATASHEET_DEFAULT: Self = Self {
+        offset: 0.0,
+        slope: 0.0,
+        time_constant: 0,
+    };
+
+    /// Validate the parameters and encode them as sent to the sensor.
+    pub(crate) fn to_words(self) -> Result<[u16; 3], InvalidValue> {
+        Ok([
+            scaled("offset", self.offset, 200.0)?,
+            scaled("slope", self.slope, 10000.0)?,
+            self.time_constant,
+        ])
+    }
+
+    /// Decode the parameters read from the sensor.
+    pub(crate) fn from_words([offset, slope, time_constant]: [u16; 3]) -> Self {
+        Self {
+            offset: f32::from(offset.cast_signed()) / 200.0,
+            slope: f32::from(slope.cast_signed()) / 10000.0,
+            time_constant,
+        }
+    }
+}
+
+/// State of the VOC algorithm.
+///
+/// Restoring a previously read state after a restart skips the initial learning phase of the VOC
+/// algorithm. The content is opaque and only meaningful to the sensor.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub struct VocAlgorithmState([u8; 8]);
+
+impl VocAlgorithmState {
+    /// Create the state from bytes previously returned by [`VocAlgorithmState::as_bytes`].
+    pub fn from_bytes(bytes: [u8; 8]) -> Self {
+        Self(bytes)
+    }
+
+    /// The raw state as exchanged with the sensor.
+    pub fn as_bytes(&self) -> &[u8; 8] {
+        &self.0
+    }
+
+    /// Encode the state as sent to the sensor.
+    #[cfg(feature = "driver")]
+    pub(crate) fn to_words(self) -> [u16; 4] {
+        let mut words = [0u16; 4];
+        for (word, &pair) in words.iter_mut().zip(self.0.as_chunks::<2>().0) {
+            *word = u16::from_be_bytes(pair);
+        }
+        words
+    }
+
+    /// Decode the state read from the sensor.
+    #[cfg(feature = "driver")]
+    pub(crate) fn from_words(words: [u16; 4]) -> Self {
+        let mut bytes = [0u8; 8];
+        for (pair, word) in bytes.chunks_exact_mut(2).zip(words) {
+            pair.copy_from_slice(&word.to_be_bytes());
+        }
+        Self(bytes)
+    }
+}
+
+/// Snapshot of all configurable parameters of the sensor.
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub struct Config {
+    /// Interval of the periodic fan cleaning.
+    pub auto_clean_interval: AutoCleanInterval,
+    /// Tuning parameters of the VOC algorithm.
+    pub voc_tuning: VocTuningParameters,
+    /// Tuning parameters of the NOx algorithm.
+    pub nox_tuning: NoxTuningParameters,
+    /// Temperature compensation parameters.
+    pub temperature_compensation: TemperatureCompensation,
+}
+
+impl Config {
+    /// The parameters of `actual`, e.g. read from the sensor, which differ from `self`.
+    pub fn diff(&self, actual: &Self) -> ConfigDiff {
+        ConfigDiff {
+            expected: *self,
+            actual: *actual,
+        }
+    }
+}
+
+/// A parameter of a [`Config`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum ConfigParameter {
+    /// [`Config::auto_clean_interval`]
+    AutoCleanInterval,
+    /// [`Config::voc_tuning`]
+    VocTuning,
+    /// [`Config::nox_tuning`]
+    NoxTuning,
+    /// [`Config::temperature_compensation`]
+    TemperatureCompensation,
+}
+
+impl ConfigParameter {
+    /// All parameters.
+    pub const ALL: [Self; 4] = [
+        Self::AutoCleanInterval,
+        Self::VocTuning,
+        Self::NoxTuning,
+        Self::TemperatureCompensation,
+    ];
+}
+
+/// The differences between an expected and the actual configuration of a sensor.
+///
+/// Parameters are compared as encoded for the sensor, so values which are rounded to the same
+/// resolution are equal. Expected parameters which cannot be encoded always differ.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ConfigDiff {
+    /// The expected configuration.
+    pub expected: Config,
+    /// The actual configuration.
+    pub actual: Config,
+}
+
+impl ConfigDiff {
+    /// Whether `parameter` differs.
+    pub fn differs(&self, parameter: ConfigParameter) -> bool {
+        fn same<T: PartialEq>(
+            expected: Result<T, InvalidValue>,
+            actual: Result<T, InvalidValue>,
+        ) -> bool {
+            matches!((expected, actual), (Ok(expected), Ok(actual)) if expected == actual)
+        }
+        let (expected, actual) = (&self.expected, &self.actual);
+        !match parameter {
+            ConfigParameter::AutoCleanInterval => same(
+                expected.auto_clean_interval.to_words(),
+                actual.auto_clean_interval.to_words(),
+            ),
+            ConfigParameter::VocTuning => {
+                same(expected.voc_tuning.to_words(), actual.voc_tuning.to_words())
+            }
+            ConfigParameter::NoxTuning => {
+                same(expected.nox_tuning.to_words(), actual.nox_tuning.to_words())
+            }
+            ConfigParameter::TemperatureCompensation => same(
+                expected.temperature_compensation.to_words(),
+                actual.temperature_compensation.to_words(),
+            ),
+        }
+    }
+
+    /// Iterate over the parameters which differ.
+    pub fn iter(&self) -> impl Iterator<Item = ConfigParameter> + '_ {
+        ConfigParameter::ALL
+            .into_iter()
+            .filter(|&parameter| self.differs(parameter))
+    }
+
+    /// Whether the actual configuration matches the expected one.
+    pub fn is_empty(&self) -> bool {
+        self.iter().next().is_none()
+    }
+}
+
+/// What the driver believes the sensor is doing, e.g. for logs or a watchdog.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+pub enum DriverState {
+    /// The measurement is not running.
+    #[default]
+    Idle,
+    /// The measurement was started, but no sample was read yet.
+    Starting,
+    /// The measurement is running and samples were read.
+    Measuring,
+    /// The fan cleaning was started and the sensor did not report its end yet.
+    Cleaning,
+    /// The last transaction failed on the bus or with a corrupted response.
+    Error,
+}
+
+/// Everything captured by [`crate::Sen5x::shutdown`] to resume operation later.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ShutdownState {
+    /// State of the VOC algorithm when the measurement was stopped.
+    pub voc_state: VocAlgorithmState,
+    /// Parameters configured when the measurement was stopped.
+    pub config: Config,
+    /// Device status flags raised since they were last cleared.
+    pub status: DeviceStatus,
 }
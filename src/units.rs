@@ -0,0 +1,181 @@
+//! Lightweight unit types of the measured values.
+//!
+//! The newtypes keep values of different units apart at compile time without depending on a
+//! units library: a temperature cannot be added to a humidity or passed where a concentration is
+//! expected. Values of the same unit support addition and subtraction, scaling with a factor and
+//! comparison, and display with their unit.
+//!
+//! ```
+//! use sen5x_rs::units::{Celsius, MicrogramsPerCubicMeter, TypedData};
+//! # let data = sen5x_rs::types::Sen5xData::sample();
+//!
+//! let typed = TypedData::from(data);
+//! assert!(typed.pm2_5 < MicrogramsPerCubicMeter(15.0));
+//! assert!(typed.temperature.is_some_and(|t| t > Celsius(20.0)));
+//! assert_eq!(typed.pm2_5.to_string(), "2.2 µg/m³");
+//! ```
+use core::fmt;
+use core::ops::{Add, Div, Mul, Neg, Sub};
+
+use crate::types::Sen5xData;
+
+/// Defines a newtype of a value in `unit` with arithmetic and `Display`.
+macro_rules! unit {
+    ($(#[$meta:meta])* $name:ident, $unit:literal) => {
+        $(#[$meta])*
+        #[derive(Debug, Copy, Clone, PartialEq, PartialOrd, Default)]
+        pub struct $name(pub f32);
+
+        impl $name {
+            /// The value without its unit.
+            pub const fn value(self) -> f32 {
+                self.0
+            }
+        }
+
+        impl Add for $name {
+            type Output = Self;
+
+            fn add(self, rhs: Self) -> Self {
+                Self(self.0 + rhs.0)
+            }
+        }
+
+        impl Sub for $name {
+            type Output = Self;
+
+            fn sub(self, rhs: Self) -> Self {
+                Self(self.0 - rhs.0)
+            }
+        }
+
+        impl Neg for $name {
+            type Output = Self;
+
+            fn neg(self) -> Self {
+                Self(-self.0)
+            }
+        }
+
+        impl Mul<f32> for $name {
+            type Output = Self;
+
+            fn mul(self, rhs: f32) -> Self {
+                Self(self.0 * rhs)
+            }
+        }
+
+        impl Div<f32> for $name {
+            type Output = Self;
+
+            fn div(self, rhs: f32) -> Self {
+                Self(self.0 / rhs)
+            }
+        }
+
+        /// The ratio of two values of the unit.
+        impl Div for $name {
+            type Output = f32;
+
+            fn div(self, rhs: Self) -> f32 {
+                self.0 / rhs.0
+            }
+        }
+
+        impl fmt::Display for $name {
+            /// The value followed by the unit, with the precision of the formatter if given.
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                fmt::Display::fmt(&self.0, f)?;
+                if !$unit.is_empty() {
+                    f.write_str(concat!(" ", $unit))?;
+                }
+                Ok(())
+            }
+        }
+    };
+}
+
+unit!(
+    /// A mass concentration [µg/m³].
+    MicrogramsPerCubicMeter,
+    "µg/m³"
+);
+unit!(
+    /// A temperature [°C].
+    Celsius,
+    "°C"
+);
+unit!(
+    /// A relative humidity [%RH].
+    RelativeHumidity,
+    "%RH"
+);
+unit!(
+    /// Points of the dimensionless VOC or NOx index.
+    IndexPoints,
+    ""
+);
+
+/// A measurement with the unit of every value in its type.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct TypedData {
+    /// Mass Concentration PM1.0
+    pub pm1_0: MicrogramsPerCubicMeter,
+    /// Mass Concentration PM2.5
+    pub pm2_5: MicrogramsPerCubicMeter,
+    /// Mass Concentration PM4.0
+    pub pm4_0: MicrogramsPerCubicMeter,
+    /// Mass Concentration PM10
+    pub pm10_0: MicrogramsPerCubicMeter,
+    /// Compensated Ambient Humidity (SEN54 and SEN55 only)
+    pub humidity: Option<RelativeHumidity>,
+    /// Compensated Ambient Temperature (SEN54 and SEN55 only)
+    pub temperature: Option<Celsius>,
+    /// VOC Index (SEN54 and SEN55 only)
+    pub voc_index: Option<IndexPoints>,
+    /// NOx Index (SEN55 only)
+    pub nox_index: Option<IndexPoints>,
+}
+
+impl From<Sen5xData> for TypedData {
+    fn from(data: Sen5xData) -> Self {
+        Self {
+            pm1_0: MicrogramsPerCubicMeter(data.pm.pm1_0),
+            pm2_5: MicrogramsPerCubicMeter(data.pm.pm2_5),
+            pm4_0: MicrogramsPerCubicMeter(data.pm.pm4_0),
+            pm10_0: MicrogramsPerCubicMeter(data.pm.pm10_0),
+            humidity: data.env.map(|env| RelativeHumidity(env.humidity)),
+            temperature: data.env.map(|env| Celsius(env.temperature)),
+            voc_index: data.gas.map(|gas| IndexPoints(gas.voc_index)),
+            nox_index: data.gas.and_then(|gas| gas.nox_index).map(IndexPoints),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test the arithmetic and formatting of the unit types
+    #[test]
+    fn test_units() {
+        let pm = MicrogramsPerCubicMeter(10.0);
+        assert_eq!(pm + pm * 0.5, MicrogramsPerCubicMeter(15.0));
+        assert_eq!((pm - pm / 4.0).value(), 7.5);
+        assert_eq!(pm / MicrogramsPerCubicMeter(4.0), 2.5);
+        assert_eq!(-Celsius(3.5), Celsius(-3.5));
+        assert!(RelativeHumidity(40.0) < RelativeHumidity(60.0));
+        assert_eq!(format!("{:.1}", Celsius(22.405)), "22.4 °C");
+        assert_eq!(RelativeHumidity(55.0).to_string(), "55 %RH");
+        assert_eq!(IndexPoints(100.0).to_string(), "100");
+
+        let data = crate::test_vectors::MEASUREMENTS[0].data;
+        let typed = TypedData::from(Sen5xData { gas: None, ..data });
+        assert_eq!(typed.pm10_0, MicrogramsPerCubicMeter(data.pm.pm10_0));
+        assert_eq!(
+            typed.humidity,
+            data.env.map(|env| RelativeHumidity(env.humidity))
+        );
+        assert_eq!(typed.voc_index, None);
+    }
+}